@@ -0,0 +1,20 @@
+-- Auto-API Discovery Store
+-- Migration 006: Versioned OpenAPI specs per discovered domain
+
+-- Lookups used by spec generation
+CREATE INDEX IF NOT EXISTS idx_api_discovery_logs_domain ON api_discovery_logs(domain, timestamp);
+CREATE INDEX IF NOT EXISTS idx_api_discovery_logs_unprocessed ON api_discovery_logs(domain) WHERE processed = FALSE;
+
+-- Generated specs, one row per change in a domain's API surface
+CREATE TABLE api_spec_versions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    domain VARCHAR(255) NOT NULL,
+    version INTEGER NOT NULL,
+    spec JSONB NOT NULL,
+    endpoint_count INTEGER NOT NULL DEFAULT 0,
+    log_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (domain, version)
+);
+
+CREATE INDEX idx_api_spec_versions_domain ON api_spec_versions(domain, version DESC);
@@ -0,0 +1,4 @@
+-- Auto-API Mock Server
+-- Migration 007: Recorded round-trip latency for replay
+
+ALTER TABLE api_discovery_logs ADD COLUMN duration_ms INTEGER;
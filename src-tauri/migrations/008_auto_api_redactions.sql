@@ -0,0 +1,4 @@
+-- Auto-API Redaction Markers
+-- Migration 008: Record which captured values were redacted before storage
+
+ALTER TABLE api_discovery_logs ADD COLUMN redactions JSONB NOT NULL DEFAULT '[]';
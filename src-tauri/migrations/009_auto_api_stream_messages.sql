@@ -0,0 +1,20 @@
+-- Auto-API Stream Capture
+-- Migration 009: Websocket frames and server-sent events per discovered domain
+
+CREATE TABLE api_stream_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    session_id VARCHAR(255),
+    domain VARCHAR(255) NOT NULL,
+    connection_id VARCHAR(255),
+    connection_url TEXT NOT NULL,
+    protocol VARCHAR(20) NOT NULL CHECK (protocol IN ('websocket', 'sse')),
+    direction VARCHAR(20) NOT NULL CHECK (direction IN ('send', 'receive')),
+    event VARCHAR(255),
+    payload TEXT,
+    timestamp TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    redactions JSONB NOT NULL DEFAULT '[]'
+);
+
+CREATE INDEX idx_api_stream_messages_domain ON api_stream_messages(domain, timestamp);
+CREATE INDEX idx_api_stream_messages_connection ON api_stream_messages(connection_id);
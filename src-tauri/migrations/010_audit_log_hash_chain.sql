@@ -0,0 +1,24 @@
+-- Tamper-Evident Audit Log
+-- Migration 010: SHA-256 hash chain over persisted audit entries
+
+-- Entries written before this migration stay unchained and are skipped by verification
+ALTER TABLE audit_logs ADD COLUMN sequence BIGINT UNIQUE;
+ALTER TABLE audit_logs ADD COLUMN prev_hash CHAR(64);
+ALTER TABLE audit_logs ADD COLUMN entry_hash CHAR(64);
+
+CREATE INDEX idx_audit_logs_sequence ON audit_logs(sequence) WHERE sequence IS NOT NULL;
+
+-- Single-row chain head. Appends lock this row, so the chain stays linear, and
+-- verification compares against it to catch entries deleted from the tail.
+-- pruned_through_* records where the chain legitimately starts after retention.
+CREATE TABLE audit_chain_state (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    last_sequence BIGINT NOT NULL DEFAULT 0,
+    last_hash CHAR(64) NOT NULL,
+    pruned_through_sequence BIGINT NOT NULL DEFAULT 0,
+    pruned_through_hash CHAR(64) NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+INSERT INTO audit_chain_state (id, last_hash, pruned_through_hash)
+VALUES (TRUE, repeat('0', 64), repeat('0', 64));
@@ -0,0 +1,6 @@
+-- Encrypted Model API Keys
+-- Migration 011: Store provider API keys as AES-GCM envelopes
+
+-- Values are "v1:<key id>:<wrapped data key>:<ciphertext>"; plaintext left by
+-- earlier builds is encrypted by POST /api/security/keys/rewrap
+ALTER TABLE ai_models ADD COLUMN IF NOT EXISTS api_key_encrypted TEXT;
@@ -0,0 +1,26 @@
+-- API Key Management
+-- Migration 012: Scoped, expiring, rotatable API keys
+
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    -- First characters of the secret, kept so users can recognise a key
+    key_prefix VARCHAR(16) NOT NULL,
+    -- HMAC-SHA256 of the secret for lookup, Argon2id hash for verification
+    lookup_hash CHAR(64) NOT NULL UNIQUE,
+    key_hash TEXT NOT NULL,
+    -- Plugin permission names, e.g. 'jean.chat', 'tabs.read'
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    last_used_ip INET,
+    rotated_from UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
+CREATE INDEX idx_api_keys_expires_at ON api_keys(expires_at) WHERE is_active;
@@ -0,0 +1,20 @@
+-- Data Subject Requests
+-- Migration 013: GDPR export and erasure jobs
+
+CREATE TABLE dsar_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('export', 'erasure')),
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'completed', 'failed')),
+    -- Per-category outcomes, see security::dsar::DsarReport
+    report JSONB,
+    error TEXT,
+    -- Zipped export, cleared once the download window passes
+    archive BYTEA,
+    archive_expires_at TIMESTAMP WITH TIME ZONE,
+    requested_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP WITH TIME ZONE
+);
+
+-- No foreign key to users: the request and its report outlive the erased account
+CREATE INDEX idx_dsar_requests_user_id ON dsar_requests(user_id, requested_at DESC);
@@ -0,0 +1,10 @@
+-- Consent Enforcement
+-- Migration 014: Expiring consent grants checked by the consent gate
+
+ALTER TABLE consent_records ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP WITH TIME ZONE;
+
+-- The gate reads the latest decision per user and consent type
+CREATE INDEX IF NOT EXISTS idx_consent_records_user_type
+    ON consent_records(user_id, consent_type, granted_at DESC);
+CREATE INDEX IF NOT EXISTS idx_consent_records_open_expiry
+    ON consent_records(expires_at) WHERE granted AND revoked_at IS NULL;
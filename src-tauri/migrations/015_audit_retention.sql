@@ -0,0 +1,21 @@
+-- Audit Retention
+-- Migration 015: Per-type retention with compressed archives that keep the hash chain verifiable
+
+CREATE TABLE IF NOT EXISTS audit_log_archives (
+    id UUID PRIMARY KEY,
+    file_path TEXT NOT NULL,
+    sha256 CHAR(64) NOT NULL,
+    entries BIGINT NOT NULL,
+    first_created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Archived entries stay in place, stripped to their chain links, until the
+-- chain start moves past them
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS archive_id UUID REFERENCES audit_log_archives(id);
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_retention
+    ON audit_logs(resource_type, created_at) WHERE archive_id IS NULL;
+CREATE INDEX IF NOT EXISTS idx_audit_logs_archived_sequence
+    ON audit_logs(sequence) WHERE archive_id IS NOT NULL;
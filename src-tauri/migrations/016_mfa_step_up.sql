@@ -0,0 +1,39 @@
+-- Two-Factor Step-Up
+-- Migration 016: TOTP factors, backup codes and elevated sessions for high-risk actions
+
+CREATE TABLE user_totp (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    -- Base32 secret in an encryption envelope bound to the user
+    secret_encrypted TEXT NOT NULL,
+    -- NULL until the user confirms a code from their authenticator
+    enabled_at TIMESTAMP WITH TIME ZONE,
+    -- Highest time step accepted, so codes cannot be replayed
+    last_used_step BIGINT,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE user_backup_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- HMAC-SHA256 of the normalised code
+    code_hash CHAR(64) NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_user_backup_codes_lookup ON user_backup_codes(user_id, code_hash);
+
+CREATE TABLE elevated_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash CHAR(64) NOT NULL UNIQUE,
+    method VARCHAR(20) NOT NULL CHECK (method IN ('totp', 'backup_code')),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_elevated_sessions_user ON elevated_sessions(user_id, expires_at) WHERE revoked_at IS NULL;
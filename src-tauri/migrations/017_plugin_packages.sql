@@ -0,0 +1,9 @@
+-- Plugin Packages
+-- Migration 017: Provenance of plugins installed from signed .jtplugin archives
+
+ALTER TABLE plugins
+    ADD COLUMN IF NOT EXISTS installed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    -- Trusted publisher key the package signature verified against
+    ADD COLUMN IF NOT EXISTS publisher_key_id VARCHAR(100),
+    -- SHA-256 of the archive as installed
+    ADD COLUMN IF NOT EXISTS package_sha256 CHAR(64);
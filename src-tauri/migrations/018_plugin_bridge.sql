@@ -0,0 +1,40 @@
+-- Plugin API Bridge
+-- Migration 018: Per-user permission consent, runtime permission prompts and plugin notifications
+
+ALTER TABLE user_plugin_settings
+    -- Optional permissions the user has allowed; required ones are implied by enabling the plugin
+    ADD COLUMN IF NOT EXISTS granted_permissions TEXT[] NOT NULL DEFAULT '{}',
+    -- Permissions the user has refused; the plugin is not prompted again for these
+    ADD COLUMN IF NOT EXISTS denied_permissions TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_plugin_settings_user_plugin
+    ON user_plugin_settings(user_id, plugin_id);
+
+CREATE TABLE plugin_permission_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    plugin_id UUID NOT NULL REFERENCES plugins(id) ON DELETE CASCADE,
+    permission VARCHAR(50) NOT NULL,
+    -- The manifest's description of why the plugin wants the permission
+    reason TEXT,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'granted', 'denied')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMP WITH TIME ZONE
+);
+
+-- At most one open prompt per permission, however often the plugin retries
+CREATE UNIQUE INDEX idx_plugin_permission_requests_pending
+    ON plugin_permission_requests(user_id, plugin_id, permission)
+    WHERE status = 'pending';
+
+CREATE TABLE plugin_notifications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    plugin_id UUID NOT NULL REFERENCES plugins(id) ON DELETE CASCADE,
+    title VARCHAR(200) NOT NULL,
+    body TEXT,
+    read_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_plugin_notifications_user ON plugin_notifications(user_id, created_at DESC);
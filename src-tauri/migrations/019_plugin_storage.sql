@@ -0,0 +1,19 @@
+-- Plugin Storage
+-- Migration 019: Key-value storage namespaced per plugin and per user
+
+CREATE TABLE plugin_storage (
+    plugin_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    -- Byte-wise collation keeps prefix listing on the primary key index
+    key TEXT COLLATE "C" NOT NULL,
+    value JSONB NOT NULL,
+    -- Key plus serialised value, counted against the namespace quota
+    size_bytes INTEGER NOT NULL CHECK (size_bytes > 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (plugin_id, user_id, key),
+    -- Entries belong to the user's install and go away with it
+    FOREIGN KEY (user_id, plugin_id) REFERENCES user_plugin_settings(user_id, plugin_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_plugin_storage_user ON plugin_storage(user_id);
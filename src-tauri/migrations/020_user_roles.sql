@@ -0,0 +1,7 @@
+-- User Roles
+-- Migration 020: Instance administrators, who manage plugins and security operations
+
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS idx_users_is_admin ON users(id) WHERE is_admin;
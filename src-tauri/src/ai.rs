@@ -1,7 +1,9 @@
-use axum::{Json, extract::State};
+use axum::{Extension, Json, extract::State};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use crate::plugins::{PluginEvent, PluginEventBus};
+use crate::security::UserContext;
 
 #[derive(Debug, Deserialize)]
 pub struct AIRequest {
@@ -38,19 +40,21 @@ pub enum JeanAction {
 }
 
 pub async fn generate_response(
+    user: Option<Extension<UserContext>>,
+    events: Option<Extension<PluginEventBus>>,
     Json(request): Json<AIRequest>,
 ) -> Result<Json<AIResponse>, axum::http::StatusCode> {
-    // Check if we should use cloud AI or local LLM
-    let use_cloud_ai = std::env::var("USE_CLOUD_AI").unwrap_or_else(|_| "false".to_string()) == "true";
-    
-    let response = if use_cloud_ai {
-        call_cloud_llm(&request).await
-    } else {
-        call_local_llm(&request).await
-    };
-
-    match response {
-        Ok(ai_response) => Ok(Json(ai_response)),
+    match complete(&request).await {
+        Ok(ai_response) => {
+            if let (Some(Extension(user)), Some(Extension(events))) = (user, events) {
+                events.publish(
+                    PluginEvent::JeanMessage,
+                    user.user_id,
+                    serde_json::json!({ "prompt": request.prompt, "response": ai_response.text() }),
+                );
+            }
+            Ok(Json(ai_response))
+        }
         Err(e) => {
             tracing::error!("AI generation failed: {}", e);
             Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
@@ -58,6 +62,30 @@ pub async fn generate_response(
     }
 }
 
+impl AIRequest {
+    pub fn new(prompt: String, context_json: Option<String>) -> Self {
+        Self { prompt, context_json, model: None }
+    }
+}
+
+impl AIResponse {
+    pub fn text(&self) -> &str {
+        &self.response
+    }
+}
+
+/// Runs a prompt through the configured backend (cloud AI or local LLM).
+pub async fn complete(request: &AIRequest) -> Result<AIResponse, Box<dyn std::error::Error>> {
+    // Check if we should use cloud AI or local LLM
+    let use_cloud_ai = std::env::var("USE_CLOUD_AI").unwrap_or_else(|_| "false".to_string()) == "true";
+
+    if use_cloud_ai {
+        call_cloud_llm(request).await
+    } else {
+        call_local_llm(request).await
+    }
+}
+
 async fn call_cloud_llm(request: &AIRequest) -> Result<AIResponse, Box<dyn std::error::Error>> {
     let cloud_endpoint = std::env::var("CLOUD_AI_ENDPOINT")?;
     let api_key = std::env::var("CLOUD_AI_API_KEY").ok();
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Extension, Path, Query, State},
+    extract::{Extension, Query, State},
     http::{StatusCode, HeaderMap},
     response::{Json, Response, Sse, sse::Event},
     routing::{get, post},
@@ -21,6 +21,7 @@ use anyhow::Result;
 use tracing::{info, warn, error, debug};
 
 use crate::error::AppError;
+use crate::security::SafePath;
 
 #[derive(Clone)]
 pub struct AiGatewayState {
@@ -565,7 +566,7 @@ pub async fn generate_image(
 
 pub async fn get_job_status(
     State(state): State<AiGatewayState>,
-    Path(job_id): Path<String>,
+    SafePath(job_id): SafePath<String>,
 ) -> Result<Json<Value>, AppError> {
     let job = state.get_job(&job_id).await?
         .ok_or_else(|| AppError::NotFound("Job not found".to_string()))?;
@@ -588,7 +589,7 @@ pub async fn get_job_status(
 
 pub async fn stream_job(
     State(state): State<AiGatewayState>,
-    Path(job_id): Path<String>,
+    SafePath(job_id): SafePath<String>,
 ) -> Result<Sse<impl Stream<Item = Result<Event, anyhow::Error>>>, AppError> {
     let (tx, rx) = tokio::sync::mpsc::channel(100);
 
@@ -0,0 +1,745 @@
+// Client stub generation for discovered APIs. Generated clients back off on
+// throttling and expose an iterator over every page of paginated operations.
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+use super::pagination::{PaginationInfo, PAGINATION_EXTENSION};
+use super::rate_limits::{RateLimitInfo, RATE_LIMIT_EXTENSION};
+use super::{format_operation_name, OpenApiSpec};
+
+struct StubOperation {
+    name: String,
+    method: String,
+    path: Vec<PathSegment>,
+    path_params: Vec<String>,
+    has_body: bool,
+    summary: String,
+    pagination: Option<PaginationInfo>,
+    rate_limit: Option<RateLimitInfo>,
+}
+
+/// A path segment, with parameters already renamed to target-language identifiers.
+enum PathSegment {
+    Literal(String),
+    Param(String),
+}
+
+#[derive(Clone, Copy)]
+enum Language {
+    JavaScript,
+    Python,
+}
+
+/// Keywords plus the names the generated runtime already uses for members and arguments.
+const JS_RESERVED: &[&str] = &[
+    "arguments", "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+    "delete", "do", "else", "enum", "eval", "export", "extends", "false", "finally", "for", "function",
+    "if", "implements", "import", "in", "instanceof", "interface", "let", "new", "null", "package",
+    "private", "protected", "public", "return", "static", "super", "switch", "this", "throw", "true",
+    "try", "typeof", "var", "void", "while", "with", "yield",
+    "apiKey", "baseUrl", "constructor", "data", "maxBackoffMs", "maxRetries", "paginate", "query",
+    "request", "resetDelay", "retryDelay",
+];
+
+const PYTHON_RESERVED: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+    "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+    "_paginate", "_parse", "_request", "_reset_delay", "_retry_delay", "api_key", "base_url", "data",
+    "max_backoff", "max_retries", "params", "self", "session",
+];
+
+impl Language {
+    fn identifier(self, raw: &str) -> String {
+        let (raw, reserved) = match self {
+            Language::JavaScript => (raw.to_string(), JS_RESERVED),
+            Language::Python => (to_snake_case(raw), PYTHON_RESERVED),
+        };
+        let mut ident: String = raw.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+        if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+            ident.insert(0, '_');
+        }
+        if reserved.contains(&ident.as_str()) {
+            ident.push('_');
+        }
+        ident
+    }
+}
+
+/// Suffixes `ident` until it no longer collides with a name already taken.
+fn unique(ident: String, taken: &mut HashSet<String>) -> String {
+    let mut candidate = ident.clone();
+    let mut suffix = 2;
+    while !taken.insert(candidate.clone()) {
+        candidate = format!("{}_{}", ident, suffix);
+        suffix += 1;
+    }
+    candidate
+}
+
+fn collect_operations(spec: &OpenApiSpec, language: Language) -> Vec<StubOperation> {
+    let mut paths: Vec<&String> = spec.paths.keys().collect();
+    paths.sort();
+
+    let mut names = HashSet::new();
+    let mut operations = Vec::new();
+    for path in paths {
+        for (method, operation) in spec.paths[path].operations() {
+            let extension = |key: &str| operation.extensions.get(key).cloned();
+            let raw_name = operation.operation_id.clone().unwrap_or_else(|| format_operation_name(method, path));
+
+            let mut params = HashSet::new();
+            let segments: Vec<PathSegment> = path
+                .split('/')
+                .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(param) => PathSegment::Param(unique(language.identifier(param), &mut params)),
+                    None => PathSegment::Literal(segment.to_string()),
+                })
+                .collect();
+
+            operations.push(StubOperation {
+                name: unique(language.identifier(&raw_name), &mut names),
+                method: method.to_uppercase(),
+                path_params: segments
+                    .iter()
+                    .filter_map(|s| match s {
+                        PathSegment::Param(param) => Some(param.clone()),
+                        PathSegment::Literal(_) => None,
+                    })
+                    .collect(),
+                path: segments,
+                has_body: operation.request_body.is_some(),
+                summary: format!("{} {}", method.to_uppercase(), path),
+                pagination: extension(PAGINATION_EXTENSION).and_then(|v| serde_json::from_value(v).ok()),
+                rate_limit: extension(RATE_LIMIT_EXTENSION).and_then(|v| serde_json::from_value(v).ok()),
+            });
+        }
+    }
+    operations
+}
+
+/// Policy tables shared by every generated client, as JSON literals.
+struct Policies {
+    rate_limits: String,
+    pagination: String,
+    default_rate_limit: Option<String>,
+}
+
+fn policies(operations: &[StubOperation]) -> Policies {
+    let mut rate_limits = Map::new();
+    let mut pagination = Map::new();
+    for op in operations {
+        if let Some(info) = &op.rate_limit {
+            rate_limits.insert(op.name.clone(), serde_json::to_value(info).unwrap_or(Value::Null));
+        }
+        if let Some(info) = &op.pagination {
+            pagination.insert(op.name.clone(), serde_json::to_value(info).unwrap_or(Value::Null));
+        }
+    }
+
+    // Rate limits are usually account-wide, so operations never seen throttled
+    // still honour the headers observed elsewhere
+    let default_rate_limit = operations
+        .iter()
+        .filter_map(|op| op.rate_limit.as_ref())
+        .find(|info| info.remaining_header.is_some() || info.reset_header.is_some())
+        .and_then(|info| serde_json::to_string(info).ok());
+
+    Policies {
+        rate_limits: Value::Object(rate_limits).to_string(),
+        pagination: Value::Object(pagination).to_string(),
+        default_rate_limit,
+    }
+}
+
+fn base_url(spec: &OpenApiSpec) -> String {
+    spec.servers.first().map(|s| s.url.clone()).unwrap_or_default()
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            snake.push(c);
+        } else {
+            snake.push('_');
+        }
+    }
+    snake
+}
+
+/// JSON string literals are also valid JavaScript and Python string literals.
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+/// Captured text flattened onto one line for a `//` or `#` comment.
+fn single_line(text: &str) -> String {
+    text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
+
+fn js_doc_text(text: &str) -> String {
+    single_line(text).replace("*/", "*\\/")
+}
+
+fn python_docstring_text(text: &str) -> String {
+    single_line(text).replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// `/users/{id}` as a JS template literal body.
+fn js_path(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Param(param) => format!("${{encodeURIComponent(String({}))}}", param),
+            PathSegment::Literal(literal) => literal
+                .chars()
+                .map(|c| match c {
+                    '\\' | '`' | '$' => format!("\\{}", c),
+                    c if c.is_control() => format!("\\u{:04x}", c as u32),
+                    c => c.to_string(),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `/users/{id}` as the body of a single-quoted Python f-string.
+fn python_path(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Param(param) => format!("{{quote(str({}), safe=\"\")}}", param),
+            PathSegment::Literal(literal) => literal
+                .chars()
+                .map(|c| match c {
+                    '\\' | '\'' => format!("\\{}", c),
+                    '{' => "{{".to_string(),
+                    '}' => "}}".to_string(),
+                    c if c.is_control() => format!("\\u{:04x}", c as u32),
+                    c => c.to_string(),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn generate_typescript_client(spec: &OpenApiSpec) -> String {
+    generate_js_family_client(spec, true)
+}
+
+pub fn generate_javascript_client(spec: &OpenApiSpec) -> String {
+    generate_js_family_client(spec, false)
+}
+
+fn generate_js_family_client(spec: &OpenApiSpec, typed: bool) -> String {
+    let operations = collect_operations(spec, Language::JavaScript);
+    let policies = policies(&operations);
+    // Type annotations are the only difference between the two clients
+    let t = |annotation: &'static str| if typed { annotation } else { "" };
+
+    let mut code = format!(
+        "// Auto-generated {} client for {}\n\n",
+        if typed { "TypeScript" } else { "JavaScript" },
+        single_line(&spec.info.title)
+    );
+    if typed {
+        code.push_str(TS_TYPES);
+    }
+    code.push_str(&format!(
+        "const RATE_LIMITS{} = {};\nconst PAGINATION{} = {};\nconst DEFAULT_RATE_LIMIT{} = {};\n\n",
+        t(": Record<string, RateLimitPolicy>"),
+        policies.rate_limits,
+        t(": Record<string, PaginationPolicy>"),
+        policies.pagination,
+        t(": RateLimitPolicy | undefined"),
+        policies.default_rate_limit.as_deref().unwrap_or("undefined"),
+    ));
+    code.push_str(&strip_types(JS_RUNTIME, typed));
+    code.push_str(&format!(
+        "  constructor(baseUrl{} = {}, apiKey{}) {{\n    this.baseUrl = baseUrl.replace(/\\/$/, '');\n    this.apiKey = apiKey;\n  }}\n\n",
+        t(": string"),
+        string_literal(&base_url(spec)),
+        t("?: string"),
+    ));
+    code.push_str(&strip_types(JS_CLIENT_RUNTIME, typed));
+
+    for op in &operations {
+        let path_args: Vec<String> = op.path_params.iter().map(|p| format!("{}{}", p, t(": string | number"))).collect();
+        let rate_limit = format!("RATE_LIMITS[{}] ?? DEFAULT_RATE_LIMIT", string_literal(&op.name));
+
+        let mut args = path_args.clone();
+        if op.has_body {
+            args.push(format!("data{}", t("?: unknown")));
+        }
+        args.push(format!("query{} = {{}}", t(": Query")));
+        code.push_str(&format!(
+            "  /** {} */\n  async {}({}){} {{\n    const response = await this.request('{}', `{}`, query, {}, {});\n    return parseBody(response);\n  }}\n\n",
+            js_doc_text(&op.summary),
+            op.name,
+            args.join(", "),
+            t(": Promise<any>"),
+            op.method,
+            js_path(&op.path),
+            if op.has_body { "data" } else { "undefined" },
+            rate_limit,
+        ));
+
+        if op.pagination.is_some() {
+            let mut args = path_args;
+            args.push(format!("query{} = {{}}", t(": Query")));
+            code.push_str(&format!(
+                "  /** Every item of {} across all pages. */\n  {}All({}){} {{\n    return this.paginate('{}', `{}`, query, PAGINATION[{}], {});\n  }}\n\n",
+                js_doc_text(&op.summary),
+                op.name,
+                args.join(", "),
+                t(": AsyncGenerator<any>"),
+                op.method,
+                js_path(&op.path),
+                string_literal(&op.name),
+                rate_limit,
+            ));
+        }
+    }
+
+    code.push_str("}\n");
+    code
+}
+
+/// The shared runtime carries its TypeScript annotations in `/*...*/`
+/// comments: unwrapped for TypeScript, dropped for plain JavaScript.
+fn strip_types(source: &str, typed: bool) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find("/*") {
+        output.push_str(&rest[..start]);
+        let end = rest[start..].find("*/").map(|e| start + e).unwrap_or(rest.len());
+        if typed {
+            output.push_str(&rest[start + 2..end]);
+        }
+        rest = &rest[(end + 2).min(rest.len())..];
+    }
+    output.push_str(rest);
+    output
+}
+
+const TS_TYPES: &str = r#"export interface RateLimitPolicy {
+  limitHeader?: string;
+  remainingHeader?: string;
+  resetHeader?: string;
+  resetFormat?: 'delta-seconds' | 'epoch-seconds' | 'epoch-milliseconds';
+}
+
+export interface PaginationPolicy {
+  style: 'link-header' | 'cursor' | 'page' | 'offset';
+  pageParam?: string;
+  sizeParam?: string;
+  nextCursorPath?: string;
+  hasMorePath?: string;
+  totalPagesPath?: string;
+  itemsPath?: string;
+  firstPage?: number;
+}
+
+export type Query = Record<string, string | number | boolean | undefined>;
+
+"#;
+
+const JS_RUNTIME: &str = r#"const sleep = (ms/*: number*/) => new Promise/*<void>*/((resolve) => setTimeout(resolve, ms));
+
+function getPath(value/*: any*/, path/*?: string*/)/*: any*/ {
+  if (!path) return value;
+  return path.split('.').reduce((current, key) => (current == null ? undefined : current[key]), value);
+}
+
+function nextLink(header/*: string | null*/)/*: string | undefined*/ {
+  for (const part of (header ?? '').split(',')) {
+    const match = part.match(/<([^>]+)>\s*;.*rel="?next"?/);
+    if (match) return match[1];
+  }
+  return undefined;
+}
+
+// Resolves a next-page link, dropping it when it leaves the API's origin so the key is never sent elsewhere
+function sameOriginLink(link/*: string | undefined*/, current/*: string*/, baseUrl/*: string*/)/*: string | undefined*/ {
+  if (!link) return undefined;
+  const url = new URL(link, current || baseUrl);
+  return url.origin === new URL(baseUrl).origin ? url.toString() : undefined;
+}
+
+async function parseBody(response/*: Response*/)/*: Promise<any>*/ {
+  const text = await response.text();
+  return text ? JSON.parse(text) : undefined;
+}
+
+export class ApiClient {
+  /*private baseUrl: string;
+  private apiKey?: string;
+  */maxRetries = 5;
+  maxBackoffMs = 60000;
+
+"#;
+
+const JS_CLIENT_RUNTIME: &str = r#"  /*private */async request(method/*: string*/, path/*: string*/, query/*: Query*/ = {}, body/*?: unknown*/, rateLimit/*: RateLimitPolicy | undefined*/ = DEFAULT_RATE_LIMIT)/*: Promise<Response>*/ {
+    const url = new URL(/^https?:\/\//.test(path) ? path : this.baseUrl + path);
+    for (const [key, value] of Object.entries(query)) {
+      if (value !== undefined) url.searchParams.set(key, String(value));
+    }
+
+    for (let attempt = 0; ; attempt++) {
+      const response = await fetch(url, {
+        method,
+        headers: {
+          ...(body !== undefined && { 'Content-Type': 'application/json' }),
+          ...(this.apiKey && { Authorization: `Bearer ${this.apiKey}` }),
+        },
+        body: body === undefined ? undefined : JSON.stringify(body),
+      });
+
+      if ((response.status === 429 || response.status === 503) && attempt < this.maxRetries) {
+        await sleep(this.retryDelay(response, attempt, rateLimit));
+        continue;
+      }
+      if (!response.ok) throw new Error(`${method} ${url.pathname} failed with ${response.status}`);
+
+      // Wait out an exhausted window instead of spending the next call on a 429
+      const remaining = rateLimit?.remainingHeader ? response.headers.get(rateLimit.remainingHeader) : null;
+      if (remaining !== null && remaining.trim() !== '' && Number(remaining) <= 0) {
+        await sleep(Math.min(this.resetDelay(response, rateLimit) ?? 0, this.maxBackoffMs));
+      }
+      return response;
+    }
+  }
+
+  /*private */retryDelay(response/*: Response*/, attempt/*: number*/, rateLimit/*?: RateLimitPolicy*/)/*: number*/ {
+    const retryAfter = response.headers.get('Retry-After');
+    if (retryAfter) {
+      const seconds = Number(retryAfter);
+      const delay = Number.isNaN(seconds) ? Date.parse(retryAfter) - Date.now() : seconds * 1000;
+      if (!Number.isNaN(delay)) return Math.min(Math.max(delay, 0), this.maxBackoffMs);
+    }
+    const reset = this.resetDelay(response, rateLimit);
+    if (reset !== undefined) return Math.min(reset, this.maxBackoffMs);
+    return Math.min(500 * 2 ** attempt + Math.random() * 250, this.maxBackoffMs);
+  }
+
+  /*private */resetDelay(response/*: Response*/, rateLimit/*?: RateLimitPolicy*/)/*: number | undefined*/ {
+    const raw = rateLimit?.resetHeader ? response.headers.get(rateLimit.resetHeader) : null;
+    if (raw === null || raw.trim() === '' || Number.isNaN(Number(raw))) return undefined;
+    const value = Number(raw);
+    switch (rateLimit?.resetFormat) {
+      case 'epoch-seconds':
+        return Math.max(value * 1000 - Date.now(), 0);
+      case 'epoch-milliseconds':
+        return Math.max(value - Date.now(), 0);
+      default:
+        return value * 1000;
+    }
+  }
+
+  /*private */async *paginate(method/*: string*/, path/*: string*/, query/*: Query*/, pagination/*: PaginationPolicy*/, rateLimit/*?: RateLimitPolicy*/)/*: AsyncGenerator<any>*/ {
+    const pageParam = pagination.pageParam ?? '';
+    const firstPage = pagination.firstPage ?? 1;
+    let page = pagination.style === 'page' && query[pageParam] !== undefined ? Number(query[pageParam]) : firstPage;
+    let offset = pagination.style === 'offset' && query[pageParam] !== undefined ? Number(query[pageParam]) : 0;
+    let next/*: string | undefined*/ = path;
+    let params/*: Query*/ = { ...query };
+
+    while (next) {
+      const response = await this.request(method, next, params, undefined, rateLimit);
+      const body = await parseBody(response);
+      const items = getPath(body, pagination.itemsPath);
+      if (!Array.isArray(items) || items.length === 0) return;
+      yield* items;
+
+      if (pagination.hasMorePath && getPath(body, pagination.hasMorePath) === false) return;
+      switch (pagination.style) {
+        case 'link-header':
+          next = sameOriginLink(nextLink(response.headers.get('Link')), response.url, this.baseUrl);
+          params = {};
+          break;
+        case 'cursor': {
+          const cursor = getPath(body, pagination.nextCursorPath);
+          if (!cursor || !pageParam) return;
+          params = { ...params, [pageParam]: cursor };
+          break;
+        }
+        case 'page': {
+          page += 1;
+          const totalPages = getPath(body, pagination.totalPagesPath);
+          if (typeof totalPages === 'number' && page - firstPage >= totalPages) return;
+          params = { ...params, [pageParam]: page };
+          break;
+        }
+        case 'offset':
+          offset += items.length;
+          params = { ...params, [pageParam]: offset };
+          break;
+      }
+    }
+  }
+
+"#;
+
+pub fn generate_python_client(spec: &OpenApiSpec) -> String {
+    let operations = collect_operations(spec, Language::Python);
+    let policies = policies(&operations);
+    let literal = string_literal;
+
+    let mut code = format!("# Auto-generated Python client for {}\n", single_line(&spec.info.title));
+    code.push_str(PYTHON_IMPORTS);
+    code.push_str(&format!(
+        "_RATE_LIMITS = json.loads({})\n_PAGINATION = json.loads({})\nDEFAULT_RATE_LIMIT = {}\n",
+        literal(&policies.rate_limits),
+        literal(&policies.pagination),
+        policies.default_rate_limit.as_deref().map(|p| format!("json.loads({})", literal(p))).unwrap_or_else(|| "None".to_string()),
+    ));
+    code.push_str(PYTHON_RUNTIME);
+    code.push_str(&format!(
+        "    def __init__(self, base_url: str = {}, api_key: str = None):\n        self.base_url = base_url.rstrip('/')\n        self.api_key = api_key\n        self.session = requests.Session()\n        if api_key:\n            self.session.headers.update({{'Authorization': f'Bearer {{api_key}}'}})\n\n",
+        literal(&base_url(spec))
+    ));
+    code.push_str(PYTHON_CLIENT_RUNTIME);
+
+    for op in &operations {
+        let name = &op.name;
+        let rate_limit = format!("_RATE_LIMITS.get({}, DEFAULT_RATE_LIMIT)", literal(&op.name));
+        let mut args: Vec<String> = std::iter::once("self".to_string()).chain(op.path_params.iter().cloned()).collect();
+        if op.has_body {
+            args.push("data=None".to_string());
+        }
+        args.push("params=None".to_string());
+
+        code.push_str(&format!(
+            "    def {}({}):\n        \"\"\"{}\"\"\"\n        response = self._request('{}', f'{}', params, {}, rate_limit={})\n        return self._parse(response)\n\n",
+            name,
+            args.join(", "),
+            python_docstring_text(&op.summary),
+            op.method,
+            python_path(&op.path),
+            if op.has_body { "data" } else { "None" },
+            rate_limit,
+        ));
+
+        if op.pagination.is_some() {
+            let args: Vec<String> = std::iter::once("self".to_string())
+                .chain(op.path_params.iter().cloned())
+                .chain(std::iter::once("params=None".to_string()))
+                .collect();
+            code.push_str(&format!(
+                "    def {}_all({}):\n        \"\"\"Every item of {} across all pages.\"\"\"\n        return self._paginate('{}', f'{}', params, _PAGINATION[{}], {})\n\n",
+                name,
+                args.join(", "),
+                python_docstring_text(&op.summary),
+                op.method,
+                python_path(&op.path),
+                literal(&op.name),
+                rate_limit,
+            ));
+        }
+    }
+
+    code
+}
+
+const PYTHON_IMPORTS: &str = r#"import json
+import random
+import time
+from datetime import datetime, timezone
+from email.utils import parsedate_to_datetime
+from urllib.parse import quote, urljoin, urlsplit
+
+import requests
+
+"#;
+
+const PYTHON_RUNTIME: &str = r#"
+
+def _get_path(value, path):
+    if not path:
+        return value
+    for key in path.split('.'):
+        if not isinstance(value, dict):
+            return None
+        value = value.get(key)
+    return value
+
+
+def _same_origin_link(link, current, base_url):
+    """The next-page URL, or None when it leaves the API's origin; the session's key never goes elsewhere."""
+    if not link:
+        return None
+    url = urljoin(current or base_url, link)
+    target, base = urlsplit(url), urlsplit(base_url)
+    return url if (target.scheme, target.hostname, target.port) == (base.scheme, base.hostname, base.port) else None
+
+
+class ApiClient:
+    max_retries = 5
+    max_backoff = 60.0
+
+"#;
+
+const PYTHON_CLIENT_RUNTIME: &str = r#"    def _request(self, method, path, params=None, body=None, rate_limit=DEFAULT_RATE_LIMIT):
+        url = path if path.startswith(('http://', 'https://')) else self.base_url + path
+        for attempt in range(self.max_retries + 1):
+            response = self.session.request(method, url, params=params, json=body)
+            if response.status_code in (429, 503) and attempt < self.max_retries:
+                time.sleep(self._retry_delay(response, attempt, rate_limit))
+                continue
+            response.raise_for_status()
+
+            # Wait out an exhausted window instead of spending the next call on a 429
+            remaining = response.headers.get((rate_limit or {}).get('remainingHeader') or '')
+            if remaining is not None and remaining.strip().isdigit() and int(remaining) <= 0:
+                time.sleep(min(self._reset_delay(response, rate_limit) or 0, self.max_backoff))
+            return response
+
+    def _retry_delay(self, response, attempt, rate_limit):
+        retry_after = response.headers.get('Retry-After')
+        if retry_after:
+            try:
+                return min(max(float(retry_after), 0), self.max_backoff)
+            except ValueError:
+                try:
+                    delay = (parsedate_to_datetime(retry_after) - datetime.now(timezone.utc)).total_seconds()
+                    return min(max(delay, 0), self.max_backoff)
+                except (TypeError, ValueError):
+                    pass
+        reset = self._reset_delay(response, rate_limit)
+        if reset is not None:
+            return min(reset, self.max_backoff)
+        return min(0.5 * 2 ** attempt + random.random() * 0.25, self.max_backoff)
+
+    def _reset_delay(self, response, rate_limit):
+        header = (rate_limit or {}).get('resetHeader')
+        if not header or header not in response.headers:
+            return None
+        try:
+            value = float(response.headers[header])
+        except ValueError:
+            return None
+        reset_format = rate_limit.get('resetFormat')
+        if reset_format == 'epoch-seconds':
+            return max(value - time.time(), 0)
+        if reset_format == 'epoch-milliseconds':
+            return max(value / 1000 - time.time(), 0)
+        return value
+
+    @staticmethod
+    def _parse(response):
+        return response.json() if response.content else None
+
+    def _paginate(self, method, path, params, pagination, rate_limit=DEFAULT_RATE_LIMIT):
+        params = dict(params or {})
+        style = pagination['style']
+        page_param = pagination.get('pageParam')
+        first_page = pagination.get('firstPage', 1)
+        page = int(params.get(page_param, first_page)) if style == 'page' else first_page
+        offset = int(params.get(page_param, 0)) if style == 'offset' else 0
+        next_url = path
+
+        while next_url:
+            response = self._request(method, next_url, params, rate_limit=rate_limit)
+            body = self._parse(response)
+            items = _get_path(body, pagination.get('itemsPath'))
+            if not isinstance(items, list) or not items:
+                return
+            yield from items
+
+            if pagination.get('hasMorePath') and _get_path(body, pagination['hasMorePath']) is False:
+                return
+            if style == 'link-header':
+                next_url = _same_origin_link(response.links.get('next', {}).get('url'), response.url, self.base_url)
+                params = {}
+            elif style == 'cursor':
+                cursor = _get_path(body, pagination.get('nextCursorPath'))
+                if not cursor or not page_param:
+                    return
+                params[page_param] = cursor
+            elif style == 'page':
+                page += 1
+                total_pages = _get_path(body, pagination.get('totalPagesPath'))
+                if isinstance(total_pages, int) and page - first_page >= total_pages:
+                    return
+                params[page_param] = page
+            else:
+                offset += len(items)
+                params[page_param] = offset
+
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hostile_spec() -> OpenApiSpec {
+        serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "evil.example\nimport os API", "version": "1.0.0"},
+            "servers": [{"url": "https://evil.example/'+alert(1)+'"}],
+            "paths": {
+                "/v1/it's/`${evil()}`*/\\\"\"\"/{user-id}/{class}": {
+                    "get": {
+                        "operation_id": "2-fetch profile",
+                        "parameters": [],
+                        "responses": {},
+                        "tags": []
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn hostile_paths_become_safe_identifiers_and_literals() {
+        let spec = hostile_spec();
+
+        let ts = generate_typescript_client(&spec);
+        assert!(ts.contains("async _2_fetch_profile(user_id: string | number, class_: string | number, query: Query = {})"));
+        assert!(ts.contains("/v1/it's/\\`\\${evil()}\\`*/\\\\\"\"\"/${encodeURIComponent(String(user_id))}"));
+        assert!(ts.contains("RATE_LIMITS[\"_2_fetch_profile\"]"));
+        assert!(ts.contains("baseUrl: string = \"https://evil.example/'+alert(1)+'\""));
+        let doc = ts.lines().find(|line| line.contains("/** GET")).unwrap();
+        assert_eq!(doc.matches("*/").count(), 1, "doc comment closes early: {}", doc);
+        assert!(ts.lines().all(|line| !line.starts_with("import os")));
+
+        let py = generate_python_client(&spec);
+        assert!(py.contains("def _2_fetch_profile(self, user_id, class_, params=None):"));
+        assert!(py.contains("f'/v1/it\\'s/`${{evil()}}`*/\\\\\"\"\"/{quote(str(user_id), safe=\"\")}"));
+        assert!(py.contains("\"\"\"GET /v1/it's/`${evil()}`*/\\\\\\\"\\\"\\\"/{user-id}/{class}\"\"\""));
+        assert!(py.contains("base_url: str = \"https://evil.example/'+alert(1)+'\""));
+        assert!(py.lines().all(|line| !line.starts_with("import os")));
+    }
+
+    #[test]
+    fn link_pagination_only_follows_same_origin_links() {
+        let spec = hostile_spec();
+
+        let ts = generate_typescript_client(&spec);
+        assert!(ts.contains("next = sameOriginLink(nextLink(response.headers.get('Link')), response.url, this.baseUrl);"));
+        assert!(ts.contains("return url.origin === new URL(baseUrl).origin ? url.toString() : undefined;"));
+
+        let py = generate_python_client(&spec);
+        assert!(py.contains("next_url = _same_origin_link(response.links.get('next', {}).get('url'), response.url, self.base_url)"));
+        assert!(py.contains("from urllib.parse import quote, urljoin, urlsplit"));
+        assert!(!py.contains("next_url = response.links"));
+    }
+
+    #[test]
+    fn colliding_identifiers_get_suffixes() {
+        let mut taken = HashSet::new();
+        assert_eq!(unique(Language::JavaScript.identifier("get-user"), &mut taken), "get_user");
+        assert_eq!(unique(Language::JavaScript.identifier("get_user"), &mut taken), "get_user_2");
+        assert_eq!(Language::Python.identifier("getUser"), "get_user");
+        assert_eq!(Language::Python.identifier("self"), "self_");
+        assert_eq!(Language::JavaScript.identifier(""), "_");
+    }
+}
@@ -0,0 +1,447 @@
+// Spec version diffing for the Auto-API discovery store
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+use super::{OpenApiOperation, OpenApiSpec};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecDiff {
+    pub domain: String,
+    pub from_version: i32,
+    pub to_version: i32,
+    pub added_endpoints: Vec<EndpointRef>,
+    pub removed_endpoints: Vec<EndpointRef>,
+    pub changed_endpoints: Vec<EndpointChange>,
+    pub breaking_changes: Vec<SchemaChange>,
+    pub has_breaking_changes: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EndpointRef {
+    pub method: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointChange {
+    pub method: String,
+    pub path: String,
+    pub changes: Vec<SchemaChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaChange {
+    pub method: String,
+    pub path: String,
+    pub location: String, // e.g. "response.200.body.user.email"
+    pub kind: SchemaChangeKind,
+    pub breaking: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaChangeKind {
+    EndpointRemoved,
+    ParameterAdded,
+    ParameterRemoved,
+    ParameterBecameRequired,
+    RequestBodyAdded,
+    RequestBodyRemoved,
+    ResponseAdded,
+    ResponseRemoved,
+    PropertyAdded,
+    PropertyRemoved,
+    PropertyBecameRequired,
+    PropertyBecameOptional,
+    TypeChanged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Request,
+    Response,
+}
+
+pub fn diff_specs(
+    domain: &str,
+    from_version: i32,
+    old: &OpenApiSpec,
+    to_version: i32,
+    new: &OpenApiSpec,
+) -> SpecDiff {
+    let old_ops = collect_operations(old);
+    let new_ops = collect_operations(new);
+
+    let old_keys: BTreeSet<&EndpointRef> = old_ops.keys().collect();
+    let new_keys: BTreeSet<&EndpointRef> = new_ops.keys().collect();
+
+    let added_endpoints: Vec<EndpointRef> = new_keys.difference(&old_keys).map(|e| (*e).clone()).collect();
+    let removed_endpoints: Vec<EndpointRef> = old_keys.difference(&new_keys).map(|e| (*e).clone()).collect();
+
+    let mut breaking_changes: Vec<SchemaChange> = removed_endpoints
+        .iter()
+        .map(|endpoint| SchemaChange {
+            method: endpoint.method.clone(),
+            path: endpoint.path.clone(),
+            location: "endpoint".to_string(),
+            kind: SchemaChangeKind::EndpointRemoved,
+            breaking: true,
+            detail: format!("{} {} is no longer observed", endpoint.method, endpoint.path),
+        })
+        .collect();
+
+    let mut changed_endpoints = Vec::new();
+    for endpoint in old_keys.intersection(&new_keys) {
+        let changes = diff_operations(endpoint, old_ops[*endpoint], new_ops[*endpoint]);
+        if changes.is_empty() {
+            continue;
+        }
+
+        breaking_changes.extend(changes.iter().filter(|c| c.breaking).cloned());
+        changed_endpoints.push(EndpointChange {
+            method: endpoint.method.clone(),
+            path: endpoint.path.clone(),
+            changes,
+        });
+    }
+
+    SpecDiff {
+        domain: domain.to_string(),
+        from_version,
+        to_version,
+        added_endpoints,
+        removed_endpoints,
+        changed_endpoints,
+        has_breaking_changes: !breaking_changes.is_empty(),
+        breaking_changes,
+    }
+}
+
+fn collect_operations(spec: &OpenApiSpec) -> HashMap<EndpointRef, &OpenApiOperation> {
+    let mut operations = HashMap::new();
+
+    for (path, path_item) in &spec.paths {
+        for (method, operation) in path_item.operations() {
+            operations.insert(
+                EndpointRef {
+                    method: method.to_uppercase(),
+                    path: path.clone(),
+                },
+                operation,
+            );
+        }
+    }
+
+    operations
+}
+
+fn diff_operations(endpoint: &EndpointRef, old: &OpenApiOperation, new: &OpenApiOperation) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    let mut push = |location: String, kind: SchemaChangeKind, breaking: bool, detail: String| {
+        changes.push(SchemaChange {
+            method: endpoint.method.clone(),
+            path: endpoint.path.clone(),
+            location,
+            kind,
+            breaking,
+            detail,
+        });
+    };
+
+    // Parameters
+    for new_param in &new.parameters {
+        let location = format!("parameters.{}.{}", new_param.in_, new_param.name);
+        match old.parameters.iter().find(|p| p.name == new_param.name && p.in_ == new_param.in_) {
+            None => push(
+                location,
+                SchemaChangeKind::ParameterAdded,
+                new_param.required,
+                format!("{} parameter '{}' added{}", new_param.in_, new_param.name, if new_param.required { " as required" } else { "" }),
+            ),
+            Some(old_param) if !old_param.required && new_param.required => push(
+                location,
+                SchemaChangeKind::ParameterBecameRequired,
+                true,
+                format!("{} parameter '{}' is now required", new_param.in_, new_param.name),
+            ),
+            Some(_) => {}
+        }
+    }
+    for old_param in &old.parameters {
+        if !new.parameters.iter().any(|p| p.name == old_param.name && p.in_ == old_param.in_) {
+            push(
+                format!("parameters.{}.{}", old_param.in_, old_param.name),
+                SchemaChangeKind::ParameterRemoved,
+                false,
+                format!("{} parameter '{}' removed", old_param.in_, old_param.name),
+            );
+        }
+    }
+
+    // Request body
+    match (&old.request_body, &new.request_body) {
+        (None, Some(body)) => push(
+            "request.body".to_string(),
+            SchemaChangeKind::RequestBodyAdded,
+            body.required.unwrap_or(false),
+            "request body added".to_string(),
+        ),
+        (Some(_), None) => push(
+            "request.body".to_string(),
+            SchemaChangeKind::RequestBodyRemoved,
+            false,
+            "request body removed".to_string(),
+        ),
+        (Some(old_body), Some(new_body)) => {
+            for (media_type, new_media) in &new_body.content {
+                if let Some(old_media) = old_body.content.get(media_type) {
+                    let mut schema_changes = Vec::new();
+                    diff_schema("request.body", &old_media.schema, &new_media.schema, Direction::Request, &mut schema_changes);
+                    for (location, kind, breaking, detail) in schema_changes {
+                        push(location, kind, breaking, detail);
+                    }
+                }
+            }
+        }
+        (None, None) => {}
+    }
+
+    // Responses
+    for (status, new_response) in &new.responses {
+        let Some(old_response) = old.responses.get(status) else {
+            push(
+                format!("response.{}", status),
+                SchemaChangeKind::ResponseAdded,
+                false,
+                format!("response {} added", status),
+            );
+            continue;
+        };
+
+        let (Some(old_content), Some(new_content)) = (&old_response.content, &new_response.content) else {
+            continue;
+        };
+        for (media_type, new_media) in new_content {
+            if let Some(old_media) = old_content.get(media_type) {
+                let mut schema_changes = Vec::new();
+                diff_schema(&format!("response.{}.body", status), &old_media.schema, &new_media.schema, Direction::Response, &mut schema_changes);
+                for (location, kind, breaking, detail) in schema_changes {
+                    push(location, kind, breaking, detail);
+                }
+            }
+        }
+    }
+    for status in old.responses.keys() {
+        if !new.responses.contains_key(status) {
+            // Losing a success response changes what clients receive; error codes come and go
+            push(
+                format!("response.{}", status),
+                SchemaChangeKind::ResponseRemoved,
+                status.starts_with('2'),
+                format!("response {} removed", status),
+            );
+        }
+    }
+
+    changes
+}
+
+fn diff_schema(
+    location: &str,
+    old: &Value,
+    new: &Value,
+    direction: Direction,
+    changes: &mut Vec<(String, SchemaChangeKind, bool, String)>,
+) {
+    // Fields seen with several types are merged into a oneOf
+    let old_variants = variants(old);
+    let new_variants = variants(new);
+    if old_variants.len() > 1 || new_variants.len() > 1 {
+        diff_variants(location, &old_variants, &new_variants, direction, changes);
+        return;
+    }
+
+    let old_type = old.get("type").and_then(|t| t.as_str());
+    let new_type = new.get("type").and_then(|t| t.as_str());
+
+    if let (Some(old_type), Some(new_type)) = (old_type, new_type) {
+        if old_type != new_type {
+            changes.push((
+                location.to_string(),
+                SchemaChangeKind::TypeChanged,
+                true,
+                format!("type changed from {} to {}", old_type, new_type),
+            ));
+            return;
+        }
+    }
+
+    if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+        diff_schema(&format!("{}[]", location), old_items, new_items, direction, changes);
+    }
+
+    let empty = serde_json::Map::new();
+    let old_props = old.get("properties").and_then(|p| p.as_object()).unwrap_or(&empty);
+    let new_props = new.get("properties").and_then(|p| p.as_object()).unwrap_or(&empty);
+    let old_required = required_set(old);
+    let new_required = required_set(new);
+
+    for (name, new_prop) in new_props {
+        let prop_location = format!("{}.{}", location, name);
+        match old_props.get(name) {
+            Some(old_prop) => {
+                let was_required = old_required.contains(name.as_str());
+                let is_required = new_required.contains(name.as_str());
+                if !was_required && is_required {
+                    // Clients that omitted the field are now rejected
+                    let breaking = direction == Direction::Request;
+                    changes.push((prop_location.clone(), SchemaChangeKind::PropertyBecameRequired, breaking, format!("property '{}' became required", name)));
+                } else if was_required && !is_required {
+                    // Clients can no longer rely on the field being present
+                    let breaking = direction == Direction::Response;
+                    changes.push((prop_location.clone(), SchemaChangeKind::PropertyBecameOptional, breaking, format!("property '{}' became optional", name)));
+                }
+                diff_schema(&prop_location, old_prop, new_prop, direction, changes);
+            }
+            None => {
+                // Clients must start sending a new required request field
+                let breaking = direction == Direction::Request && new_required.contains(name.as_str());
+                changes.push((prop_location, SchemaChangeKind::PropertyAdded, breaking, format!("property '{}' added", name)));
+            }
+        }
+    }
+
+    for name in old_props.keys() {
+        if !new_props.contains_key(name) {
+            // Clients reading a response field lose it; servers ignoring a request field is harmless
+            let breaking = direction == Direction::Response;
+            changes.push((format!("{}.{}", location, name), SchemaChangeKind::PropertyRemoved, breaking, format!("property '{}' removed", name)));
+        }
+    }
+}
+
+fn diff_variants(
+    location: &str,
+    old: &[&Value],
+    new: &[&Value],
+    direction: Direction,
+    changes: &mut Vec<(String, SchemaChangeKind, bool, String)>,
+) {
+    let old_types: BTreeSet<&str> = old.iter().filter_map(|v| schema_type(v)).collect();
+    let new_types: BTreeSet<&str> = new.iter().filter_map(|v| schema_type(v)).collect();
+
+    if old_types != new_types {
+        // New response types surprise readers; dropped request types reject senders
+        let breaking = match direction {
+            Direction::Response => new_types.difference(&old_types).next().is_some(),
+            Direction::Request => old_types.difference(&new_types).next().is_some(),
+        };
+        changes.push((
+            location.to_string(),
+            SchemaChangeKind::TypeChanged,
+            breaking,
+            format!(
+                "type changed from {} to {}",
+                old_types.iter().copied().collect::<Vec<_>>().join(" | "),
+                new_types.iter().copied().collect::<Vec<_>>().join(" | ")
+            ),
+        ));
+    }
+
+    // Compare the shapes of the variants both versions share
+    for schema_type_name in old_types.intersection(&new_types) {
+        let old_variant = old.iter().find(|v| schema_type(v) == Some(schema_type_name));
+        let new_variant = new.iter().find(|v| schema_type(v) == Some(schema_type_name));
+        if let (Some(old_variant), Some(new_variant)) = (old_variant, new_variant) {
+            diff_schema(location, old_variant, new_variant, direction, changes);
+        }
+    }
+}
+
+fn variants(schema: &Value) -> Vec<&Value> {
+    match schema.get("oneOf").and_then(|v| v.as_array()) {
+        Some(variants) => variants.iter().collect(),
+        None => vec![schema],
+    }
+}
+
+fn schema_type(schema: &Value) -> Option<&str> {
+    schema.get("type").and_then(|t| t.as_str())
+}
+
+fn required_set(schema: &Value) -> BTreeSet<&str> {
+    schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn changes_for(old: Value, new: Value, direction: Direction) -> Vec<(String, SchemaChangeKind, bool, String)> {
+        let mut changes = Vec::new();
+        diff_schema("body", &old, &new, direction, &mut changes);
+        changes
+    }
+
+    #[test]
+    fn response_field_gaining_a_type_is_breaking() {
+        let old = json!({"type": "object", "properties": {"id": {"type": "integer"}}});
+        let new = json!({"type": "object", "properties": {"id": {"oneOf": [{"type": "integer"}, {"type": "string"}]}}});
+
+        let changes = changes_for(old, new, Direction::Response);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "body.id");
+        assert_eq!(changes[0].1, SchemaChangeKind::TypeChanged);
+        assert!(changes[0].2);
+    }
+
+    #[test]
+    fn request_field_gaining_a_type_is_not_breaking() {
+        let old = json!({"oneOf": [{"type": "integer"}, {"type": "string"}]});
+        let new = json!({"oneOf": [{"type": "integer"}, {"type": "string"}, {"type": "boolean"}]});
+
+        let changes = changes_for(old, new, Direction::Request);
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].2);
+    }
+
+    #[test]
+    fn shared_object_variants_are_compared() {
+        let old = json!({"oneOf": [{"type": "string"}, {"type": "object", "properties": {"a": {"type": "string"}}}]});
+        let new = json!({"oneOf": [{"type": "string"}, {"type": "object", "properties": {}}]});
+
+        let changes = changes_for(old, new, Direction::Response);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "body.a");
+        assert_eq!(changes[0].1, SchemaChangeKind::PropertyRemoved);
+    }
+
+    #[test]
+    fn required_changes_break_the_side_that_relies_on_them() {
+        let optional = json!({"type": "object", "properties": {"email": {"type": "string"}}});
+        let required = json!({"type": "object", "properties": {"email": {"type": "string"}}, "required": ["email"]});
+
+        let tightened = changes_for(optional.clone(), required.clone(), Direction::Request);
+        assert_eq!(tightened[0].1, SchemaChangeKind::PropertyBecameRequired);
+        assert!(tightened[0].2);
+        assert!(!changes_for(optional.clone(), required.clone(), Direction::Response)[0].2);
+
+        let loosened = changes_for(required.clone(), optional.clone(), Direction::Response);
+        assert_eq!(loosened[0].1, SchemaChangeKind::PropertyBecameOptional);
+        assert!(loosened[0].2);
+        assert!(!changes_for(required, optional, Direction::Request)[0].2);
+    }
+
+    #[test]
+    fn identical_schemas_have_no_changes() {
+        let schema = json!({"type": "object", "properties": {"id": {"oneOf": [{"type": "integer"}, {"type": "string"}]}}, "required": ["id"]});
+        assert!(changes_for(schema.clone(), schema, Direction::Response).is_empty());
+    }
+}
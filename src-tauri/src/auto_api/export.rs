@@ -0,0 +1,609 @@
+// Export of discovered APIs as OpenAPI 3.1 YAML and Postman v2.1 collections
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use super::{extract_path_from_url, extract_path_parameters, ApiDiscoveryLog, OpenApiOperation, OpenApiSpec, RedactionPipeline};
+
+const POSTMAN_SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Header names whose values are replaced by collection variables.
+const SECRET_HEADERS: &[(&str, &str)] = &[
+    ("authorization", "authToken"),
+    ("proxy-authorization", "proxyAuthToken"),
+    ("cookie", "cookie"),
+    ("x-api-key", "apiKey"),
+    ("api-key", "apiKey"),
+    ("x-auth-token", "authToken"),
+    ("x-csrf-token", "csrfToken"),
+    ("x-xsrf-token", "csrfToken"),
+];
+
+/// Query parameter and body field names treated as secrets.
+const SECRET_FIELDS: &[&str] = &[
+    "token", "access_token", "refresh_token", "id_token", "api_key", "apikey", "key",
+    "secret", "client_secret", "password", "passwd", "signature", "sig", "session", "sessionid",
+];
+
+/// Converts the internal spec into an OpenAPI 3.1 document.
+pub fn to_openapi_31(spec: &OpenApiSpec) -> Value {
+    let mut paths = Map::new();
+    let mut sorted_paths: Vec<_> = spec.paths.iter().collect();
+    sorted_paths.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (path, path_item) in sorted_paths {
+        let mut item = Map::new();
+        for (method, operation) in path_item.operations() {
+            item.insert(method.to_string(), operation_to_31(operation));
+        }
+        paths.insert(path.clone(), Value::Object(item));
+    }
+
+    let mut document = json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": spec.info.title,
+            "version": spec.info.version,
+        },
+        "servers": spec.servers.iter().map(|s| {
+            let mut server = json!({"url": s.url});
+            if let Some(description) = &s.description {
+                server["description"] = json!(description);
+            }
+            server
+        }).collect::<Vec<_>>(),
+        "paths": paths,
+    });
+
+    if let Some(description) = &spec.info.description {
+        document["info"]["description"] = json!(description);
+    }
+    if let Some(schemas) = spec.components.as_ref().and_then(|c| c.schemas.as_ref()) {
+        if !schemas.is_empty() {
+            let schemas: Map<String, Value> = schemas.iter().map(|(k, v)| (k.clone(), schema_to_31(v))).collect();
+            document["components"] = json!({"schemas": schemas});
+        }
+    }
+
+    document
+}
+
+pub fn to_openapi_31_yaml(spec: &OpenApiSpec) -> Result<String, serde_yaml::Error> {
+    serde_yaml::to_string(&to_openapi_31(spec))
+}
+
+fn operation_to_31(operation: &OpenApiOperation) -> Value {
+    let mut op = Map::new();
+
+    if let Some(operation_id) = &operation.operation_id {
+        op.insert("operationId".to_string(), json!(operation_id));
+    }
+    if let Some(summary) = &operation.summary {
+        op.insert("summary".to_string(), json!(summary));
+    }
+    if let Some(description) = &operation.description {
+        op.insert("description".to_string(), json!(description));
+    }
+    if !operation.tags.is_empty() {
+        op.insert("tags".to_string(), json!(operation.tags));
+    }
+    if !operation.parameters.is_empty() {
+        op.insert("parameters".to_string(), Value::Array(operation.parameters.iter().map(|p| json!({
+            "name": p.name,
+            "in": p.in_,
+            "required": p.required,
+            "schema": schema_to_31(&p.schema),
+        })).collect()));
+    }
+    if let Some(body) = &operation.request_body {
+        op.insert("requestBody".to_string(), json!({
+            "required": body.required.unwrap_or(false),
+            "content": content_to_31(&body.content),
+        }));
+    }
+
+    let mut responses = Map::new();
+    for (status, response) in &operation.responses {
+        let mut entry = json!({"description": response.description});
+        if let Some(content) = &response.content {
+            entry["content"] = content_to_31(content);
+        }
+        responses.insert(status.clone(), entry);
+    }
+    op.insert("responses".to_string(), Value::Object(responses));
+
+    for (key, value) in &operation.extensions {
+        op.insert(key.clone(), value.clone());
+    }
+
+    Value::Object(op)
+}
+
+fn content_to_31(content: &HashMap<String, super::OpenApiMediaType>) -> Value {
+    Value::Object(content.iter().map(|(media_type, media)| {
+        (media_type.clone(), json!({"schema": schema_to_31(&media.schema)}))
+    }).collect())
+}
+
+/// OpenAPI 3.1 is JSON Schema 2020-12: `nullable` becomes a `null` member of `type`.
+pub(crate) fn schema_to_31(schema: &Value) -> Value {
+    match schema {
+        Value::Object(obj) => {
+            let mut out = Map::new();
+            let nullable = obj.get("nullable").and_then(|n| n.as_bool()).unwrap_or(false);
+
+            for (key, value) in obj {
+                match key.as_str() {
+                    "nullable" => {}
+                    "properties" => {
+                        let props = value.as_object().map(|props| {
+                            props.iter().map(|(k, v)| (k.clone(), schema_to_31(v))).collect::<Map<_, _>>()
+                        }).unwrap_or_default();
+                        out.insert(key.clone(), Value::Object(props));
+                    }
+                    "items" => {
+                        out.insert(key.clone(), schema_to_31(value));
+                    }
+                    "oneOf" | "anyOf" | "allOf" => {
+                        let variants = value.as_array().map(|v| v.iter().map(schema_to_31).collect()).unwrap_or_default();
+                        out.insert(key.clone(), Value::Array(variants));
+                    }
+                    _ => {
+                        out.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+
+            if nullable {
+                if let Some(Value::Array(variants)) = out.get_mut("oneOf") {
+                    variants.push(json!({"type": "null"}));
+                    return Value::Object(out);
+                }
+                match out.get("type").cloned() {
+                    Some(Value::String(t)) => {
+                        out.insert("type".to_string(), json!([t, "null"]));
+                    }
+                    None => {
+                        out.insert("type".to_string(), json!("null"));
+                    }
+                    _ => {}
+                }
+            }
+
+            Value::Object(out)
+        }
+        other => other.clone(),
+    }
+}
+
+/// Collects secrets found in samples and maps them to collection variables;
+/// whatever else looks sensitive goes through the capture redactor.
+struct SecretVariables<'a> {
+    variables: BTreeMap<String, String>,
+    redactor: &'a RedactionPipeline,
+}
+
+impl<'a> SecretVariables<'a> {
+    fn new(redactor: &'a RedactionPipeline) -> Self {
+        Self { variables: BTreeMap::new(), redactor }
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.redactor.redact_text(text, "export", &mut Vec::new())
+    }
+    fn placeholder(&mut self, name: &str) -> String {
+        self.variables.entry(name.to_string()).or_default();
+        format!("{{{{{}}}}}", name)
+    }
+
+    fn header_value(&mut self, name: &str, value: &str) -> String {
+        let lower = name.to_lowercase();
+        match SECRET_HEADERS.iter().find(|(header, _)| *header == lower) {
+            Some((_, variable)) => {
+                // Keep the auth scheme visible so the collection stays self-explanatory
+                match value.split_once(' ') {
+                    Some((scheme, _)) if lower.ends_with("authorization") => {
+                        format!("{} {}", scheme, self.placeholder(variable))
+                    }
+                    _ => self.placeholder(variable),
+                }
+            }
+            None => value.to_string(),
+        }
+    }
+
+    fn field_value(&mut self, name: &str, value: &str) -> String {
+        if is_secret_field(name) {
+            self.placeholder(&to_camel_case(name))
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn scrub_json(&mut self, value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(map.iter().map(|(k, v)| {
+                let scrubbed = if is_secret_field(k) && !v.is_object() && !v.is_array() {
+                    Value::String(self.placeholder(&to_camel_case(k)))
+                } else {
+                    self.scrub_json(v)
+                };
+                (k.clone(), scrubbed)
+            }).collect()),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.scrub_json(v)).collect()),
+            Value::String(text) => Value::String(self.redact(text)),
+            other => other.clone(),
+        }
+    }
+
+    /// Form bodies get the same field handling as query strings; other text is redacted as a whole.
+    fn scrub_text(&mut self, body: &str) -> String {
+        if !body.contains('=') || body.contains(char::is_whitespace) {
+            return self.redact(body);
+        }
+        let pairs: Vec<(String, String)> = url::form_urlencoded::parse(body.as_bytes())
+            .map(|(name, value)| {
+                let value = if is_secret_field(&name) { self.field_value(&name, &value) } else { self.redact(&value) };
+                (name.into_owned(), value)
+            })
+            .collect();
+        url::form_urlencoded::Serializer::new(String::new()).extend_pairs(pairs).finish()
+    }
+}
+
+fn is_secret_field(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    SECRET_FIELDS.contains(&lower.as_str())
+}
+
+fn to_camel_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper_next = false;
+    for c in name.chars() {
+        if c == '_' || c == '-' {
+            upper_next = true;
+        } else if upper_next {
+            out.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Builds a Postman v2.1 collection with one request per operation, using the
+/// most recent captured sample of that operation as the example.
+pub fn to_postman_collection(domain: &str, spec: &OpenApiSpec, logs: &[ApiDiscoveryLog], redactor: &RedactionPipeline) -> Value {
+    let mut secrets = SecretVariables::new(redactor);
+
+    // Latest sample per (METHOD, templated path)
+    let mut samples: HashMap<(String, String), &ApiDiscoveryLog> = HashMap::new();
+    for log in logs {
+        let (clean_path, _) = extract_path_parameters(&extract_path_from_url(&log.url));
+        let key = (log.method.to_uppercase(), clean_path);
+        match samples.get(&key) {
+            Some(existing) if existing.timestamp >= log.timestamp => {}
+            _ => {
+                samples.insert(key, log);
+            }
+        }
+    }
+
+    // One folder per top-level path segment
+    let mut folders: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let mut sorted_paths: Vec<_> = spec.paths.iter().collect();
+    sorted_paths.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (path, path_item) in sorted_paths {
+        for (method, operation) in path_item.operations() {
+            let method = method.to_uppercase();
+            let sample = samples.get(&(method.clone(), path.clone())).copied();
+            let item = postman_item(&method, path, operation, sample, &mut secrets);
+
+            let folder = path.split('/').find(|s| !s.is_empty() && !s.starts_with('{')).unwrap_or("root");
+            folders.entry(folder.to_string()).or_default().push(item);
+        }
+    }
+
+    let mut variables = vec![json!({"key": "baseUrl", "value": format!("https://{}", domain), "type": "string"})];
+    variables.extend(secrets.variables.keys().map(|key| json!({"key": key, "value": "", "type": "secret"})));
+
+    json!({
+        "info": {
+            "_postman_id": Uuid::new_v4(),
+            "name": spec.info.title,
+            "description": spec.info.description,
+            "schema": POSTMAN_SCHEMA,
+        },
+        "item": folders.into_iter().map(|(name, items)| json!({"name": name, "item": items})).collect::<Vec<_>>(),
+        "variable": variables,
+    })
+}
+
+fn postman_item(
+    method: &str,
+    path: &str,
+    operation: &OpenApiOperation,
+    sample: Option<&ApiDiscoveryLog>,
+    secrets: &mut SecretVariables,
+) -> Value {
+    let path_segments: Vec<String> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(param) => format!(":{}", param),
+            None => s.to_string(),
+        })
+        .collect();
+
+    let path_variables: Vec<Value> = operation.parameters.iter()
+        .filter(|p| p.in_ == "path")
+        .map(|p| {
+            let example = sample.and_then(|s| sample_path_value(&s.url, path, &p.name)).unwrap_or_default();
+            json!({"key": p.name, "value": example})
+        })
+        .collect();
+
+    let query: Vec<Value> = match sample {
+        Some(s) => url::Url::parse(&s.url)
+            .map(|u| u.query_pairs().map(|(k, v)| json!({"key": k, "value": secrets.field_value(&k, &v)})).collect())
+            .unwrap_or_default(),
+        None => operation.parameters.iter()
+            .filter(|p| p.in_ == "query")
+            .map(|p| json!({"key": p.name, "value": "", "disabled": !p.required}))
+            .collect(),
+    };
+
+    let headers: Vec<Value> = sample
+        .and_then(|s| s.headers.as_object())
+        .map(|headers| headers.iter()
+            .filter(|(name, _)| !is_transport_header(name))
+            .filter_map(|(name, value)| value.as_str().map(|v| json!({"key": name, "value": secrets.header_value(name, v)})))
+            .collect())
+        .unwrap_or_default();
+
+    let raw_query = query.iter()
+        .filter_map(|q| Some(format!("{}={}", q["key"].as_str()?, q["value"].as_str()?)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let raw_url = format!(
+        "{{{{baseUrl}}}}/{}{}",
+        path_segments.join("/"),
+        if raw_query.is_empty() { String::new() } else { format!("?{}", raw_query) }
+    );
+
+    let mut request = json!({
+        "method": method,
+        "header": headers,
+        "url": {
+            "raw": raw_url,
+            "host": ["{{baseUrl}}"],
+            "path": path_segments,
+            "query": query,
+            "variable": path_variables,
+        },
+    });
+    if let Some(description) = &operation.description {
+        request["description"] = json!(description);
+    }
+    if let Some(body) = sample.and_then(|s| s.request_body.as_deref()) {
+        request["body"] = postman_body(body, secrets);
+    }
+
+    let mut responses = Vec::new();
+    if let Some(sample) = sample {
+        let status = sample.response_status.unwrap_or(200);
+        let response_headers: Vec<Value> = sample.response_headers.as_ref()
+            .and_then(|h| h.as_object())
+            .map(|h| h.iter()
+                .filter(|(name, _)| !name.eq_ignore_ascii_case("set-cookie"))
+                .filter_map(|(k, v)| v.as_str().map(|v| json!({"key": k, "value": v})))
+                .collect())
+            .unwrap_or_default();
+
+        responses.push(json!({
+            "name": format!("Captured {} response", status),
+            "originalRequest": request.clone(),
+            "status": status_text(status),
+            "code": status,
+            "_postman_previewlanguage": "json",
+            "header": response_headers,
+            "body": sample.response_body.as_deref().map(|b| scrub_body(b, secrets)).unwrap_or_default(),
+        }));
+    }
+
+    json!({
+        "name": operation.summary.clone().unwrap_or_else(|| format!("{} {}", method, path)),
+        "request": request,
+        "response": responses,
+    })
+}
+
+fn postman_body(body: &str, secrets: &mut SecretVariables) -> Value {
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => json!({
+            "mode": "raw",
+            "raw": serde_json::to_string_pretty(&secrets.scrub_json(&value)).unwrap_or_default(),
+            "options": {"raw": {"language": "json"}},
+        }),
+        Err(_) => json!({"mode": "raw", "raw": secrets.scrub_text(body)}),
+    }
+}
+
+fn scrub_body(body: &str, secrets: &mut SecretVariables) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => serde_json::to_string_pretty(&secrets.scrub_json(&value)).unwrap_or_default(),
+        Err(_) => secrets.scrub_text(body),
+    }
+}
+
+fn sample_path_value(url: &str, template: &str, param: &str) -> Option<String> {
+    let actual = extract_path_from_url(url);
+    let placeholder = format!("{{{}}}", param);
+    template
+        .split('/')
+        .zip(actual.split('/'))
+        .find(|(t, _)| *t == placeholder)
+        .map(|(_, value)| value.to_string())
+}
+
+fn is_transport_header(name: &str) -> bool {
+    matches!(
+        name.to_lowercase().as_str(),
+        "host" | "content-length" | "connection" | "accept-encoding" | "transfer-encoding"
+    )
+}
+
+fn status_text(status: i32) -> String {
+    u16::try_from(status)
+        .ok()
+        .and_then(|s| axum::http::StatusCode::from_u16(s).ok())
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auto_api::RedactionConfig;
+    use chrono::{TimeZone, Utc};
+
+    fn spec() -> OpenApiSpec {
+        serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "shop.example.com API", "version": "1.0.0", "description": "Discovered from traffic"},
+            "servers": [{"url": "https://shop.example.com", "description": null}],
+            "paths": {
+                "/api/users/{id}": {
+                    "get": {
+                        "operation_id": "getUser",
+                        "summary": "Get a user",
+                        "description": null,
+                        "parameters": [{"name": "id", "in_": "path", "required": true, "schema": {"type": "integer"}}],
+                        "request_body": null,
+                        "responses": {"200": {"description": "OK", "content": {"application/json": {"schema": {
+                            "type": "object",
+                            "properties": {"nickname": {"type": "string", "nullable": true}, "id": {"oneOf": [{"type": "integer"}, {"type": "string"}], "nullable": true}}
+                        }}}}},
+                        "tags": ["users"],
+                        "extensions": {"x-rate-limit": {"retryAfter": true}}
+                    }
+                },
+                "/api/login": {
+                    "post": {
+                        "operation_id": "login",
+                        "summary": null,
+                        "description": null,
+                        "parameters": [],
+                        "request_body": {"content": {"application/x-www-form-urlencoded": {"schema": {"type": "object"}}}, "required": true},
+                        "responses": {"200": {"description": "OK", "content": null}},
+                        "tags": []
+                    }
+                }
+            },
+            "components": null
+        }))
+        .unwrap()
+    }
+
+    fn log(method: &str, url: &str, headers: Value, request_body: Option<&str>, response_body: Option<&str>) -> ApiDiscoveryLog {
+        ApiDiscoveryLog {
+            id: Uuid::new_v4(),
+            user_id: None,
+            session_id: None,
+            domain: "shop.example.com".to_string(),
+            method: method.to_string(),
+            url: url.to_string(),
+            headers,
+            request_body: request_body.map(str::to_string),
+            response_status: Some(200),
+            response_headers: Some(json!({"Content-Type": "application/json", "Set-Cookie": "sid=abc"})),
+            response_body: response_body.map(str::to_string),
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            processed: false,
+            api_spec: None,
+            duration_ms: None,
+            redactions: json!([]),
+        }
+    }
+
+    #[test]
+    fn openapi_31_yaml_has_the_31_shape() {
+        let yaml = to_openapi_31_yaml(&spec()).unwrap();
+        let document: Value = serde_yaml::from_str(&yaml).unwrap();
+
+        assert_eq!(document["openapi"], "3.1.0");
+        assert_eq!(document["info"]["description"], "Discovered from traffic");
+        assert_eq!(document["servers"], json!([{"url": "https://shop.example.com"}]));
+        let get = &document["paths"]["/api/users/{id}"]["get"];
+        assert_eq!(get["operationId"], "getUser");
+        assert_eq!(get["parameters"][0], json!({"name": "id", "in": "path", "required": true, "schema": {"type": "integer"}}));
+        assert_eq!(get["x-rate-limit"], json!({"retryAfter": true}));
+
+        let properties = &get["responses"]["200"]["content"]["application/json"]["schema"]["properties"];
+        assert_eq!(properties["nickname"], json!({"type": ["string", "null"]}));
+        assert_eq!(properties["id"], json!({"oneOf": [{"type": "integer"}, {"type": "string"}, {"type": "null"}]}));
+        assert!(!yaml.contains("nullable"));
+
+        let login = &document["paths"]["/api/login"]["post"];
+        assert_eq!(login["requestBody"]["required"], true);
+        assert_eq!(login["responses"]["200"], json!({"description": "OK"}));
+    }
+
+    #[test]
+    fn postman_collection_turns_secrets_into_variables() {
+        let redactor = RedactionPipeline::new(RedactionConfig::default());
+        let logs = [log(
+            "GET",
+            "https://shop.example.com/api/users/42?access_token=abc&expand=orders",
+            json!({"Authorization": "Bearer abc123", "Accept": "application/json", "Host": "shop.example.com"}),
+            None,
+            Some(r#"{"id":42,"email":"jane@example.com","password":"hunter2"}"#),
+        )];
+        let collection = to_postman_collection("shop.example.com", &spec(), &logs, &redactor);
+
+        assert_eq!(collection["info"]["schema"], POSTMAN_SCHEMA);
+        let folders: Vec<&str> = collection["item"].as_array().unwrap().iter().map(|f| f["name"].as_str().unwrap()).collect();
+        assert_eq!(folders, vec!["api"]);
+
+        let get = collection["item"][0]["item"].as_array().unwrap().iter().find(|i| i["request"]["method"] == "GET").unwrap();
+        let request = &get["request"];
+        assert_eq!(request["url"]["raw"], "{{baseUrl}}/api/users/:id?access_token={{accessToken}}&expand=orders");
+        assert_eq!(request["url"]["variable"], json!([{"key": "id", "value": "42"}]));
+        let headers = request["header"].as_array().unwrap();
+        assert!(headers.contains(&json!({"key": "Authorization", "value": "Bearer {{authToken}}"})));
+        assert!(headers.iter().all(|h| h["key"] != "Host"));
+
+        let response = &get["response"][0];
+        assert_eq!(response["code"], 200);
+        assert!(response["header"].as_array().unwrap().iter().all(|h| h["key"] != "Set-Cookie"));
+        let body: Value = serde_json::from_str(response["body"].as_str().unwrap()).unwrap();
+        assert_eq!(body, json!({"id": 42, "email": "[REDACTED:email]", "password": "{{password}}"}));
+
+        let variables: Vec<&str> = collection["variable"].as_array().unwrap().iter().map(|v| v["key"].as_str().unwrap()).collect();
+        assert_eq!(variables, vec!["baseUrl", "accessToken", "authToken", "password"]);
+    }
+
+    #[test]
+    fn form_and_text_bodies_are_redacted() {
+        let redactor = RedactionPipeline::new(RedactionConfig::default());
+        let logs = [log(
+            "POST",
+            "https://shop.example.com/api/login",
+            json!({"Content-Type": "application/x-www-form-urlencoded"}),
+            Some("username=jane%40example.com&password=hunter2&remember=1"),
+            Some("Welcome back jane@example.com, card 4111 1111 1111 1111 is on file"),
+        )];
+        let collection = to_postman_collection("shop.example.com", &spec(), &logs, &redactor);
+        let login = &collection["item"][0]["item"].as_array().unwrap().iter().find(|i| i["request"]["method"] == "POST").unwrap();
+
+        let form = login["request"]["body"]["raw"].as_str().unwrap();
+        assert_eq!(form, "username=%5BREDACTED%3Aemail%5D&password=%7B%7Bpassword%7D%7D&remember=1");
+        assert_eq!(
+            login["response"][0]["body"],
+            "Welcome back [REDACTED:email], card [REDACTED:card_number] is on file"
+        );
+    }
+}
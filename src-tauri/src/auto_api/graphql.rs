@@ -0,0 +1,848 @@
+// GraphQL traffic detection and partial schema reconstruction for Auto-API
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{extract_path_from_url, header_value, ApiDiscoveryLog, OpenApiPathItem};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphqlSchema {
+    pub endpoints: Vec<String>,
+    pub operations: Vec<GraphqlOperationSummary>,
+    pub sdl: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphqlOperationSummary {
+    pub key: String, // operationName, or "anonymous_<hash>" when unnamed
+    pub operation_name: Option<String>,
+    pub operation_type: String,
+    pub query_hash: String,
+    pub endpoint: String,
+    pub sample_count: usize,
+    pub variables: Vec<String>,
+    pub root_fields: Vec<String>,
+}
+
+/// One GraphQL request extracted from a captured log; batched requests yield several.
+#[derive(Debug, Clone)]
+struct GraphqlRequest {
+    query: Option<String>,
+    operation_name: Option<String>,
+    persisted_hash: Option<String>,
+}
+
+pub fn is_graphql_log(log: &ApiDiscoveryLog) -> bool {
+    let path = extract_path_from_url(&log.url).to_lowercase();
+    if path.ends_with("/graphql") || path.ends_with("/gql") {
+        return true;
+    }
+    if header_value(Some(&log.headers), "content-type").is_some_and(|ct| ct.starts_with("application/graphql")) {
+        return true;
+    }
+    !extract_requests(log).is_empty()
+}
+
+fn extract_requests(log: &ApiDiscoveryLog) -> Vec<GraphqlRequest> {
+    let from_value = |value: &Value| -> Option<GraphqlRequest> {
+        let query = value.get("query").and_then(|q| q.as_str()).map(|q| q.to_string());
+        let persisted_hash = value
+            .pointer("/extensions/persistedQuery/sha256Hash")
+            .and_then(|h| h.as_str())
+            .map(|h| h.to_string());
+        if query.as_deref().is_none_or(|q| !looks_like_graphql(q)) && persisted_hash.is_none() {
+            return None;
+        }
+        Some(GraphqlRequest {
+            query,
+            operation_name: value.get("operationName").and_then(|n| n.as_str()).map(|n| n.to_string()),
+            persisted_hash,
+        })
+    };
+
+    if let Some(body) = &log.request_body {
+        if header_value(Some(&log.headers), "content-type").is_some_and(|ct| ct.starts_with("application/graphql")) {
+            return vec![GraphqlRequest { query: Some(body.clone()), operation_name: None, persisted_hash: None }];
+        }
+        return match serde_json::from_str::<Value>(body) {
+            Ok(Value::Array(batch)) => batch.iter().filter_map(from_value).collect(),
+            Ok(value) => from_value(&value).into_iter().collect(),
+            Err(_) => Vec::new(),
+        };
+    }
+
+    // GET requests carry the document in the query string
+    let Ok(url) = url::Url::parse(&log.url) else {
+        return Vec::new();
+    };
+    let params: HashMap<String, String> = url.query_pairs().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    let value = json!({
+        "query": params.get("query"),
+        "operationName": params.get("operationName"),
+        "extensions": params.get("extensions").and_then(|e| serde_json::from_str::<Value>(e).ok()),
+    });
+    from_value(&value).into_iter().collect()
+}
+
+fn looks_like_graphql(query: &str) -> bool {
+    let trimmed = query.trim_start();
+    trimmed.starts_with('{')
+        || ["query", "mutation", "subscription", "fragment"].iter().any(|kw| trimmed.starts_with(kw))
+}
+
+/// Deepest selection, list or fragment nesting accepted from captured
+/// documents; real queries stay far below this.
+const MAX_NESTING_DEPTH: usize = 32;
+
+fn query_hash(query: &str) -> String {
+    let normalized = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+/// Groups GraphQL traffic by operation and rebuilds a partial SDL from the
+/// selection sets and the `data` returned for them.
+pub fn reconstruct_schema(logs: &[ApiDiscoveryLog]) -> Option<GraphqlSchema> {
+    let mut registry = TypeRegistry::default();
+    let mut operations: BTreeMap<String, GraphqlOperationSummary> = BTreeMap::new();
+    let mut endpoints: Vec<String> = Vec::new();
+
+    for log in logs.iter().filter(|log| is_graphql_log(log)) {
+        let endpoint = extract_path_from_url(&log.url);
+        if !endpoints.contains(&endpoint) {
+            endpoints.push(endpoint.clone());
+        }
+
+        let responses: Vec<Value> = match log.response_body.as_deref().and_then(|b| serde_json::from_str(b).ok()) {
+            Some(Value::Array(batch)) => batch,
+            Some(single) => vec![single],
+            None => Vec::new(),
+        };
+
+        for (index, request) in extract_requests(log).into_iter().enumerate() {
+            let document = request.query.as_deref().and_then(|q| parse_document(q).ok());
+            let hash = request
+                .persisted_hash
+                .clone()
+                .or_else(|| request.query.as_deref().map(query_hash))
+                .unwrap_or_default();
+
+            let operation = document.as_ref().and_then(|doc| {
+                doc.operations
+                    .iter()
+                    .find(|op| request.operation_name.is_none() || op.name == request.operation_name)
+            });
+            let operation_name = request.operation_name.clone().or_else(|| operation.and_then(|op| op.name.clone()));
+            let key = summary_key(&operation_name, &hash);
+
+            let summary = operations.entry(key.clone()).or_insert_with(|| GraphqlOperationSummary {
+                key,
+                operation_name: operation_name.clone(),
+                operation_type: operation.map(|op| op.op_type.clone()).unwrap_or_else(|| "query".to_string()),
+                query_hash: hash.clone(),
+                endpoint: endpoint.clone(),
+                sample_count: 0,
+                variables: operation
+                    .map(|op| op.variables.iter().map(|(name, ty)| format!("${}: {}", name, ty)).collect())
+                    .unwrap_or_default(),
+                root_fields: operation
+                    .map(|op| op.selection.iter().filter_map(|s| match s {
+                        Selection::Field(f) => Some(f.name.clone()),
+                        _ => None,
+                    }).collect())
+                    .unwrap_or_default(),
+            });
+            summary.sample_count += 1;
+
+            let (Some(document), Some(operation)) = (&document, operation) else {
+                continue;
+            };
+            let data = responses.get(index).and_then(|r| r.get("data")).cloned().unwrap_or(Value::Null);
+            let variables: HashMap<&str, &str> = operation.variables.iter().map(|(n, t)| (n.as_str(), t.as_str())).collect();
+            let root_type = match operation.op_type.as_str() {
+                "mutation" => "Mutation",
+                "subscription" => "Subscription",
+                _ => "Query",
+            };
+            let mut walk = Walk { document, variables: &variables, fragments: HashSet::new(), depth: 0 };
+            if let Err(e) = registry.record(root_type, &operation.selection, &data, &mut walk) {
+                tracing::debug!("Skipping schema inference for GraphQL operation {}: {}", summary_key(&operation_name, &hash), e);
+            }
+        }
+    }
+
+    if operations.is_empty() {
+        return None;
+    }
+
+    Some(GraphqlSchema {
+        endpoints,
+        operations: operations.into_values().collect(),
+        sdl: registry.to_sdl(),
+    })
+}
+
+fn summary_key(operation_name: &Option<String>, hash: &str) -> String {
+    // Persisted hashes come from the client, so truncate by character
+    operation_name.clone().unwrap_or_else(|| format!("anonymous_{}", hash.chars().take(12).collect::<String>()))
+}
+
+/// Annotates the collapsed GraphQL operations in an OpenAPI path map with the
+/// individual GraphQL operations observed behind them.
+pub fn annotate_paths(paths: &mut HashMap<String, OpenApiPathItem>, logs: &[ApiDiscoveryLog]) {
+    let Some(schema) = reconstruct_schema(logs) else {
+        return;
+    };
+
+    for endpoint in &schema.endpoints {
+        let Some(item) = paths.get_mut(endpoint) else {
+            continue;
+        };
+        let operations: Vec<Value> = schema
+            .operations
+            .iter()
+            .filter(|op| &op.endpoint == endpoint)
+            .map(|op| json!({
+                "name": op.key,
+                "type": op.operation_type,
+                "hash": op.query_hash,
+                "variables": op.variables,
+                "rootFields": op.root_fields,
+            }))
+            .collect();
+
+        for operation in [&mut item.post, &mut item.get].into_iter().flatten() {
+            operation.summary = Some(format!("GraphQL endpoint ({} operations)", operations.len()));
+            operation.tags = vec!["graphql".to_string()];
+            operation.extensions.insert("x-graphql-operations".to_string(), Value::Array(operations.clone()));
+        }
+    }
+}
+
+// --- Schema registry -------------------------------------------------------
+
+#[derive(Debug, Default)]
+struct TypeRegistry {
+    types: BTreeMap<String, BTreeMap<String, FieldDef>>,
+}
+
+/// Traversal state for one operation: the document it came from, its
+/// variable types, and the fragments and depth currently being expanded.
+struct Walk<'a> {
+    document: &'a Document,
+    variables: &'a HashMap<&'a str, &'a str>,
+    fragments: HashSet<String>,
+    depth: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+struct FieldDef {
+    args: BTreeMap<String, String>,
+    ty: Option<String>,
+}
+
+impl TypeRegistry {
+    fn record(&mut self, type_name: &str, selection: &[Selection], data: &Value, walk: &mut Walk) -> Result<(), String> {
+        if walk.depth >= MAX_NESTING_DEPTH {
+            return Err(format!("selection nested deeper than {} levels", MAX_NESTING_DEPTH));
+        }
+        walk.depth += 1;
+        let result = self.record_selection(type_name, selection, data, walk);
+        walk.depth -= 1;
+        result
+    }
+
+    fn record_selection(&mut self, type_name: &str, selection: &[Selection], data: &Value, walk: &mut Walk) -> Result<(), String> {
+        self.types.entry(type_name.to_string()).or_default();
+
+        for item in selection {
+            match item {
+                Selection::Field(field) => self.record_field(type_name, field, data, walk)?,
+                Selection::InlineFragment { type_condition, selection } => {
+                    let target = type_condition.as_deref().unwrap_or(type_name);
+                    if typename_matches(data, target) {
+                        self.record(target, selection, data, walk)?;
+                    }
+                }
+                Selection::FragmentSpread(name) => {
+                    let Some(fragment) = walk.document.fragments.get(name) else {
+                        continue;
+                    };
+                    if !typename_matches(data, &fragment.type_condition) {
+                        continue;
+                    }
+                    if !walk.fragments.insert(name.clone()) {
+                        return Err(format!("fragment '{}' spreads itself", name));
+                    }
+                    let result = self.record(&fragment.type_condition, &fragment.selection, data, walk);
+                    walk.fragments.remove(name);
+                    result?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn record_field(&mut self, type_name: &str, field: &Field, data: &Value, walk: &mut Walk) -> Result<(), String> {
+        if field.name.starts_with("__") {
+            return Ok(());
+        }
+
+        let value = data.get(field.alias.as_deref().unwrap_or(&field.name)).unwrap_or(&Value::Null);
+        let observed = if field.selection.is_empty() {
+            scalar_type(&field.name, value)
+        } else {
+            let object_type = object_type_name(&field.name, value);
+            for element in elements(value) {
+                self.record(&object_type, &field.selection, element, walk)?;
+            }
+            if element_count(value) == 0 {
+                self.types.entry(object_type.clone()).or_default();
+            }
+            Some(object_type)
+        }
+        .map(|base| if value.is_array() { format!("[{}]", base) } else { base });
+
+        let def = self.types.entry(type_name.to_string()).or_default().entry(field.name.clone()).or_default();
+        if def.ty.is_none() {
+            def.ty = observed;
+        }
+        for (arg, arg_value) in &field.args {
+            let arg_type = match arg_value {
+                ArgValue::Variable(var) => walk.variables.get(var.as_str()).map(|t| t.to_string()),
+                ArgValue::Literal(ty) => Some(ty.clone()),
+            };
+            if let Some(arg_type) = arg_type {
+                def.args.entry(arg.clone()).or_insert(arg_type);
+            }
+        }
+        Ok(())
+    }
+
+    fn to_sdl(&self) -> String {
+        let mut sdl = String::new();
+        for (type_name, fields) in &self.types {
+            sdl.push_str(&format!("type {} {{\n", type_name));
+            for (field_name, def) in fields {
+                let args = if def.args.is_empty() {
+                    String::new()
+                } else {
+                    format!("({})", def.args.iter().map(|(n, t)| format!("{}: {}", n, t)).collect::<Vec<_>>().join(", "))
+                };
+                sdl.push_str(&format!("  {}{}: {}\n", field_name, args, def.ty.as_deref().unwrap_or("String")));
+            }
+            sdl.push_str("}\n\n");
+        }
+        sdl.trim_end().to_string() + "\n"
+    }
+}
+
+fn typename_matches(data: &Value, type_name: &str) -> bool {
+    first_element(data)
+        .and_then(|d| d.get("__typename"))
+        .and_then(|t| t.as_str())
+        .is_none_or(|t| t == type_name)
+}
+
+fn elements(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(elements).collect(),
+        Value::Object(_) => vec![value],
+        _ => Vec::new(),
+    }
+}
+
+fn element_count(value: &Value) -> usize {
+    elements(value).len()
+}
+
+fn first_element(value: &Value) -> Option<&Value> {
+    elements(value).into_iter().next()
+}
+
+fn object_type_name(field_name: &str, value: &Value) -> String {
+    if let Some(typename) = first_element(value).and_then(|v| v.get("__typename")).and_then(|t| t.as_str()) {
+        return typename.to_string();
+    }
+    let mut chars = field_name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => "Unknown".to_string(),
+    }
+}
+
+fn scalar_type(field_name: &str, value: &Value) -> Option<String> {
+    let sample = match value {
+        Value::Array(items) => items.iter().find(|v| !v.is_null())?,
+        other => other,
+    };
+    let ty = match sample {
+        Value::String(_) if field_name == "id" || field_name.ends_with("Id") => "ID",
+        Value::String(_) => "String",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "Int",
+        Value::Number(_) => "Float",
+        Value::Bool(_) => "Boolean",
+        Value::Object(_) => "JSON",
+        _ => return None,
+    };
+    Some(ty.to_string())
+}
+
+// --- Minimal GraphQL document parser ---------------------------------------
+
+#[derive(Debug, Default)]
+struct Document {
+    operations: Vec<Operation>,
+    fragments: HashMap<String, Fragment>,
+}
+
+#[derive(Debug)]
+struct Operation {
+    op_type: String,
+    name: Option<String>,
+    variables: Vec<(String, String)>,
+    selection: Vec<Selection>,
+}
+
+#[derive(Debug)]
+struct Fragment {
+    type_condition: String,
+    selection: Vec<Selection>,
+}
+
+#[derive(Debug)]
+enum Selection {
+    Field(Field),
+    InlineFragment { type_condition: Option<String>, selection: Vec<Selection> },
+    FragmentSpread(String),
+}
+
+#[derive(Debug)]
+struct Field {
+    alias: Option<String>,
+    name: String,
+    args: Vec<(String, ArgValue)>,
+    selection: Vec<Selection>,
+}
+
+#[derive(Debug)]
+enum ArgValue {
+    Variable(String),
+    Literal(String), // inferred GraphQL type of the literal
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Punct(char),
+    Spread,
+    Str,
+    Int,
+    Float,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() || c == ',' => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '.' if chars[i..].starts_with(&['.', '.', '.']) => {
+                tokens.push(Token::Spread);
+                i += 3;
+            }
+            '"' => {
+                let block = chars[i..].starts_with(&['"', '"', '"']);
+                i += if block { 3 } else { 1 };
+                loop {
+                    if i >= chars.len() {
+                        return Err("unterminated string".to_string());
+                    }
+                    if block && chars[i..].starts_with(&['"', '"', '"']) {
+                        i += 3;
+                        break;
+                    }
+                    if !block && chars[i] == '"' {
+                        i += 1;
+                        break;
+                    }
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                tokens.push(Token::Str);
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || matches!(chars[i], '.' | 'e' | 'E' | '+' | '-')) {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().collect();
+                tokens.push(if literal.contains(['.', 'e', 'E']) { Token::Float } else { Token::Int });
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Name(chars[start..i].iter().collect()));
+            }
+            '{' | '}' | '(' | ')' | '[' | ']' | ':' | '!' | '$' | '@' | '=' | '|' | '&' => {
+                tokens.push(Token::Punct(c));
+                i += 1;
+            }
+            other => return Err(format!("unexpected character '{}'", other)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+fn parse_document(source: &str) -> Result<Document, String> {
+    let mut parser = Parser { tokens: tokenize(source)?, pos: 0, depth: 0 };
+    let mut document = Document::default();
+
+    while parser.peek().is_some() {
+        match parser.peek() {
+            Some(Token::Punct('{')) => document.operations.push(Operation {
+                op_type: "query".to_string(),
+                name: None,
+                variables: Vec::new(),
+                selection: parser.selection_set()?,
+            }),
+            Some(Token::Name(kw)) if kw == "fragment" => {
+                parser.pos += 1;
+                let name = parser.name()?;
+                parser.expect_name("on")?;
+                let type_condition = parser.name()?;
+                parser.directives()?;
+                let selection = parser.selection_set()?;
+                document.fragments.insert(name, Fragment { type_condition, selection });
+            }
+            Some(Token::Name(kw)) if matches!(kw.as_str(), "query" | "mutation" | "subscription") => {
+                let op_type = kw.clone();
+                parser.pos += 1;
+                let name = match parser.peek() {
+                    Some(Token::Name(_)) => Some(parser.name()?),
+                    _ => None,
+                };
+                let variables = parser.variable_definitions()?;
+                parser.directives()?;
+                let selection = parser.selection_set()?;
+                document.operations.push(Operation { op_type, name, variables, selection });
+            }
+            other => return Err(format!("unexpected token {:?}", other)),
+        }
+    }
+
+    Ok(document)
+}
+
+impl Parser {
+    /// Runs a nested production, refusing documents nested past the limit.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(format!("document nested deeper than {} levels", MAX_NESTING_DEPTH));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) { Ok(()) } else { Err(format!("expected '{}'", c)) }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Name(name)) => Ok(name),
+            other => Err(format!("expected name, found {:?}", other)),
+        }
+    }
+
+    fn expect_name(&mut self, expected: &str) -> Result<(), String> {
+        let name = self.name()?;
+        if name == expected { Ok(()) } else { Err(format!("expected '{}'", expected)) }
+    }
+
+    fn variable_definitions(&mut self) -> Result<Vec<(String, String)>, String> {
+        let mut variables = Vec::new();
+        if !self.eat('(') {
+            return Ok(variables);
+        }
+        while !self.eat(')') {
+            self.expect('$')?;
+            let name = self.name()?;
+            self.expect(':')?;
+            let ty = self.type_ref()?;
+            if self.eat('=') {
+                self.value()?;
+            }
+            self.directives()?;
+            variables.push((name, ty));
+        }
+        Ok(variables)
+    }
+
+    fn type_ref(&mut self) -> Result<String, String> {
+        let mut ty = if self.eat('[') {
+            let inner = self.nested(Self::type_ref)?;
+            self.expect(']')?;
+            format!("[{}]", inner)
+        } else {
+            self.name()?
+        };
+        if self.eat('!') {
+            ty.push('!');
+        }
+        Ok(ty)
+    }
+
+    fn directives(&mut self) -> Result<(), String> {
+        while self.eat('@') {
+            self.name()?;
+            self.arguments()?;
+        }
+        Ok(())
+    }
+
+    fn arguments(&mut self) -> Result<Vec<(String, ArgValue)>, String> {
+        let mut args = Vec::new();
+        if !self.eat('(') {
+            return Ok(args);
+        }
+        while !self.eat(')') {
+            let name = self.name()?;
+            self.expect(':')?;
+            args.push((name, self.value()?));
+        }
+        Ok(args)
+    }
+
+    fn value(&mut self) -> Result<ArgValue, String> {
+        let value = match self.next() {
+            Some(Token::Punct('$')) => ArgValue::Variable(self.name()?),
+            Some(Token::Int) => ArgValue::Literal("Int".to_string()),
+            Some(Token::Float) => ArgValue::Literal("Float".to_string()),
+            Some(Token::Str) => ArgValue::Literal("String".to_string()),
+            Some(Token::Name(n)) if n == "true" || n == "false" => ArgValue::Literal("Boolean".to_string()),
+            Some(Token::Name(_)) => ArgValue::Literal("String".to_string()), // null or an enum value
+            Some(Token::Punct('[')) => {
+                let mut inner = "String".to_string();
+                while !self.eat(']') {
+                    if let ArgValue::Literal(ty) = self.nested(Self::value)? {
+                        inner = ty;
+                    }
+                }
+                ArgValue::Literal(format!("[{}]", inner))
+            }
+            Some(Token::Punct('{')) => {
+                while !self.eat('}') {
+                    self.name()?;
+                    self.expect(':')?;
+                    self.nested(Self::value)?;
+                }
+                ArgValue::Literal("JSON".to_string())
+            }
+            other => return Err(format!("unexpected value token {:?}", other)),
+        };
+        Ok(value)
+    }
+
+    fn selection_set(&mut self) -> Result<Vec<Selection>, String> {
+        self.nested(Self::selection_set_body)
+    }
+
+    fn selection_set_body(&mut self) -> Result<Vec<Selection>, String> {
+        self.expect('{')?;
+        let mut selection = Vec::new();
+
+        while !self.eat('}') {
+            if self.peek() == Some(&Token::Spread) {
+                self.pos += 1;
+                match self.peek() {
+                    Some(Token::Name(n)) if n == "on" => {
+                        self.pos += 1;
+                        let type_condition = Some(self.name()?);
+                        self.directives()?;
+                        selection.push(Selection::InlineFragment { type_condition, selection: self.selection_set()? });
+                    }
+                    Some(Token::Name(_)) => {
+                        let name = self.name()?;
+                        self.directives()?;
+                        selection.push(Selection::FragmentSpread(name));
+                    }
+                    _ => {
+                        self.directives()?;
+                        selection.push(Selection::InlineFragment { type_condition: None, selection: self.selection_set()? });
+                    }
+                }
+                continue;
+            }
+
+            let first = self.name()?;
+            let (alias, name) = if self.eat(':') { (Some(first), self.name()?) } else { (None, first) };
+            let args = self.arguments()?;
+            self.directives()?;
+            let nested = if self.peek() == Some(&Token::Punct('{')) { self.selection_set()? } else { Vec::new() };
+            selection.push(Selection::Field(Field { alias, name, args, selection: nested }));
+        }
+
+        Ok(selection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn graphql_log(request_body: Value, response_body: Value) -> ApiDiscoveryLog {
+        ApiDiscoveryLog {
+            id: Uuid::new_v4(),
+            user_id: None,
+            session_id: None,
+            domain: "api.example.com".to_string(),
+            method: "POST".to_string(),
+            url: "https://api.example.com/graphql".to_string(),
+            headers: json!({"content-type": "application/json"}),
+            request_body: Some(request_body.to_string()),
+            response_status: Some(200),
+            response_headers: None,
+            response_body: Some(response_body.to_string()),
+            timestamp: Utc::now(),
+            processed: false,
+            api_spec: None,
+            duration_ms: None,
+            redactions: json!([]),
+        }
+    }
+
+    #[test]
+    fn cyclic_fragments_do_not_recurse_forever() {
+        let query = "query Me { me { ...A } } fragment A on User { id ...B } fragment B on User { name ...A }";
+        let log = graphql_log(json!({"query": query}), json!({"data": {"me": {"id": "1", "name": "x"}}}));
+
+        let schema = reconstruct_schema(&[log]).expect("operation is still summarised");
+        assert_eq!(schema.operations[0].key, "Me");
+    }
+
+    #[test]
+    fn deeply_nested_documents_are_rejected() {
+        let query = format!("{}id{}", "{ a ".repeat(MAX_NESTING_DEPTH + 1), " }".repeat(MAX_NESTING_DEPTH + 1));
+        assert!(parse_document(&query).is_err());
+
+        let list = format!("{{ a(x: {}1{}) }}", "[".repeat(MAX_NESTING_DEPTH + 1), "]".repeat(MAX_NESTING_DEPTH + 1));
+        assert!(parse_document(&list).is_err());
+
+        let shallow = format!("{}id{}", "{ a ".repeat(4), " }".repeat(4));
+        assert!(parse_document(&shallow).is_ok());
+    }
+
+    #[test]
+    fn multibyte_persisted_hashes_are_truncated_by_character() {
+        let hash = "é".repeat(20);
+        let log = graphql_log(
+            json!({"extensions": {"persistedQuery": {"sha256Hash": hash}}}),
+            json!({"data": null}),
+        );
+
+        let schema = reconstruct_schema(&[log]).unwrap();
+        assert_eq!(schema.operations[0].key, format!("anonymous_{}", "é".repeat(12)));
+    }
+
+    #[test]
+    fn sdl_is_rebuilt_from_selections_and_data() {
+        let query = r#"
+            query GetUser($id: ID!) {
+                account: user(id: $id) {
+                    __typename
+                    id
+                    name
+                    posts(first: 10) { title score }
+                    ... on Admin { role }
+                }
+            }
+        "#;
+        let data = json!({"data": {"account": {
+            "__typename": "User",
+            "id": "u1",
+            "name": "Ada",
+            "posts": [{"title": "Hello", "score": 1.5}]
+        }}});
+        let schema = reconstruct_schema(&[graphql_log(json!({"query": query, "variables": {"id": "u1"}}), data)]).unwrap();
+
+        assert_eq!(schema.endpoints, vec!["/graphql"]);
+        // Without a __typename, object types are named after their field
+        assert_eq!(
+            schema.sdl,
+            "type Posts {\n  score: Float\n  title: String\n}\n\n\
+             type Query {\n  user(id: ID!): User\n}\n\n\
+             type User {\n  id: ID\n  name: String\n  posts(first: Int): [Posts]\n}\n"
+        );
+        let operation = &schema.operations[0];
+        assert_eq!(operation.variables, vec!["$id: ID!"]);
+        assert_eq!(operation.root_fields, vec!["user"]);
+    }
+
+    #[test]
+    fn operations_are_grouped_by_operation_name() {
+        let document = "query ListUsers { users { id } } mutation Rename($name: String!) { rename(name: $name) { id } }";
+        let logs = [
+            graphql_log(
+                json!([
+                    {"query": document, "operationName": "ListUsers"},
+                    {"query": document, "operationName": "Rename", "variables": {"name": "x"}}
+                ]),
+                json!([{"data": {"users": []}}, {"data": {"rename": {"id": "1"}}}]),
+            ),
+            graphql_log(json!({"query": document, "operationName": "Rename"}), json!({"data": {"rename": {"id": "2"}}})),
+            graphql_log(json!({"query": "{ viewer { id } }"}), json!({"data": {"viewer": {"id": "1"}}})),
+            graphql_log(json!({"query": "{\n  viewer {\n    id\n  }\n}"}), json!({"data": {"viewer": {"id": "1"}}})),
+        ];
+
+        let schema = reconstruct_schema(&logs).unwrap();
+        let summary: Vec<(&str, &str, usize)> = schema
+            .operations
+            .iter()
+            .map(|op| (op.key.as_str(), op.operation_type.as_str(), op.sample_count))
+            .collect();
+        let anonymous = format!("anonymous_{}", &query_hash("{ viewer { id } }")[..12]);
+        assert_eq!(summary, vec![("ListUsers", "query", 1), ("Rename", "mutation", 2), (anonymous.as_str(), "query", 2)]);
+        assert!(schema.sdl.contains("type Mutation {\n  rename(name: String!): Rename\n}"));
+    }
+}
@@ -0,0 +1,364 @@
+// Local mock server backed by stored Auto-API specs and recorded samples
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderMap, Method, StatusCode, Uri},
+    response::Response,
+};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::{
+    extract_path_from_url, get_recent_api_logs_by_domain, header_value, ApiDiscoveryLog, AutoApiState, OpenApiOperation,
+    OpenApiSpec,
+};
+use crate::security::SafePath;
+
+#[derive(Debug, Clone)]
+pub struct MockServerConfig {
+    pub replay_latency: bool,
+    pub max_latency_ms: u64,
+    pub prefer_recorded: bool,
+}
+
+impl Default for MockServerConfig {
+    fn default() -> Self {
+        Self {
+            replay_latency: false,
+            max_latency_ms: 10_000,
+            prefer_recorded: true,
+        }
+    }
+}
+
+impl MockServerConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            replay_latency: std::env::var("AUTO_API_MOCK_REPLAY_LATENCY").map(|v| v == "true").unwrap_or(defaults.replay_latency),
+            max_latency_ms: std::env::var("AUTO_API_MOCK_MAX_LATENCY_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(defaults.max_latency_ms),
+            prefer_recorded: std::env::var("AUTO_API_MOCK_PREFER_RECORDED").map(|v| v != "false").unwrap_or(defaults.prefer_recorded),
+        }
+    }
+}
+
+/// Serves `/api/auto-api/mock/:domain/*path` as if it were the discovered API.
+///
+/// Per-request overrides: `x-mock-replay-latency: true|false` and
+/// `x-mock-source: recorded|schema`.
+pub async fn serve_mock(
+    State(state): State<AutoApiState>,
+    SafePath((domain, path)): SafePath<(String, String)>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let request_path = format!("/{}", path.trim_start_matches('/'));
+    let method_name = method.as_str().to_lowercase();
+
+    let spec = load_latest_spec(&state.db, &domain).await?;
+    let (template, operation) = match_operation(&spec, &method_name, &request_path).ok_or(StatusCode::NOT_FOUND)?;
+
+    let replay_latency = header_flag(&headers, "x-mock-replay-latency").unwrap_or(state.mock.replay_latency);
+    let prefer_recorded = match headers.get("x-mock-source").and_then(|h| h.to_str().ok()) {
+        Some("schema") => false,
+        Some("recorded") => true,
+        _ => state.mock.prefer_recorded,
+    };
+
+    // Samples come from the same recent window the spec was built from
+    let logs = if prefer_recorded {
+        get_recent_api_logs_by_domain(&state.db, &domain).await?
+    } else {
+        Vec::new()
+    };
+    let sample = select_sample(&logs, &method_name, &template, &request_path, uri.query());
+
+    if replay_latency {
+        if let Some(duration_ms) = sample.and_then(|s| s.duration_ms) {
+            let delay = (duration_ms.max(0) as u64).min(state.mock.max_latency_ms);
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        }
+    }
+
+    let response = match sample {
+        Some(sample) => recorded_response(sample),
+        None => schema_response(operation, &template, &request_path),
+    };
+
+    response.map_err(|e| {
+        tracing::error!("Failed to build mock response for {} {}: {}", domain, request_path, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn load_latest_spec(db: &PgPool, domain: &str) -> Result<OpenApiSpec, StatusCode> {
+    let row = sqlx::query!(
+        "SELECT spec FROM api_spec_versions WHERE domain = $1 ORDER BY version DESC LIMIT 1",
+        domain
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(super::db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    serde_json::from_value(row.spec).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+fn header_flag(headers: &HeaderMap, name: &str) -> Option<bool> {
+    headers.get(name).and_then(|h| h.to_str().ok()).map(|v| v.eq_ignore_ascii_case("true"))
+}
+
+/// Matches a concrete path against a templated one, returning the bound parameters.
+pub fn match_path_template(template: &str, path: &str) -> Option<HashMap<String, String>> {
+    let template_segments: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+    let path_segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+
+    if template_segments.len() != path_segments.len() {
+        return None;
+    }
+
+    let mut params = HashMap::new();
+    for (t, p) in template_segments.iter().zip(&path_segments) {
+        match t.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+            Some(name) if !p.is_empty() => {
+                params.insert(name.to_string(), p.to_string());
+            }
+            Some(_) => return None,
+            None if t == p => {}
+            None => return None,
+        }
+    }
+
+    Some(params)
+}
+
+/// Finds the operation for a request, preferring the most literal template.
+fn match_operation<'a>(spec: &'a OpenApiSpec, method: &str, path: &str) -> Option<(String, &'a OpenApiOperation)> {
+    spec.paths
+        .iter()
+        .filter_map(|(template, item)| {
+            let params = match_path_template(template, path)?;
+            let operation = item.operations().into_iter().find(|(m, _)| *m == method)?.1;
+            Some((params.len(), template, operation))
+        })
+        .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+        .map(|(_, template, operation)| (template.clone(), operation))
+}
+
+/// Picks the recorded sample that best fits the request: exact path and query
+/// first, then exact path, then any sample of the same operation. Most recent wins ties.
+fn select_sample<'a>(
+    logs: &'a [ApiDiscoveryLog],
+    method: &str,
+    template: &str,
+    path: &str,
+    query: Option<&str>,
+) -> Option<&'a ApiDiscoveryLog> {
+    logs.iter()
+        .filter(|log| log.method.eq_ignore_ascii_case(method) && log.response_status.is_some())
+        .filter_map(|log| {
+            let log_path = extract_path_from_url(&log.url);
+            match_path_template(template, &log_path)?;
+
+            let log_query = url::Url::parse(&log.url).ok().and_then(|u| u.query().map(|q| q.to_string()));
+            let score = match (log_path == path, log_query.as_deref() == query) {
+                (true, true) => 2,
+                (true, false) => 1,
+                _ => 0,
+            };
+            Some((score, log))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.timestamp.cmp(&b.1.timestamp)))
+        .map(|(_, log)| log)
+}
+
+fn recorded_response(sample: &ApiDiscoveryLog) -> Result<Response, axum::http::Error> {
+    let status = sample.response_status.and_then(|s| u16::try_from(s).ok()).unwrap_or(200);
+    let content_type = header_value(sample.response_headers.as_ref(), "content-type").unwrap_or("application/json");
+
+    Response::builder()
+        .status(status)
+        .header("Content-Type", content_type)
+        .header("X-Mock-Source", "recorded")
+        .header("X-Mock-Sample-Id", sample.id.to_string())
+        .body(Body::from(sample.response_body.clone().unwrap_or_default()))
+}
+
+fn schema_response(operation: &OpenApiOperation, template: &str, path: &str) -> Result<Response, axum::http::Error> {
+    // Lowest success status the API was seen returning, else the lowest status at all
+    let mut statuses: Vec<&String> = operation.responses.keys().collect();
+    statuses.sort_by_key(|s| (!s.starts_with('2'), s.to_string()));
+    let status = statuses.first().map(|s| s.as_str()).unwrap_or("200");
+
+    let media = operation
+        .responses
+        .get(status)
+        .and_then(|r| r.content.as_ref())
+        .and_then(|c| c.get("application/json").map(|m| ("application/json", m)).or_else(|| c.iter().next().map(|(k, m)| (k.as_str(), m))));
+
+    let path_params = match_path_template(template, path).unwrap_or_default();
+    let body = match media {
+        Some((_, media)) => serde_json::to_string(&example_from_schema(&media.schema, &path_params, 0)).unwrap_or_default(),
+        None => String::new(),
+    };
+
+    Response::builder()
+        .status(status.parse::<u16>().unwrap_or(200))
+        .header("Content-Type", media.map(|(ct, _)| ct).unwrap_or("application/json"))
+        .header("X-Mock-Source", "schema")
+        .body(Body::from(body))
+}
+
+/// Generates a plausible value for an inferred schema. Properties named after a
+/// bound path parameter echo the request's value, so `/users/42` returns `id: 42`.
+pub fn example_from_schema(schema: &Value, path_params: &HashMap<String, String>, depth: usize) -> Value {
+    if depth > 8 {
+        return Value::Null;
+    }
+
+    if let Some(variants) = schema.get("oneOf").and_then(|v| v.as_array()) {
+        return variants.first().map(|v| example_from_schema(v, path_params, depth + 1)).unwrap_or(Value::Null);
+    }
+
+    match schema.get("type").and_then(|t| t.as_str()) {
+        Some("object") => {
+            let mut object = serde_json::Map::new();
+            if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+                for (name, property) in properties {
+                    let value = match path_params.get(name) {
+                        Some(bound) => coerce_to_schema(bound, property),
+                        None => example_from_schema(property, path_params, depth + 1),
+                    };
+                    object.insert(name.clone(), value);
+                }
+            }
+            Value::Object(object)
+        }
+        Some("array") => {
+            let item = schema.get("items").map(|i| example_from_schema(i, path_params, depth + 1)).unwrap_or(Value::Null);
+            json!([item])
+        }
+        Some("integer") => json!(1),
+        Some("number") => json!(1.0),
+        Some("boolean") => json!(true),
+        Some("string") => match schema.get("format").and_then(|f| f.as_str()) {
+            Some("date-time") => json!(chrono::Utc::now().to_rfc3339()),
+            Some("uuid") => json!(Uuid::new_v4()),
+            _ => json!("string"),
+        },
+        _ => Value::Null,
+    }
+}
+
+fn coerce_to_schema(raw: &str, schema: &Value) -> Value {
+    match schema.get("type").and_then(|t| t.as_str()) {
+        Some("integer") => raw.parse::<i64>().map(|n| json!(n)).unwrap_or_else(|_| json!(raw)),
+        Some("number") => raw.parse::<f64>().map(|n| json!(n)).unwrap_or_else(|_| json!(raw)),
+        _ => json!(raw),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn operation(id: &str) -> Value {
+        json!({
+            "operation_id": id,
+            "summary": null,
+            "description": null,
+            "parameters": [],
+            "request_body": null,
+            "responses": {"200": {"description": "OK", "content": null}},
+            "tags": []
+        })
+    }
+
+    fn spec() -> OpenApiSpec {
+        serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "shop.example.com API", "version": "1.0.0", "description": null},
+            "servers": [],
+            "paths": {
+                "/api/users/{id}": {"get": operation("getUser"), "delete": operation("deleteUser")},
+                "/api/users/me": {"get": operation("getMe")},
+                "/api/{resource}/{id}": {"get": operation("getResource")}
+            },
+            "components": null
+        }))
+        .unwrap()
+    }
+
+    fn log(method: &str, url: &str, status: Option<i32>, minute: u32) -> ApiDiscoveryLog {
+        ApiDiscoveryLog {
+            id: Uuid::new_v4(),
+            user_id: None,
+            session_id: None,
+            domain: "shop.example.com".to_string(),
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: json!({}),
+            request_body: None,
+            response_status: status,
+            response_headers: None,
+            response_body: None,
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 3, minute, 0).unwrap(),
+            processed: false,
+            api_spec: None,
+            duration_ms: None,
+            redactions: json!([]),
+        }
+    }
+
+    #[test]
+    fn path_templates_bind_parameters() {
+        let params = match_path_template("/api/users/{id}/orders/{order}", "/api/users/42/orders/7/").unwrap();
+        assert_eq!(params.get("id").map(String::as_str), Some("42"));
+        assert_eq!(params.get("order").map(String::as_str), Some("7"));
+
+        assert!(match_path_template("/api/users/{id}", "/api/users").is_none());
+        assert!(match_path_template("/api/users/{id}", "/api/users//").is_none());
+        assert!(match_path_template("/api/users/{id}", "/api/teams/42").is_none());
+    }
+
+    #[test]
+    fn the_most_literal_template_wins() {
+        let spec = spec();
+        let (template, op) = match_operation(&spec, "get", "/api/users/me").unwrap();
+        assert_eq!((template.as_str(), op.operation_id.as_deref()), ("/api/users/me", Some("getMe")));
+
+        let (template, _) = match_operation(&spec, "get", "/api/users/42").unwrap();
+        assert_eq!(template, "/api/users/{id}");
+        let (template, _) = match_operation(&spec, "get", "/api/orders/42").unwrap();
+        assert_eq!(template, "/api/{resource}/{id}");
+
+        // Only templates that define the method are candidates
+        let (template, op) = match_operation(&spec, "delete", "/api/users/me").unwrap();
+        assert_eq!((template.as_str(), op.operation_id.as_deref()), ("/api/users/{id}", Some("deleteUser")));
+        assert!(match_operation(&spec, "post", "/api/users/42").is_none());
+    }
+
+    #[test]
+    fn samples_prefer_exact_path_and_query_then_recency() {
+        let logs = vec![
+            log("GET", "https://shop.example.com/api/users/7", Some(200), 1),
+            log("GET", "https://shop.example.com/api/users/42", Some(200), 2),
+            log("GET", "https://shop.example.com/api/users/42?expand=orders", Some(200), 3),
+            log("GET", "https://shop.example.com/api/users/42", Some(200), 4),
+            log("GET", "https://shop.example.com/api/users/42", None, 5),
+            log("DELETE", "https://shop.example.com/api/users/42", Some(204), 6),
+        ];
+        let pick = |path: &str, query: Option<&str>| select_sample(&logs, "get", "/api/users/{id}", path, query).map(|l| l.id);
+
+        assert_eq!(pick("/api/users/42", Some("expand=orders")), Some(logs[2].id));
+        assert_eq!(pick("/api/users/42", None), Some(logs[3].id));
+        assert_eq!(pick("/api/users/42", Some("page=2")), Some(logs[3].id));
+        assert_eq!(pick("/api/users/99", None), Some(logs[3].id));
+        assert_eq!(pick("/api/users/7", None), Some(logs[0].id));
+        assert!(select_sample(&logs, "patch", "/api/users/{id}", "/api/users/42", None).is_none());
+    }
+}
@@ -0,0 +1,1196 @@
+// Auto-API Extractor / Generator Module
+pub mod client_stubs;
+pub mod diff;
+pub mod export;
+pub mod graphql;
+pub mod mock_server;
+pub mod pagination;
+pub mod rate_limits;
+pub mod redaction;
+pub mod streams;
+
+pub use diff::*;
+pub use graphql::*;
+pub use mock_server::*;
+pub use redaction::{RedactionConfig, RedactionMarker, RedactionPipeline};
+pub use streams::*;
+
+use axum::{Extension, Json, extract::{Query, State}, extract::Multipart};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::security::{ConsentGate, ConsentPurpose, SafePath, UserContext};
+
+#[derive(Clone)]
+pub struct AutoApiState {
+    pub db: PgPool,
+    pub mock: MockServerConfig,
+    pub redaction: Arc<RedactionPipeline>,
+    pub consent: ConsentGate,
+}
+
+impl AutoApiState {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            consent: ConsentGate::new(db.clone()),
+            db,
+            mock: MockServerConfig::from_env(),
+            redaction: Arc::new(RedactionPipeline::new(RedactionConfig::load())),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApiDiscoveryLog {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub session_id: Option<String>,
+    pub domain: String,
+    pub method: String,
+    pub url: String,
+    pub headers: Value,
+    pub request_body: Option<String>,
+    pub response_status: Option<i32>,
+    pub response_headers: Option<Value>,
+    pub response_body: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub processed: bool,
+    pub api_spec: Option<Value>,
+    pub duration_ms: Option<i32>,
+    pub redactions: Value, // RedactionMarker list recorded at ingestion
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogApiRequestRequest {
+    pub session_id: Option<String>,
+    pub domain: String,
+    pub method: String,
+    pub url: String,
+    pub headers: Value,
+    pub request_body: Option<String>,
+    pub response_status: Option<i32>,
+    pub response_headers: Option<Value>,
+    pub response_body: Option<String>,
+    pub duration_ms: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiSpec {
+    pub openapi: String,
+    pub info: OpenApiInfo,
+    pub servers: Vec<OpenApiServer>,
+    pub paths: HashMap<String, OpenApiPathItem>,
+    pub components: Option<OpenApiComponents>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiInfo {
+    pub title: String,
+    pub version: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiServer {
+    pub url: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenApiPathItem {
+    pub get: Option<OpenApiOperation>,
+    pub post: Option<OpenApiOperation>,
+    pub put: Option<OpenApiOperation>,
+    pub delete: Option<OpenApiOperation>,
+    pub patch: Option<OpenApiOperation>,
+}
+
+impl OpenApiPathItem {
+    pub fn operations(&self) -> Vec<(&'static str, &OpenApiOperation)> {
+        [
+            ("get", &self.get),
+            ("post", &self.post),
+            ("put", &self.put),
+            ("delete", &self.delete),
+            ("patch", &self.patch),
+        ]
+        .into_iter()
+        .filter_map(|(method, op)| op.as_ref().map(|op| (method, op)))
+        .collect()
+    }
+
+    pub fn operation_mut(&mut self, method: &str) -> Option<&mut Option<OpenApiOperation>> {
+        match method {
+            "get" => Some(&mut self.get),
+            "post" => Some(&mut self.post),
+            "put" => Some(&mut self.put),
+            "delete" => Some(&mut self.delete),
+            "patch" => Some(&mut self.patch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiOperation {
+    pub operation_id: Option<String>,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub parameters: Vec<OpenApiParameter>,
+    pub request_body: Option<OpenApiRequestBody>,
+    pub responses: HashMap<String, OpenApiResponse>,
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extensions: BTreeMap<String, Value>, // "x-" vendor extensions
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiParameter {
+    pub name: String,
+    pub in_: String, // "query", "header", "path", "cookie"
+    pub required: bool,
+    pub schema: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiRequestBody {
+    pub content: HashMap<String, OpenApiMediaType>,
+    pub required: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiMediaType {
+    pub schema: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiResponse {
+    pub description: String,
+    pub content: Option<HashMap<String, OpenApiMediaType>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApiComponents {
+    pub schemas: Option<HashMap<String, Value>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApiSpecVersion {
+    pub id: Uuid,
+    pub domain: String,
+    pub version: i32,
+    pub spec: Value,
+    pub endpoint_count: i32,
+    pub log_count: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+fn db_error(e: sqlx::Error) -> axum::http::StatusCode {
+    tracing::error!("Auto-API database error: {}", e);
+    axum::http::StatusCode::INTERNAL_SERVER_ERROR
+}
+
+pub async fn log_api_request(
+    State(state): State<AutoApiState>,
+    user: Option<Extension<UserContext>>,
+    Json(mut request): Json<LogApiRequestRequest>,
+) -> Result<Json<Value>, axum::http::StatusCode> {
+    let user_id = user.map(|Extension(context)| context.user_id);
+    if !state.consent.check(user_id, ConsentPurpose::TrafficCapture).await {
+        return Ok(Json(consent_skipped()));
+    }
+
+    // Nothing sensitive may reach the database, so redact before building the row
+    let redactions = state.redaction.redact_log(&mut request);
+
+    let log_entry = ApiDiscoveryLog {
+        id: Uuid::new_v4(),
+        user_id,
+        session_id: request.session_id,
+        domain: request.domain,
+        method: request.method.to_uppercase(),
+        url: request.url,
+        headers: request.headers,
+        request_body: request.request_body,
+        response_status: request.response_status,
+        response_headers: request.response_headers,
+        response_body: request.response_body,
+        timestamp: Utc::now(),
+        processed: false,
+        api_spec: None,
+        duration_ms: request.duration_ms,
+        redactions: serde_json::to_value(&redactions).unwrap_or_else(|_| Value::Array(Vec::new())),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO api_discovery_logs
+            (id, user_id, session_id, domain, method, url, headers, request_body,
+             response_status, response_headers, response_body, timestamp, processed, duration_ms, redactions)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, FALSE, $13, $14)
+        "#,
+        log_entry.id,
+        log_entry.user_id,
+        log_entry.session_id,
+        log_entry.domain,
+        log_entry.method,
+        log_entry.url,
+        log_entry.headers,
+        log_entry.request_body,
+        log_entry.response_status,
+        log_entry.response_headers,
+        log_entry.response_body,
+        log_entry.timestamp,
+        log_entry.duration_ms,
+        log_entry.redactions
+    )
+    .execute(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "log_id": log_entry.id,
+        "redacted": redactions.len()
+    })))
+}
+
+/// Capture is degraded rather than rejected so the extension keeps browsing normally.
+pub(crate) fn consent_skipped() -> Value {
+    serde_json::json!({
+        "success": false,
+        "stored": false,
+        "consent_required": ConsentPurpose::TrafficCapture.as_str()
+    })
+}
+
+pub async fn get_discovered_apis(
+    State(state): State<AutoApiState>,
+    Query(params): Query<GetDiscoveredApisQuery>,
+) -> Result<Json<Vec<Value>>, axum::http::StatusCode> {
+    // Group logs by domain and attach the latest stored spec version
+    let rows = sqlx::query!(
+        r#"
+        SELECT l.domain AS "domain!",
+               COUNT(*) AS "log_count!",
+               COUNT(*) FILTER (WHERE NOT l.processed) AS "unprocessed_count!",
+               ARRAY_AGG(DISTINCT l.method) AS "methods!: Vec<String>",
+               MAX(l.timestamp) AS last_discovered,
+               v.version AS "latest_version?",
+               v.endpoint_count AS "endpoints_count?",
+               v.spec AS "openapi_spec?"
+        FROM api_discovery_logs l
+        LEFT JOIN LATERAL (
+            SELECT version, endpoint_count, spec FROM api_spec_versions
+            WHERE domain = l.domain ORDER BY version DESC LIMIT 1
+        ) v ON TRUE
+        WHERE ($1::TEXT IS NULL OR l.domain = $1)
+          AND ($2::BOOLEAN IS NULL OR l.processed = $2)
+        GROUP BY l.domain, v.version, v.endpoint_count, v.spec
+        ORDER BY MAX(l.timestamp) DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        params.domain,
+        params.processed,
+        params.limit.unwrap_or(50),
+        params.offset.unwrap_or(0)
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let apis = rows
+        .into_iter()
+        .map(|row| serde_json::json!({
+            "domain": row.domain,
+            "base_url": format!("https://{}", row.domain),
+            "log_count": row.log_count,
+            "unprocessed_count": row.unprocessed_count,
+            "endpoints_count": row.endpoints_count.unwrap_or(0),
+            "methods": row.methods,
+            "last_discovered": row.last_discovered,
+            "latest_version": row.latest_version,
+            "openapi_spec": row.openapi_spec
+        }))
+        .collect();
+
+    Ok(Json(apis))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetDiscoveredApisQuery {
+    pub domain: Option<String>,
+    pub processed: Option<bool>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub async fn generate_openapi_spec(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+) -> Result<Json<OpenApiSpec>, axum::http::StatusCode> {
+    let (openapi_spec, _) = build_current_spec(&state.db, &domain).await?;
+    Ok(Json(openapi_spec))
+}
+
+/// Builds the spec from the recent capture window and records it as a new
+/// version when the API surface changed since the last one.
+pub async fn create_spec_version(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+) -> Result<Json<Value>, axum::http::StatusCode> {
+    let (openapi_spec, log_count) = build_current_spec(&state.db, &domain).await?;
+
+    // Only record a new version when the API surface actually changed
+    let version = store_spec_version(&state.db, &domain, &openapi_spec, log_count as i32).await?;
+
+    // Mark logs as processed
+    mark_logs_as_processed(&state.db, &domain).await?;
+
+    Ok(Json(serde_json::json!({
+        "domain": domain,
+        "version": version,
+        "spec": openapi_spec
+    })))
+}
+
+async fn build_current_spec(db: &PgPool, domain: &str) -> Result<(OpenApiSpec, usize), axum::http::StatusCode> {
+    let logs = get_recent_api_logs_by_domain(db, domain).await?;
+    if logs.is_empty() {
+        return Err(axum::http::StatusCode::NOT_FOUND);
+    }
+    Ok((build_openapi_spec(domain, &logs), logs.len()))
+}
+
+pub fn build_openapi_spec(domain: &str, logs: &[ApiDiscoveryLog]) -> OpenApiSpec {
+    let mut paths: HashMap<String, OpenApiPathItem> = HashMap::new();
+
+    for log in logs {
+        let path = extract_path_from_url(&log.url);
+        let method = log.method.to_lowercase();
+
+        // Extract path parameters
+        let (clean_path, params) = extract_path_parameters(&path);
+
+        // Create or update path item
+        let path_item = paths.entry(clean_path.clone()).or_default();
+        let Some(slot) = path_item.operation_mut(&method) else {
+            continue;
+        };
+
+        // Add operation, folding repeated samples of the same endpoint together
+        let operation = create_openapi_operation_from_log(log, &clean_path, &params);
+        match slot {
+            Some(existing) => merge_operations(existing, operation),
+            None => *slot = Some(operation),
+        }
+    }
+
+    // Annotate collapsed GraphQL endpoints with the operations sent through them
+    graphql::annotate_paths(&mut paths, logs);
+    pagination::annotate_pagination(&mut paths, logs);
+    rate_limits::annotate_rate_limits(&mut paths, logs);
+
+    OpenApiSpec {
+        openapi: "3.0.0".to_string(),
+        info: OpenApiInfo {
+            title: format!("{} API", domain),
+            version: "1.0.0".to_string(),
+            description: Some(format!("Auto-generated OpenAPI specification for {}", domain)),
+        },
+        servers: vec![
+            OpenApiServer {
+                url: format!("https://{}", domain),
+                description: Some("Production server".to_string()),
+            }
+        ],
+        paths,
+        components: Some(OpenApiComponents {
+            schemas: Some(HashMap::new()),
+        }),
+    }
+}
+
+/// Groups logs by the operation `build_openapi_spec` folds them into, keyed by
+/// templated path and lowercase method.
+pub(crate) fn logs_by_operation(logs: &[ApiDiscoveryLog]) -> BTreeMap<(String, String), Vec<&ApiDiscoveryLog>> {
+    let mut groups: BTreeMap<(String, String), Vec<&ApiDiscoveryLog>> = BTreeMap::new();
+    for log in logs {
+        let (clean_path, _) = extract_path_parameters(&extract_path_from_url(&log.url));
+        groups.entry((clean_path, log.method.to_lowercase())).or_default().push(log);
+    }
+    groups
+}
+
+async fn get_api_logs_by_domain(db: &PgPool, domain: &str) -> Result<Vec<ApiDiscoveryLog>, axum::http::StatusCode> {
+    sqlx::query_as!(
+        ApiDiscoveryLog,
+        r#"
+        SELECT id, user_id, session_id, domain, method, url,
+               headers AS "headers!", request_body, response_status,
+               response_headers, response_body,
+               timestamp AS "timestamp!", processed AS "processed!", api_spec, duration_ms,
+               redactions AS "redactions!"
+        FROM api_discovery_logs
+        WHERE domain = $1
+        ORDER BY timestamp ASC
+        "#,
+        domain
+    )
+    .fetch_all(db)
+    .await
+    .map_err(db_error)
+}
+
+/// Specs describe the API as it behaves now, so only recent captures feed
+/// them; older samples would keep retired field types alive as `oneOf`.
+const SPEC_WINDOW_DAYS: i32 = 30;
+const SPEC_WINDOW_MAX_LOGS: i64 = 5000;
+
+async fn get_recent_api_logs_by_domain(db: &PgPool, domain: &str) -> Result<Vec<ApiDiscoveryLog>, axum::http::StatusCode> {
+    let mut logs = sqlx::query_as!(
+        ApiDiscoveryLog,
+        r#"
+        SELECT id, user_id, session_id, domain, method, url,
+               headers AS "headers!", request_body, response_status,
+               response_headers, response_body,
+               timestamp AS "timestamp!", processed AS "processed!", api_spec, duration_ms,
+               redactions AS "redactions!"
+        FROM api_discovery_logs
+        WHERE domain = $1 AND timestamp >= NOW() - make_interval(days => $2)
+        ORDER BY timestamp DESC
+        LIMIT $3
+        "#,
+        domain,
+        SPEC_WINDOW_DAYS,
+        SPEC_WINDOW_MAX_LOGS
+    )
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+
+    // Oldest first, matching the order specs are folded in
+    logs.reverse();
+    Ok(logs)
+}
+
+const STORE_SPEC_ATTEMPTS: usize = 3;
+
+async fn store_spec_version(
+    db: &PgPool,
+    domain: &str,
+    spec: &OpenApiSpec,
+    log_count: i32,
+) -> Result<i32, axum::http::StatusCode> {
+    let spec_json = serde_json::to_value(spec).map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let endpoint_count: usize = spec.paths.values().map(|p| p.operations().len()).sum();
+
+    // Concurrent generations race for the next version number; the unique
+    // (domain, version) constraint picks a winner and the loser re-reads it
+    for _ in 0..STORE_SPEC_ATTEMPTS {
+        let latest = sqlx::query!(
+            "SELECT version, spec FROM api_spec_versions WHERE domain = $1 ORDER BY version DESC LIMIT 1",
+            domain
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(db_error)?;
+
+        if let Some(latest) = &latest {
+            if latest.spec == spec_json {
+                return Ok(latest.version);
+            }
+        }
+
+        let version = latest.map(|l| l.version + 1).unwrap_or(1);
+        let inserted = sqlx::query_scalar!(
+            r#"
+            INSERT INTO api_spec_versions (domain, version, spec, endpoint_count, log_count)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (domain, version) DO NOTHING
+            RETURNING version
+            "#,
+            domain,
+            version,
+            spec_json,
+            endpoint_count as i32,
+            log_count
+        )
+        .fetch_optional(db)
+        .await
+        .map_err(db_error)?;
+
+        if let Some(version) = inserted {
+            tracing::info!("Stored OpenAPI spec version {} for {}", version, domain);
+            return Ok(version);
+        }
+    }
+
+    tracing::error!("Gave up storing spec version for {} after {} conflicting attempts", domain, STORE_SPEC_ATTEMPTS);
+    Err(axum::http::StatusCode::CONFLICT)
+}
+
+async fn load_spec_version(db: &PgPool, domain: &str, version: i32) -> Result<OpenApiSpec, axum::http::StatusCode> {
+    let row = sqlx::query!(
+        "SELECT spec FROM api_spec_versions WHERE domain = $1 AND version = $2",
+        domain,
+        version
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?
+    .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+
+    serde_json::from_value(row.spec).map_err(|e| {
+        tracing::error!("Stored spec {} v{} is unreadable: {}", domain, version, e);
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+pub async fn list_spec_versions(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+) -> Result<Json<Vec<Value>>, axum::http::StatusCode> {
+    let versions = sqlx::query!(
+        r#"
+        SELECT id, version, endpoint_count, log_count, created_at
+        FROM api_spec_versions
+        WHERE domain = $1
+        ORDER BY version DESC
+        "#,
+        domain
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(versions.into_iter().map(|v| serde_json::json!({
+        "id": v.id,
+        "version": v.version,
+        "endpoint_count": v.endpoint_count,
+        "log_count": v.log_count,
+        "created_at": v.created_at
+    })).collect()))
+}
+
+pub async fn get_spec_version(
+    State(state): State<AutoApiState>,
+    SafePath((domain, version)): SafePath<(String, i32)>,
+) -> Result<Json<OpenApiSpec>, axum::http::StatusCode> {
+    Ok(Json(load_spec_version(&state.db, &domain, version).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffSpecVersionsQuery {
+    pub from: Option<i32>,
+    pub to: Option<i32>,
+}
+
+pub async fn diff_spec_versions(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+    Query(params): Query<DiffSpecVersionsQuery>,
+) -> Result<Json<SpecDiff>, axum::http::StatusCode> {
+    // Default to comparing the two most recent versions
+    let to = match params.to {
+        Some(to) => to,
+        None => sqlx::query_scalar!(
+            r#"SELECT MAX(version) AS "version?" FROM api_spec_versions WHERE domain = $1"#,
+            domain
+        )
+        .fetch_one(&state.db)
+        .await
+        .map_err(db_error)?
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?,
+    };
+    // With a single version there is nothing older to compare against
+    let from = params.from.unwrap_or((to - 1).max(1));
+
+    if from < 1 {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    if from == to {
+        let spec = load_spec_version(&state.db, &domain, to).await?;
+        return Ok(Json(diff_specs(&domain, from, &spec, to, &spec)));
+    }
+
+    let old_spec = load_spec_version(&state.db, &domain, from).await?;
+    let new_spec = load_spec_version(&state.db, &domain, to).await?;
+
+    Ok(Json(diff_specs(&domain, from, &old_spec, to, &new_spec)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportSpecQuery {
+    pub format: String, // "openapi-yaml", "openapi-json", "postman", "graphql-sdl", "asyncapi-yaml", "asyncapi-json"
+    pub version: Option<i32>,
+}
+
+pub async fn export_api_spec(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+    Query(params): Query<ExportSpecQuery>,
+) -> Result<axum::response::Response, axum::http::StatusCode> {
+    let file_stem = domain.replace('.', "_");
+
+    // Streaming APIs are described separately from the request/response spec
+    if let Some(kind) = params.format.strip_prefix("asyncapi-") {
+        let messages = get_stream_messages_by_domain(&state.db, &domain).await?;
+        if messages.is_empty() {
+            return Err(axum::http::StatusCode::NOT_FOUND);
+        }
+        let document = build_asyncapi_spec(&domain, &messages);
+        let (content_type, extension, body) = match kind {
+            "yaml" => ("application/yaml", "yaml", to_asyncapi_yaml(&document).map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?),
+            "json" => ("application/json", "json", serde_json::to_string_pretty(&document).map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?),
+            _ => return Err(axum::http::StatusCode::BAD_REQUEST),
+        };
+        return Ok(axum::response::Response::builder()
+            .status(200)
+            .header("Content-Type", content_type)
+            .header("Content-Disposition", format!("attachment; filename=\"{}.asyncapi.{}\"", file_stem, extension))
+            .body(axum::body::Body::from(body))
+            .unwrap());
+    }
+
+    let spec = match params.version {
+        Some(version) => load_spec_version(&state.db, &domain, version).await?,
+        None => build_current_spec(&state.db, &domain).await?.0,
+    };
+
+    let (content_type, filename, body) = match params.format.as_str() {
+        "openapi-yaml" => {
+            let yaml = export::to_openapi_31_yaml(&spec).map_err(|e| {
+                tracing::error!("Failed to render OpenAPI YAML for {}: {}", domain, e);
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            ("application/yaml", format!("{}.openapi.yaml", file_stem), yaml)
+        }
+        "openapi-json" => {
+            let json = serde_json::to_string_pretty(&export::to_openapi_31(&spec))
+                .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+            ("application/json", format!("{}.openapi.json", file_stem), json)
+        }
+        "graphql-sdl" => {
+            let logs = get_api_logs_by_domain(&state.db, &domain).await?;
+            let schema = graphql::reconstruct_schema(&logs).ok_or(axum::http::StatusCode::NOT_FOUND)?;
+            ("application/graphql", format!("{}.graphql", file_stem), schema.sdl)
+        }
+        "postman" => {
+            // Example requests come from real captures of this domain
+            let logs = get_api_logs_by_domain(&state.db, &domain).await?;
+            let collection = export::to_postman_collection(&domain, &spec, &logs, &state.redaction);
+            let json = serde_json::to_string_pretty(&collection)
+                .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?;
+            ("application/json", format!("{}.postman_collection.json", file_stem), json)
+        }
+        _ => return Err(axum::http::StatusCode::BAD_REQUEST),
+    };
+
+    Ok(axum::response::Response::builder()
+        .status(200)
+        .header("Content-Type", content_type)
+        .header("Content-Disposition", format!("attachment; filename=\"{}\"", filename))
+        .body(axum::body::Body::from(body))
+        .unwrap())
+}
+
+pub async fn get_graphql_schema(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+) -> Result<Json<GraphqlSchema>, axum::http::StatusCode> {
+    let logs = get_api_logs_by_domain(&state.db, &domain).await?;
+    graphql::reconstruct_schema(&logs)
+        .map(Json)
+        .ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
+fn extract_path_from_url(url: &str) -> String {
+    if let Ok(parsed_url) = url::Url::parse(url) {
+        parsed_url.path().to_string()
+    } else {
+        url.to_string()
+    }
+}
+
+fn extract_path_parameters(path: &str) -> (String, Vec<String>) {
+    let mut params = Vec::new();
+
+    // Simple pattern matching for UUIDs, numeric IDs and redacted values, one segment at a time
+    let re = regex::Regex::new(r"^([0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}|\d+)$").unwrap();
+
+    let segments: Vec<String> = path
+        .split('/')
+        .map(|segment| {
+            if !re.is_match(segment) && !redaction::is_marker(segment) {
+                return segment.to_string();
+            }
+            let name = if params.is_empty() { "id".to_string() } else { format!("id{}", params.len() + 1) };
+            params.push(name.clone());
+            format!("{{{}}}", name)
+        })
+        .collect();
+
+    (segments.join("/"), params)
+}
+
+fn extract_query_parameters(url: &str) -> Vec<(String, String)> {
+    url::Url::parse(url)
+        .map(|parsed_url| parsed_url.query_pairs().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn header_value<'a>(headers: Option<&'a Value>, name: &str) -> Option<&'a str> {
+    headers?
+        .as_object()?
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .and_then(|(_, value)| value.as_str())
+}
+
+fn media_type_of(headers: Option<&Value>) -> String {
+    header_value(headers, "content-type")
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_lowercase())
+        .filter(|ct| !ct.is_empty())
+        .unwrap_or_else(|| "application/json".to_string())
+}
+
+fn body_schema(body: &str) -> Value {
+    serde_json::from_str::<Value>(body)
+        .map(|value| infer_json_schema(&value))
+        .unwrap_or_else(|_| serde_json::json!({"type": "string"}))
+}
+
+fn create_openapi_operation_from_log(log: &ApiDiscoveryLog, clean_path: &str, params: &[String]) -> OpenApiOperation {
+    let mut operation = OpenApiOperation {
+        operation_id: Some(format_operation_name(&log.method.to_lowercase(), clean_path)),
+        summary: Some(format!("{} {}", log.method, clean_path)),
+        description: Some(format!("Auto-generated from {} request", log.method)),
+        parameters: Vec::new(),
+        request_body: None,
+        responses: HashMap::new(),
+        tags: vec!["auto-generated".to_string()],
+        extensions: BTreeMap::new(),
+    };
+
+    // Add path parameters
+    for param in params {
+        operation.parameters.push(OpenApiParameter {
+            name: param.clone(),
+            in_: "path".to_string(),
+            required: true,
+            schema: serde_json::json!({"type": "string"}),
+        });
+    }
+
+    // Add query parameters seen on this sample
+    for (name, value) in extract_query_parameters(&log.url) {
+        if operation.parameters.iter().any(|p| p.name == name && p.in_ == "query") {
+            continue;
+        }
+        operation.parameters.push(OpenApiParameter {
+            name,
+            in_: "query".to_string(),
+            required: false,
+            schema: infer_json_schema(&infer_scalar(&value)),
+        });
+    }
+
+    // Add request body if present
+    if let Some(request_body) = &log.request_body {
+        operation.request_body = Some(OpenApiRequestBody {
+            content: HashMap::from([
+                (media_type_of(Some(&log.headers)), OpenApiMediaType {
+                    schema: body_schema(request_body),
+                })
+            ]),
+            required: Some(false),
+        });
+    }
+
+    // Add response
+    operation.responses.insert(
+        log.response_status.unwrap_or(200).to_string(),
+        OpenApiResponse {
+            description: format!("Response from {} request", log.method),
+            content: log.response_body.as_ref().map(|body| HashMap::from([
+                (media_type_of(log.response_headers.as_ref()), OpenApiMediaType {
+                    schema: body_schema(body),
+                })
+            ])),
+        }
+    );
+
+    operation
+}
+
+fn merge_operations(existing: &mut OpenApiOperation, incoming: OpenApiOperation) {
+    for param in incoming.parameters {
+        if !existing.parameters.iter().any(|p| p.name == param.name && p.in_ == param.in_) {
+            existing.parameters.push(param);
+        }
+    }
+
+    match (&mut existing.request_body, incoming.request_body) {
+        (Some(body), Some(incoming_body)) => merge_content(&mut body.content, incoming_body.content),
+        (slot @ None, Some(incoming_body)) => *slot = Some(incoming_body),
+        _ => {}
+    }
+
+    for (status, response) in incoming.responses {
+        match existing.responses.get_mut(&status) {
+            Some(current) => match (&mut current.content, response.content) {
+                (Some(content), Some(incoming_content)) => merge_content(content, incoming_content),
+                (slot @ None, Some(incoming_content)) => *slot = Some(incoming_content),
+                _ => {}
+            },
+            None => {
+                existing.responses.insert(status, response);
+            }
+        }
+    }
+}
+
+fn merge_content(content: &mut HashMap<String, OpenApiMediaType>, incoming: HashMap<String, OpenApiMediaType>) {
+    for (media_type, media) in incoming {
+        match content.get_mut(&media_type) {
+            Some(current) => current.schema = merge_schemas(&current.schema, &media.schema),
+            None => {
+                content.insert(media_type, media);
+            }
+        }
+    }
+}
+
+fn infer_scalar(raw: &str) -> Value {
+    if let Ok(n) = raw.parse::<i64>() {
+        serde_json::json!(n)
+    } else if let Ok(f) = raw.parse::<f64>() {
+        serde_json::json!(f)
+    } else if raw == "true" || raw == "false" {
+        serde_json::json!(raw == "true")
+    } else {
+        Value::String(raw.to_string())
+    }
+}
+
+/// Infers a JSON schema from a single captured sample.
+pub fn infer_json_schema(value: &Value) -> Value {
+    match value {
+        Value::Null => serde_json::json!({"nullable": true}),
+        Value::Bool(_) => serde_json::json!({"type": "boolean"}),
+        Value::Number(n) if n.is_i64() || n.is_u64() => serde_json::json!({"type": "integer"}),
+        Value::Number(_) => serde_json::json!({"type": "number"}),
+        Value::String(s) => {
+            // Redacted values keep their original type; the marker kind hints at the format
+            if let Some((kind, ty)) = redaction::parse_marker(s) {
+                let mut schema = serde_json::json!({"type": ty, "x-redacted": kind});
+                if kind == "email" {
+                    schema["format"] = Value::from("email");
+                }
+                schema
+            } else if DateTime::parse_from_rfc3339(s).is_ok() {
+                serde_json::json!({"type": "string", "format": "date-time"})
+            } else if Uuid::parse_str(s).is_ok() {
+                serde_json::json!({"type": "string", "format": "uuid"})
+            } else {
+                serde_json::json!({"type": "string"})
+            }
+        }
+        Value::Array(items) => {
+            let item_schema = items
+                .iter()
+                .map(infer_json_schema)
+                .reduce(|a, b| merge_schemas(&a, &b))
+                .unwrap_or_else(|| serde_json::json!({}));
+            serde_json::json!({"type": "array", "items": item_schema})
+        }
+        Value::Object(map) => {
+            let properties: serde_json::Map<String, Value> = map
+                .iter()
+                .map(|(k, v)| (k.clone(), infer_json_schema(v)))
+                .collect();
+            let required: Vec<&String> = map.iter().filter(|(_, v)| !v.is_null()).map(|(k, _)| k).collect();
+            serde_json::json!({"type": "object", "properties": properties, "required": required})
+        }
+    }
+}
+
+/// Merges two inferred schemas so that both samples validate against the result.
+///
+/// Samples of different types merge into one flat `oneOf` holding a member per
+/// type; a null sample anywhere marks the result `nullable`.
+pub fn merge_schemas(a: &Value, b: &Value) -> Value {
+    let merged = if a.get("oneOf").is_some() || b.get("oneOf").is_some() { merge_union(a, b) } else { merge_types(a, b) };
+    if is_nullable(a) || is_nullable(b) { with_nullable(&merged) } else { merged }
+}
+
+fn merge_types(a: &Value, b: &Value) -> Value {
+    let a_type = a.get("type").and_then(|t| t.as_str());
+    let b_type = b.get("type").and_then(|t| t.as_str());
+
+    match (a_type, b_type) {
+        // A null sample only widens the other side
+        (None, Some(_)) if is_nullable(a) => b.clone(),
+        (Some(_), None) if is_nullable(b) => a.clone(),
+        (Some("object"), Some("object")) => {
+            let empty = serde_json::Map::new();
+            let a_props = a.get("properties").and_then(|p| p.as_object()).unwrap_or(&empty);
+            let b_props = b.get("properties").and_then(|p| p.as_object()).unwrap_or(&empty);
+
+            let mut properties = a_props.clone();
+            for (name, schema) in b_props {
+                let merged = match a_props.get(name) {
+                    Some(existing) => merge_schemas(existing, schema),
+                    None => schema.clone(),
+                };
+                properties.insert(name.clone(), merged);
+            }
+
+            // Only fields present in every sample stay required
+            let b_required: Vec<&Value> = b.get("required").and_then(|r| r.as_array()).map(|r| r.iter().collect()).unwrap_or_default();
+            let required: Vec<Value> = a
+                .get("required")
+                .and_then(|r| r.as_array())
+                .map(|r| r.iter().filter(|name| b_required.contains(name)).cloned().collect())
+                .unwrap_or_default();
+
+            serde_json::json!({"type": "object", "properties": properties, "required": required})
+        }
+        (Some("array"), Some("array")) => {
+            let items = match (a.get("items"), b.get("items")) {
+                (Some(a_items), Some(b_items)) => merge_schemas(a_items, b_items),
+                (Some(items), None) | (None, Some(items)) => items.clone(),
+                (None, None) => serde_json::json!({}),
+            };
+            serde_json::json!({"type": "array", "items": items})
+        }
+        (Some("integer"), Some("number")) | (Some("number"), Some("integer")) => serde_json::json!({"type": "number"}),
+        (Some(x), Some(y)) if x == y => {
+            // Keep a format only when both samples agree on it
+            if a.get("format") == b.get("format") { without_nullable(a) } else { serde_json::json!({"type": x}) }
+        }
+        (Some(_), Some(_)) => merge_union(a, b),
+        _ => without_nullable(a),
+    }
+}
+
+/// Folds both sides' members into one `oneOf`, merging members of the same type.
+fn merge_union(a: &Value, b: &Value) -> Value {
+    let mut members = union_members(a);
+    for member in union_members(b) {
+        match members.iter().position(|existing| same_type(existing, &member)) {
+            Some(i) => members[i] = merge_schemas(&members[i], &member),
+            None => members.push(member),
+        }
+    }
+    match members.len() {
+        0 => serde_json::json!({}),
+        1 => members.remove(0),
+        _ => serde_json::json!({"oneOf": members}),
+    }
+}
+
+/// The typed alternatives a schema stands for; nullability is tracked on the union itself.
+fn union_members(schema: &Value) -> Vec<Value> {
+    match schema.get("oneOf").and_then(|v| v.as_array()) {
+        Some(members) => members.iter().flat_map(union_members).collect(),
+        None if schema.get("type").is_some() => vec![without_nullable(schema)],
+        None => Vec::new(),
+    }
+}
+
+fn same_type(a: &Value, b: &Value) -> bool {
+    match (a.get("type").and_then(|t| t.as_str()), b.get("type").and_then(|t| t.as_str())) {
+        (Some("integer" | "number"), Some("integer" | "number")) => true,
+        (Some(x), Some(y)) => x == y,
+        _ => false,
+    }
+}
+
+fn is_nullable(schema: &Value) -> bool {
+    schema.get("nullable").and_then(|n| n.as_bool()).unwrap_or(false)
+        || schema.get("oneOf").and_then(|v| v.as_array()).is_some_and(|members| members.iter().any(is_nullable))
+}
+
+fn without_nullable(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    if let Some(obj) = schema.as_object_mut() {
+        obj.remove("nullable");
+    }
+    schema
+}
+
+fn with_nullable(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    if let Some(obj) = schema.as_object_mut() {
+        obj.insert("nullable".to_string(), Value::Bool(true));
+    }
+    schema
+}
+
+async fn mark_logs_as_processed(db: &PgPool, domain: &str) -> Result<(), axum::http::StatusCode> {
+    let result = sqlx::query!(
+        "UPDATE api_discovery_logs SET processed = TRUE WHERE domain = $1 AND processed = FALSE",
+        domain
+    )
+    .execute(db)
+    .await
+    .map_err(db_error)?;
+
+    tracing::info!("Marked {} API logs for domain {} as processed", result.rows_affected(), domain);
+    Ok(())
+}
+
+pub async fn generate_client_stubs(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+    Query(params): Query<GenerateClientStubsQuery>,
+) -> Result<Json<Value>, axum::http::StatusCode> {
+    let (openapi_spec, _) = build_current_spec(&state.db, &domain).await?;
+    
+    let mut stubs = HashMap::new();
+    
+    for language in &params.languages {
+        match language.as_str() {
+            "typescript" => {
+                let typescript_stub = client_stubs::generate_typescript_client(&openapi_spec);
+                stubs.insert(language.clone(), serde_json::json!({
+                    "language": language,
+                    "content": typescript_stub,
+                    "filename": format!("{}.ts", domain.replace(".", "_"))
+                }));
+            },
+            "python" => {
+                let python_stub = client_stubs::generate_python_client(&openapi_spec);
+                stubs.insert(language.clone(), serde_json::json!({
+                    "language": language,
+                    "content": python_stub,
+                    "filename": format!("{}.py", domain.replace(".", "_"))
+                }));
+            },
+            "javascript" => {
+                let javascript_stub = client_stubs::generate_javascript_client(&openapi_spec);
+                stubs.insert(language.clone(), serde_json::json!({
+                    "language": language,
+                    "content": javascript_stub,
+                    "filename": format!("{}.js", domain.replace(".", "_"))
+                }));
+            },
+            _ => {}
+        }
+    }
+    
+    Ok(Json(serde_json::json!({
+        "domain": domain,
+        "stubs": stubs
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateClientStubsQuery {
+    pub languages: Vec<String>,
+}
+
+fn format_operation_name(method: &str, path: &str) -> String {
+    let path_parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let mut name = method.to_string();
+    
+    for part in path_parts {
+        if part.starts_with('{') && part.ends_with('}') {
+            name.push_str("ById");
+        } else {
+            let mut chars = part.chars();
+            if let Some(first) = chars.next() {
+                name.extend(first.to_uppercase());
+                name.push_str(chars.as_str());
+            }
+        }
+    }
+    
+    name
+}
+
+pub async fn download_client_stub(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+    Query(params): Query<DownloadStubQuery>,
+) -> Result<axum::response::Response, axum::http::StatusCode> {
+    let stub_query = GenerateClientStubsQuery { languages: vec![params.language.clone()] };
+    let stubs_response = generate_client_stubs(State(state), SafePath(domain.to_string()), Query(stub_query)).await?;
+    let stubs = stubs_response["stubs"].as_object().unwrap();
+    
+    let stub_data = stubs.get(&params.language)
+        .and_then(|s| s["content"].as_str())
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    
+    let filename = stubs.get(&params.language)
+        .and_then(|s| s["filename"].as_str())
+        .unwrap_or("client.txt");
+    
+    let headers = [
+        ("Content-Type", "text/plain"),
+        ("Content-Disposition", &format!("attachment; filename=&quot;{}&quot;", filename)),
+    ];
+    
+    Ok(axum::response::Response::builder()
+        .status(200)
+        .header("Content-Type", "text/plain")
+        .header("Content-Disposition", &format!("attachment; filename=&quot;{}&quot;", filename))
+        .body(axum::body::Body::from(stub_data.to_string()))
+        .unwrap())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadStubQuery {
+    pub language: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn merged(samples: &[Value]) -> Value {
+        samples.iter().map(infer_json_schema).reduce(|a, b| merge_schemas(&a, &b)).unwrap()
+    }
+
+    #[test]
+    fn three_types_merge_into_one_flat_one_of() {
+        let schema = merged(&[json!(1), json!("one"), json!(true)]);
+        assert_eq!(schema, json!({"oneOf": [{"type": "integer"}, {"type": "string"}, {"type": "boolean"}]}));
+
+        // Repeats of a type already present do not add members or nest
+        let again = merge_schemas(&schema, &infer_json_schema(&json!("two")));
+        assert_eq!(again, schema);
+        let widened = merge_schemas(&schema, &infer_json_schema(&json!(1.5)));
+        assert_eq!(widened["oneOf"][0], json!({"type": "number"}));
+        assert_eq!(widened["oneOf"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn nulls_survive_unions_in_any_order() {
+        let expected = json!({"oneOf": [{"type": "integer"}, {"type": "string"}], "nullable": true});
+        assert_eq!(merged(&[json!(null), json!(1), json!("one")]), expected);
+        assert_eq!(merged(&[json!(1), json!(null), json!("one")]), expected);
+        assert_eq!(merged(&[json!(1), json!("one"), json!(null)]), expected);
+        assert_eq!(merged(&[json!("a"), json!(null), json!("b")]), json!({"type": "string", "nullable": true}));
+    }
+
+    #[test]
+    fn unions_merge_object_members_field_by_field() {
+        let schema = merged(&[json!({"a": 1}), json!("text"), json!({"a": 2, "b": true})]);
+        let members = schema["oneOf"].as_array().unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0]["required"], json!(["a"]));
+        assert!(members[0]["properties"].get("b").is_some());
+    }
+}
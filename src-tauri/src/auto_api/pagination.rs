@@ -0,0 +1,276 @@
+// Pagination style detection for discovered operations
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::{extract_query_parameters, header_value, logs_by_operation, ApiDiscoveryLog, OpenApiPathItem};
+
+pub const PAGINATION_EXTENSION: &str = "x-pagination";
+
+const CURSOR_PARAMS: &[&str] = &[
+    "cursor", "after", "page_token", "pageToken", "next_token", "nextToken",
+    "starting_after", "continuation_token", "continuationToken",
+];
+const PAGE_PARAMS: &[&str] = &["page", "page_number", "pageNumber"];
+const OFFSET_PARAMS: &[&str] = &["offset", "skip", "start"];
+const SIZE_PARAMS: &[&str] = &[
+    "limit", "per_page", "perPage", "page_size", "pageSize", "size", "count", "max_results", "maxResults", "take",
+];
+
+const CURSOR_FIELDS: &[&str] = &[
+    "next_cursor", "nextCursor", "cursor", "next_page_token", "nextPageToken", "next_token", "nextToken",
+    "continuation_token", "continuationToken",
+];
+const HAS_MORE_FIELDS: &[&str] = &["has_more", "hasMore", "has_next_page", "hasNextPage"];
+const TOTAL_PAGES_FIELDS: &[&str] = &["total_pages", "totalPages", "last_page", "lastPage", "page_count", "pageCount"];
+const ITEMS_FIELDS: &[&str] = &["data", "items", "results", "records", "entries", "list"];
+const ENVELOPE_FIELDS: &[&str] = &["meta", "pagination", "paging", "page_info", "pageInfo", "links"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaginationStyle {
+    LinkHeader,
+    Cursor,
+    Page,
+    Offset,
+}
+
+/// Stored as the `x-pagination` extension on an operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginationInfo {
+    pub style: PaginationStyle,
+    /// Query parameter that selects the page: the cursor, page number or offset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_param: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_param: Option<String>,
+    /// Dotted path to the next cursor in the response body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_more_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_pages_path: Option<String>,
+    /// Dotted path to the item array; absent when the body itself is the array.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_page: Option<i64>,
+}
+
+/// Adds `x-pagination` to every operation whose traffic shows a paging scheme.
+pub fn annotate_pagination(paths: &mut HashMap<String, OpenApiPathItem>, logs: &[ApiDiscoveryLog]) {
+    for ((path, method), samples) in logs_by_operation(logs) {
+        let Some(Some(operation)) = paths.get_mut(&path).and_then(|item| item.operation_mut(&method)) else {
+            continue;
+        };
+        // GraphQL pages through variables, not the transport
+        if operation.extensions.contains_key("x-graphql-operations") {
+            continue;
+        }
+        if let Some(info) = detect_pagination(&samples) {
+            if let Ok(value) = serde_json::to_value(&info) {
+                operation.extensions.insert(PAGINATION_EXTENSION.to_string(), value);
+            }
+        }
+    }
+}
+
+pub fn detect_pagination(samples: &[&ApiDiscoveryLog]) -> Option<PaginationInfo> {
+    let query: Vec<(String, String)> = samples.iter().flat_map(|log| extract_query_parameters(&log.url)).collect();
+    let bodies: Vec<Value> = samples
+        .iter()
+        .filter(|log| log.response_status.is_none_or(|s| (200..300).contains(&s)))
+        .filter_map(|log| log.response_body.as_deref())
+        .filter_map(|body| serde_json::from_str(body).ok())
+        .collect();
+
+    let find_param = |candidates: &[&str]| {
+        candidates.iter().find(|c| query.iter().any(|(name, _)| name == *c)).map(|c| c.to_string())
+    };
+    let find_field = |candidates: &[&str]| bodies.iter().find_map(|body| find_body_field(body, candidates));
+
+    let has_link_next = samples.iter().any(|log| {
+        header_value(log.response_headers.as_ref(), "link").is_some_and(|link| next_link(link).is_some())
+    });
+    let cursor_param = find_param(CURSOR_PARAMS);
+    let cursor_field = find_field(CURSOR_FIELDS);
+    let page_param = find_param(PAGE_PARAMS);
+    let offset_param = find_param(OFFSET_PARAMS);
+    let items = bodies.iter().find_map(items_path);
+
+    let style = if has_link_next {
+        PaginationStyle::LinkHeader
+    } else if cursor_param.is_some() || (cursor_field.is_some() && items.is_some()) {
+        PaginationStyle::Cursor
+    } else if page_param.is_some() {
+        PaginationStyle::Page
+    } else if offset_param.is_some() {
+        PaginationStyle::Offset
+    } else {
+        return None;
+    };
+
+    let page_param = match style {
+        PaginationStyle::LinkHeader => None,
+        PaginationStyle::Cursor => cursor_param.or_else(|| cursor_field.as_deref().map(cursor_param_for_field)),
+        PaginationStyle::Page => page_param,
+        PaginationStyle::Offset => offset_param,
+    };
+    let first_page = (style == PaginationStyle::Page)
+        .then(|| {
+            let name = page_param.as_deref()?;
+            query.iter().filter(|(n, _)| n == name).filter_map(|(_, v)| v.parse::<i64>().ok()).min().map(|min| min.min(1))
+        })
+        .flatten();
+
+    Some(PaginationInfo {
+        style,
+        size_param: find_param(SIZE_PARAMS),
+        next_cursor_path: if style == PaginationStyle::Cursor { cursor_field } else { None },
+        has_more_path: find_field(HAS_MORE_FIELDS),
+        total_pages_path: if style == PaginationStyle::Page { find_field(TOTAL_PAGES_FIELDS) } else { None },
+        items_path: items.flatten(),
+        first_page,
+        page_param,
+    })
+}
+
+/// Extracts the `rel="next"` target from an RFC 8288 Link header.
+pub fn next_link(link: &str) -> Option<&str> {
+    link.split(',').find_map(|entry| {
+        let (target, params) = entry.split_once(';')?;
+        let is_next = params.split(';').any(|p| {
+            let p = p.trim().replace(' ', "");
+            p == "rel=\"next\"" || p == "rel=next"
+        });
+        is_next.then(|| target.trim().trim_start_matches('<').trim_end_matches('>'))
+    })
+}
+
+/// Looks for a field at the top level or inside a common pagination envelope.
+fn find_body_field(body: &Value, candidates: &[&str]) -> Option<String> {
+    let object = body.as_object()?;
+    if let Some(name) = candidates.iter().find(|c| object.contains_key(**c)) {
+        return Some(name.to_string());
+    }
+    ENVELOPE_FIELDS.iter().find_map(|envelope| {
+        let inner = object.get(*envelope)?.as_object()?;
+        let name = candidates.iter().find(|c| inner.contains_key(**c))?;
+        Some(format!("{}.{}", envelope, name))
+    })
+}
+
+/// `Some(None)` when the body itself is the item array.
+fn items_path(body: &Value) -> Option<Option<String>> {
+    match body {
+        Value::Array(_) => Some(None),
+        Value::Object(object) => ITEMS_FIELDS
+            .iter()
+            .find(|name| object.get(**name).is_some_and(Value::is_array))
+            .map(|name| name.to_string())
+            .or_else(|| object.iter().find(|(_, v)| v.is_array()).map(|(name, _)| name.clone()))
+            .map(Some),
+        _ => None,
+    }
+}
+
+/// Guesses the request parameter that echoes a response cursor field.
+fn cursor_param_for_field(field: &str) -> String {
+    let name = field.rsplit('.').next().unwrap_or(field);
+    match name {
+        "next_cursor" | "nextCursor" | "cursor" => "cursor".to_string(),
+        "next_page_token" => "page_token".to_string(),
+        "nextPageToken" => "pageToken".to_string(),
+        "next_token" => "next_token".to_string(),
+        "nextToken" => "nextToken".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn log(url: &str, response_headers: Option<Value>, response_body: Value) -> ApiDiscoveryLog {
+        ApiDiscoveryLog {
+            id: Uuid::new_v4(),
+            user_id: None,
+            session_id: None,
+            domain: "api.example.com".to_string(),
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: json!({}),
+            request_body: None,
+            response_status: Some(200),
+            response_headers,
+            response_body: Some(response_body.to_string()),
+            timestamp: Utc::now(),
+            processed: false,
+            api_spec: None,
+            duration_ms: None,
+            redactions: json!([]),
+        }
+    }
+
+    fn detect(logs: &[ApiDiscoveryLog]) -> Option<PaginationInfo> {
+        detect_pagination(&logs.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn link_headers_win_over_query_parameters() {
+        let link = json!({"Link": "<https://api.example.com/items?page=3>; rel=\"next\", <https://api.example.com/items?page=9>; rel=\"last\""});
+        let info = detect(&[log("https://api.example.com/items?page=2", Some(link), json!([{"id": 1}]))]).unwrap();
+        assert_eq!(info.style, PaginationStyle::LinkHeader);
+        assert_eq!(info.page_param, None);
+        assert_eq!(info.items_path, None);
+        assert_eq!(next_link("</items?page=3>; rel=next"), Some("/items?page=3"));
+        assert_eq!(next_link("</items?page=1>; rel=\"prev\""), None);
+    }
+
+    #[test]
+    fn cursors_are_found_in_bodies_and_envelopes() {
+        let body = json!({"data": [{"id": 1}], "meta": {"next_cursor": "abc", "has_more": true}});
+        let info = detect(&[log("https://api.example.com/items?limit=20", None, body)]).unwrap();
+        assert_eq!(info.style, PaginationStyle::Cursor);
+        assert_eq!(info.page_param.as_deref(), Some("cursor"));
+        assert_eq!(info.size_param.as_deref(), Some("limit"));
+        assert_eq!(info.next_cursor_path.as_deref(), Some("meta.next_cursor"));
+        assert_eq!(info.has_more_path.as_deref(), Some("meta.has_more"));
+        assert_eq!(info.items_path.as_deref(), Some("data"));
+
+        let token = json!({"results": [], "nextPageToken": "t2"});
+        let info = detect(&[log("https://api.example.com/items?pageToken=t1", None, token)]).unwrap();
+        assert_eq!(info.page_param.as_deref(), Some("pageToken"));
+    }
+
+    #[test]
+    fn page_numbers_keep_the_lowest_first_page_seen() {
+        let logs = [
+            log("https://api.example.com/items?page=0&per_page=10", None, json!({"items": [], "total_pages": 4})),
+            log("https://api.example.com/items?page=2&per_page=10", None, json!({"items": [], "total_pages": 4})),
+        ];
+        let info = detect(&logs).unwrap();
+        assert_eq!(info.style, PaginationStyle::Page);
+        assert_eq!(info.page_param.as_deref(), Some("page"));
+        assert_eq!(info.first_page, Some(0));
+        assert_eq!(info.total_pages_path.as_deref(), Some("total_pages"));
+        assert_eq!(info.size_param.as_deref(), Some("per_page"));
+    }
+
+    #[test]
+    fn offsets_and_unpaginated_operations() {
+        let info = detect(&[log("https://api.example.com/items?offset=40&limit=20", None, json!([]))]).unwrap();
+        assert_eq!(info.style, PaginationStyle::Offset);
+        assert_eq!(info.page_param.as_deref(), Some("offset"));
+        assert_eq!(info.first_page, None);
+
+        assert_eq!(detect(&[log("https://api.example.com/items/1", None, json!({"id": 1}))]), None);
+        // A cursor-looking field without an item list is not pagination
+        assert_eq!(detect(&[log("https://api.example.com/sync", None, json!({"cursor": "abc"}))]), None);
+    }
+}
@@ -0,0 +1,186 @@
+// Rate-limit header detection for discovered operations
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{logs_by_operation, ApiDiscoveryLog, OpenApiPathItem};
+
+pub const RATE_LIMIT_EXTENSION: &str = "x-rate-limit";
+
+const LIMIT_HEADERS: &[&str] = &["x-ratelimit-limit", "x-rate-limit-limit", "ratelimit-limit"];
+const REMAINING_HEADERS: &[&str] = &["x-ratelimit-remaining", "x-rate-limit-remaining", "ratelimit-remaining"];
+const RESET_HEADERS: &[&str] = &["x-ratelimit-reset", "x-rate-limit-reset", "ratelimit-reset"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResetFormat {
+    DeltaSeconds,
+    EpochSeconds,
+    EpochMilliseconds,
+}
+
+/// Stored as the `x-rate-limit` extension on an operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_format: Option<ResetFormat>,
+    /// Highest limit the API advertised.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    pub retry_after: bool,
+    /// Only used while detecting; the count grows with every capture, so it
+    /// stays out of stored specs and their version fingerprints.
+    #[serde(skip)]
+    pub throttled_responses: usize,
+}
+
+/// Adds `x-rate-limit` to every operation whose responses carry rate-limit
+/// headers or were throttled.
+pub fn annotate_rate_limits(paths: &mut HashMap<String, OpenApiPathItem>, logs: &[ApiDiscoveryLog]) {
+    for ((path, method), samples) in logs_by_operation(logs) {
+        let Some(Some(operation)) = paths.get_mut(&path).and_then(|item| item.operation_mut(&method)) else {
+            continue;
+        };
+        if let Some(info) = detect_rate_limit(&samples) {
+            if let Ok(value) = serde_json::to_value(&info) {
+                operation.extensions.insert(RATE_LIMIT_EXTENSION.to_string(), value);
+            }
+        }
+    }
+}
+
+pub fn detect_rate_limit(samples: &[&ApiDiscoveryLog]) -> Option<RateLimitInfo> {
+    let mut info = RateLimitInfo {
+        limit_header: None,
+        remaining_header: None,
+        reset_header: None,
+        reset_format: None,
+        limit: None,
+        retry_after: false,
+        throttled_responses: 0,
+    };
+
+    for log in samples {
+        if log.response_status == Some(429) {
+            info.throttled_responses += 1;
+        }
+        let Some(headers) = log.response_headers.as_ref().and_then(|h| h.as_object()) else {
+            continue;
+        };
+
+        for (name, value) in headers {
+            let lower = name.to_lowercase();
+            let value = value.as_str().unwrap_or_default().trim();
+
+            if LIMIT_HEADERS.contains(&lower.as_str()) {
+                info.limit_header.get_or_insert_with(|| name.clone());
+                // IETF drafts allow "100, 100;w=60"; the first number is the active limit
+                if let Some(limit) = value.split([',', ';']).next().and_then(|v| v.trim().parse::<i64>().ok()) {
+                    info.limit = Some(info.limit.map_or(limit, |l| l.max(limit)));
+                }
+            } else if REMAINING_HEADERS.contains(&lower.as_str()) {
+                info.remaining_header.get_or_insert_with(|| name.clone());
+            } else if RESET_HEADERS.contains(&lower.as_str()) {
+                info.reset_header.get_or_insert_with(|| name.clone());
+                if info.reset_format.is_none() {
+                    info.reset_format = reset_format(value);
+                }
+            } else if lower == "retry-after" {
+                info.retry_after = true;
+            }
+        }
+    }
+
+    let detected = info.limit_header.is_some()
+        || info.remaining_header.is_some()
+        || info.reset_header.is_some()
+        || info.retry_after
+        || info.throttled_responses > 0;
+    detected.then_some(info)
+}
+
+/// Tells reset timestamps from countdowns by magnitude.
+fn reset_format(value: &str) -> Option<ResetFormat> {
+    let number = value.parse::<f64>().ok()?;
+    Some(if number >= 1e12 {
+        ResetFormat::EpochMilliseconds
+    } else if number >= 1e9 {
+        ResetFormat::EpochSeconds
+    } else {
+        ResetFormat::DeltaSeconds
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    fn log(status: i32, response_headers: Value) -> ApiDiscoveryLog {
+        ApiDiscoveryLog {
+            id: Uuid::new_v4(),
+            user_id: None,
+            session_id: None,
+            domain: "api.example.com".to_string(),
+            method: "GET".to_string(),
+            url: "https://api.example.com/items".to_string(),
+            headers: json!({}),
+            request_body: None,
+            response_status: Some(status),
+            response_headers: Some(response_headers),
+            response_body: None,
+            timestamp: Utc::now(),
+            processed: false,
+            api_spec: None,
+            duration_ms: None,
+            redactions: json!([]),
+        }
+    }
+
+    fn detect(logs: &[ApiDiscoveryLog]) -> Option<RateLimitInfo> {
+        detect_rate_limit(&logs.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn headers_keep_their_original_names_and_the_highest_limit() {
+        let logs = [
+            log(200, json!({"X-RateLimit-Limit": "60", "X-RateLimit-Remaining": "59", "X-RateLimit-Reset": "1700000000"})),
+            log(200, json!({"X-RateLimit-Limit": "100, 100;w=60", "X-RateLimit-Remaining": "12"})),
+        ];
+        let info = detect(&logs).unwrap();
+        assert_eq!(info.limit_header.as_deref(), Some("X-RateLimit-Limit"));
+        assert_eq!(info.remaining_header.as_deref(), Some("X-RateLimit-Remaining"));
+        assert_eq!(info.reset_header.as_deref(), Some("X-RateLimit-Reset"));
+        assert_eq!(info.reset_format, Some(ResetFormat::EpochSeconds));
+        assert_eq!(info.limit, Some(100));
+        assert!(!info.retry_after);
+    }
+
+    #[test]
+    fn reset_formats_are_told_apart_by_magnitude() {
+        assert_eq!(reset_format("30"), Some(ResetFormat::DeltaSeconds));
+        assert_eq!(reset_format("1700000000"), Some(ResetFormat::EpochSeconds));
+        assert_eq!(reset_format("1700000000000"), Some(ResetFormat::EpochMilliseconds));
+        assert_eq!(reset_format("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn throttled_responses_count_without_headers() {
+        let info = detect(&[log(429, json!({"Retry-After": "5"})), log(429, json!({}))]).unwrap();
+        assert!(info.retry_after);
+        assert_eq!(info.throttled_responses, 2);
+        assert_eq!(info.limit_header, None);
+        // The count is detection-only and never reaches a stored spec
+        assert!(serde_json::to_value(&info).unwrap().get("throttledResponses").is_none());
+
+        assert!(detect(&[log(200, json!({"Content-Type": "application/json"}))]).is_none());
+    }
+}
@@ -0,0 +1,413 @@
+// Websocket and server-sent event capture, described as an AsyncAPI 2.x document
+use axum::{
+    extract::State,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::security::{ConsentPurpose, UserContext};
+
+use super::{consent_skipped, db_error, extract_path_from_url, extract_path_parameters, infer_json_schema, merge_schemas, AutoApiState};
+use crate::security::SafePath;
+
+/// JSON fields that name the message type inside a websocket frame.
+const DISCRIMINATOR_FIELDS: &[&str] = &["type", "event", "action", "op"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamProtocol {
+    Websocket,
+    Sse,
+}
+
+impl StreamProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamProtocol::Websocket => "websocket",
+            StreamProtocol::Sse => "sse",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamDirection {
+    Send,    // client to server
+    Receive, // server to client
+}
+
+impl StreamDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamDirection::Send => "send",
+            StreamDirection::Receive => "receive",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StreamMessage {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub session_id: Option<String>,
+    pub domain: String,
+    pub connection_id: Option<String>,
+    pub connection_url: String,
+    pub protocol: String,
+    pub direction: String,
+    pub event: Option<String>, // SSE `event:` field
+    pub payload: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub redactions: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogStreamMessagesRequest {
+    pub session_id: Option<String>,
+    pub domain: String,
+    pub connection_id: Option<String>,
+    pub connection_url: String,
+    pub protocol: StreamProtocol,
+    pub messages: Vec<CapturedStreamMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CapturedStreamMessage {
+    pub direction: StreamDirection,
+    pub event: Option<String>,
+    pub payload: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+pub async fn log_stream_messages(
+    State(state): State<AutoApiState>,
+    user: Option<Extension<UserContext>>,
+    Json(mut request): Json<LogStreamMessagesRequest>,
+) -> Result<Json<Value>, axum::http::StatusCode> {
+    let user_id = user.map(|Extension(context)| context.user_id);
+    if !state.consent.check(user_id, ConsentPurpose::TrafficCapture).await {
+        return Ok(Json(consent_skipped()));
+    }
+
+    // Event streams only flow from server to client
+    if request.protocol == StreamProtocol::Sse && request.messages.iter().any(|m| m.direction == StreamDirection::Send) {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    let redactions = state.redaction.redact_stream(&mut request);
+
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    for (message, markers) in request.messages.iter().zip(&redactions) {
+        sqlx::query!(
+            r#"
+            INSERT INTO api_stream_messages
+                (id, user_id, session_id, domain, connection_id, connection_url, protocol,
+                 direction, event, payload, timestamp, redactions)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            "#,
+            Uuid::new_v4(),
+            user_id,
+            request.session_id,
+            request.domain,
+            request.connection_id,
+            request.connection_url,
+            request.protocol.as_str(),
+            message.direction.as_str(),
+            message.event,
+            message.payload,
+            message.timestamp.unwrap_or_else(Utc::now),
+            serde_json::to_value(markers).unwrap_or_else(|_| Value::Array(Vec::new()))
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(Json(json!({
+        "success": true,
+        "logged": request.messages.len(),
+        "redacted": redactions.iter().map(|m| m.len()).sum::<usize>()
+    })))
+}
+
+pub async fn get_asyncapi_spec(
+    State(state): State<AutoApiState>,
+    SafePath(domain): SafePath<String>,
+) -> Result<Json<Value>, axum::http::StatusCode> {
+    let messages = get_stream_messages_by_domain(&state.db, &domain).await?;
+    if messages.is_empty() {
+        return Err(axum::http::StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(build_asyncapi_spec(&domain, &messages)))
+}
+
+/// Busy sockets produce far more frames than HTTP logs, so the AsyncAPI
+/// document is built from the same window as specs with its own sample cap.
+const STREAM_WINDOW_MAX_MESSAGES: i64 = 5000;
+
+pub(crate) async fn get_stream_messages_by_domain(db: &PgPool, domain: &str) -> Result<Vec<StreamMessage>, axum::http::StatusCode> {
+    let mut messages = sqlx::query_as!(
+        StreamMessage,
+        r#"
+        SELECT id, user_id, session_id, domain, connection_id, connection_url, protocol,
+               direction, event, payload, timestamp, redactions
+        FROM api_stream_messages
+        WHERE domain = $1 AND timestamp >= NOW() - make_interval(days => $2)
+        ORDER BY timestamp DESC
+        LIMIT $3
+        "#,
+        domain,
+        super::SPEC_WINDOW_DAYS,
+        STREAM_WINDOW_MAX_MESSAGES
+    )
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+
+    // Oldest first, so each message type's example is its earliest sample
+    messages.reverse();
+    Ok(messages)
+}
+
+/// Accumulates the samples seen for one message type on one channel.
+struct MessageShape {
+    name: String,
+    direction: String,
+    content_type: &'static str,
+    schema: Option<Value>,
+    example: Option<Value>,
+    count: usize,
+}
+
+/// Protocol, path parameters and message shapes seen on one channel.
+type ChannelShapes = (String, Vec<String>, BTreeMap<String, MessageShape>);
+
+/// Builds an AsyncAPI 2.6 document from captured stream traffic. Each
+/// connection path is a channel; messages are split by direction and by SSE
+/// event name or the frame's `type`-like discriminator field.
+pub fn build_asyncapi_spec(domain: &str, messages: &[StreamMessage]) -> Value {
+    let mut servers = BTreeMap::new();
+    let mut channels: BTreeMap<String, ChannelShapes> = BTreeMap::new();
+
+    for message in messages {
+        let (server_name, server) = server_for(&message.connection_url, &message.protocol);
+        servers.entry(server_name).or_insert(server);
+
+        let (channel, params) = extract_path_parameters(&extract_path_from_url(&message.connection_url));
+        let parsed = message.payload.as_deref().map(|p| serde_json::from_str::<Value>(p).map_err(|_| p));
+        let name = message_name(message, parsed.as_ref().and_then(|p| p.as_ref().ok()));
+        // Component names are global, so they carry the channel as well
+        let id = format!("{}{}{}", message.direction, to_pascal_case(&channel), to_pascal_case(&name));
+
+        let (_, _, shapes) = channels.entry(channel).or_insert_with(|| (message.protocol.clone(), params, BTreeMap::new()));
+
+        let (sample_schema, example, content_type) = match parsed {
+            Some(Ok(value)) => (Some(infer_json_schema(&value)), Some(value), "application/json"),
+            Some(Err(text)) => (Some(json!({"type": "string"})), Some(Value::from(text)), "text/plain"),
+            None => (None, None, "application/json"),
+        };
+
+        let shape = shapes.entry(id).or_insert_with(|| MessageShape {
+            name: name.clone(),
+            direction: message.direction.clone(),
+            content_type,
+            schema: None,
+            example: None,
+            count: 0,
+        });
+        shape.count += 1;
+        shape.schema = match (shape.schema.take(), sample_schema) {
+            (Some(existing), Some(incoming)) => Some(merge_schemas(&existing, &incoming)),
+            (existing, incoming) => existing.or(incoming),
+        };
+        if shape.example.is_none() {
+            shape.example = example;
+        }
+    }
+
+    let mut channel_docs = Map::new();
+    let mut component_messages = Map::new();
+
+    for (channel, (protocol, params, shapes)) in channels {
+        let mut publish = Vec::new();
+        let mut subscribe = Vec::new();
+
+        for (id, shape) in shapes {
+            let reference = json!({"$ref": format!("#/components/messages/{}", id)});
+            // AsyncAPI 2.x operations are from the server's point of view:
+            // clients publish what they send and subscribe to what they receive
+            if shape.direction == StreamDirection::Send.as_str() {
+                publish.push(reference);
+            } else {
+                subscribe.push(reference);
+            }
+
+            let mut message = json!({
+                "name": shape.name,
+                "title": shape.name,
+                "contentType": shape.content_type,
+                // AsyncAPI payloads are JSON Schema, which has no `nullable`
+                "payload": shape.schema.map(|s| super::export::schema_to_31(&s)).unwrap_or_else(|| json!({})),
+                "x-sample-count": shape.count,
+            });
+            if let Some(example) = shape.example {
+                message["examples"] = json!([{"payload": example}]);
+            }
+            component_messages.insert(id, message);
+        }
+
+        let mut channel_doc = json!({"x-transport": protocol});
+        if !params.is_empty() {
+            let parameters: Map<String, Value> = params.into_iter().map(|p| (p, json!({"schema": {"type": "string"}}))).collect();
+            channel_doc["parameters"] = Value::Object(parameters);
+        }
+        if !publish.is_empty() {
+            channel_doc["publish"] = json!({"operationId": format!("send{}", to_pascal_case(&channel)), "message": one_or_many(publish)});
+        }
+        if !subscribe.is_empty() {
+            channel_doc["subscribe"] = json!({"operationId": format!("receive{}", to_pascal_case(&channel)), "message": one_or_many(subscribe)});
+        }
+        channel_docs.insert(channel, channel_doc);
+    }
+
+    json!({
+        "asyncapi": "2.6.0",
+        "info": {
+            "title": format!("{} Streaming API", domain),
+            "version": "1.0.0",
+            "description": format!("Auto-generated from captured websocket and event-stream traffic on {}", domain),
+        },
+        "defaultContentType": "application/json",
+        "servers": servers,
+        "channels": channel_docs,
+        "components": {"messages": component_messages},
+    })
+}
+
+pub fn to_asyncapi_yaml(document: &Value) -> Result<String, serde_yaml::Error> {
+    serde_yaml::to_string(document)
+}
+
+fn server_for(connection_url: &str, protocol: &str) -> (String, Value) {
+    let Ok(parsed) = url::Url::parse(connection_url) else {
+        return ("default".to_string(), json!({"url": connection_url, "protocol": protocol}));
+    };
+
+    let host = match parsed.port() {
+        Some(port) => format!("{}:{}", parsed.host_str().unwrap_or_default(), port),
+        None => parsed.host_str().unwrap_or_default().to_string(),
+    };
+    let name = format!("{}-{}", parsed.scheme(), host.replace([':', '.'], "-"));
+    (name, json!({"url": host, "protocol": parsed.scheme()}))
+}
+
+fn message_name(message: &StreamMessage, payload: Option<&Value>) -> String {
+    if let Some(event) = message.event.as_deref().filter(|e| !e.is_empty()) {
+        return event.to_string();
+    }
+
+    DISCRIMINATOR_FIELDS
+        .iter()
+        .find_map(|field| payload?.get(*field)?.as_str())
+        .filter(|name| !name.is_empty() && name.len() <= 64)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| "message".to_string())
+}
+
+fn one_or_many(mut references: Vec<Value>) -> Value {
+    if references.len() == 1 {
+        references.remove(0)
+    } else {
+        json!({"oneOf": references})
+    }
+}
+
+fn to_pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn message(url: &str, protocol: &str, direction: &str, event: Option<&str>, payload: Option<&str>) -> StreamMessage {
+        StreamMessage {
+            id: Uuid::new_v4(),
+            user_id: None,
+            session_id: None,
+            domain: "chat.example.com".to_string(),
+            connection_id: Some("c1".to_string()),
+            connection_url: url.to_string(),
+            protocol: protocol.to_string(),
+            direction: direction.to_string(),
+            event: event.map(str::to_string),
+            payload: payload.map(str::to_string),
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            redactions: json!([]),
+        }
+    }
+
+    #[test]
+    fn asyncapi_document_has_the_26_shape() {
+        let messages = [
+            message("wss://chat.example.com/rooms/42/socket", "websocket", "send", None, Some(r#"{"type":"chat.send","text":"hi"}"#)),
+            message("wss://chat.example.com/rooms/42/socket", "websocket", "send", None, Some(r#"{"type":"ping"}"#)),
+            message("wss://chat.example.com/rooms/7/socket", "websocket", "receive", None, Some(r#"{"type":"chat.message","text":"hi","edited":null}"#)),
+            message("wss://chat.example.com/rooms/7/socket", "websocket", "receive", None, Some(r#"{"type":"chat.message","text":"yo","edited":true}"#)),
+            message("https://chat.example.com:8443/events", "sse", "receive", Some("tick"), Some("heartbeat")),
+        ];
+        let document = build_asyncapi_spec("chat.example.com", &messages);
+
+        assert_eq!(document["asyncapi"], "2.6.0");
+        assert_eq!(document["info"]["title"], "chat.example.com Streaming API");
+        assert_eq!(
+            document["servers"],
+            json!({
+                "wss-chat-example-com": {"url": "chat.example.com", "protocol": "wss"},
+                "https-chat-example-com-8443": {"url": "chat.example.com:8443", "protocol": "https"}
+            })
+        );
+
+        let channels = document["channels"].as_object().unwrap();
+        assert_eq!(channels.keys().collect::<Vec<_>>(), vec!["/events", "/rooms/{id}/socket"]);
+        let socket = &channels["/rooms/{id}/socket"];
+        assert_eq!(socket["x-transport"], "websocket");
+        assert_eq!(socket["parameters"], json!({"id": {"schema": {"type": "string"}}}));
+        assert_eq!(socket["publish"]["operationId"], "sendRoomsIdSocket");
+        assert_eq!(socket["publish"]["message"]["oneOf"].as_array().unwrap().len(), 2);
+        assert_eq!(socket["subscribe"]["message"], json!({"$ref": "#/components/messages/receiveRoomsIdSocketChatMessage"}));
+
+        let messages = &document["components"]["messages"];
+        let received = &messages["receiveRoomsIdSocketChatMessage"];
+        assert_eq!(received["name"], "chat.message");
+        assert_eq!(received["x-sample-count"], 2);
+        assert_eq!(received["payload"]["properties"]["edited"], json!({"type": ["boolean", "null"]}));
+        assert_eq!(received["examples"][0]["payload"]["text"], "hi");
+
+        let tick = &messages["receiveEventsTick"];
+        assert_eq!(tick["contentType"], "text/plain");
+        assert_eq!(tick["payload"], json!({"type": "string"}));
+        assert_eq!(channels["/events"]["subscribe"]["message"]["$ref"], "#/components/messages/receiveEventsTick");
+        assert!(channels["/events"].get("publish").is_none());
+
+        let yaml = to_asyncapi_yaml(&document).unwrap();
+        assert!(yaml.starts_with("asyncapi: 2.6.0"));
+    }
+}
@@ -4,6 +4,7 @@ use chrono::{DateTime, Utc};
 use sqlx::{PgPool, Row};
 
 use crate::commands::{CommandResult, DatabasePool};
+use crate::plugins::{PluginEvent, PluginEventBus};
 use super::products::Product;
 use super::promotions::PromotionService;
 
@@ -103,6 +104,7 @@ pub struct OrderAnalytics {
 pub struct OrderService {
     db_pool: sqlx::PgPool,
     promotion_service: PromotionService,
+    plugin_events: Option<PluginEventBus>,
 }
 
 impl OrderService {
@@ -110,9 +112,16 @@ impl OrderService {
         Self {
             db_pool: db_pool.clone(),
             promotion_service: PromotionService::new(db_pool, None),
+            plugin_events: None,
         }
     }
 
+    /// Announce new orders to the customer's plugins.
+    pub fn with_plugin_events(mut self, events: PluginEventBus) -> Self {
+        self.plugin_events = Some(events);
+        self
+    }
+
     /// Create new order
     pub async fn create_order(&self, request: CreateOrderRequest) -> CommandResult<OrderSummary> {
         let mut conn = self.db_pool.acquire().await
@@ -273,6 +282,19 @@ impl OrderService {
             false,
         ).await?;
 
+        if let Some(events) = &self.plugin_events {
+            events.publish(
+                PluginEvent::OrderCreated,
+                order.customer_id,
+                serde_json::json!({
+                    "order_id": order.id,
+                    "order_number": order.order_number,
+                    "total_amount": order.total_amount,
+                    "currency": order.currency,
+                }),
+            );
+        }
+
         Ok(OrderSummary {
             order,
             items: items_with_product,
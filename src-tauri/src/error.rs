@@ -23,12 +23,15 @@ pub enum AppError {
     #[error("Service unavailable: {0}")]
     ServiceUnavailable(String),
     
-    #[error("Rate limit exceeded")]
-    RateLimitExceeded,
+    #[error("Rate limit exceeded, retry after {retry_after}s")]
+    RateLimitExceeded { retry_after: u64 },
     
     #[error("Cost limit exceeded")]
     CostLimitExceeded,
     
+    #[error("Request body exceeds {limit} bytes")]
+    PayloadTooLarge { limit: usize },
+    
     #[error("Permission denied: {0}")]
     PermissionDenied(String),
     
@@ -56,6 +59,10 @@ pub enum AppError {
 
 impl axum::response::IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
+        let retry_after = match self {
+            AppError::RateLimitExceeded { retry_after } => Some(retry_after),
+            _ => None,
+        };
         let (status, error_message) = match self {
             AppError::Database(ref e) => {
                 tracing::error!("Database error: {:?}", e);
@@ -76,12 +83,15 @@ impl axum::response::IntoResponse for AppError {
             AppError::ServiceUnavailable(ref service) => {
                 (axum::http::StatusCode::SERVICE_UNAVAILABLE, service.as_str())
             }
-            AppError::RateLimitExceeded => {
+            AppError::RateLimitExceeded { .. } => {
                 (axum::http::StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded")
             }
             AppError::CostLimitExceeded => {
                 (axum::http::StatusCode::PAYMENT_REQUIRED, "Cost limit exceeded")
             }
+            AppError::PayloadTooLarge { .. } => {
+                (axum::http::StatusCode::PAYLOAD_TOO_LARGE, "Request body too large")
+            }
             AppError::PermissionDenied(ref msg) => {
                 (axum::http::StatusCode::FORBIDDEN, msg.as_str())
             }
@@ -99,7 +109,11 @@ impl axum::response::IntoResponse for AppError {
             "status": status.as_u16()
         });
 
-        (status, axum::Json(body)).into_response()
+        let mut response = (status, axum::Json(body)).into_response();
+        if let Some(seconds) = retry_after {
+            response.headers_mut().insert(axum::http::header::RETRY_AFTER, axum::http::HeaderValue::from(seconds));
+        }
+        response
     }
 }
 
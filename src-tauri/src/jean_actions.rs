@@ -0,0 +1,160 @@
+// Jean action approval: pending assistant actions wait here for the user to confirm them
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::security::{
+    elevated_token_from_headers, elevation_required_response, record_audit_event, verify_elevated_session, ClientInfo,
+    UserContext,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+/// Risk of an intent keyword Jean extracted from a request.
+pub fn keyword_risk_level(keyword: &str) -> RiskLevel {
+    match keyword {
+        "file_delete" | "system_operation" | "financial_transaction" | "outbound_message" => RiskLevel::High,
+        "file_operation" | "browser_tab" => RiskLevel::Medium,
+        _ => RiskLevel::Low,
+    }
+}
+
+/// Risk of a stored `jean_actions_log` entry, on the same scale Jean applies
+/// to the request that proposed it. Types not listed here are at least medium
+/// risk, since nothing is known about what executing them does.
+pub fn action_risk_level(action_type: &str) -> RiskLevel {
+    let keyword = match action_type {
+        "delete_file" | "delete_directory" => "file_delete",
+        "docker_control" | "restart_service" => "system_operation",
+        "purchase" | "checkout" | "place_order" | "apply_pricing" => "financial_transaction",
+        "send_email" => "outbound_message",
+        "open_tab" | "close_tab" | "navigate" => "browser_tab",
+        other => {
+            return match keyword_risk_level(other) {
+                RiskLevel::Low => RiskLevel::Medium,
+                level => level,
+            }
+        }
+    };
+    keyword_risk_level(keyword)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApprovedAction {
+    pub action_id: Uuid,
+    pub action_type: String,
+    pub risk_level: RiskLevel,
+    pub status: String,
+    pub confirmed_at: DateTime<Utc>,
+}
+
+/// Confirms a pending action; high-risk actions need an elevated session from step-up authentication.
+pub async fn approve_action(
+    State(db): State<PgPool>,
+    Path(action_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    headers: HeaderMap,
+) -> Result<Json<ApprovedAction>, Response> {
+    let Some(Extension(user)) = user else {
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    };
+    let action = sqlx::query!(
+        "SELECT action_type FROM jean_actions_log WHERE id = $1 AND user_id = $2 AND status = 'pending'",
+        action_id,
+        user.user_id
+    )
+    .fetch_optional(&db)
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+
+    let risk_level = action_risk_level(&action.action_type);
+    if risk_level == RiskLevel::High {
+        let Some(token) = elevated_token_from_headers(&headers) else {
+            return Err(elevation_required_response());
+        };
+        if !verify_elevated_session(&db, user.user_id, token).await.map_err(internal_error)? {
+            return Err(elevation_required_response());
+        }
+    }
+
+    // The status guard keeps a concurrent approval from confirming twice
+    let confirmed_at = sqlx::query_scalar!(
+        r#"
+        UPDATE jean_actions_log
+        SET status = 'confirmed', confirmed_at = NOW()
+        WHERE id = $1 AND user_id = $2 AND status = 'pending'
+        RETURNING confirmed_at AS "confirmed_at!"
+        "#,
+        action_id,
+        user.user_id
+    )
+    .fetch_optional(&db)
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+
+    let action_name = if risk_level == RiskLevel::High { "jean_action.approved_with_step_up" } else { "jean_action.approved" };
+    record_audit_event(&db, Some(user.user_id), action_name, "jean_action", &action_id.to_string(), serde_json::json!({
+        "action_type": action.action_type,
+        "risk_level": risk_level
+    }), Some(&client)).await;
+
+    Ok(Json(ApprovedAction {
+        action_id,
+        action_type: action.action_type,
+        risk_level,
+        status: "confirmed".to_string(),
+        confirmed_at,
+    }))
+}
+
+fn internal_error(e: sqlx::Error) -> Response {
+    tracing::error!("Jean action database error: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn destructive_and_financial_actions_are_high_risk() {
+        for action_type in [
+            "delete_file",
+            "delete_directory",
+            "docker_control",
+            "restart_service",
+            "purchase",
+            "checkout",
+            "apply_pricing",
+            "send_email",
+        ] {
+            assert_eq!(action_risk_level(action_type), RiskLevel::High, "{}", action_type);
+        }
+        assert_eq!(action_risk_level("navigate"), RiskLevel::Medium);
+        assert_eq!(action_risk_level("file_operation"), RiskLevel::Medium);
+    }
+
+    #[test]
+    fn unknown_action_types_are_never_low_risk() {
+        for action_type in ["summarize_page", "run_scraper", "create_agent", ""] {
+            assert_eq!(action_risk_level(action_type), RiskLevel::Medium, "{}", action_type);
+        }
+        assert_eq!(action_risk_level("file_delete"), RiskLevel::High);
+    }
+}
@@ -9,6 +9,8 @@ use regex::Regex;
 use crate::models::{JeanMemory, JeanAction, JeanPermission, User};
 use crate::commands::{CommandResult, DatabasePool};
 use crate::jean_permissions::JeanPermissions;
+use crate::security::{ConsentGate, ConsentPurpose};
+pub use crate::jean_actions::{action_risk_level, keyword_risk_level, RiskLevel};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JeanRequest {
@@ -118,16 +120,19 @@ pub struct JeanCore {
     permission_manager: Arc<PermissionManager>,
     action_executor: Arc<ActionExecutor>,
     ai_client: Arc<AIClient>,
+    consent: ConsentGate,
 }
 
 impl JeanCore {
     pub fn new(db: Arc<DatabasePool>) -> Self {
+        let consent = ConsentGate::new(db.as_ref().clone());
         Self {
             db: db.clone(),
-            memory_store: Arc::new(RwLock::new(MemoryStore::new(db.clone()))),
+            memory_store: Arc::new(RwLock::new(MemoryStore::new(db.clone(), consent.clone()))),
             permission_manager: Arc::new(PermissionManager::new(db.clone())),
             action_executor: Arc::new(ActionExecutor::new()),
             ai_client: Arc::new(AIClient::new()),
+            consent,
         }
     }
 
@@ -285,6 +290,11 @@ impl JeanCore {
         request: &JeanRequest,
         response: &AIResponse
     ) -> Result<(), String> {
+        // Without memory_personalization consent the conversation goes on, it just isn't remembered
+        if !self.consent.check(Some(request.user_id), ConsentPurpose::MemoryPersonalization).await {
+            return Ok(());
+        }
+
         let memory = JeanMemory {
             id: Uuid::new_v4(),
             user_id: request.user_id,
@@ -341,14 +351,9 @@ impl JeanCore {
 
     fn assess_risk_level(&self, keywords: &[String]) -> RiskLevel {
         for keyword in keywords {
-            match keyword.as_str() {
-                "file_delete" | "system_operation" | "financial_transaction" => {
-                    return RiskLevel::High;
-                }
-                "file_operation" | "browser_tab" => {
-                    return RiskLevel::Medium;
-                }
-                _ => continue,
+            match keyword_risk_level(keyword) {
+                RiskLevel::Low => continue,
+                level => return level,
             }
         }
         RiskLevel::Low
@@ -424,13 +429,6 @@ pub struct ActionAnalysis {
     pub suggested_permissions: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
-pub enum RiskLevel {
-    Low,
-    Medium,
-    High,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AIResponse {
     pub message: String,
@@ -450,14 +448,20 @@ pub struct ActionResult {
 
 pub struct MemoryStore {
     db: Arc<DatabasePool>,
+    consent: ConsentGate,
 }
 
 impl MemoryStore {
-    pub fn new(db: Arc<DatabasePool>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<DatabasePool>, consent: ConsentGate) -> Self {
+        Self { db, consent }
     }
 
     pub async fn store_memory(&self, memory: JeanMemory) -> Result<(), String> {
+        self.consent
+            .require(Some(memory.user_id), ConsentPurpose::MemoryPersonalization)
+            .await
+            .map_err(|e| e.to_string())?;
+
         let mut conn = self.db.acquire().await
             .map_err(|e| format!("Database error: {}", e))?;
 
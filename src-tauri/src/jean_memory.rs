@@ -7,6 +7,8 @@ use std::sync::Arc;
 use regex::Regex;
 
 use crate::commands::{CommandResult, DatabasePool};
+use crate::plugins::{PluginEvent, PluginEventBus};
+use crate::security::{ConsentGate, ConsentPurpose};
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct MemoryFolder {
@@ -145,11 +147,20 @@ pub struct MemoryTag {
 #[derive(Debug, Clone)]
 pub struct JeanMemory {
     db: Arc<DatabasePool>,
+    consent: ConsentGate,
+    plugin_events: Option<PluginEventBus>,
 }
 
 impl JeanMemory {
     pub fn new(db: Arc<DatabasePool>) -> Self {
-        Self { db }
+        let consent = ConsentGate::new(db.as_ref().clone());
+        Self { db, consent, plugin_events: None }
+    }
+
+    /// Announce saved memories to the owner's plugins.
+    pub fn with_plugin_events(mut self, events: PluginEventBus) -> Self {
+        self.plugin_events = Some(events);
+        self
     }
 
     /// Create a new memory folder
@@ -232,6 +243,11 @@ impl JeanMemory {
 
     /// Create a new memory
     pub async fn create_memory(&self, request: MemoryCreateRequest) -> CommandResult<Memory> {
+        self.consent
+            .require(Some(request.user_id), ConsentPurpose::MemoryPersonalization)
+            .await
+            .map_err(|e| e.to_string())?;
+
         let mut conn = self.db.acquire().await
             .map_err(|e| format!("Failed to get connection: {}", e))?;
 
@@ -281,6 +297,20 @@ impl JeanMemory {
         .await
         .map_err(|e| format!("Failed to create memory: {}", e))?;
 
+        // Plugins only hear about non-private memories, and never their content
+        if let Some(events) = self.plugin_events.as_ref().filter(|_| !memory.is_private) {
+            events.publish(
+                PluginEvent::MemorySaved,
+                memory.user_id,
+                serde_json::json!({
+                    "memory_id": memory.id,
+                    "memory_type": memory.memory_type,
+                    "title": memory.title,
+                    "context_tags": memory.context_tags,
+                }),
+            );
+        }
+
         Ok(memory)
     }
 
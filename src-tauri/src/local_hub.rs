@@ -1,5 +1,5 @@
 // P2P Local Hub / Offline Chat Module
-use axum::{Json, extract::{Path, Query, State}, extract::Multipart, response::sse, ws::{WebSocket, Message}};
+use axum::{Json, extract::{Query, State}, extract::Multipart, response::sse, ws::{WebSocket, Message}};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
@@ -8,6 +8,7 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::{RwLock, broadcast};
 use tokio_stream::wrappers::BroadcastStream;
+use crate::security::SafePath;
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct LocalHubRoom {
@@ -198,7 +199,7 @@ pub async fn join_room(
 }
 
 pub async fn get_room_info(
-    Path(room_code): Path<String>,
+    SafePath(room_code): SafePath<String>,
 ) -> Result<Json<Value>, axum::http::StatusCode> {
     let rooms = RoomsStorage::default();
     let rooms_read = rooms.read().await;
@@ -225,8 +226,7 @@ pub async fn get_room_info(
 }
 
 pub async fn leave_room(
-    Path(room_code): Path<String>,
-    Path(peer_id): Path<String>,
+    SafePath((room_code, peer_id)): SafePath<(String, String)>,
 ) -> Result<Json<Value>, axum::http::StatusCode> {
     let rooms = RoomsStorage::default();
     let mut rooms_write = rooms.write().await;
@@ -258,7 +258,7 @@ pub async fn leave_room(
 }
 
 pub async fn send_message(
-    Path(room_code): Path<String>,
+    SafePath(room_code): SafePath<String>,
     Json(request): Json<SendMessageRequest>,
 ) -> Result<Json<Value>, axum::http::StatusCode> {
     let rooms = RoomsStorage::default();
@@ -305,7 +305,7 @@ async fn store_message_locally(message: &ChatMessage) {
 }
 
 pub async fn get_room_messages(
-    Path(room_code): Path<String>,
+    SafePath(room_code): SafePath<String>,
     Query(params): Query<GetMessagesQuery>,
 ) -> Result<Json<Vec<ChatMessage>>, axum::http::StatusCode> {
     // Get messages from local storage
@@ -333,8 +333,7 @@ pub struct GetMessagesQuery {
 
 // WebRTC Signalling
 pub async fn websocket_handler(
-    Path(room_code): Path<String>,
-    Path(peer_id): Path<String>,
+    SafePath((room_code, peer_id)): SafePath<(String, String)>,
     ws: WebSocket,
 ) -> Result<(), axum::http::StatusCode> {
     let rooms = RoomsStorage::default();
@@ -415,7 +414,7 @@ async fn handle_webrtc_signalling(room_code: &str, from_peer_id: &str, message:
 }
 
 pub async fn get_room_qr_code(
-    Path(room_code): Path<String>,
+    SafePath(room_code): SafePath<String>,
 ) -> Result<Json<Value>, axum::http::StatusCode> {
     use qrcode::QrCode;
     
@@ -438,7 +437,7 @@ pub async fn get_room_qr_code(
 }
 
 pub async fn export_chat_history(
-    Path(room_code): Path<String>,
+    SafePath(room_code): SafePath<String>,
 ) -> Result<Json<Value>, axum::http::StatusCode> {
     // Get all messages for room and export as JSON/CSV
     let export_data = serde_json::json!({
@@ -0,0 +1,213 @@
+// Assistant memories: what Jean remembers about a user, stored only with memory_personalization consent
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::plugins::{PluginEvent, PluginEventBus};
+use crate::security::{ConsentError, ConsentGate, ConsentPurpose, UserContext};
+
+const MEMORY_TYPES: [&str; 4] = ["conversation", "knowledge", "preference", "context"];
+const MAX_LISTED_MEMORIES: i64 = 100;
+
+#[derive(Clone)]
+pub struct MemoryState {
+    pub db: PgPool,
+    pub consent: ConsentGate,
+}
+
+impl MemoryState {
+    pub fn new(db: PgPool, consent: ConsentGate) -> Self {
+        Self { db, consent }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Memory {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub memory_type: String,
+    pub content: Value,
+    pub context_tags: Vec<String>,
+    pub session_id: Option<String>,
+    pub is_private: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveMemoryRequest {
+    pub memory_type: String,
+    pub content: Value,
+    #[serde(default)]
+    pub context_tags: Vec<String>,
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub is_private: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListMemoriesQuery {
+    pub memory_type: Option<String>,
+    pub limit: Option<i64>,
+}
+
+fn consent_error(e: ConsentError) -> Response {
+    if let ConsentError::Database(e) = &e {
+        tracing::error!("Memory consent check failed: {}", e);
+    }
+    (e.status_code(), Json(serde_json::json!({ "error": e.to_string() }))).into_response()
+}
+
+fn db_error(e: sqlx::Error) -> Response {
+    tracing::error!("Memory database error: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+}
+
+/// What plugins hear about a saved memory; the content stays with Jean.
+fn memory_saved_payload(memory: &Memory) -> Value {
+    serde_json::json!({
+        "memory_id": memory.id,
+        "memory_type": memory.memory_type,
+        "context_tags": memory.context_tags,
+        "session_id": memory.session_id,
+    })
+}
+
+/// Saves a memory for the caller; refused with 403 until they consent to memory personalization.
+/// Plugins hear about memories that are not private.
+pub async fn save_memory(
+    State(state): State<MemoryState>,
+    user: Option<Extension<UserContext>>,
+    events: Option<Extension<PluginEventBus>>,
+    Json(request): Json<SaveMemoryRequest>,
+) -> Result<Json<Memory>, Response> {
+    let Some(Extension(user)) = user else {
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    };
+    if !MEMORY_TYPES.contains(&request.memory_type.as_str()) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY.into_response());
+    }
+    state
+        .consent
+        .require(Some(user.user_id), ConsentPurpose::MemoryPersonalization)
+        .await
+        .map_err(consent_error)?;
+
+    let memory = sqlx::query_as!(
+        Memory,
+        r#"
+        INSERT INTO jean_memory (user_id, memory_type, content, context_tags, session_id, is_private)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, user_id, memory_type, content, context_tags AS "context_tags!",
+                  session_id, is_private AS "is_private!", created_at AS "created_at!"
+        "#,
+        user.user_id,
+        request.memory_type,
+        request.content,
+        &request.context_tags,
+        request.session_id,
+        request.is_private
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    if let (false, Some(Extension(events))) = (memory.is_private, events) {
+        events.publish(PluginEvent::MemorySaved, user.user_id, memory_saved_payload(&memory));
+    }
+    Ok(Json(memory))
+}
+
+/// Lists the caller's most recent memories.
+pub async fn list_memories(
+    State(state): State<MemoryState>,
+    user: Option<Extension<UserContext>>,
+    Query(params): Query<ListMemoriesQuery>,
+) -> Result<Json<Vec<Memory>>, Response> {
+    let Some(Extension(user)) = user else {
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    };
+    sqlx::query_as!(
+        Memory,
+        r#"
+        SELECT id, user_id, memory_type, content, COALESCE(context_tags, '{}') AS "context_tags!",
+               session_id, COALESCE(is_private, FALSE) AS "is_private!", created_at AS "created_at!"
+        FROM jean_memory
+        WHERE user_id = $1 AND NOT COALESCE(is_archived, FALSE)
+          AND ($2::text IS NULL OR memory_type = $2)
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+        user.user_id,
+        params.memory_type,
+        params.limit.unwrap_or(50).clamp(1, MAX_LISTED_MEMORIES)
+    )
+    .fetch_all(&state.db)
+    .await
+    .map(Json)
+    .map_err(db_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> MemoryState {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        MemoryState::new(db.clone(), ConsentGate::new(db))
+    }
+
+    fn request(memory_type: &str) -> SaveMemoryRequest {
+        SaveMemoryRequest {
+            memory_type: memory_type.to_string(),
+            content: serde_json::json!({ "text": "prefers dark mode" }),
+            context_tags: Vec::new(),
+            session_id: None,
+            is_private: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn saving_needs_a_user_and_a_known_memory_type() {
+        let anonymous = save_memory(State(state()), None, None, Json(request("preference"))).await;
+        assert_eq!(anonymous.unwrap_err().status(), StatusCode::UNAUTHORIZED);
+
+        let user = UserContext { user_id: Uuid::new_v4(), permissions: Vec::new(), is_admin: false };
+        let unknown = save_memory(State(state()), Some(Extension(user)), None, Json(request("secret"))).await;
+        assert_eq!(unknown.unwrap_err().status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn plugins_hear_about_memories_without_their_content() {
+        let memory = Memory {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            memory_type: "preference".to_string(),
+            content: serde_json::json!({ "text": "prefers dark mode" }),
+            context_tags: vec!["ui".to_string()],
+            session_id: None,
+            is_private: false,
+            created_at: Utc::now(),
+        };
+        let payload = memory_saved_payload(&memory);
+        assert_eq!(payload["memory_id"], serde_json::json!(memory.id));
+        assert_eq!(payload["context_tags"], serde_json::json!(["ui"]));
+        assert!(payload.get("content").is_none() && payload.get("user_id").is_none());
+    }
+
+    #[test]
+    fn missing_consent_is_forbidden_with_the_reason() {
+        let response = consent_error(ConsentError::NotGranted {
+            purpose: ConsentPurpose::MemoryPersonalization,
+            status: crate::security::ConsentStatus::Missing,
+        });
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
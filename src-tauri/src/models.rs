@@ -5,6 +5,9 @@ use serde_json::Value;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use sqlx::PgPool;
+
+use crate::security::{CryptoKeys, SafePath};
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct AIModel {
@@ -155,15 +158,24 @@ pub async fn get_model(
 pub async fn create_model(
     Json(request): Json<CreateModelRequest>,
 ) -> Result<Json<AIModel>, axum::http::StatusCode> {
+    let id = Uuid::new_v4();
+    let api_key_encrypted = match request.api_key.as_deref().filter(|key| !key.is_empty()) {
+        Some(key) => Some(CryptoKeys::global().encrypt_secret(key, &api_key_context(id)).map_err(|e| {
+            tracing::error!("Failed to encrypt model API key: {}", e);
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        })?),
+        None => None,
+    };
+
     let new_model = AIModel {
-        id: Uuid::new_v4(),
+        id,
         name: request.name.clone(),
         display_name: request.display_name,
         model_type: request.model_type,
         backend_type: request.backend_type,
         endpoint_url: request.endpoint_url,
         docker_image: request.docker_image,
-        api_key_encrypted: request.api_key, // In production, encrypt this
+        api_key_encrypted,
         model_config: request.model_config,
         parameters: request.parameters,
         capabilities: request.capabilities,
@@ -198,8 +210,7 @@ pub async fn delete_model(
 }
 
 pub async fn set_default_model(
-    Path(model_type): Path<String>,
-    Path(id): Path<Uuid>,
+    SafePath((model_type, id)): SafePath<(String, Uuid)>,
 ) -> Result<(), axum::http::StatusCode> {
     // Set model as default for its type
     Ok(())
@@ -235,4 +246,72 @@ pub async fn get_default_model(model_type: &ModelType) -> Option<AIModel> {
 pub async fn update_model_status(id: Uuid, status: ModelStatus) -> Result<(), Box<dyn std::error::Error>> {
     // Update model status in database
     Ok(())
-}
\ No newline at end of file
+}
+
+// Envelopes are bound to their row, so a copied ciphertext will not decrypt elsewhere
+fn api_key_context(model_id: Uuid) -> String {
+    format!("ai_models.api_key_encrypted:{}", model_id)
+}
+
+pub fn decrypt_model_api_key(model: &AIModel) -> Option<String> {
+    let envelope = model.api_key_encrypted.as_deref()?;
+    CryptoKeys::global()
+        .decrypt_secret(envelope, &api_key_context(model.id))
+        .map_err(|e| tracing::error!("Cannot decrypt API key for model {}: {}", model.id, e))
+        .ok()
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RewrapReport {
+    pub rewrapped: u64,
+    /// Plaintext keys stored before encryption was introduced.
+    pub encrypted_legacy: u64,
+    pub failed: u64,
+}
+
+/// Moves every stored model API key onto the active encryption key.
+pub async fn rewrap_model_api_keys(db: &PgPool) -> Result<RewrapReport, sqlx::Error> {
+    let keys = CryptoKeys::global();
+    let rows = sqlx::query!(
+        r#"SELECT id, api_key_encrypted AS "api_key_encrypted!" FROM ai_models WHERE api_key_encrypted IS NOT NULL"#
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut report = RewrapReport::default();
+    for row in rows {
+        let updated = if !CryptoKeys::is_envelope(&row.api_key_encrypted) {
+            keys.encrypt_secret(&row.api_key_encrypted, &api_key_context(row.id)).map(|e| (e, true))
+        } else if keys.needs_rewrap(&row.api_key_encrypted) {
+            keys.rewrap_secret(&row.api_key_encrypted).map(|e| (e, false))
+        } else {
+            continue;
+        };
+
+        match updated {
+            Ok((envelope, legacy)) => {
+                // Skip rows changed since they were read
+                let result = sqlx::query!(
+                    "UPDATE ai_models SET api_key_encrypted = $1 WHERE id = $2 AND api_key_encrypted = $3",
+                    envelope,
+                    row.id,
+                    row.api_key_encrypted
+                )
+                .execute(db)
+                .await?;
+                if result.rows_affected() > 0 {
+                    if legacy {
+                        report.encrypted_legacy += 1;
+                    } else {
+                        report.rewrapped += 1;
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Cannot re-wrap API key for model {}: {}", row.id, e);
+                report.failed += 1;
+            }
+        }
+    }
+    Ok(report)
+}
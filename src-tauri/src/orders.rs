@@ -0,0 +1,300 @@
+// Storefront orders: priced from the catalogue on the server and announced to the customer's plugins
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::plugins::{PluginEvent, PluginEventBus};
+use crate::security::UserContext;
+
+const TAX_RATE: f64 = 0.08;
+const FREE_SHIPPING_FROM: f64 = 50.0;
+const STANDARD_SHIPPING: f64 = 5.99;
+const MAX_ITEM_QUANTITY: i32 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrderRequest {
+    pub items: Vec<OrderItemRequest>,
+    pub shipping_address: Value,
+    pub billing_address: Option<Value>,
+    pub customer_email: String,
+    pub customer_phone: Option<String>,
+    pub payment_method: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderItemRequest {
+    pub product_id: Uuid,
+    pub quantity: i32,
+}
+
+/// A catalogue entry as it stands when the order is placed.
+#[derive(Debug, Clone)]
+struct PricedProduct {
+    id: Uuid,
+    title: String,
+    sku: Option<String>,
+    selling_price: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrderLine {
+    pub product_id: Uuid,
+    pub sku: Option<String>,
+    pub title: String,
+    pub quantity: i32,
+    pub unit_price: f64,
+    pub total_price: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrderTotals {
+    pub lines: Vec<OrderLine>,
+    pub subtotal: f64,
+    pub shipping_cost: f64,
+    pub tax_amount: f64,
+    pub total_amount: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedOrder {
+    pub id: Uuid,
+    pub order_number: String,
+    pub status: String,
+    #[serde(flatten)]
+    pub totals: OrderTotals,
+    pub currency: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OrderError {
+    #[error("An order needs at least one item")]
+    Empty,
+    #[error("Quantity for {0} must be between 1 and {MAX_ITEM_QUANTITY}")]
+    InvalidQuantity(Uuid),
+    #[error("Product {0} is not available for purchase")]
+    Unavailable(Uuid),
+    #[error("A customer email is required")]
+    MissingEmail,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl IntoResponse for OrderError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            OrderError::Database(e) => {
+                tracing::error!("Order database error: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        (status, Json(serde_json::json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+/// Prices the requested items from the catalogue; client-supplied prices are never trusted.
+fn price_order(items: &[OrderItemRequest], products: &HashMap<Uuid, PricedProduct>) -> Result<OrderTotals, OrderError> {
+    if items.is_empty() {
+        return Err(OrderError::Empty);
+    }
+    let mut lines = Vec::with_capacity(items.len());
+    for item in items {
+        if !(1..=MAX_ITEM_QUANTITY).contains(&item.quantity) {
+            return Err(OrderError::InvalidQuantity(item.product_id));
+        }
+        let product = products.get(&item.product_id).ok_or(OrderError::Unavailable(item.product_id))?;
+        lines.push(OrderLine {
+            product_id: product.id,
+            sku: product.sku.clone(),
+            title: product.title.clone(),
+            quantity: item.quantity,
+            unit_price: product.selling_price,
+            total_price: round_cents(product.selling_price * item.quantity as f64),
+        });
+    }
+
+    let subtotal = round_cents(lines.iter().map(|line| line.total_price).sum());
+    let shipping_cost = if subtotal >= FREE_SHIPPING_FROM { 0.0 } else { STANDARD_SHIPPING };
+    let tax_amount = round_cents(subtotal * TAX_RATE);
+    Ok(OrderTotals { lines, subtotal, shipping_cost, tax_amount, total_amount: round_cents(subtotal + shipping_cost + tax_amount) })
+}
+
+fn order_number(id: Uuid, at: DateTime<Utc>) -> String {
+    format!("JT-{}-{}", at.format("%Y%m%d"), &id.simple().to_string()[..8].to_uppercase())
+}
+
+/// What plugins hear about a new order: enough to react to it, nothing about the customer.
+fn order_created_payload(order: &CreatedOrder) -> Value {
+    serde_json::json!({
+        "order_id": order.id,
+        "order_number": order.order_number,
+        "item_count": order.totals.lines.len(),
+        "total_amount": order.totals.total_amount,
+        "currency": order.currency,
+    })
+}
+
+/// Places an order for the caller and publishes `order.created` to their plugins.
+pub async fn create_order(
+    State(db): State<PgPool>,
+    user: Option<Extension<UserContext>>,
+    events: Option<Extension<PluginEventBus>>,
+    Json(request): Json<CreateOrderRequest>,
+) -> Result<Json<CreatedOrder>, Response> {
+    let Some(Extension(user)) = user else {
+        return Err(StatusCode::UNAUTHORIZED.into_response());
+    };
+    if request.customer_email.trim().is_empty() {
+        return Err(OrderError::MissingEmail.into_response());
+    }
+
+    let product_ids: Vec<Uuid> = request.items.iter().map(|item| item.product_id).collect();
+    let products: HashMap<Uuid, PricedProduct> = sqlx::query!(
+        r#"
+        SELECT id, title, sku, selling_price::float8 AS "selling_price!"
+        FROM products
+        WHERE id = ANY($1) AND COALESCE(is_visible, TRUE) AND selling_price IS NOT NULL
+        "#,
+        &product_ids
+    )
+    .fetch_all(&db)
+    .await
+    .map_err(|e| OrderError::from(e).into_response())?
+    .into_iter()
+    .map(|row| (row.id, PricedProduct { id: row.id, title: row.title, sku: row.sku, selling_price: row.selling_price }))
+    .collect();
+    let totals = price_order(&request.items, &products).map_err(IntoResponse::into_response)?;
+
+    let id = Uuid::new_v4();
+    let created_at = Utc::now();
+    let order = CreatedOrder {
+        id,
+        order_number: order_number(id, created_at),
+        status: "pending".to_string(),
+        totals,
+        currency: "USD".to_string(),
+        created_at,
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO orders (
+            id, order_number, customer_id, items, subtotal, shipping_cost, tax_amount, total_amount, currency,
+            shipping_address, billing_address, customer_email, customer_phone, payment_method, notes, source,
+            order_date, created_at, updated_at
+        ) VALUES (
+            $1, $2, $3, $4, $5::float8, $6::float8, $7::float8, $8::float8, $9,
+            $10, $11, $12, $13, $14, $15, 'api', $16, $16, $16
+        )
+        "#,
+        order.id,
+        order.order_number,
+        user.user_id,
+        serde_json::to_value(&order.totals.lines).unwrap_or_default(),
+        order.totals.subtotal,
+        order.totals.shipping_cost,
+        order.totals.tax_amount,
+        order.totals.total_amount,
+        order.currency,
+        request.shipping_address,
+        request.billing_address,
+        request.customer_email.trim(),
+        request.customer_phone,
+        request.payment_method,
+        request.notes,
+        order.created_at
+    )
+    .execute(&db)
+    .await
+    .map_err(|e| OrderError::from(e).into_response())?;
+
+    if let Some(Extension(events)) = events {
+        events.publish(PluginEvent::OrderCreated, user.user_id, order_created_payload(&order));
+    }
+    Ok(Json(order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn catalogue(prices: &[f64]) -> (Vec<Uuid>, HashMap<Uuid, PricedProduct>) {
+        let ids: Vec<Uuid> = prices.iter().map(|_| Uuid::new_v4()).collect();
+        let products = ids
+            .iter()
+            .zip(prices)
+            .map(|(id, price)| (*id, PricedProduct { id: *id, title: format!("Item {}", price), sku: None, selling_price: *price }))
+            .collect();
+        (ids, products)
+    }
+
+    #[test]
+    fn orders_are_priced_from_the_catalogue() {
+        let (ids, products) = catalogue(&[12.5, 3.99]);
+        let items = [OrderItemRequest { product_id: ids[0], quantity: 2 }, OrderItemRequest { product_id: ids[1], quantity: 3 }];
+
+        let totals = price_order(&items, &products).unwrap();
+        assert_eq!(totals.lines[1].total_price, 11.97);
+        assert_eq!(totals.subtotal, 36.97);
+        assert_eq!(totals.shipping_cost, STANDARD_SHIPPING);
+        assert_eq!(totals.tax_amount, 2.96);
+        assert_eq!(totals.total_amount, 45.92);
+
+        let large = [OrderItemRequest { product_id: ids[0], quantity: 4 }];
+        assert_eq!(price_order(&large, &products).unwrap().shipping_cost, 0.0);
+    }
+
+    #[test]
+    fn unavailable_products_and_bad_quantities_are_rejected() {
+        let (ids, products) = catalogue(&[10.0]);
+        assert!(matches!(price_order(&[], &products), Err(OrderError::Empty)));
+        let unknown = Uuid::new_v4();
+        assert!(matches!(
+            price_order(&[OrderItemRequest { product_id: unknown, quantity: 1 }], &products),
+            Err(OrderError::Unavailable(id)) if id == unknown
+        ));
+        for quantity in [0, -1, MAX_ITEM_QUANTITY + 1] {
+            assert!(matches!(
+                price_order(&[OrderItemRequest { product_id: ids[0], quantity }], &products),
+                Err(OrderError::InvalidQuantity(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn plugins_hear_about_orders_without_customer_details() {
+        let id = Uuid::new_v4();
+        let created_at = Utc.with_ymd_and_hms(2026, 2, 3, 4, 5, 6).unwrap();
+        let (ids, products) = catalogue(&[20.0]);
+        let order = CreatedOrder {
+            id,
+            order_number: order_number(id, created_at),
+            status: "pending".to_string(),
+            totals: price_order(&[OrderItemRequest { product_id: ids[0], quantity: 1 }], &products).unwrap(),
+            currency: "USD".to_string(),
+            created_at,
+        };
+
+        assert!(order.order_number.starts_with("JT-20260203-"));
+        let payload = order_created_payload(&order);
+        assert_eq!(payload["order_id"], serde_json::json!(id));
+        assert_eq!(payload["item_count"], 1);
+        let keys: Vec<&str> = payload.as_object().unwrap().keys().map(String::as_str).collect();
+        assert!(keys.iter().all(|key| !key.contains("address") && !key.contains("email")));
+    }
+}
@@ -1,369 +0,0 @@
-// Plugin System Module
-use axum::{Json, extract::{Path, Query, State}};
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use uuid::Uuid;
-use chrono::{DateTime, Utc};
-use std::collections::HashMap;
-
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Plugin {
-    pub id: Uuid,
-    pub name: String,
-    pub display_name: String,
-    pub description: Option<String>,
-    pub version: String,
-    pub author: Option<String>,
-    pub manifest: Value,
-    pub entry_point: Option<String>,
-    pub permissions: Vec<String>,
-    pub is_active: bool,
-    pub is_system: bool,
-    pub installed_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-    pub created_by: Option<Uuid>,
-}
-
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct UserPluginSettings {
-    pub id: Uuid,
-    pub user_id: Uuid,
-    pub plugin_id: Uuid,
-    pub settings: Value,
-    pub is_enabled: bool,
-    pub last_used_at: Option<DateTime<Utc>>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct CreatePluginRequest {
-    pub name: String,
-    pub display_name: String,
-    pub description: Option<String>,
-    pub version: String,
-    pub author: Option<String>,
-    pub manifest: Value,
-    pub entry_point: Option<String>,
-    pub permissions: Vec<String>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct UpdatePluginRequest {
-    pub display_name: Option<String>,
-    pub description: Option<String>,
-    pub version: Option<String>,
-    pub manifest: Option<Value>,
-    pub entry_point: Option<String>,
-    pub permissions: Option<Vec<String>>,
-    pub is_active: Option<bool>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct UpdateUserPluginSettingsRequest {
-    pub settings: Value,
-    pub is_enabled: bool,
-}
-
-// Plugin Manifest Structure
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PluginManifest {
-    pub name: String,
-    pub version: String,
-    pub description: Option<String>,
-    pub author: Option<String>,
-    pub homepage: Option<String>,
-    pub repository: Option<String>,
-    pub license: Option<String>,
-    pub keywords: Vec<String>,
-    pub entry: String,
-    pub permissions: Vec<PluginPermission>,
-    pub api_version: String,
-    pub min_jeantrail_version: String,
-    pub max_jeantrail_version: Option<String>,
-    pub dependencies: HashMap<String, String>,
-    pub resources: PluginResources,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PluginPermission {
-    pub name: String,
-    pub description: String,
-    pub required: bool,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PluginResources {
-    pub css_files: Vec<String>,
-    pub js_files: Vec<String>,
-    pub assets: Vec<String>,
-    pub icons: PluginIcons,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PluginIcons {
-    pub icon_16x16: Option<String>,
-    pub icon_32x32: Option<String>,
-    pub icon_48x48: Option<String>,
-    pub icon_128x128: Option<String>,
-}
-
-pub async fn list_plugins(
-    Query(params): Query<ListPluginsQuery>,
-) -> Result<Json<Vec<Plugin>>, axum::http::StatusCode> {
-    let mock_plugins = vec![
-        Plugin {
-            id: Uuid::new_v4(),
-            name: "hello-ai".to_string(),
-            display_name: "Hello AI Assistant".to_string(),
-            description: Some("A simple AI assistant plugin that uses Jean API".to_string()),
-            version: "1.0.0".to_string(),
-            author: Some("JeanTrail Team".to_string()),
-            manifest: serde_json::json!({
-                "name": "hello-ai",
-                "version": "1.0.0",
-                "description": "Simple AI assistant plugin",
-                "entry": "/plugins/hello-ai/index.html",
-                "permissions": [
-                    {
-                        "name": "jean.chat",
-                        "description": "Access Jean AI chat functionality",
-                        "required": true
-                    }
-                ],
-                "api_version": "v1",
-                "min_jeantrail_version": "1.0.0"
-            }),
-            entry_point: Some("/plugins/hello-ai/index.html".to_string()),
-            permissions: vec!["jean.chat".to_string()],
-            is_active: true,
-            is_system: false,
-            installed_at: Utc::now(),
-            updated_at: Utc::now(),
-            created_by: Some(Uuid::new_v4()),
-        }
-    ];
-
-    Ok(Json(mock_plugins))
-}
-
-#[derive(Debug, Deserialize)]
-pub struct ListPluginsQuery {
-    pub is_active: Option<bool>,
-    pub is_system: Option<bool>,
-    pub author: Option<String>,
-}
-
-pub async fn get_plugin(
-    Path(id): Path<Uuid>,
-) -> Result<Json<Plugin>, axum::http::StatusCode> {
-    // Fetch specific plugin
-    Err(axum::http::StatusCode::NOT_FOUND)
-}
-
-pub async fn create_plugin(
-    Json(request): Json<CreatePluginRequest>,
-) -> Result<Json<Plugin>, axum::http::StatusCode> {
-    let new_plugin = Plugin {
-        id: Uuid::new_v4(),
-        name: request.name.clone(),
-        display_name: request.display_name,
-        description: request.description,
-        version: request.version,
-        author: request.author,
-        manifest: request.manifest,
-        entry_point: request.entry_point,
-        permissions: request.permissions,
-        is_active: false,
-        is_system: false,
-        installed_at: Utc::now(),
-        updated_at: Utc::now(),
-        created_by: Some(Uuid::new_v4()),
-    };
-
-    // Validate manifest and save to database
-    Ok(Json(new_plugin))
-}
-
-pub async fn update_plugin(
-    Path(id): Path<Uuid>,
-    Json(request): Json<UpdatePluginRequest>,
-) -> Result<Json<Plugin>, axum::http::StatusCode> {
-    // Update plugin in database
-    Err(axum::http::StatusCode::NOT_FOUND)
-}
-
-pub async fn delete_plugin(
-    Path(id): Path<Uuid>,
-) -> Result<(), axum::http::StatusCode> {
-    // Delete plugin from database
-    Ok(())
-}
-
-pub async fn activate_plugin(
-    Path(id): Path<Uuid>,
-) -> Result<Json<Value>, axum::http::StatusCode> {
-    // Activate plugin
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "message": "Plugin activated successfully"
-    })))
-}
-
-pub async fn deactivate_plugin(
-    Path(id): Path<Uuid>,
-) -> Result<Json<Value>, axum::http::StatusCode> {
-    // Deactivate plugin
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "message": "Plugin deactivated successfully"
-    })))
-}
-
-pub async fn get_user_plugin_settings(
-    Path(user_id): Path<Uuid>,
-    Path(plugin_id): Path<Uuid>,
-) -> Result<Json<UserPluginSettings>, axum::http::StatusCode> {
-    // Get user's plugin settings
-    Err(axum::http::StatusCode::NOT_FOUND)
-}
-
-pub async fn update_user_plugin_settings(
-    Path(user_id): Path<Uuid>,
-    Path(plugin_id): Path<Uuid>,
-    Json(request): Json<UpdateUserPluginSettingsRequest>,
-) -> Result<Json<UserPluginSettings>, axum::http::StatusCode> {
-    let settings = UserPluginSettings {
-        id: Uuid::new_v4(),
-        user_id,
-        plugin_id,
-        settings: request.settings,
-        is_enabled: request.is_enabled,
-        last_used_at: Some(Utc::now()),
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-    };
-
-    // Save to database
-    Ok(Json(settings))
-}
-
-pub async fn install_plugin_from_url(
-    Json(request): Json<InstallPluginRequest>,
-) -> Result<Json<Value>, axum::http::StatusCode> {
-    // Download and install plugin from URL
-    // Validate manifest
-    // Extract files
-    // Save to database
-    
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "message": "Plugin installed successfully",
-        "plugin_id": Uuid::new_v4()
-    })))
-}
-
-#[derive(Debug, Deserialize)]
-pub struct InstallPluginRequest {
-    pub url: String,
-    pub auto_activate: bool,
-}
-
-pub async fn validate_plugin_manifest(
-    Json(manifest): Json<PluginManifest>,
-) -> Result<Json<Value>, axum::http::StatusCode> {
-    let mut validation_errors = Vec::new();
-    
-    // Validate required fields
-    if manifest.name.is_empty() {
-        validation_errors.push("Plugin name is required");
-    }
-    
-    if manifest.entry.is_empty() {
-        validation_errors.push("Plugin entry point is required");
-    }
-    
-    // Validate version format
-    if !is_valid_version(&manifest.version) {
-        validation_errors.push("Invalid version format");
-    }
-    
-    // Validate permissions
-    for permission in &manifest.permissions {
-        if !is_valid_permission(&permission.name) {
-            validation_errors.push(&format!("Invalid permission: {}", permission.name));
-        }
-    }
-    
-    Ok(Json(serde_json::json!({
-        "valid": validation_errors.is_empty(),
-        "errors": validation_errors
-    })))
-}
-
-fn is_valid_version(version: &str) -> bool {
-    // Simple semantic version validation
-    version.split('.').count() == 3 && version.split('.').all(|part| part.parse::<u32>().is_ok())
-}
-
-fn is_valid_permission(permission: &str) -> bool {
-    // List of valid permissions
-    let valid_permissions = vec![
-        "jean.chat",
-        "jean.actions",
-        "tabs.read",
-        "tabs.write",
-        "proxy.read",
-        "proxy.write",
-        "files.read",
-        "files.write",
-        "network.request",
-        "storage.read",
-        "storage.write",
-        "ui.panel",
-        "ui.modal",
-        "notifications.show",
-    ];
-    
-    valid_permissions.contains(&permission)
-}
-
-// Plugin Sandbox Execution
-pub async fn execute_plugin_command(
-    Path(plugin_id): Path<Uuid>,
-    Path(user_id): Path<Uuid>,
-    Json(command): Json<PluginCommand>,
-) -> Result<Json<Value>, axum::http::StatusCode> {
-    // Validate user has plugin enabled
-    // Check permissions
-    // Execute command in sandbox
-    
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "result": "Command executed successfully",
-        "command_id": Uuid::new_v4()
-    })))
-}
-
-#[derive(Debug, Deserialize)]
-pub struct PluginCommand {
-    pub action: String,
-    pub parameters: Value,
-}
-
-// Plugin API Bridge
-pub async fn bridge_plugin_api_request(
-    Path(plugin_id): Path<Uuid>,
-    Path(user_id): Path<Uuid>,
-    Path(api_endpoint): Path<String>,
-    Json(request): Json<Value>,
-) -> Result<Json<Value>, axum::http::StatusCode> {
-    // Route plugin API requests to appropriate handlers
-    // Apply permission checks
-    // Log API calls for audit
-    
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "data": "API response"
-    })))
-}
\ No newline at end of file
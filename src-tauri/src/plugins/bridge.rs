@@ -0,0 +1,731 @@
+//! The host side of the plugin API: `jean.chat`, `tabs.*`, `storage.*`,
+//! `network.request` and `notifications.show`.
+//!
+//! Every call is checked twice. The manifest must declare the permission, and
+//! the user must have consented to it. Enabling a plugin consents to its
+//! required permissions. Optional permissions are asked for the first time
+//! the plugin uses them; the call fails with `permission_prompt` until the
+//! user answers. Allowed and refused calls alike land in the audit log.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{HostCallError, HostFunction, PluginCallContext, PluginHost, PluginPermission, PluginStorage, PluginStorageConfig, StorageOp};
+use crate::ai::{self, AIRequest};
+use crate::security::{record_audit_event, ClientInfo};
+
+const MAX_PROMPT_CHARS: usize = 8_000;
+const MAX_TAB_TITLE_CHARS: usize = 255;
+const MAX_NOTIFICATION_TITLE_CHARS: usize = 200;
+const MAX_NOTIFICATION_BODY_CHARS: usize = 2_000;
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_NETWORK_BODY_BYTES: usize = 1024 * 1024;
+/// Headers a plugin may not set on outgoing requests.
+const FORBIDDEN_HEADERS: &[&str] = &["host", "cookie", "connection", "content-length", "transfer-encoding", "proxy-authorization"];
+
+/// The parts of a manifest the bridge enforces.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ManifestAccess {
+    permissions: Vec<PluginPermission>,
+    allowed_hosts: Vec<String>,
+}
+
+/// A plugin's declared permissions together with one user's answers.
+#[derive(Debug, Default)]
+pub struct PluginGrants {
+    pub declared: Vec<PluginPermission>,
+    pub allowed_hosts: Vec<String>,
+    pub is_active: bool,
+    /// Whether the user has the plugin enabled; `false` when they never set it up.
+    pub enabled: bool,
+    pub granted: Vec<String>,
+    pub denied: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consent {
+    Granted,
+    /// Optional permission the user has not answered yet.
+    Prompt,
+    Denied,
+    Undeclared,
+    Disabled,
+}
+
+impl PluginGrants {
+    pub fn from_row(plugin_id: Uuid, manifest: Value, is_active: bool, enabled: bool, granted: Vec<String>, denied: Vec<String>) -> Self {
+        let access: ManifestAccess = serde_json::from_value(manifest).unwrap_or_else(|e| {
+            tracing::warn!("Plugin {} has an unreadable manifest: {}", plugin_id, e);
+            ManifestAccess::default()
+        });
+        Self { declared: access.permissions, allowed_hosts: access.allowed_hosts, is_active, enabled, granted, denied }
+    }
+
+    pub fn consent(&self, permission: &str) -> Consent {
+        let Some(declared) = self.declared.iter().find(|p| p.name == permission) else {
+            return Consent::Undeclared;
+        };
+        if !self.enabled {
+            Consent::Disabled
+        } else if self.denied.iter().any(|p| p == permission) {
+            Consent::Denied
+        } else if declared.required || self.granted.iter().any(|p| p == permission) {
+            Consent::Granted
+        } else {
+            Consent::Prompt
+        }
+    }
+
+    fn reason(&self, permission: &str) -> Option<&str> {
+        self.declared.iter().find(|p| p.name == permission).map(|p| p.description.as_str())
+    }
+}
+
+/// Whether `host` matches one of the manifest's `allowed_hosts`, either exactly
+/// or through a `*.example.com` wildcard covering its subdomains.
+pub fn host_allowed(host: &str, patterns: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(suffix) => host.strip_suffix(suffix).is_some_and(|rest| rest.len() > 1 && rest.ends_with('.')),
+            None => host == pattern,
+        }
+    })
+}
+
+/// Maps a bridge error code to the HTTP status the API bridge answers with.
+pub fn host_error_status(code: &str) -> StatusCode {
+    match code {
+        "invalid_params" | "invalid_payload" | "invalid_url" => StatusCode::BAD_REQUEST,
+        "permission_denied" | "host_not_allowed" => StatusCode::FORBIDDEN,
+        "permission_prompt" => StatusCode::PRECONDITION_REQUIRED,
+        "not_found" | "unknown_endpoint" => StatusCode::NOT_FOUND,
+        "plugin_inactive" => StatusCode::CONFLICT,
+        "payload_too_large" => StatusCode::PAYLOAD_TOO_LARGE,
+        "upstream_error" | "response_too_large" => StatusCode::BAD_GATEWAY,
+        "quota_exceeded" => StatusCode::INSUFFICIENT_STORAGE,
+        "unavailable" => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn internal(e: sqlx::Error) -> HostCallError {
+    tracing::error!("Plugin bridge database error: {}", e);
+    HostCallError::new("internal", "The host could not complete the call")
+}
+
+fn params<T: DeserializeOwned>(payload: Value) -> Result<T, HostCallError> {
+    serde_json::from_value(payload).map_err(|e| HostCallError::new("invalid_params", e.to_string()))
+}
+
+fn web_url(raw: &str) -> Result<url::Url, HostCallError> {
+    let url = url::Url::parse(raw).map_err(|e| HostCallError::new("invalid_url", e.to_string()))?;
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        scheme => Err(HostCallError::new("invalid_url", format!("{} URLs are not allowed", scheme))),
+    }
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PluginTab {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub title: String,
+    pub url: String,
+    pub zone: String,
+    pub position: i32,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatParams {
+    prompt: String,
+    #[serde(default)]
+    context: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TabsReadParams {
+    workspace_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum TabsWriteParams {
+    Open {
+        workspace_id: Uuid,
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+    },
+    Navigate {
+        tab_id: Uuid,
+        url: String,
+    },
+    Close {
+        tab_id: Uuid,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct StorageKeyParams {
+    key: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct StorageListParams {
+    prefix: String,
+    after: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StorageSetParams {
+    key: String,
+    value: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct StorageBatchParams {
+    ops: Vec<StorageOp>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NetworkParams {
+    url: String,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotifyParams {
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PluginPermissionRequest {
+    pub id: Uuid,
+    pub plugin_id: Uuid,
+    pub plugin_name: String,
+    pub permission: String,
+    pub reason: Option<String>,
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PluginNotification {
+    pub id: Uuid,
+    pub plugin_id: Uuid,
+    pub plugin_name: String,
+    pub title: String,
+    pub body: Option<String>,
+    pub read_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Serves plugin API calls, both host imports from the wasm runtime and
+/// requests through the HTTP bridge.
+pub struct PluginBridge {
+    db: PgPool,
+    http: reqwest::Client,
+    storage: PluginStorage,
+}
+
+impl PluginBridge {
+    pub fn new(db: PgPool) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(NETWORK_TIMEOUT)
+            // A redirect could leave the manifest's allowed hosts
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        let storage = PluginStorage::new(db.clone(), PluginStorageConfig::load());
+        Self { db, http, storage }
+    }
+
+    /// Checks consent, runs the capability and audit-logs the outcome.
+    pub async fn dispatch(
+        &self,
+        context: &PluginCallContext,
+        function: HostFunction,
+        payload: Value,
+        client: Option<&ClientInfo>,
+    ) -> Result<Value, HostCallError> {
+        let result = self.authorize_and_call(context, function, payload).await;
+        let details = serde_json::json!({
+            "endpoint": function.endpoint(),
+            "permission": function.permission(),
+            "command_id": context.command_id,
+            "outcome": if result.is_ok() { "allowed" } else { "failed" },
+            "error_code": result.as_ref().err().map(|e| e.code.as_str()),
+        });
+        record_audit_event(&self.db, Some(context.user_id), "plugin.api_call", "plugin", &context.plugin_id.to_string(), details, client).await;
+        result
+    }
+
+    async fn authorize_and_call(&self, context: &PluginCallContext, function: HostFunction, payload: Value) -> Result<Value, HostCallError> {
+        let grants = load_grants(&self.db, context.plugin_id, context.user_id)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| HostCallError::new("not_found", "Plugin is not installed"))?;
+        if !grants.is_active {
+            return Err(HostCallError::new("plugin_inactive", "Plugin is not active"));
+        }
+
+        let permission = function.permission();
+        match grants.consent(permission) {
+            Consent::Granted => {}
+            Consent::Undeclared => {
+                return Err(HostCallError::new("permission_denied", format!("The manifest does not declare {}", permission)));
+            }
+            Consent::Disabled => {
+                return Err(HostCallError::new("permission_denied", "The user has not enabled this plugin"));
+            }
+            Consent::Denied => {
+                return Err(HostCallError::new("permission_denied", format!("The user refused {}", permission)));
+            }
+            Consent::Prompt => {
+                let request_id = open_permission_request(&self.db, context, permission, grants.reason(permission))
+                    .await
+                    .map_err(internal)?;
+                return Err(HostCallError::new("permission_prompt", format!("Waiting for the user to allow {}", permission))
+                    .with_details(serde_json::json!({ "request_id": request_id, "permission": permission })));
+            }
+        }
+
+        match function {
+            HostFunction::JeanChat => self.jean_chat(context, params(payload)?).await,
+            HostFunction::TabsRead => self.tabs_read(context, params(payload)?).await,
+            HostFunction::TabsWrite => self.tabs_write(context, params(payload)?).await,
+            HostFunction::StorageGet => {
+                let StorageKeyParams { key } = params(payload)?;
+                let value = self.storage.get(context.plugin_id, context.user_id, &key).await?;
+                Ok(serde_json::json!({ "value": value }))
+            }
+            HostFunction::StorageList => self.storage_list(context, params(payload)?).await,
+            HostFunction::StorageSet => {
+                let StorageSetParams { key, value } = params(payload)?;
+                self.storage_write(context, &[StorageOp::Set { key, value }]).await
+            }
+            HostFunction::StorageDelete => {
+                let StorageKeyParams { key } = params(payload)?;
+                self.storage_write(context, &[StorageOp::Delete { key }]).await
+            }
+            HostFunction::StorageBatch => {
+                let StorageBatchParams { ops } = params(payload)?;
+                self.storage_write(context, &ops).await
+            }
+            HostFunction::NetworkRequest => self.network_request(&grants, params(payload)?).await,
+            HostFunction::Notify => self.notify(context, params(payload)?).await,
+        }
+    }
+
+    async fn jean_chat(&self, context: &PluginCallContext, params: ChatParams) -> Result<Value, HostCallError> {
+        if params.prompt.trim().is_empty() {
+            return Err(HostCallError::new("invalid_params", "prompt must not be empty"));
+        }
+        if params.prompt.chars().count() > MAX_PROMPT_CHARS {
+            return Err(HostCallError::new("payload_too_large", format!("prompt exceeds {} characters", MAX_PROMPT_CHARS)));
+        }
+        let context_json = serde_json::json!({ "plugin": context.plugin_name, "context": params.context }).to_string();
+        let response = ai::complete(&AIRequest::new(params.prompt, Some(context_json))).await.map_err(|e| {
+            tracing::warn!("Jean chat for plugin {} failed: {}", context.plugin_id, e);
+            HostCallError::new("upstream_error", "Jean could not answer")
+        })?;
+        Ok(serde_json::json!({ "response": response.text() }))
+    }
+
+    async fn tabs_read(&self, context: &PluginCallContext, params: TabsReadParams) -> Result<Value, HostCallError> {
+        let tabs = sqlx::query_as!(
+            PluginTab,
+            r#"
+            SELECT t.id, t.workspace_id AS "workspace_id!", t.title, t.url, t.zone, t.position,
+                   COALESCE(t.is_active, false) AS "is_active!"
+            FROM tabs t
+            JOIN workspaces w ON w.id = t.workspace_id
+            WHERE w.user_id = $1 AND ($2::uuid IS NULL OR t.workspace_id = $2)
+            ORDER BY t.workspace_id, t.position
+            "#,
+            context.user_id,
+            params.workspace_id
+        )
+        .fetch_all(&self.db)
+        .await
+        .map_err(internal)?;
+        Ok(serde_json::json!({ "tabs": tabs }))
+    }
+
+    async fn tabs_write(&self, context: &PluginCallContext, params: TabsWriteParams) -> Result<Value, HostCallError> {
+        let tab_id = match params {
+            TabsWriteParams::Open { workspace_id, url, title } => {
+                let url = web_url(&url)?;
+                let title: String = title.unwrap_or_else(|| url.to_string()).chars().take(MAX_TAB_TITLE_CHARS).collect();
+                sqlx::query_scalar!(
+                    r#"
+                    INSERT INTO tabs (workspace_id, title, url, zone, position)
+                    SELECT w.id, $3, $4, 'web',
+                           COALESCE((SELECT MAX(position) + 1 FROM tabs WHERE workspace_id = w.id), 0)
+                    FROM workspaces w
+                    WHERE w.id = $1 AND w.user_id = $2
+                    RETURNING id
+                    "#,
+                    workspace_id,
+                    context.user_id,
+                    title,
+                    url.as_str()
+                )
+                .fetch_optional(&self.db)
+                .await
+            }
+            TabsWriteParams::Navigate { tab_id, url } => {
+                let url = web_url(&url)?;
+                sqlx::query_scalar!(
+                    r#"
+                    UPDATE tabs t SET url = $3, updated_at = NOW()
+                    FROM workspaces w
+                    WHERE t.id = $1 AND w.id = t.workspace_id AND w.user_id = $2
+                    RETURNING t.id
+                    "#,
+                    tab_id,
+                    context.user_id,
+                    url.as_str()
+                )
+                .fetch_optional(&self.db)
+                .await
+            }
+            TabsWriteParams::Close { tab_id } => {
+                sqlx::query_scalar!(
+                    r#"
+                    DELETE FROM tabs t
+                    USING workspaces w
+                    WHERE t.id = $1 AND w.id = t.workspace_id AND w.user_id = $2
+                    RETURNING t.id
+                    "#,
+                    tab_id,
+                    context.user_id
+                )
+                .fetch_optional(&self.db)
+                .await
+            }
+        }
+        .map_err(internal)?
+        .ok_or_else(|| HostCallError::new("not_found", "No such tab or workspace"))?;
+        Ok(serde_json::json!({ "tab_id": tab_id }))
+    }
+
+    async fn storage_list(&self, context: &PluginCallContext, params: StorageListParams) -> Result<Value, HostCallError> {
+        let limit = params.limit.unwrap_or(100).clamp(1, self.storage.config().max_list_entries);
+        let entries = self
+            .storage
+            .list(context.plugin_id, context.user_id, &params.prefix, params.after.as_deref(), Some(limit))
+            .await?;
+        // A full page may have more behind it
+        let next = if entries.len() as i64 == limit { entries.last().map(|entry| entry.key.clone()) } else { None };
+        Ok(serde_json::json!({ "entries": entries, "next": next }))
+    }
+
+    async fn storage_write(&self, context: &PluginCallContext, ops: &[StorageOp]) -> Result<Value, HostCallError> {
+        let usage = self.storage.batch(context.plugin_id, context.user_id, ops).await?;
+        Ok(serde_json::json!({ "written": ops.len(), "usage": usage, "quota_bytes": self.storage.config().quota_bytes }))
+    }
+
+    async fn network_request(&self, grants: &PluginGrants, params: NetworkParams) -> Result<Value, HostCallError> {
+        let url = url::Url::parse(&params.url).map_err(|e| HostCallError::new("invalid_url", e.to_string()))?;
+        if url.scheme() != "https" {
+            return Err(HostCallError::new("invalid_url", "Only https URLs are allowed"));
+        }
+        // IP literals would sidestep the host allowlist
+        let Some(url::Host::Domain(host)) = url.host() else {
+            return Err(HostCallError::new("invalid_url", "URL must name a host"));
+        };
+        if !host_allowed(host, &grants.allowed_hosts) {
+            return Err(HostCallError::new("host_not_allowed", format!("{} is not in the manifest's allowed_hosts", host)));
+        }
+
+        let method = params.method.as_deref().unwrap_or("GET").to_ascii_uppercase();
+        if !matches!(method.as_str(), "GET" | "HEAD" | "POST" | "PUT" | "PATCH" | "DELETE") {
+            return Err(HostCallError::new("invalid_params", format!("Unsupported method {}", method)));
+        }
+        let method = reqwest::Method::from_bytes(method.as_bytes()).map_err(|e| HostCallError::new("invalid_params", e.to_string()))?;
+        let mut request = self.http.request(method, url.as_str());
+        for (name, value) in &params.headers {
+            if FORBIDDEN_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                return Err(HostCallError::new("invalid_params", format!("Header {} may not be set", name)));
+            }
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some(body) = params.body {
+            if body.len() > MAX_NETWORK_BODY_BYTES {
+                return Err(HostCallError::new("payload_too_large", "Request body is too large"));
+            }
+            request = request.body(body);
+        }
+
+        let upstream = |e: reqwest::Error| HostCallError::new("upstream_error", e.to_string());
+        let mut response = request.send().await.map_err(upstream)?;
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(upstream)? {
+            if body.len() + chunk.len() > MAX_NETWORK_BODY_BYTES {
+                return Err(HostCallError::new("response_too_large", "Response body is too large"));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(serde_json::json!({
+            "status": status,
+            "content_type": content_type,
+            "body": String::from_utf8_lossy(&body),
+        }))
+    }
+
+    async fn notify(&self, context: &PluginCallContext, params: NotifyParams) -> Result<Value, HostCallError> {
+        let title = params.title.trim();
+        if title.is_empty() || title.chars().count() > MAX_NOTIFICATION_TITLE_CHARS {
+            return Err(HostCallError::new("invalid_params", format!("title must be 1-{} characters", MAX_NOTIFICATION_TITLE_CHARS)));
+        }
+        if params.body.as_ref().is_some_and(|body| body.chars().count() > MAX_NOTIFICATION_BODY_CHARS) {
+            return Err(HostCallError::new("payload_too_large", format!("body exceeds {} characters", MAX_NOTIFICATION_BODY_CHARS)));
+        }
+        let notification_id = sqlx::query_scalar!(
+            "INSERT INTO plugin_notifications (user_id, plugin_id, title, body) VALUES ($1, $2, $3, $4) RETURNING id",
+            context.user_id,
+            context.plugin_id,
+            title,
+            params.body
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(internal)?;
+        Ok(serde_json::json!({ "notification_id": notification_id }))
+    }
+}
+
+impl PluginHost for PluginBridge {
+    fn call(&self, context: &PluginCallContext, function: HostFunction, payload: Value) -> Result<Value, HostCallError> {
+        // The runtime calls hosts from a blocking thread inside the server's runtime
+        tokio::runtime::Handle::current().block_on(self.dispatch(context, function, payload, None))
+    }
+}
+
+async fn load_grants(db: &PgPool, plugin_id: Uuid, user_id: Uuid) -> Result<Option<PluginGrants>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT p.manifest, COALESCE(p.is_active, false) AS "is_active!",
+               s.is_enabled AS "enabled?",
+               s.granted_permissions AS "granted?",
+               s.denied_permissions AS "denied?"
+        FROM plugins p
+        LEFT JOIN user_plugin_settings s ON s.plugin_id = p.id AND s.user_id = $2
+        WHERE p.id = $1
+        "#,
+        plugin_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(row.map(|row| {
+        PluginGrants::from_row(
+            plugin_id,
+            row.manifest,
+            row.is_active,
+            row.enabled.unwrap_or(false),
+            row.granted.unwrap_or_default(),
+            row.denied.unwrap_or_default(),
+        )
+    }))
+}
+
+/// Opens a prompt for `permission`, or returns the one already waiting.
+async fn open_permission_request(db: &PgPool, context: &PluginCallContext, permission: &str, reason: Option<&str>) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO plugin_permission_requests (user_id, plugin_id, permission, reason)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, plugin_id, permission) WHERE status = 'pending'
+        DO UPDATE SET reason = EXCLUDED.reason
+        RETURNING id
+        "#,
+        context.user_id,
+        context.plugin_id,
+        permission,
+        reason
+    )
+    .fetch_one(db)
+    .await
+}
+
+pub async fn pending_permission_requests(db: &PgPool, user_id: Uuid) -> Result<Vec<PluginPermissionRequest>, sqlx::Error> {
+    sqlx::query_as!(
+        PluginPermissionRequest,
+        r#"
+        SELECT r.id, r.plugin_id, p.name AS plugin_name, r.permission, r.reason, r.status, r.created_at
+        FROM plugin_permission_requests r
+        JOIN plugins p ON p.id = r.plugin_id
+        WHERE r.user_id = $1 AND r.status = 'pending'
+        ORDER BY r.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn recent_notifications(db: &PgPool, user_id: Uuid) -> Result<Vec<PluginNotification>, sqlx::Error> {
+    sqlx::query_as!(
+        PluginNotification,
+        r#"
+        SELECT n.id, n.plugin_id, p.name AS plugin_name, n.title, n.body, n.read_at, n.created_at
+        FROM plugin_notifications n
+        JOIN plugins p ON p.id = n.plugin_id
+        WHERE n.user_id = $1
+        ORDER BY n.created_at DESC
+        LIMIT 100
+        "#,
+        user_id
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Records the user's answer to a prompt and carries it into their consent for
+/// the plugin. `None` when there is no such pending prompt.
+pub async fn answer_permission_request(db: &PgPool, user_id: Uuid, request_id: Uuid, grant: bool) -> Result<Option<PluginPermissionRequest>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let status = if grant { "granted" } else { "denied" };
+    let Some(answered) = sqlx::query_as!(
+        PluginPermissionRequest,
+        r#"
+        UPDATE plugin_permission_requests r SET status = $3, resolved_at = NOW()
+        FROM plugins p
+        WHERE r.id = $1 AND r.user_id = $2 AND r.status = 'pending' AND p.id = r.plugin_id
+        RETURNING r.id, r.plugin_id, p.name AS plugin_name, r.permission, r.reason, r.status, r.created_at
+        "#,
+        request_id,
+        user_id,
+        status
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE user_plugin_settings SET
+            granted_permissions = CASE WHEN $3 THEN array_append(array_remove(granted_permissions, $4), $4)
+                                       ELSE array_remove(granted_permissions, $4) END,
+            denied_permissions = CASE WHEN $3 THEN array_remove(denied_permissions, $4)
+                                      ELSE array_append(array_remove(denied_permissions, $4), $4) END,
+            updated_at = NOW()
+        WHERE user_id = $1 AND plugin_id = $2
+        "#,
+        user_id,
+        answered.plugin_id,
+        grant,
+        answered.permission
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(Some(answered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permission(name: &str, required: bool) -> PluginPermission {
+        PluginPermission { name: name.to_string(), description: format!("needs {}", name), required }
+    }
+
+    fn grants() -> PluginGrants {
+        PluginGrants {
+            declared: vec![permission("tabs.read", true), permission("network.request", false), permission("notifications.show", false)],
+            is_active: true,
+            enabled: true,
+            granted: vec!["network.request".to_string()],
+            denied: vec!["notifications.show".to_string()],
+            ..PluginGrants::default()
+        }
+    }
+
+    #[test]
+    fn consent_combines_manifest_and_user_answers() {
+        let grants = grants();
+        assert_eq!(grants.consent("tabs.read"), Consent::Granted);
+        assert_eq!(grants.consent("network.request"), Consent::Granted);
+        assert_eq!(grants.consent("notifications.show"), Consent::Denied);
+        assert_eq!(grants.consent("jean.chat"), Consent::Undeclared);
+
+        let fresh = PluginGrants { granted: Vec::new(), ..grants };
+        assert_eq!(fresh.consent("network.request"), Consent::Prompt);
+        assert_eq!(fresh.reason("network.request"), Some("needs network.request"));
+
+        let disabled = PluginGrants { enabled: false, ..fresh };
+        assert_eq!(disabled.consent("tabs.read"), Consent::Disabled);
+        assert_eq!(disabled.consent("jean.chat"), Consent::Undeclared);
+    }
+
+    #[test]
+    fn host_allowlist_matches_exact_and_wildcard_hosts() {
+        let allowed = vec!["api.example.com".to_string(), "*.Weather.io".to_string()];
+        assert!(host_allowed("api.example.com", &allowed));
+        assert!(host_allowed("API.example.com.", &allowed));
+        assert!(host_allowed("eu.weather.io", &allowed));
+        assert!(host_allowed("a.b.weather.io", &allowed));
+        assert!(!host_allowed("weather.io", &allowed));
+        assert!(!host_allowed("evilweather.io", &allowed));
+        assert!(!host_allowed("example.com", &allowed));
+        assert!(!host_allowed("api.example.com.evil.net", &allowed));
+    }
+
+    #[test]
+    fn endpoints_round_trip_and_map_errors() {
+        for function in HostFunction::ALL {
+            assert_eq!(HostFunction::from_endpoint(function.endpoint()), Some(function));
+        }
+        assert_eq!(HostFunction::from_endpoint("files.read"), None);
+        assert_eq!(host_error_status("permission_prompt"), StatusCode::PRECONDITION_REQUIRED);
+        assert_eq!(host_error_status("host_not_allowed"), StatusCode::FORBIDDEN);
+        assert_eq!(host_error_status("something_new"), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn tab_writes_parse_by_action() {
+        let open: TabsWriteParams = params(serde_json::json!({
+            "action": "open",
+            "workspace_id": Uuid::nil(),
+            "url": "https://example.com"
+        }))
+        .unwrap();
+        assert!(matches!(open, TabsWriteParams::Open { title: None, .. }));
+        let err = params::<TabsWriteParams>(serde_json::json!({ "action": "pin", "tab_id": Uuid::nil() })).unwrap_err();
+        assert_eq!(err.code, "invalid_params");
+        assert_eq!(web_url("javascript:alert(1)").unwrap_err().code, "invalid_url");
+    }
+}
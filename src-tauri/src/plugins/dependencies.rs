@@ -0,0 +1,717 @@
+//! Semver rules for plugins: host compatibility, dependency resolution
+//! against installed plugins and the registry index, and the order plugins
+//! have to be activated in.
+//!
+//! Requirements use Cargo's syntax (`^1.2`, `~1.4.0`, `>=1.0, <2.0`, `*`).
+//! Pre-releases only satisfy a requirement that names a pre-release of the
+//! same version. A plugin has one installed version, so every dependent has
+//! to agree on it; resolution picks the highest acceptable version and
+//! reports a conflict instead of backtracking.
+
+use std::collections::{BTreeMap, HashMap};
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{fetch_limited, PluginInstallConfig, PluginManifest, PluginPackageError};
+
+/// Product version plugins are checked against. It follows the released app
+/// (package.json), not this crate's version, which manifests never target.
+pub const JEANTRAIL_VERSION: &str = "1.0.0";
+const MAX_INDEX_BYTES: u64 = 1024 * 1024;
+/// Distinct plugins one install may pull in.
+const MAX_RESOLVED_PLUGINS: usize = 100;
+
+pub fn host_version() -> Version {
+    Version::parse(JEANTRAIL_VERSION).expect("product version is semver")
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DependencyError {
+    #[error("Invalid version {value}: {reason}")]
+    InvalidVersion { value: String, reason: String },
+    #[error("Invalid requirement {requirement} for {name}: {reason}")]
+    InvalidRequirement { name: String, requirement: String, reason: String },
+    #[error("{plugin} {version} does not support JeanTrail {host}")]
+    IncompatibleHost { plugin: String, version: String, host: String },
+    #[error("Dependency cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("{required_by} requires {name} {requirement}, but {found} is selected")]
+    Conflict { name: String, requirement: String, found: String, required_by: String },
+    #[error("No version of {name} matches {requirement}")]
+    Unsatisfiable { name: String, requirement: String },
+    #[error("{required_by} depends on {name} {requirement}, which is not installed")]
+    MissingDependency { name: String, requirement: String, required_by: String },
+    #[error("Dependency {0} is not installed and no plugin registry is configured")]
+    NoRegistry(String),
+    #[error("Registry package for {name} {version} does not match its index entry")]
+    IndexMismatch { name: String, version: String },
+    #[error("More than {0} plugins would be installed")]
+    TooManyDependencies(usize),
+    #[error("{name} is required by {}", .dependents.join(", "))]
+    HasDependents { name: String, dependents: Vec<String> },
+    #[error("Plugin not found")]
+    NotFound,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl DependencyError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DependencyError::InvalidVersion { .. } => "invalid_version",
+            DependencyError::InvalidRequirement { .. } => "invalid_requirement",
+            DependencyError::IncompatibleHost { .. } => "incompatible_host",
+            DependencyError::Cycle(_) => "dependency_cycle",
+            DependencyError::Conflict { .. } => "dependency_conflict",
+            DependencyError::Unsatisfiable { .. } => "unsatisfiable_dependency",
+            DependencyError::MissingDependency { .. } => "missing_dependency",
+            DependencyError::NoRegistry(_) => "no_registry",
+            DependencyError::IndexMismatch { .. } => "index_mismatch",
+            DependencyError::TooManyDependencies(_) => "too_many_dependencies",
+            DependencyError::HasDependents { .. } => "has_dependents",
+            DependencyError::NotFound => "not_found",
+            DependencyError::Database(_) => "database",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            DependencyError::NotFound => StatusCode::NOT_FOUND,
+            DependencyError::HasDependents { .. } | DependencyError::Conflict { .. } => StatusCode::CONFLICT,
+            DependencyError::IndexMismatch { .. } => StatusCode::BAD_GATEWAY,
+            DependencyError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl IntoResponse for DependencyError {
+    fn into_response(self) -> Response {
+        let message = match &self {
+            DependencyError::Database(e) => {
+                tracing::error!("Database error: {}", e);
+                "Internal server error".to_string()
+            }
+            other => other.to_string(),
+        };
+        let mut error = serde_json::json!({ "code": self.code(), "message": message });
+        if let DependencyError::HasDependents { dependents, .. } = &self {
+            error["dependents"] = serde_json::json!(dependents);
+        }
+        (self.status_code(), Json(serde_json::json!({ "success": false, "error": error }))).into_response()
+    }
+}
+
+pub fn parse_version(value: &str) -> Result<Version, DependencyError> {
+    Version::parse(value.trim()).map_err(|e| DependencyError::InvalidVersion { value: value.to_string(), reason: e.to_string() })
+}
+
+pub fn parse_requirement(name: &str, requirement: &str) -> Result<VersionReq, DependencyError> {
+    VersionReq::parse(requirement.trim()).map_err(|e| DependencyError::InvalidRequirement {
+        name: name.to_string(),
+        requirement: requirement.to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// The parts of a plugin version that resolution and activation look at.
+#[derive(Debug, Clone)]
+pub struct PluginVersionInfo {
+    pub name: String,
+    pub version: Version,
+    pub dependencies: BTreeMap<String, VersionReq>,
+    pub min_host: Option<Version>,
+    pub max_host: Option<Version>,
+}
+
+impl PluginVersionInfo {
+    pub fn new(
+        name: &str,
+        version: &str,
+        dependencies: &HashMap<String, String>,
+        min_host: Option<&str>,
+        max_host: Option<&str>,
+    ) -> Result<Self, DependencyError> {
+        let optional = |value: Option<&str>| value.filter(|v| !v.trim().is_empty()).map(parse_version).transpose();
+        Ok(Self {
+            name: name.to_string(),
+            version: parse_version(version)?,
+            dependencies: dependencies
+                .iter()
+                .map(|(dependency, requirement)| Ok((dependency.clone(), parse_requirement(dependency, requirement)?)))
+                .collect::<Result<_, DependencyError>>()?,
+            min_host: optional(min_host)?,
+            max_host: optional(max_host)?,
+        })
+    }
+
+    pub fn from_manifest(manifest: &PluginManifest) -> Result<Self, DependencyError> {
+        Self::new(
+            &manifest.name,
+            &manifest.version,
+            &manifest.dependencies,
+            Some(&manifest.min_jeantrail_version),
+            manifest.max_jeantrail_version.as_deref(),
+        )
+    }
+
+    /// Both bounds are inclusive.
+    pub fn supports_host(&self, host: &Version) -> bool {
+        self.min_host.as_ref().is_none_or(|min| host >= min) && self.max_host.as_ref().is_none_or(|max| host <= max)
+    }
+
+    pub fn check_host(&self, host: &Version) -> Result<(), DependencyError> {
+        if self.supports_host(host) {
+            return Ok(());
+        }
+        Err(DependencyError::IncompatibleHost {
+            plugin: self.name.clone(),
+            version: self.version.to_string(),
+            host: host.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstalledPluginVersion {
+    pub id: Uuid,
+    pub info: PluginVersionInfo,
+    pub is_active: bool,
+}
+
+/// One published version in a registry's `<name>/index.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryIndexEntry {
+    pub version: String,
+    /// Package URL, absolute or relative to the index.
+    pub url: String,
+    #[serde(default)]
+    pub dependencies: HashMap<String, String>,
+    #[serde(default)]
+    pub min_jeantrail_version: Option<String>,
+    #[serde(default)]
+    pub max_jeantrail_version: Option<String>,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    pub versions: Vec<RegistryIndexEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedStep {
+    /// Already installed at a matching version.
+    Installed { name: String, version: Version },
+    Install { name: String, version: Version, url: String },
+}
+
+#[derive(Debug)]
+pub enum Resolution {
+    /// Steps in dependency order; the root itself is not included.
+    Plan(Vec<PlannedStep>),
+    /// Resolution cannot go on until this plugin's registry index is known.
+    NeedsIndex(String),
+}
+
+pub struct DependencyResolver<'a> {
+    pub host: &'a Version,
+    pub installed: &'a HashMap<String, InstalledPluginVersion>,
+    pub index: &'a HashMap<String, RegistryIndex>,
+}
+
+#[derive(Default)]
+struct ResolveState {
+    selected: HashMap<String, Version>,
+    path: Vec<String>,
+    steps: Vec<PlannedStep>,
+}
+
+impl DependencyResolver<'_> {
+    pub fn resolve(&self, root: &PluginVersionInfo) -> Result<Resolution, DependencyError> {
+        root.check_host(self.host)?;
+        // Installing a new version must not break what already depends on the old one
+        for installed in self.installed.values() {
+            if let Some(requirement) = installed.info.dependencies.get(&root.name) {
+                if installed.info.name != root.name && !requirement.matches(&root.version) {
+                    return Err(DependencyError::Conflict {
+                        name: root.name.clone(),
+                        requirement: requirement.to_string(),
+                        found: root.version.to_string(),
+                        required_by: installed.info.name.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut state = ResolveState::default();
+        state.selected.insert(root.name.clone(), root.version.clone());
+        state.path.push(root.name.clone());
+        for (name, requirement) in &root.dependencies {
+            if let Some(missing) = self.visit(name, requirement, &root.name, &mut state)? {
+                return Ok(Resolution::NeedsIndex(missing));
+            }
+        }
+        Ok(Resolution::Plan(state.steps))
+    }
+
+    fn visit(
+        &self,
+        name: &str,
+        requirement: &VersionReq,
+        required_by: &str,
+        state: &mut ResolveState,
+    ) -> Result<Option<String>, DependencyError> {
+        if let Some(start) = state.path.iter().position(|visiting| visiting == name) {
+            let mut cycle = state.path[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(DependencyError::Cycle(cycle));
+        }
+        let conflict = |found: &Version| DependencyError::Conflict {
+            name: name.to_string(),
+            requirement: requirement.to_string(),
+            found: found.to_string(),
+            required_by: required_by.to_string(),
+        };
+        if let Some(selected) = state.selected.get(name) {
+            return if requirement.matches(selected) { Ok(None) } else { Err(conflict(selected)) };
+        }
+        if let Some(installed) = self.installed.get(name) {
+            if !requirement.matches(&installed.info.version) {
+                return Err(conflict(&installed.info.version));
+            }
+            installed.info.check_host(self.host)?;
+            state.selected.insert(name.to_string(), installed.info.version.clone());
+            state.steps.push(PlannedStep::Installed { name: name.to_string(), version: installed.info.version.clone() });
+            return Ok(None);
+        }
+
+        let Some(index) = self.index.get(name) else {
+            return Ok(Some(name.to_string()));
+        };
+        let (entry, info) = index
+            .versions
+            .iter()
+            .filter(|entry| !entry.yanked)
+            .filter_map(|entry| {
+                let info = PluginVersionInfo::new(
+                    name,
+                    &entry.version,
+                    &entry.dependencies,
+                    entry.min_jeantrail_version.as_deref(),
+                    entry.max_jeantrail_version.as_deref(),
+                )
+                .ok()?;
+                (requirement.matches(&info.version) && info.supports_host(self.host)).then_some((entry, info))
+            })
+            .max_by(|(_, a), (_, b)| a.version.cmp(&b.version))
+            .ok_or_else(|| DependencyError::Unsatisfiable { name: name.to_string(), requirement: requirement.to_string() })?;
+        if state.selected.len() > MAX_RESOLVED_PLUGINS {
+            return Err(DependencyError::TooManyDependencies(MAX_RESOLVED_PLUGINS));
+        }
+
+        state.selected.insert(name.to_string(), info.version.clone());
+        state.path.push(name.to_string());
+        for (dependency, dependency_requirement) in &info.dependencies {
+            if let Some(missing) = self.visit(dependency, dependency_requirement, name, state)? {
+                return Ok(Some(missing));
+            }
+        }
+        state.path.pop();
+        state.steps.push(PlannedStep::Install { name: name.to_string(), version: info.version, url: entry.url.clone() });
+        Ok(None)
+    }
+}
+
+/// Resolves `root`'s dependencies, fetching registry indexes as they turn out to be needed.
+pub async fn plan_dependencies(
+    installed: &HashMap<String, InstalledPluginVersion>,
+    root: &PluginVersionInfo,
+    config: &PluginInstallConfig,
+) -> Result<Vec<PlannedStep>, PluginPackageError> {
+    let host = host_version();
+    let mut index = HashMap::new();
+    loop {
+        let resolver = DependencyResolver { host: &host, installed, index: &index };
+        match resolver.resolve(root)? {
+            Resolution::Plan(steps) => return Ok(steps),
+            Resolution::NeedsIndex(name) => {
+                if index.len() >= MAX_RESOLVED_PLUGINS {
+                    return Err(DependencyError::TooManyDependencies(MAX_RESOLVED_PLUGINS).into());
+                }
+                let fetched = fetch_registry_index(&name, config).await?;
+                index.insert(name, fetched);
+            }
+        }
+    }
+}
+
+/// Reads `<registry_url>/<name>/index.json`, resolving package URLs against it.
+pub async fn fetch_registry_index(name: &str, config: &PluginInstallConfig) -> Result<RegistryIndex, PluginPackageError> {
+    let registry = config.registry_url.as_deref().ok_or_else(|| DependencyError::NoRegistry(name.to_string()))?;
+    let base = url::Url::parse(&format!("{}/", registry.trim_end_matches('/')))
+        .map_err(|e| PluginPackageError::InvalidUrl(e.to_string()))?;
+    let index_url = base
+        .join(&format!("{}/index.json", name))
+        .map_err(|e| PluginPackageError::InvalidUrl(e.to_string()))?;
+    let bytes = fetch_limited(index_url.as_str(), MAX_INDEX_BYTES, config).await?;
+    let mut index: RegistryIndex =
+        serde_json::from_slice(&bytes).map_err(|e| PluginPackageError::Download(format!("invalid index for {}: {}", name, e)))?;
+    for entry in &mut index.versions {
+        if let Ok(url) = index_url.join(&entry.url) {
+            entry.url = url.to_string();
+        }
+    }
+    Ok(index)
+}
+
+/// Installed plugins by name. Rows whose version or manifest cannot be parsed are skipped.
+pub async fn load_installed<'e, E>(executor: E) -> Result<HashMap<String, InstalledPluginVersion>, sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let rows = sqlx::query!(
+        r#"SELECT id, name, version, manifest, COALESCE(is_active, false) AS "is_active!" FROM plugins"#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| match installed_version(row.id, &row.name, &row.version, &row.manifest, row.is_active) {
+            Ok(installed) => Some((row.name, installed)),
+            Err(e) => {
+                tracing::warn!("Ignoring plugin {} for dependency checks: {}", row.name, e);
+                None
+            }
+        })
+        .collect())
+}
+
+fn installed_version(id: Uuid, name: &str, version: &str, manifest: &Value, is_active: bool) -> Result<InstalledPluginVersion, DependencyError> {
+    let dependencies: HashMap<String, String> = manifest
+        .get("dependencies")
+        .and_then(|dependencies| serde_json::from_value(dependencies.clone()).ok())
+        .unwrap_or_default();
+    let field = |key: &str| manifest.get(key).and_then(Value::as_str);
+    let info = PluginVersionInfo::new(name, version, &dependencies, field("min_jeantrail_version"), field("max_jeantrail_version"))?;
+    Ok(InstalledPluginVersion { id, info, is_active })
+}
+
+/// Plugins that have to be switched on, dependencies first, for `name` to run.
+pub fn activation_order(
+    name: &str,
+    installed: &HashMap<String, InstalledPluginVersion>,
+    host: &Version,
+) -> Result<Vec<String>, DependencyError> {
+    fn visit(
+        name: &str,
+        installed: &HashMap<String, InstalledPluginVersion>,
+        host: &Version,
+        path: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<(), DependencyError> {
+        if let Some(start) = path.iter().position(|visiting| visiting == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(DependencyError::Cycle(cycle));
+        }
+        if order.iter().any(|done| done == name) {
+            return Ok(());
+        }
+        let plugin = installed.get(name).ok_or(DependencyError::NotFound)?;
+        plugin.info.check_host(host)?;
+
+        path.push(name.to_string());
+        for (dependency, requirement) in &plugin.info.dependencies {
+            match installed.get(dependency) {
+                Some(found) if requirement.matches(&found.info.version) => {}
+                Some(found) => {
+                    return Err(DependencyError::Conflict {
+                        name: dependency.clone(),
+                        requirement: requirement.to_string(),
+                        found: found.info.version.to_string(),
+                        required_by: name.to_string(),
+                    })
+                }
+                None => {
+                    return Err(DependencyError::MissingDependency {
+                        name: dependency.clone(),
+                        requirement: requirement.to_string(),
+                        required_by: name.to_string(),
+                    })
+                }
+            }
+            visit(dependency, installed, host, path, order)?;
+        }
+        path.pop();
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    visit(name, installed, host, &mut Vec::new(), &mut order)?;
+    order.retain(|plugin| installed.get(plugin).is_some_and(|plugin| !plugin.is_active));
+    Ok(order)
+}
+
+/// Active plugins that declare a dependency on `name`, sorted.
+pub fn active_dependents(name: &str, installed: &HashMap<String, InstalledPluginVersion>) -> Vec<String> {
+    let mut dependents: Vec<String> = installed
+        .values()
+        .filter(|plugin| plugin.is_active && plugin.info.name != name && plugin.info.dependencies.contains_key(name))
+        .map(|plugin| plugin.info.name.clone())
+        .collect();
+    dependents.sort();
+    dependents
+}
+
+/// Installed plugins, active or not, that declare a dependency on `name`.
+pub fn installed_dependents(name: &str, installed: &HashMap<String, InstalledPluginVersion>) -> Vec<String> {
+    let mut dependents: Vec<String> = installed
+        .values()
+        .filter(|plugin| plugin.info.name != name && plugin.info.dependencies.contains_key(name))
+        .map(|plugin| plugin.info.name.clone())
+        .collect();
+    dependents.sort();
+    dependents
+}
+
+/// Activates a plugin along with any inactive dependencies; returns the names switched on.
+pub async fn activate_with_dependencies(db: &PgPool, name: &str) -> Result<Vec<String>, DependencyError> {
+    let mut tx = db.begin().await?;
+    sqlx::query!("LOCK TABLE plugins IN SHARE ROW EXCLUSIVE MODE").execute(&mut *tx).await?;
+    let installed = load_installed(&mut *tx).await?;
+    let order = activation_order(name, &installed, &host_version())?;
+    sqlx::query!(
+        "UPDATE plugins SET is_active = true, updated_at = NOW() WHERE name = ANY($1)",
+        &order
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(order)
+}
+
+/// Deactivates a plugin unless an active plugin still depends on it.
+pub async fn deactivate_unless_required(db: &PgPool, id: Uuid) -> Result<String, DependencyError> {
+    let mut tx = db.begin().await?;
+    sqlx::query!("LOCK TABLE plugins IN SHARE ROW EXCLUSIVE MODE").execute(&mut *tx).await?;
+    let name = sqlx::query_scalar!("SELECT name FROM plugins WHERE id = $1", id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(DependencyError::NotFound)?;
+    let installed = load_installed(&mut *tx).await?;
+    let dependents = active_dependents(&name, &installed);
+    if !dependents.is_empty() {
+        return Err(DependencyError::HasDependents { name, dependents });
+    }
+    sqlx::query!("UPDATE plugins SET is_active = false, updated_at = NOW() WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, version: &str, dependencies: &[(&str, &str)]) -> PluginVersionInfo {
+        let dependencies = dependencies.iter().map(|(n, r)| (n.to_string(), r.to_string())).collect();
+        PluginVersionInfo::new(name, version, &dependencies, Some("1.0.0"), None).unwrap()
+    }
+
+    /// Name, version, dependencies and whether it is active.
+    type Fixture<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)], bool);
+
+    fn installed(plugins: &[Fixture]) -> HashMap<String, InstalledPluginVersion> {
+        plugins
+            .iter()
+            .map(|(name, version, dependencies, is_active)| {
+                let plugin = InstalledPluginVersion { id: Uuid::new_v4(), info: info(name, version, dependencies), is_active: *is_active };
+                (name.to_string(), plugin)
+            })
+            .collect()
+    }
+
+    fn entry(version: &str, dependencies: &[(&str, &str)]) -> RegistryIndexEntry {
+        RegistryIndexEntry {
+            version: version.to_string(),
+            url: format!("https://registry.example/{}.jtplugin", version),
+            dependencies: dependencies.iter().map(|(n, r)| (n.to_string(), r.to_string())).collect(),
+            min_jeantrail_version: None,
+            max_jeantrail_version: None,
+            yanked: false,
+        }
+    }
+
+    fn host() -> Version {
+        Version::parse("1.4.0").unwrap()
+    }
+
+    fn plan(resolution: Resolution) -> Vec<PlannedStep> {
+        match resolution {
+            Resolution::Plan(steps) => steps,
+            Resolution::NeedsIndex(name) => panic!("unexpected index request for {}", name),
+        }
+    }
+
+    #[test]
+    fn host_bounds_and_prerelease_ordering() {
+        let dependencies = HashMap::new();
+        let bounded = PluginVersionInfo::new("p", "1.0.0", &dependencies, Some("1.2.0"), Some("2.0.0-rc.1")).unwrap();
+        assert!(bounded.supports_host(&Version::parse("1.2.0").unwrap()));
+        assert!(bounded.supports_host(&Version::parse("2.0.0-beta.3").unwrap()));
+        assert!(!bounded.supports_host(&Version::parse("1.2.0-alpha").unwrap()));
+        assert!(!bounded.supports_host(&Version::parse("2.0.0").unwrap()));
+        assert!(matches!(bounded.check_host(&Version::parse("3.0.0").unwrap()), Err(DependencyError::IncompatibleHost { .. })));
+        assert!(PluginVersionInfo::new("p", "1.0", &dependencies, None, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn installs_check_the_root_against_the_product_version() {
+        let config = PluginInstallConfig::default();
+        let manifest: PluginManifest = serde_json::from_value(serde_json::json!({
+            "name": "hello-ai",
+            "version": "1.0.0",
+            "entry": "main.wasm",
+            "api_version": "v1",
+            "min_jeantrail_version": "1.0.0"
+        }))
+        .unwrap();
+        let root = PluginVersionInfo::from_manifest(&manifest).unwrap();
+        let steps = plan_dependencies(&HashMap::new(), &root, &config).await.unwrap();
+        assert!(steps.is_empty());
+
+        let newer = PluginVersionInfo::new("future", "1.0.0", &HashMap::new(), Some("99.0.0"), None).unwrap();
+        match plan_dependencies(&HashMap::new(), &newer, &config).await {
+            Err(PluginPackageError::Dependency(DependencyError::IncompatibleHost { host, .. })) => assert_eq!(host, JEANTRAIL_VERSION),
+            other => panic!("expected incompatible_host, got {:?}", other.map(|steps| steps.len())),
+        }
+    }
+
+    #[test]
+    fn resolves_highest_matching_versions_dependencies_first() {
+        let installed = installed(&[("ui-kit", "2.3.1", &[], true)]);
+        let index = HashMap::from([
+            ("storage".to_string(), RegistryIndex { versions: vec![entry("1.1.0", &[("ui-kit", "^2.0")]), entry("1.4.2", &[("ui-kit", "^2.0")]), entry("2.0.0", &[])] }),
+            ("charts".to_string(), RegistryIndex { versions: vec![entry("0.9.0", &[]), entry("1.0.0-beta.2", &[])] }),
+        ]);
+        let root = info("dashboard", "1.0.0", &[("storage", ">=1.0, <2.0"), ("charts", "^0.9")]);
+        let resolver = DependencyResolver { host: &host(), installed: &installed, index: &index };
+
+        let steps = plan(resolver.resolve(&root).unwrap());
+        let summary: Vec<String> = steps
+            .iter()
+            .map(|step| match step {
+                PlannedStep::Installed { name, version } => format!("={}@{}", name, version),
+                PlannedStep::Install { name, version, .. } => format!("+{}@{}", name, version),
+            })
+            .collect();
+        assert_eq!(summary, vec!["+charts@0.9.0", "=ui-kit@2.3.1", "+storage@1.4.2"]);
+    }
+
+    #[test]
+    fn asks_for_missing_indexes_and_reports_unsatisfiable() {
+        let installed = HashMap::new();
+        let mut index = HashMap::new();
+        let root = info("dashboard", "1.0.0", &[("storage", "^3")]);
+        let resolver = DependencyResolver { host: &host(), installed: &installed, index: &index };
+        assert!(matches!(resolver.resolve(&root).unwrap(), Resolution::NeedsIndex(name) if name == "storage"));
+
+        index.insert("storage".to_string(), RegistryIndex { versions: vec![entry("2.0.0", &[]), entry("3.0.0-rc.1", &[])] });
+        let resolver = DependencyResolver { host: &host(), installed: &installed, index: &index };
+        assert!(matches!(resolver.resolve(&root), Err(DependencyError::Unsatisfiable { .. })));
+    }
+
+    #[test]
+    fn detects_cycles_and_conflicts() {
+        let installed_none = HashMap::new();
+        let index = HashMap::from([
+            ("a".to_string(), RegistryIndex { versions: vec![entry("1.0.0", &[("b", "*")])] }),
+            ("b".to_string(), RegistryIndex { versions: vec![entry("1.0.0", &[("root", "*")])] }),
+        ]);
+        let resolver = DependencyResolver { host: &host(), installed: &installed_none, index: &index };
+        match resolver.resolve(&info("root", "1.0.0", &[("a", "^1")])) {
+            Err(DependencyError::Cycle(cycle)) => assert_eq!(cycle, vec!["root", "a", "b", "root"]),
+            other => panic!("expected a cycle, got {:?}", other.map(|_| ())),
+        }
+
+        let installed = installed(&[("ui-kit", "1.9.0", &[], true), ("editor", "1.0.0", &[("root", "^1")], true)]);
+        let resolver = DependencyResolver { host: &host(), installed: &installed, index: &index };
+        assert!(matches!(
+            resolver.resolve(&info("root", "1.0.0", &[("ui-kit", "^2")])),
+            Err(DependencyError::Conflict { required_by, .. }) if required_by == "root"
+        ));
+        // Upgrading root to 2.0.0 would break editor
+        assert!(matches!(
+            resolver.resolve(&info("root", "2.0.0", &[])),
+            Err(DependencyError::Conflict { required_by, .. }) if required_by == "editor"
+        ));
+    }
+
+    #[test]
+    fn activation_pulls_in_dependencies_and_deactivation_respects_dependents() {
+        let installed = installed(&[
+            ("ui-kit", "2.0.0", &[], false),
+            ("storage", "1.2.0", &[("ui-kit", "^2")], true),
+            ("dashboard", "1.0.0", &[("storage", "^1.1"), ("ui-kit", "~2.0")], false),
+            ("broken", "1.0.0", &[("storage", "^2")], false),
+            ("orphan", "1.0.0", &[("missing", "*")], false),
+        ]);
+        assert_eq!(activation_order("dashboard", &installed, &host()).unwrap(), vec!["ui-kit", "dashboard"]);
+        assert!(matches!(activation_order("broken", &installed, &host()), Err(DependencyError::Conflict { .. })));
+        assert!(matches!(activation_order("orphan", &installed, &host()), Err(DependencyError::MissingDependency { .. })));
+
+        assert_eq!(active_dependents("ui-kit", &installed), vec!["storage"]);
+        assert!(active_dependents("storage", &installed).is_empty());
+        // Uninstalling looks at every installed dependent, active or not
+        assert_eq!(installed_dependents("storage", &installed), vec!["broken", "dashboard"]);
+        assert!(installed_dependents("dashboard", &installed).is_empty());
+    }
+
+    #[tokio::test]
+    async fn plans_against_a_registry_fixture() {
+        let index = serde_json::json!({
+            "versions": [
+                { "version": "1.0.0", "url": "storage-1.0.0.jtplugin" },
+                { "version": "1.1.0", "url": "storage-1.1.0.jtplugin", "yanked": true },
+                { "version": "1.2.0", "url": "https://cdn.example/storage-1.2.0.jtplugin", "max_jeantrail_version": "0.0.1" }
+            ]
+        });
+        let app = axum::Router::new().route("/registry/storage/index.json", axum::routing::get(move || async move { Json(index) }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/registry", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = PluginInstallConfig { registry_url: Some(base.clone()), allow_http: true, ..PluginInstallConfig::default() };
+        let dependencies = HashMap::from([("storage".to_string(), "^1".to_string())]);
+        let root = PluginVersionInfo::new("dashboard", "1.0.0", &dependencies, None, None).unwrap();
+
+        // 1.1.0 is yanked and 1.2.0 does not support this host
+        let steps = plan_dependencies(&HashMap::new(), &root, &config).await.unwrap();
+        assert_eq!(
+            steps,
+            vec![PlannedStep::Install {
+                name: "storage".to_string(),
+                version: Version::new(1, 0, 0),
+                url: format!("{}/storage/storage-1.0.0.jtplugin", base),
+            }]
+        );
+
+        let offline = PluginInstallConfig::default();
+        assert!(matches!(
+            plan_dependencies(&HashMap::new(), &root, &offline).await,
+            Err(PluginPackageError::Dependency(DependencyError::NoRegistry(_)))
+        ));
+    }
+}
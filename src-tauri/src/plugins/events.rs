@@ -0,0 +1,526 @@
+//! Event bus and lifecycle hooks for plugins.
+//!
+//! A manifest subscribes to events by name under `events` and opts into
+//! lifecycle hooks under `hooks`. Both reach the guest through `jt_run`.
+//! Events arrive as an `on_event` command with `{ event, payload, occurred_at }`.
+//! Hooks arrive as a command named after the hook.
+//!
+//! Each handler runs in its own task under a timeout, so a slow or failing
+//! plugin never holds up the publisher or the other subscribers. Fuel still
+//! bounds the guest's CPU after a timeout fires. A plugin whose event handlers
+//! keep failing is left out of deliveries for a while.
+//!
+//! Events go to a plugin only when the user has it enabled and has consented
+//! to the permission the event needs. Hooks run for the user who made the
+//! change. Host calls from `on_install` and `on_deactivate` are refused while
+//! the plugin is inactive.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use super::{is_valid_permission, Consent, InstalledPlugin, PluginCallContext, PluginCommand, PluginGrants, PluginHost, PluginRuntime};
+
+/// Command action events are delivered under.
+pub const EVENT_ACTION: &str = "on_event";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginEvent {
+    TabOpened,
+    TabNavigated,
+    JeanMessage,
+    MemorySaved,
+    OrderCreated,
+    ProxySessionStarted,
+}
+
+impl PluginEvent {
+    pub const ALL: [PluginEvent; 6] = [
+        PluginEvent::TabOpened,
+        PluginEvent::TabNavigated,
+        PluginEvent::JeanMessage,
+        PluginEvent::MemorySaved,
+        PluginEvent::OrderCreated,
+        PluginEvent::ProxySessionStarted,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PluginEvent::TabOpened => "tab.opened",
+            PluginEvent::TabNavigated => "tab.navigated",
+            PluginEvent::JeanMessage => "jean.message",
+            PluginEvent::MemorySaved => "memory.saved",
+            PluginEvent::OrderCreated => "order.created",
+            PluginEvent::ProxySessionStarted => "proxy.session_started",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.name() == name)
+    }
+
+    /// Permission a subscriber needs, on top of being enabled, to receive the event.
+    pub fn permission(self) -> Option<&'static str> {
+        match self {
+            PluginEvent::TabOpened | PluginEvent::TabNavigated => Some("tabs.read"),
+            PluginEvent::JeanMessage => Some("jean.chat"),
+            PluginEvent::ProxySessionStarted => Some("proxy.read"),
+            PluginEvent::MemorySaved => Some("memory.read"),
+            PluginEvent::OrderCreated => Some("orders.read"),
+        }
+    }
+
+    /// Whether a subscriber with these grants may receive the event.
+    pub fn delivers_to(self, grants: &PluginGrants) -> bool {
+        self.permission().is_none_or(|permission| grants.consent(permission) == Consent::Granted)
+    }
+
+    /// Tabs live in the browser UI, which reports their events through the API.
+    pub fn is_client_reported(self) -> bool {
+        matches!(self, PluginEvent::TabOpened | PluginEvent::TabNavigated)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleHook {
+    Install,
+    Activate,
+    Deactivate,
+    Uninstall,
+}
+
+impl LifecycleHook {
+    pub const ALL: [LifecycleHook; 4] = [LifecycleHook::Install, LifecycleHook::Activate, LifecycleHook::Deactivate, LifecycleHook::Uninstall];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LifecycleHook::Install => "on_install",
+            LifecycleHook::Activate => "on_activate",
+            LifecycleHook::Deactivate => "on_deactivate",
+            LifecycleHook::Uninstall => "on_uninstall",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|hook| hook.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginEventConfig {
+    pub handler_timeout_ms: u64,
+    pub hook_timeout_ms: u64,
+    /// Handlers running at once across all plugins.
+    pub max_concurrent_handlers: usize,
+    /// Consecutive event handler failures before a plugin is suspended.
+    pub failure_threshold: u32,
+    pub suspend_secs: u64,
+}
+
+impl Default for PluginEventConfig {
+    fn default() -> Self {
+        Self {
+            handler_timeout_ms: 5_000,
+            hook_timeout_ms: 15_000,
+            max_concurrent_handlers: 16,
+            failure_threshold: 5,
+            suspend_secs: 300,
+        }
+    }
+}
+
+impl PluginEventConfig {
+    /// Loads the config from the JSON file named by `PLUGIN_EVENT_CONFIG`,
+    /// falling back to the defaults.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("PLUGIN_EVENT_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring plugin event config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct PluginHealth {
+    consecutive_failures: u32,
+    suspended_until: Option<Instant>,
+}
+
+/// Consecutive event handler failures per plugin.
+#[derive(Debug, Default)]
+struct HandlerHealth {
+    plugins: HashMap<Uuid, PluginHealth>,
+}
+
+impl HandlerHealth {
+    /// Whether deliveries to the plugin are paused; an expired suspension
+    /// gives the plugin a fresh run of attempts.
+    fn is_suspended(&mut self, plugin_id: Uuid, now: Instant) -> bool {
+        let Some(health) = self.plugins.get_mut(&plugin_id) else {
+            return false;
+        };
+        match health.suspended_until {
+            Some(until) if until > now => true,
+            Some(_) => {
+                *health = PluginHealth::default();
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Records one handler run; true when it tips the plugin into suspension.
+    fn record(&mut self, plugin_id: Uuid, ok: bool, now: Instant, config: &PluginEventConfig) -> bool {
+        if ok {
+            self.plugins.remove(&plugin_id);
+            return false;
+        }
+        let health = self.plugins.entry(plugin_id).or_default();
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= config.failure_threshold && health.suspended_until.is_none() {
+            health.suspended_until = Some(now + Duration::from_secs(config.suspend_secs));
+            return true;
+        }
+        false
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HandlerOutcome {
+    pub plugin_id: Uuid,
+    pub plugin_name: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// An installed plugin a handler runs in.
+struct HandlerTarget {
+    id: Uuid,
+    name: String,
+    version: String,
+    entry: Option<String>,
+    permissions: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct PluginEventBus {
+    db: PgPool,
+    runtime: PluginRuntime,
+    host: Arc<dyn PluginHost>,
+    config: Arc<PluginEventConfig>,
+    health: Arc<Mutex<HandlerHealth>>,
+    permits: Arc<Semaphore>,
+}
+
+impl fmt::Debug for PluginEventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginEventBus").field("config", &self.config).finish_non_exhaustive()
+    }
+}
+
+impl PluginEventBus {
+    pub fn new(db: PgPool, runtime: PluginRuntime, host: Arc<dyn PluginHost>, config: PluginEventConfig) -> Self {
+        let permits = Arc::new(Semaphore::new(config.max_concurrent_handlers.max(1)));
+        Self { db, runtime, host, config: Arc::new(config), health: Arc::default(), permits }
+    }
+
+    /// Delivers `event` in the background to every plugin subscribed for `user_id`.
+    pub fn publish(&self, event: PluginEvent, user_id: Uuid, payload: Value) {
+        let bus = self.clone();
+        tokio::spawn(async move {
+            bus.deliver(event, user_id, payload).await;
+        });
+    }
+
+    /// Runs every subscriber's handler concurrently and waits for all of them.
+    pub async fn deliver(&self, event: PluginEvent, user_id: Uuid, payload: Value) -> Vec<HandlerOutcome> {
+        let subscribers = match load_subscribers(&self.db, event, user_id).await {
+            Ok(subscribers) => subscribers,
+            Err(e) => {
+                tracing::error!("Failed to load subscribers for {}: {}", event.name(), e);
+                return Vec::new();
+            }
+        };
+        let command = PluginCommand {
+            action: EVENT_ACTION.to_string(),
+            parameters: serde_json::json!({ "event": event.name(), "payload": payload, "occurred_at": Utc::now() }),
+        };
+        let timeout = Duration::from_millis(self.config.handler_timeout_ms);
+
+        let mut handlers = JoinSet::new();
+        for target in subscribers {
+            if self.health.lock().unwrap().is_suspended(target.id, Instant::now()) {
+                tracing::debug!("Skipping suspended plugin {} for {}", target.name, event.name());
+                continue;
+            }
+            let bus = self.clone();
+            let command = command.clone();
+            handlers.spawn(async move { bus.execute_handler(target, user_id, command, timeout).await });
+        }
+
+        let mut outcomes = Vec::new();
+        while let Some(joined) = handlers.join_next().await {
+            let outcome = match joined {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    tracing::error!("Plugin handler for {} panicked: {}", event.name(), e);
+                    continue;
+                }
+            };
+            let suspended = self.health.lock().unwrap().record(outcome.plugin_id, outcome.ok, Instant::now(), &self.config);
+            if suspended {
+                tracing::warn!(
+                    "Suspending events to plugin {} for {}s after {} failures",
+                    outcome.plugin_name,
+                    self.config.suspend_secs,
+                    self.config.failure_threshold
+                );
+            }
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+
+    /// Runs a lifecycle hook if the plugin's manifest asks for it.
+    pub async fn run_hook(&self, plugin_name: &str, hook: LifecycleHook, user_id: Uuid) -> Option<HandlerOutcome> {
+        // Host calls are checked against this user's consent; the nil id has none to check
+        if user_id.is_nil() {
+            tracing::error!("Refusing to run {} for plugin {} without a user", hook.name(), plugin_name);
+            return None;
+        }
+        let target = match load_hook_target(&self.db, plugin_name, hook).await {
+            Ok(target) => target?,
+            Err(e) => {
+                tracing::error!("Failed to load plugin {} for {}: {}", plugin_name, hook.name(), e);
+                return None;
+            }
+        };
+        let command = PluginCommand {
+            action: hook.name().to_string(),
+            parameters: serde_json::json!({ "hook": hook.name(), "version": target.version }),
+        };
+        Some(self.execute_handler(target, user_id, command, Duration::from_millis(self.config.hook_timeout_ms)).await)
+    }
+
+    /// `on_install` for everything an install added, dependencies first, then
+    /// `on_activate` for whatever it switched on.
+    pub async fn after_install(&self, installed: &InstalledPlugin, user_id: Uuid) {
+        let plugins: Vec<&InstalledPlugin> = installed.installed_dependencies.iter().chain(std::iter::once(installed)).collect();
+        for plugin in &plugins {
+            self.run_hook(&plugin.name, LifecycleHook::Install, user_id).await;
+        }
+        for plugin in plugins.iter().filter(|plugin| plugin.is_active) {
+            self.run_hook(&plugin.name, LifecycleHook::Activate, user_id).await;
+        }
+    }
+
+    async fn execute_handler(&self, target: HandlerTarget, user_id: Uuid, command: PluginCommand, timeout: Duration) -> HandlerOutcome {
+        let started = Instant::now();
+        let action = command.action.clone();
+        let result = self.invoke(&target, user_id, command, timeout).await;
+        if let Err(e) = &result {
+            tracing::warn!("Plugin {} {} handler failed: {}", target.name, action, e);
+        }
+        HandlerOutcome {
+            plugin_id: target.id,
+            plugin_name: target.name,
+            ok: result.is_ok(),
+            error: result.err(),
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    async fn invoke(&self, target: &HandlerTarget, user_id: Uuid, command: PluginCommand, timeout: Duration) -> Result<(), String> {
+        let _permit = self.permits.acquire().await.map_err(|e| e.to_string())?;
+        let entry = target.entry.as_deref().ok_or("plugin has no entry")?;
+        let path = self.runtime.resolve_entry(&target.name, &target.version, entry).map_err(|e| e.to_string())?;
+        let context = PluginCallContext {
+            plugin_id: target.id,
+            plugin_name: target.name.clone(),
+            user_id,
+            command_id: Uuid::new_v4(),
+            granted: target.permissions.iter().filter(|p| is_valid_permission(p)).cloned().collect(),
+        };
+        match tokio::time::timeout(timeout, self.runtime.execute(path, context, command, self.host.clone())).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!("timed out after {} ms", timeout.as_millis())),
+        }
+    }
+}
+
+/// Active plugins the user has enabled that subscribe to `event` and hold its permission.
+async fn load_subscribers(db: &PgPool, event: PluginEvent, user_id: Uuid) -> Result<Vec<HandlerTarget>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT p.id, p.name, p.version,
+               COALESCE(p.entry_point, p.manifest->>'entry') AS entry,
+               COALESCE(p.permissions, '{}') AS "permissions!",
+               p.manifest, s.granted_permissions, s.denied_permissions
+        FROM plugins p
+        JOIN user_plugin_settings s ON s.plugin_id = p.id AND s.user_id = $2
+        WHERE COALESCE(p.is_active, false) AND COALESCE(s.is_enabled, false)
+          AND p.manifest->'events' ? $1
+        "#,
+        event.name(),
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|row| {
+            event.delivers_to(&PluginGrants::from_row(row.id, row.manifest.clone(), true, true, row.granted_permissions.clone(), row.denied_permissions.clone()))
+        })
+        .map(|row| HandlerTarget { id: row.id, name: row.name, version: row.version, entry: row.entry, permissions: row.permissions })
+        .collect())
+}
+
+async fn load_hook_target(db: &PgPool, plugin_name: &str, hook: LifecycleHook) -> Result<Option<HandlerTarget>, sqlx::Error> {
+    sqlx::query_as!(
+        HandlerTarget,
+        r#"
+        SELECT id, name, version,
+               COALESCE(entry_point, manifest->>'entry') AS entry,
+               COALESCE(permissions, '{}') AS "permissions!"
+        FROM plugins
+        WHERE name = $1 AND manifest->'hooks' ? $2
+        "#,
+        plugin_name,
+        hook.name()
+    )
+    .fetch_optional(db)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::{PluginRuntimeConfig, UnavailableHost};
+
+    const ALLOC: &str = r#"
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 4096))
+        (func (export "jt_alloc") (param $len i32) (result i32)
+          (local $ptr i32)
+          (local.set $ptr (global.get $next))
+          (global.set $next (i32.add (global.get $next) (local.get $len)))
+          (local.get $ptr))
+    "#;
+
+    #[test]
+    fn names_round_trip() {
+        for event in PluginEvent::ALL {
+            assert_eq!(PluginEvent::from_name(event.name()), Some(event));
+        }
+        for hook in LifecycleHook::ALL {
+            assert_eq!(LifecycleHook::from_name(hook.name()), Some(hook));
+        }
+        assert_eq!(PluginEvent::from_name("tab.closed"), None);
+        assert!(PluginEvent::TabNavigated.is_client_reported());
+        assert!(!PluginEvent::OrderCreated.is_client_reported());
+    }
+
+    #[test]
+    fn every_event_needs_a_consented_permission() {
+        for event in PluginEvent::ALL {
+            assert!(event.permission().is_some_and(is_valid_permission), "{}", event.name());
+        }
+
+        let manifest = serde_json::json!({ "permissions": [
+            { "name": "memory.read", "description": "Index saved memories", "required": false },
+            { "name": "orders.read", "description": "Track orders", "required": false }
+        ] });
+        let grants = |granted: &[&str], denied: &[&str]| {
+            PluginGrants::from_row(
+                Uuid::new_v4(),
+                manifest.clone(),
+                true,
+                true,
+                granted.iter().map(|p| p.to_string()).collect(),
+                denied.iter().map(|p| p.to_string()).collect(),
+            )
+        };
+
+        assert!(!PluginEvent::MemorySaved.delivers_to(&grants(&[], &[])));
+        assert!(PluginEvent::MemorySaved.delivers_to(&grants(&["memory.read"], &[])));
+        assert!(!PluginEvent::OrderCreated.delivers_to(&grants(&["memory.read"], &[])));
+        assert!(!PluginEvent::OrderCreated.delivers_to(&grants(&["orders.read"], &["orders.read"])));
+        assert!(PluginEvent::OrderCreated.delivers_to(&grants(&["orders.read"], &[])));
+        assert!(!PluginEvent::JeanMessage.delivers_to(&grants(&["memory.read", "orders.read"], &[])));
+    }
+
+    #[test]
+    fn repeated_failures_suspend_a_plugin_until_the_cooldown_ends() {
+        let config = PluginEventConfig { failure_threshold: 3, suspend_secs: 60, ..PluginEventConfig::default() };
+        let mut health = HandlerHealth::default();
+        let plugin = Uuid::new_v4();
+        let now = Instant::now();
+
+        assert!(!health.record(plugin, false, now, &config));
+        assert!(!health.record(plugin, true, now, &config));
+        assert!(!health.record(plugin, false, now, &config));
+        assert!(!health.record(plugin, false, now, &config));
+        assert!(!health.is_suspended(plugin, now));
+        assert!(health.record(plugin, false, now, &config));
+        assert!(health.is_suspended(plugin, now + Duration::from_secs(59)));
+        assert!(!health.is_suspended(Uuid::new_v4(), now));
+
+        assert!(!health.is_suspended(plugin, now + Duration::from_secs(61)));
+        assert!(!health.record(plugin, false, now, &config));
+    }
+
+    #[tokio::test]
+    async fn handlers_fail_and_time_out_on_their_own() {
+        let dir = std::env::temp_dir().join(format!("jt-events-{}", Uuid::new_v4()));
+        let runtime = PluginRuntime::new(PluginRuntimeConfig { plugins_dir: dir.clone(), fuel_per_command: 20_000_000, ..PluginRuntimeConfig::default() });
+        let modules = [
+            ("echo", r#"(func (export "jt_run") (param $ptr i32) (param $len i32) (result i64)
+                (i64.or (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32)) (i64.extend_i32_u (local.get $len))))"#),
+            ("spin", r#"(func (export "jt_run") (param i32 i32) (result i64) (loop $spin (br $spin)) (i64.const 0))"#),
+        ];
+        for (name, body) in modules {
+            let path = runtime.resolve_entry(name, "1.0.0", "main.wasm").unwrap();
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, wat::parse_str(format!("(module {} {})", ALLOC, body)).unwrap()).unwrap();
+        }
+
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let bus = PluginEventBus::new(db, runtime, Arc::new(UnavailableHost), PluginEventConfig::default());
+        let target = |name: &str| HandlerTarget {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            entry: Some("main.wasm".to_string()),
+            permissions: Vec::new(),
+        };
+        let command = PluginCommand { action: EVENT_ACTION.to_string(), parameters: serde_json::json!({ "event": "tab.opened" }) };
+        let user = Uuid::new_v4();
+
+        let echoed = bus.execute_handler(target("echo"), user, command.clone(), Duration::from_secs(10)).await;
+        assert!(echoed.ok, "{:?}", echoed.error);
+        let slow = bus.execute_handler(target("spin"), user, command.clone(), Duration::from_millis(1)).await;
+        assert!(slow.error.is_some_and(|e| e.contains("timed out")));
+        let missing = bus.execute_handler(target("gone"), user, command, Duration::from_secs(10)).await;
+        assert!(!missing.ok && missing.plugin_name == "gone");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,910 @@
+// Plugin System Module
+pub mod bridge;
+pub mod dependencies;
+pub mod events;
+pub mod package;
+pub mod runtime;
+pub mod storage;
+
+pub use bridge::*;
+pub use dependencies::*;
+pub use events::*;
+pub use package::*;
+pub use runtime::*;
+pub use storage::*;
+
+use axum::{Extension, Json, body::Bytes, extract::{Path, Query, State}, http::StatusCode, response::{IntoResponse, Response}};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::security::{is_unsafe_path_param, record_audit_event, require_admin, require_self, ClientInfo, SafePath, UserContext};
+
+#[derive(Clone)]
+pub struct PluginState {
+    pub db: PgPool,
+    pub runtime: PluginRuntime,
+    pub bridge: Arc<PluginBridge>,
+    pub install: Arc<PluginInstallConfig>,
+    pub events: PluginEventBus,
+}
+
+impl PluginState {
+    pub fn new(db: PgPool) -> Self {
+        let runtime = PluginRuntime::new(PluginRuntimeConfig::load());
+        let bridge = Arc::new(PluginBridge::new(db.clone()));
+        let events = PluginEventBus::new(db.clone(), runtime.clone(), bridge.clone(), PluginEventConfig::load());
+        Self {
+            db,
+            runtime,
+            bridge,
+            install: Arc::new(PluginInstallConfig::load()),
+            events,
+        }
+    }
+}
+
+fn db_error(e: sqlx::Error) -> StatusCode {
+    tracing::error!("Database error: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Plugin {
+    pub id: Uuid,
+    pub name: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub version: String,
+    pub author: Option<String>,
+    pub manifest: Value,
+    pub entry_point: Option<String>,
+    pub permissions: Vec<String>,
+    pub is_active: bool,
+    pub is_system: bool,
+    pub installed_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserPluginSettings {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub plugin_id: Uuid,
+    pub settings: Value,
+    pub is_enabled: bool,
+    /// Optional permissions the user allowed.
+    pub granted_permissions: Vec<String>,
+    /// Permissions the user refused.
+    pub denied_permissions: Vec<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePluginRequest {
+    pub name: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub version: String,
+    pub author: Option<String>,
+    pub manifest: Value,
+    pub entry_point: Option<String>,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePluginRequest {
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub manifest: Option<Value>,
+    pub entry_point: Option<String>,
+    pub permissions: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserPluginSettingsRequest {
+    pub settings: Value,
+    pub is_enabled: bool,
+    /// Replaces the user's answers for optional permissions when present.
+    pub granted_permissions: Option<Vec<String>>,
+    pub denied_permissions: Option<Vec<String>>,
+}
+
+// Plugin Manifest Structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    pub version: String,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    pub license: Option<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    pub entry: String,
+    #[serde(default)]
+    pub permissions: Vec<PluginPermission>,
+    pub api_version: String,
+    pub min_jeantrail_version: String,
+    pub max_jeantrail_version: Option<String>,
+    #[serde(default)]
+    pub dependencies: HashMap<String, String>,
+    #[serde(default)]
+    pub resources: PluginResources,
+    /// Hosts `network.request` may reach, exact or as `*.example.com`.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// Bus events the plugin subscribes to, e.g. `tab.opened`.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Lifecycle hooks the plugin implements, e.g. `on_install`.
+    #[serde(default)]
+    pub hooks: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginPermission {
+    pub name: String,
+    pub description: String,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginResources {
+    pub css_files: Vec<String>,
+    pub js_files: Vec<String>,
+    pub assets: Vec<String>,
+    pub icons: PluginIcons,
+}
+
+impl PluginResources {
+    /// Every file the manifest points at besides the entry.
+    pub fn files(&self) -> impl Iterator<Item = &String> {
+        let icons = [&self.icons.icon_16x16, &self.icons.icon_32x32, &self.icons.icon_48x48, &self.icons.icon_128x128];
+        self.css_files
+            .iter()
+            .chain(&self.js_files)
+            .chain(&self.assets)
+            .chain(icons.into_iter().flatten())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginIcons {
+    pub icon_16x16: Option<String>,
+    pub icon_32x32: Option<String>,
+    pub icon_48x48: Option<String>,
+    pub icon_128x128: Option<String>,
+}
+
+pub async fn list_plugins(
+    Query(params): Query<ListPluginsQuery>,
+) -> Result<Json<Vec<Plugin>>, axum::http::StatusCode> {
+    let mock_plugins = vec![
+        Plugin {
+            id: Uuid::new_v4(),
+            name: "hello-ai".to_string(),
+            display_name: "Hello AI Assistant".to_string(),
+            description: Some("A simple AI assistant plugin that uses Jean API".to_string()),
+            version: "1.0.0".to_string(),
+            author: Some("JeanTrail Team".to_string()),
+            manifest: serde_json::json!({
+                "name": "hello-ai",
+                "version": "1.0.0",
+                "description": "Simple AI assistant plugin",
+                "entry": "/plugins/hello-ai/index.html",
+                "permissions": [
+                    {
+                        "name": "jean.chat",
+                        "description": "Access Jean AI chat functionality",
+                        "required": true
+                    }
+                ],
+                "api_version": "v1",
+                "min_jeantrail_version": "1.0.0"
+            }),
+            entry_point: Some("/plugins/hello-ai/index.html".to_string()),
+            permissions: vec!["jean.chat".to_string()],
+            is_active: true,
+            is_system: false,
+            installed_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: Some(Uuid::new_v4()),
+        }
+    ];
+
+    Ok(Json(mock_plugins))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListPluginsQuery {
+    pub is_active: Option<bool>,
+    pub is_system: Option<bool>,
+    pub author: Option<String>,
+}
+
+pub async fn get_plugin(
+    Path(id): Path<Uuid>,
+) -> Result<Json<Plugin>, axum::http::StatusCode> {
+    // Fetch specific plugin
+    Err(axum::http::StatusCode::NOT_FOUND)
+}
+
+pub async fn create_plugin(
+    Json(request): Json<CreatePluginRequest>,
+) -> Result<Json<Plugin>, axum::http::StatusCode> {
+    let new_plugin = Plugin {
+        id: Uuid::new_v4(),
+        name: request.name.clone(),
+        display_name: request.display_name,
+        description: request.description,
+        version: request.version,
+        author: request.author,
+        manifest: request.manifest,
+        entry_point: request.entry_point,
+        permissions: request.permissions,
+        is_active: false,
+        is_system: false,
+        installed_at: Utc::now(),
+        updated_at: Utc::now(),
+        created_by: Some(Uuid::new_v4()),
+    };
+
+    // Validate manifest and save to database
+    Ok(Json(new_plugin))
+}
+
+pub async fn update_plugin(
+    Path(id): Path<Uuid>,
+    Json(request): Json<UpdatePluginRequest>,
+) -> Result<Json<Plugin>, axum::http::StatusCode> {
+    // Update plugin in database
+    Err(axum::http::StatusCode::NOT_FOUND)
+}
+
+pub async fn delete_plugin(
+    State(state): State<PluginState>,
+    Path(id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Json<UninstalledPlugin>, Response> {
+    let user = require_admin(user).map_err(IntoResponse::into_response)?;
+    let failed = |e: PluginPackageError| {
+        tracing::warn!("Plugin {} uninstall failed: {}", id, e);
+        e.into_response()
+    };
+    // on_uninstall runs while the plugin, its files and its storage still exist
+    let name = check_uninstall(&state.db, id).await.map_err(failed)?;
+    state.events.run_hook(&name, LifecycleHook::Uninstall, user.user_id).await;
+    let uninstalled = uninstall_plugin(&state.db, &state.runtime, id).await.map_err(failed)?;
+    record_audit_event(
+        &state.db,
+        Some(user.user_id),
+        "plugin.uninstalled",
+        "plugin",
+        &id.to_string(),
+        serde_json::json!({
+            "name": uninstalled.name,
+            "version": uninstalled.version,
+            "storage_entries_removed": uninstalled.storage_entries_removed,
+        }),
+        Some(&client),
+    )
+    .await;
+    Ok(Json(uninstalled))
+}
+
+async fn plugin_name(db: &PgPool, id: Uuid) -> Result<String, DependencyError> {
+    sqlx::query_scalar!("SELECT name FROM plugins WHERE id = $1", id)
+        .fetch_optional(db)
+        .await?
+        .ok_or(DependencyError::NotFound)
+}
+
+/// Activates a plugin once it is compatible with this host, switching on its dependencies first.
+///
+/// Lifecycle hooks run for the admin making the change, whose consent host calls go on.
+pub async fn activate_plugin(
+    State(state): State<PluginState>,
+    Path(id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Json<Value>, Response> {
+    let admin = require_admin(user).map_err(IntoResponse::into_response)?;
+    let name = plugin_name(&state.db, id).await.map_err(IntoResponse::into_response)?;
+    let activated = activate_with_dependencies(&state.db, &name).await.map_err(IntoResponse::into_response)?;
+    for plugin in &activated {
+        state.events.run_hook(plugin, LifecycleHook::Activate, admin.user_id).await;
+    }
+    record_audit_event(&state.db, Some(admin.user_id), "plugin.activated", "plugin", &id.to_string(), serde_json::json!({
+        "name": name,
+        "activated": activated
+    }), Some(&client)).await;
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Plugin activated successfully",
+        "activated": activated
+    })))
+}
+
+/// Refused with 409 while an active plugin depends on this one.
+pub async fn deactivate_plugin(
+    State(state): State<PluginState>,
+    Path(id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Json<Value>, Response> {
+    let admin = require_admin(user).map_err(IntoResponse::into_response)?;
+    let name = deactivate_unless_required(&state.db, id).await.map_err(IntoResponse::into_response)?;
+    state.events.run_hook(&name, LifecycleHook::Deactivate, admin.user_id).await;
+    record_audit_event(&state.db, Some(admin.user_id), "plugin.deactivated", "plugin", &id.to_string(), serde_json::json!({
+        "name": name
+    }), Some(&client)).await;
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": "Plugin deactivated successfully"
+    })))
+}
+
+pub async fn get_user_plugin_settings(
+    State(state): State<PluginState>,
+    Path((user_id, plugin_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<UserPluginSettings>, axum::http::StatusCode> {
+    require_self(user, user_id)?;
+    sqlx::query_as!(
+        UserPluginSettings,
+        r#"
+        SELECT id, user_id AS "user_id!", plugin_id AS "plugin_id!",
+               COALESCE(settings, '{}') AS "settings!",
+               COALESCE(is_enabled, false) AS "is_enabled!",
+               granted_permissions, denied_permissions,
+               last_used_at,
+               COALESCE(created_at, NOW()) AS "created_at!",
+               COALESCE(updated_at, NOW()) AS "updated_at!"
+        FROM user_plugin_settings
+        WHERE user_id = $1 AND plugin_id = $2
+        "#,
+        user_id,
+        plugin_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .map(Json)
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Enabling a plugin is the user's consent to its required permissions; the
+/// optional ones are answered here or through runtime permission prompts.
+pub async fn update_user_plugin_settings(
+    State(state): State<PluginState>,
+    Path((user_id, plugin_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<UpdateUserPluginSettingsRequest>,
+) -> Result<Json<UserPluginSettings>, axum::http::StatusCode> {
+    require_self(user, user_id)?;
+    let mut answers = request.granted_permissions.iter().chain(&request.denied_permissions).flatten();
+    if answers.any(|p| !is_valid_permission(p)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let (Some(granted), Some(denied)) = (&request.granted_permissions, &request.denied_permissions) {
+        if granted.iter().any(|p| denied.contains(p)) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let settings = sqlx::query_as!(
+        UserPluginSettings,
+        r#"
+        INSERT INTO user_plugin_settings (user_id, plugin_id, settings, is_enabled, granted_permissions, denied_permissions)
+        SELECT $1, p.id, $3, $4, COALESCE($5, '{}'), COALESCE($6, '{}')
+        FROM plugins p WHERE p.id = $2
+        ON CONFLICT (user_id, plugin_id) DO UPDATE SET
+            settings = EXCLUDED.settings,
+            is_enabled = EXCLUDED.is_enabled,
+            granted_permissions = COALESCE($5, user_plugin_settings.granted_permissions),
+            denied_permissions = COALESCE($6, user_plugin_settings.denied_permissions),
+            updated_at = NOW()
+        RETURNING id, user_id AS "user_id!", plugin_id AS "plugin_id!",
+                  COALESCE(settings, '{}') AS "settings!",
+                  COALESCE(is_enabled, false) AS "is_enabled!",
+                  granted_permissions, denied_permissions,
+                  last_used_at,
+                  COALESCE(created_at, NOW()) AS "created_at!",
+                  COALESCE(updated_at, NOW()) AS "updated_at!"
+        "#,
+        user_id,
+        plugin_id,
+        request.settings,
+        request.is_enabled,
+        request.granted_permissions.as_deref(),
+        request.denied_permissions.as_deref()
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    record_audit_event(
+        &state.db,
+        Some(user_id),
+        "plugin.settings_updated",
+        "plugin",
+        &plugin_id.to_string(),
+        serde_json::json!({
+            "is_enabled": settings.is_enabled,
+            "granted_permissions": settings.granted_permissions,
+            "denied_permissions": settings.denied_permissions,
+        }),
+        Some(&client),
+    )
+    .await;
+    Ok(Json(settings))
+}
+
+pub async fn list_permission_requests(
+    State(state): State<PluginState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<Vec<PluginPermissionRequest>>, axum::http::StatusCode> {
+    require_self(user, user_id)?;
+    let requests = pending_permission_requests(&state.db, user_id).await.map_err(db_error)?;
+    Ok(Json(requests))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnswerPermissionRequest {
+    pub grant: bool,
+}
+
+pub async fn answer_permission_prompt(
+    State(state): State<PluginState>,
+    Path((user_id, request_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<AnswerPermissionRequest>,
+) -> Result<Json<PluginPermissionRequest>, axum::http::StatusCode> {
+    require_self(user, user_id)?;
+    let answered = answer_permission_request(&state.db, user_id, request_id, request.grant)
+        .await
+        .map_err(db_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let action = if request.grant { "plugin.permission_granted" } else { "plugin.permission_denied" };
+    record_audit_event(
+        &state.db,
+        Some(user_id),
+        action,
+        "plugin",
+        &answered.plugin_id.to_string(),
+        serde_json::json!({ "permission": answered.permission, "request_id": answered.id }),
+        Some(&client),
+    )
+    .await;
+    Ok(Json(answered))
+}
+
+pub async fn list_plugin_notifications(
+    State(state): State<PluginState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<Vec<PluginNotification>>, axum::http::StatusCode> {
+    require_self(user, user_id)?;
+    let notifications = recent_notifications(&state.db, user_id).await.map_err(db_error)?;
+    Ok(Json(notifications))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportPluginEventRequest {
+    pub event: String,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+/// Lets the browser UI publish the events only it sees, such as tab changes.
+pub async fn report_plugin_event(
+    State(state): State<PluginState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    Json(request): Json<ReportPluginEventRequest>,
+) -> Result<StatusCode, axum::http::StatusCode> {
+    require_self(user, user_id)?;
+    let event = PluginEvent::from_name(&request.event)
+        .filter(|event| event.is_client_reported())
+        .ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+    state.events.publish(event, user_id, request.payload);
+    Ok(StatusCode::ACCEPTED)
+}
+
+pub async fn install_plugin_from_url(
+    State(state): State<PluginState>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<InstallPluginRequest>,
+) -> Result<Json<InstalledPlugin>, Response> {
+    let user = require_admin(user).map_err(IntoResponse::into_response)?;
+    let source = serde_json::json!({ "url": request.url });
+    let result = match download_package(&request.url, &state.install).await {
+        Ok(bytes) => install_package(&state.db, &state.runtime, &state.install, bytes, request.auto_activate, Some(user.user_id)).await,
+        Err(e) => Err(e),
+    };
+    audit_install(&state.db, user.user_id, source, &result, &client).await;
+    if let Ok(installed) = &result {
+        state.events.after_install(installed, user.user_id).await;
+    }
+    result.map(Json).map_err(IntoResponse::into_response)
+}
+
+/// Installs a `.jtplugin` archive sent as the raw request body.
+pub async fn upload_plugin_package(
+    State(state): State<PluginState>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Query(params): Query<UploadPluginQuery>,
+    body: Bytes,
+) -> Result<Json<InstalledPlugin>, Response> {
+    let user = require_admin(user).map_err(IntoResponse::into_response)?;
+    let result = install_package(&state.db, &state.runtime, &state.install, body.to_vec(), params.auto_activate, Some(user.user_id)).await;
+    audit_install(&state.db, user.user_id, serde_json::json!({ "upload": true }), &result, &client).await;
+    if let Ok(installed) = &result {
+        state.events.after_install(installed, user.user_id).await;
+    }
+    result.map(Json).map_err(IntoResponse::into_response)
+}
+
+async fn audit_install(
+    db: &PgPool,
+    user_id: Uuid,
+    mut details: Value,
+    result: &Result<InstalledPlugin, PluginPackageError>,
+    client: &ClientInfo,
+) {
+    let (action, resource_id) = match result {
+        Ok(installed) => {
+            details["name"] = Value::String(installed.name.clone());
+            details["version"] = Value::String(installed.version.clone());
+            details["publisher_key_id"] = Value::String(installed.publisher_key_id.clone());
+            details["package_sha256"] = Value::String(installed.package_sha256.clone());
+            details["upgraded_from"] = serde_json::json!(installed.upgraded_from);
+            details["disabled_for_users"] = serde_json::json!(installed.disabled_for_users);
+            ("plugin.installed", installed.plugin_id.to_string())
+        }
+        Err(e) => {
+            details["error"] = Value::String(e.code().to_string());
+            ("plugin.install_rejected", String::new())
+        }
+    };
+    record_audit_event(db, Some(user_id), action, "plugin", &resource_id, details, Some(client)).await;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstallPluginRequest {
+    pub url: String,
+    #[serde(default)]
+    pub auto_activate: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct UploadPluginQuery {
+    pub auto_activate: bool,
+}
+
+pub async fn validate_plugin_manifest(
+    Json(manifest): Json<PluginManifest>,
+) -> Result<Json<Value>, axum::http::StatusCode> {
+    let validation_errors = manifest_errors(&manifest);
+
+    Ok(Json(serde_json::json!({
+        "valid": validation_errors.is_empty(),
+        "errors": validation_errors
+    })))
+}
+
+/// Problems that make a manifest unusable; empty when it is valid.
+pub fn manifest_errors(manifest: &PluginManifest) -> Vec<String> {
+    let mut validation_errors = Vec::new();
+
+    // Validate required fields
+    if manifest.name.is_empty() {
+        validation_errors.push("Plugin name is required".to_string());
+    } else if !is_valid_plugin_name(&manifest.name) {
+        validation_errors.push("Plugin name may only contain lowercase letters, digits, '-' and '_'".to_string());
+    }
+
+    if manifest.entry.is_empty() {
+        validation_errors.push("Plugin entry point is required".to_string());
+    }
+
+    // Every path is resolved inside the plugin directory
+    for path in std::iter::once(&manifest.entry).chain(manifest.resources.files()) {
+        if is_unsafe_path_param(path.strip_prefix("./").unwrap_or(path)) {
+            validation_errors.push(format!("Path escapes the plugin directory: {}", path));
+        }
+    }
+
+    // Validate version format
+    if !is_valid_version(&manifest.version) {
+        validation_errors.push("Invalid version format".to_string());
+    }
+
+    // Host bounds are inclusive semver versions
+    match parse_version(&manifest.min_jeantrail_version) {
+        Ok(min) => {
+            if let Some(max) = &manifest.max_jeantrail_version {
+                match parse_version(max) {
+                    Ok(max) if max < min => validation_errors.push("max_jeantrail_version is below min_jeantrail_version".to_string()),
+                    Ok(_) => {}
+                    Err(e) => validation_errors.push(e.to_string()),
+                }
+            }
+        }
+        Err(e) => validation_errors.push(e.to_string()),
+    }
+
+    // Dependencies are other plugins by name, each with a semver requirement
+    for (name, requirement) in &manifest.dependencies {
+        if name == &manifest.name {
+            validation_errors.push("A plugin cannot depend on itself".to_string());
+        } else if !is_valid_plugin_name(name) {
+            validation_errors.push(format!("Invalid dependency name: {}", name));
+        }
+        if let Err(e) = parse_requirement(name, requirement) {
+            validation_errors.push(e.to_string());
+        }
+    }
+
+    // Validate permissions
+    for permission in &manifest.permissions {
+        if !is_valid_permission(&permission.name) {
+            validation_errors.push(format!("Invalid permission: {}", permission.name));
+        }
+    }
+
+    for host in &manifest.allowed_hosts {
+        if !is_valid_host_pattern(host) {
+            validation_errors.push(format!("Invalid allowed host: {}", host));
+        }
+    }
+
+    // Subscribing to an event needs the permission that guards its payload
+    for name in &manifest.events {
+        match PluginEvent::from_name(name) {
+            Some(event) => {
+                if let Some(permission) = event.permission() {
+                    if !manifest.permissions.iter().any(|p| p.name == permission) {
+                        validation_errors.push(format!("Event {} needs the {} permission", name, permission));
+                    }
+                }
+            }
+            None => validation_errors.push(format!("Unknown event: {}", name)),
+        }
+    }
+
+    for hook in &manifest.hooks {
+        if LifecycleHook::from_name(hook).is_none() {
+            validation_errors.push(format!("Unknown hook: {}", hook));
+        }
+    }
+
+    validation_errors
+}
+
+/// Names double as directory names, so they are kept to one plain segment.
+fn is_valid_plugin_name(name: &str) -> bool {
+    name.len() <= 100
+        && name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// A DNS name, optionally behind a `*.` wildcard; IP literals are not allowed.
+fn is_valid_host_pattern(host: &str) -> bool {
+    let name = host.strip_prefix("*.").unwrap_or(host);
+    let labels: Vec<&str> = name.split('.').collect();
+    labels.len() >= 2
+        && name.len() <= 253
+        && !labels.last().is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit()))
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+fn is_valid_version(version: &str) -> bool {
+    // Full semantic versions, pre-release and build metadata included
+    parse_version(version).is_ok()
+}
+
+/// Permission names a plugin manifest may request; API key scopes use the same names.
+pub const PLUGIN_PERMISSIONS: &[&str] = &[
+    "jean.chat",
+    "jean.actions",
+    "tabs.read",
+    "tabs.write",
+    "proxy.read",
+    "proxy.write",
+    "memory.read",
+    "orders.read",
+    "files.read",
+    "files.write",
+    "network.request",
+    "storage.read",
+    "storage.write",
+    "ui.panel",
+    "ui.modal",
+    "notifications.show",
+];
+
+pub fn is_valid_permission(permission: &str) -> bool {
+    PLUGIN_PERMISSIONS.contains(&permission)
+}
+
+/// What the runtime needs to know about an installed plugin to run it for one user.
+struct RunnablePlugin {
+    name: String,
+    version: String,
+    entry: Option<String>,
+    permissions: Vec<String>,
+    is_active: bool,
+    user_enabled: Option<bool>,
+}
+
+async fn load_runnable_plugin(db: &PgPool, plugin_id: Uuid, user_id: Uuid) -> Result<Option<RunnablePlugin>, sqlx::Error> {
+    sqlx::query_as!(
+        RunnablePlugin,
+        r#"
+        SELECT p.name, p.version,
+               COALESCE(p.entry_point, p.manifest->>'entry') AS entry,
+               COALESCE(p.permissions, '{}') AS "permissions!",
+               COALESCE(p.is_active, false) AS "is_active!",
+               s.is_enabled AS "user_enabled?"
+        FROM plugins p
+        LEFT JOIN user_plugin_settings s ON s.plugin_id = p.id AND s.user_id = $2
+        WHERE p.id = $1
+        "#,
+        plugin_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+}
+
+async fn touch_user_plugin(db: &PgPool, plugin_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE user_plugin_settings SET last_used_at = NOW() WHERE plugin_id = $1 AND user_id = $2",
+        plugin_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+// Plugin Sandbox Execution
+pub async fn execute_plugin_command(
+    State(state): State<PluginState>,
+    Path((plugin_id, user_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+    Json(command): Json<PluginCommand>,
+) -> Result<Json<PluginExecution>, Response> {
+    require_self(user, user_id).map_err(IntoResponse::into_response)?;
+    let plugin = load_runnable_plugin(&state.db, plugin_id, user_id)
+        .await
+        .map_err(|e| db_error(e).into_response())?
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+    if !plugin.is_active {
+        return Err(StatusCode::CONFLICT.into_response());
+    }
+    if plugin.user_enabled != Some(true) {
+        return Err(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let entry = plugin.entry.ok_or(PluginRuntimeError::InvalidEntry).map_err(IntoResponse::into_response)?;
+    let path = state
+        .runtime
+        .resolve_entry(&plugin.name, &plugin.version, &entry)
+        .map_err(IntoResponse::into_response)?;
+    let context = PluginCallContext {
+        plugin_id,
+        plugin_name: plugin.name,
+        user_id,
+        command_id: Uuid::new_v4(),
+        granted: plugin.permissions.into_iter().filter(|p| is_valid_permission(p)).collect(),
+    };
+
+    let result = state.runtime.execute(path, context, command, state.bridge.clone()).await;
+    if let Err(e) = touch_user_plugin(&state.db, plugin_id, user_id).await {
+        tracing::warn!("Failed to record plugin use: {}", e);
+    }
+    match result {
+        Ok(execution) => Ok(Json(execution)),
+        Err(e) => {
+            tracing::warn!("Plugin {} command failed: {}", plugin_id, e);
+            Err(e.into_response())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginCommand {
+    pub action: String,
+    pub parameters: Value,
+}
+
+// Plugin API Bridge
+pub async fn bridge_plugin_api_request(
+    State(state): State<PluginState>,
+    SafePath((plugin_id, user_id, api_endpoint)): SafePath<(Uuid, Uuid, String)>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<Value>,
+) -> Result<Json<Value>, Response> {
+    require_self(user, user_id).map_err(IntoResponse::into_response)?;
+    let function = HostFunction::from_endpoint(&api_endpoint).ok_or_else(|| {
+        bridge_error(HostCallError::new("unknown_endpoint", format!("No plugin API named {}", api_endpoint)))
+    })?;
+    let plugin_name = plugin_name(&state.db, plugin_id).await.map_err(IntoResponse::into_response)?;
+
+    // The bridge re-checks the manifest and the user's consent on every call
+    let context = PluginCallContext {
+        plugin_id,
+        plugin_name,
+        user_id,
+        command_id: Uuid::new_v4(),
+        granted: Vec::new(),
+    };
+    match state.bridge.dispatch(&context, function, request, Some(&client)).await {
+        Ok(data) => Ok(Json(serde_json::json!({
+            "success": true,
+            "data": data
+        }))),
+        Err(e) => Err(bridge_error(e)),
+    }
+}
+
+fn bridge_error(error: HostCallError) -> Response {
+    let status = host_error_status(&error.code);
+    (status, Json(serde_json::json!({ "success": false, "error": error }))).into_response()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caller(is_admin: bool) -> Option<Extension<UserContext>> {
+        Some(Extension(UserContext { user_id: Uuid::new_v4(), permissions: Vec::new(), is_admin }))
+    }
+
+    #[tokio::test]
+    async fn lifecycle_changes_are_refused_without_an_admin() {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let state = PluginState::new(db);
+        let id = Uuid::new_v4();
+
+        // Hooks would otherwise run with no user to take consent from
+        for (user, expected) in [(None, StatusCode::UNAUTHORIZED), (caller(false), StatusCode::FORBIDDEN)] {
+            let activated = activate_plugin(State(state.clone()), Path(id), user.clone(), ClientInfo::default()).await;
+            assert_eq!(activated.unwrap_err().status(), expected);
+            let deactivated = deactivate_plugin(State(state.clone()), Path(id), user.clone(), ClientInfo::default()).await;
+            assert_eq!(deactivated.unwrap_err().status(), expected);
+            let deleted = delete_plugin(State(state.clone()), Path(id), user, ClientInfo::default()).await;
+            assert_eq!(deleted.unwrap_err().status(), expected);
+        }
+    }
+}
@@ -0,0 +1,821 @@
+//! `.jtplugin` packages: a zip archive holding `manifest.json`, the entry
+//! module, its resources and a detached `signature.json`.
+//!
+//! The publisher signs a digest listing of every file except the signature:
+//! a `jtplugin-v1` header line, then one `<sha256 hex>  <path>` line per file
+//! sorted by path. Adding, dropping or altering any file breaks the signature.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{
+    activate_with_dependencies, clear_plugin_storage, installed_dependents, load_installed, manifest_errors, parse_version,
+    plan_dependencies, DependencyError, PlannedStep, PluginManifest, PluginRuntime, PluginVersionInfo,
+};
+use crate::security::is_unsafe_path_param;
+
+pub const PACKAGE_EXTENSION: &str = "jtplugin";
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const SIGNATURE_FILE: &str = "signature.json";
+const DIGEST_HEADER: &str = "jtplugin-v1";
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginInstallConfig {
+    /// Publisher key id to base64 raw Ed25519 public key.
+    pub trusted_publishers: HashMap<String, String>,
+    pub max_package_bytes: u64,
+    pub max_unpacked_bytes: u64,
+    pub max_files: usize,
+    pub download_timeout_secs: u64,
+    /// Plain `http://` package URLs are refused unless set, e.g. for a local registry.
+    pub allow_http: bool,
+    /// Base URL serving `<name>/index.json` for dependencies that are not installed.
+    pub registry_url: Option<String>,
+}
+
+impl Default for PluginInstallConfig {
+    fn default() -> Self {
+        Self {
+            trusted_publishers: HashMap::new(),
+            max_package_bytes: 32 * 1024 * 1024,
+            max_unpacked_bytes: 128 * 1024 * 1024,
+            max_files: 1000,
+            download_timeout_secs: 60,
+            allow_http: false,
+            registry_url: None,
+        }
+    }
+}
+
+impl PluginInstallConfig {
+    /// Loads the config from the JSON file named by `PLUGIN_INSTALL_CONFIG`,
+    /// falling back to the defaults, which trust no publisher.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("PLUGIN_INSTALL_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring plugin install config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Contents of `signature.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageSignature {
+    pub key_id: String,
+    pub algorithm: String,
+    /// Base64 signature over the digest listing.
+    pub signature: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PluginPackageError {
+    #[error("Invalid package URL: {0}")]
+    InvalidUrl(String),
+    #[error("Package download failed: {0}")]
+    Download(String),
+    #[error("Package exceeds {limit} bytes")]
+    TooLarge { limit: u64 },
+    #[error("Package is not a valid archive: {0}")]
+    InvalidArchive(String),
+    #[error("Package path is not allowed: {0}")]
+    UnsafePath(String),
+    #[error("Package is missing {0}")]
+    MissingFile(String),
+    #[error("Package manifest is invalid: {}", .0.join("; "))]
+    InvalidManifest(Vec<String>),
+    #[error("Package is not signed")]
+    Unsigned,
+    #[error("Package is signed by an untrusted publisher: {0}")]
+    UntrustedPublisher(String),
+    #[error("Package signature does not match its contents")]
+    BadSignature,
+    #[error("{name} {version} is already installed")]
+    AlreadyInstalled { name: String, version: String },
+    #[error("Plugin {0} is a system plugin and cannot be replaced")]
+    SystemPlugin(String),
+    #[error("Package could not be written: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Dependency(#[from] DependencyError),
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl PluginPackageError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PluginPackageError::InvalidUrl(_) => "invalid_url",
+            PluginPackageError::Download(_) => "download_failed",
+            PluginPackageError::TooLarge { .. } => "too_large",
+            PluginPackageError::InvalidArchive(_) => "invalid_archive",
+            PluginPackageError::UnsafePath(_) => "unsafe_path",
+            PluginPackageError::MissingFile(_) => "missing_file",
+            PluginPackageError::InvalidManifest(_) => "invalid_manifest",
+            PluginPackageError::Unsigned => "unsigned",
+            PluginPackageError::UntrustedPublisher(_) => "untrusted_publisher",
+            PluginPackageError::BadSignature => "bad_signature",
+            PluginPackageError::AlreadyInstalled { .. } => "already_installed",
+            PluginPackageError::SystemPlugin(_) => "system_plugin",
+            PluginPackageError::Io(_) => "io",
+            PluginPackageError::Dependency(e) => e.code(),
+            PluginPackageError::Database(_) => "database",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            PluginPackageError::InvalidUrl(_) => StatusCode::BAD_REQUEST,
+            PluginPackageError::Download(_) => StatusCode::BAD_GATEWAY,
+            PluginPackageError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            PluginPackageError::Unsigned | PluginPackageError::UntrustedPublisher(_) | PluginPackageError::BadSignature => {
+                StatusCode::FORBIDDEN
+            }
+            PluginPackageError::AlreadyInstalled { .. } | PluginPackageError::SystemPlugin(_) => StatusCode::CONFLICT,
+            PluginPackageError::Io(_) | PluginPackageError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            PluginPackageError::Dependency(e) => e.status_code(),
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl IntoResponse for PluginPackageError {
+    fn into_response(self) -> Response {
+        if let PluginPackageError::Dependency(e) = self {
+            return e.into_response();
+        }
+        let message = match &self {
+            // Internal failures are logged, not echoed
+            PluginPackageError::Io(e) => {
+                tracing::error!("Plugin package I/O error: {}", e);
+                "Internal server error".to_string()
+            }
+            PluginPackageError::Database(e) => {
+                tracing::error!("Database error: {}", e);
+                "Internal server error".to_string()
+            }
+            other => other.to_string(),
+        };
+        let body = serde_json::json!({ "success": false, "error": { "code": self.code(), "message": message } });
+        (self.status_code(), Json(body)).into_response()
+    }
+}
+
+/// A package whose signature and manifest have been checked.
+#[derive(Debug)]
+pub struct VerifiedPackage {
+    pub manifest: PluginManifest,
+    /// Every file but the signature, keyed by archive path.
+    pub files: BTreeMap<String, Vec<u8>>,
+    pub publisher_key_id: String,
+    /// SHA-256 of the archive as received.
+    pub sha256: String,
+}
+
+/// The text a publisher signs for `files`.
+pub fn digest_listing(files: &BTreeMap<String, Vec<u8>>) -> String {
+    let mut listing = format!("{}\n", DIGEST_HEADER);
+    for (path, contents) in files {
+        listing.push_str(&format!("{:x}  {}\n", Sha256::digest(contents), path));
+    }
+    listing
+}
+
+/// Unpacks an archive into memory, refusing links, traversal, duplicates and
+/// anything past the configured file and size limits.
+pub fn read_archive(bytes: &[u8], config: &PluginInstallConfig) -> Result<BTreeMap<String, Vec<u8>>, PluginPackageError> {
+    if bytes.len() as u64 > config.max_package_bytes {
+        return Err(PluginPackageError::TooLarge { limit: config.max_package_bytes });
+    }
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| PluginPackageError::InvalidArchive(e.to_string()))?;
+    if archive.len() > config.max_files {
+        return Err(PluginPackageError::InvalidArchive(format!("more than {} files", config.max_files)));
+    }
+
+    let mut files = BTreeMap::new();
+    let mut remaining = config.max_unpacked_bytes;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|e| PluginPackageError::InvalidArchive(e.to_string()))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        if entry.unix_mode().is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+            return Err(PluginPackageError::UnsafePath(name));
+        }
+        if name.is_empty() || name.split('/').any(str::is_empty) || is_unsafe_path_param(&name) || entry.enclosed_name().is_none() {
+            return Err(PluginPackageError::UnsafePath(name));
+        }
+
+        // The declared size is only a hint; the read itself is bounded
+        let mut contents = Vec::new();
+        (&mut entry)
+            .take(remaining + 1)
+            .read_to_end(&mut contents)
+            .map_err(|e| PluginPackageError::InvalidArchive(e.to_string()))?;
+        if contents.len() as u64 > remaining {
+            return Err(PluginPackageError::TooLarge { limit: config.max_unpacked_bytes });
+        }
+        remaining -= contents.len() as u64;
+
+        if files.insert(name.clone(), contents).is_some() {
+            return Err(PluginPackageError::InvalidArchive(format!("duplicate entry {}", name)));
+        }
+    }
+    Ok(files)
+}
+
+/// Checks the signature against the trusted publishers, then the manifest
+/// against the files actually shipped.
+pub fn verify_package(bytes: &[u8], config: &PluginInstallConfig) -> Result<VerifiedPackage, PluginPackageError> {
+    let mut files = read_archive(bytes, config)?;
+
+    let raw_signature = files.remove(SIGNATURE_FILE).ok_or(PluginPackageError::Unsigned)?;
+    let signature: PackageSignature =
+        serde_json::from_slice(&raw_signature).map_err(|e| PluginPackageError::InvalidArchive(format!("{}: {}", SIGNATURE_FILE, e)))?;
+    if !signature.algorithm.eq_ignore_ascii_case("ed25519") {
+        return Err(PluginPackageError::BadSignature);
+    }
+    let public_key = config
+        .trusted_publishers
+        .get(&signature.key_id)
+        .ok_or_else(|| PluginPackageError::UntrustedPublisher(signature.key_id.clone()))?;
+    let public_key = BASE64.decode(public_key).map_err(|_| PluginPackageError::UntrustedPublisher(signature.key_id.clone()))?;
+    let signature_bytes = BASE64.decode(&signature.signature).map_err(|_| PluginPackageError::BadSignature)?;
+    UnparsedPublicKey::new(&ED25519, &public_key)
+        .verify(digest_listing(&files).as_bytes(), &signature_bytes)
+        .map_err(|_| PluginPackageError::BadSignature)?;
+
+    let raw_manifest = files.get(MANIFEST_FILE).ok_or_else(|| PluginPackageError::MissingFile(MANIFEST_FILE.to_string()))?;
+    let manifest: PluginManifest =
+        serde_json::from_slice(raw_manifest).map_err(|e| PluginPackageError::InvalidManifest(vec![e.to_string()]))?;
+    let errors = manifest_errors(&manifest);
+    if !errors.is_empty() {
+        return Err(PluginPackageError::InvalidManifest(errors));
+    }
+    for path in std::iter::once(&manifest.entry).chain(manifest.resources.files()) {
+        let path = path.strip_prefix("./").unwrap_or(path);
+        if !files.contains_key(path) {
+            return Err(PluginPackageError::MissingFile(path.to_string()));
+        }
+    }
+
+    Ok(VerifiedPackage {
+        manifest,
+        files,
+        publisher_key_id: signature.key_id,
+        sha256: format!("{:x}", Sha256::digest(bytes)),
+    })
+}
+
+/// Writes the package into `target`, staging beside it so a half-written
+/// directory is never visible under the final name.
+pub fn extract_package(package: &VerifiedPackage, target: &Path) -> Result<(), PluginPackageError> {
+    if target.exists() {
+        return Err(PluginPackageError::AlreadyInstalled {
+            name: package.manifest.name.clone(),
+            version: package.manifest.version.clone(),
+        });
+    }
+    let parent = target.parent().ok_or_else(|| PluginPackageError::UnsafePath(target.display().to_string()))?;
+    std::fs::create_dir_all(parent)?;
+    let staging = parent.join(format!(".staging-{}", Uuid::new_v4()));
+
+    let written = (|| -> std::io::Result<()> {
+        for (path, contents) in &package.files {
+            let destination = staging.join(path);
+            if let Some(dir) = destination.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(destination, contents)?;
+        }
+        std::fs::rename(&staging, target)
+    })();
+    if let Err(e) = written {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Downloads an archive, refusing non-https URLs unless allowed and bodies past the size limit.
+pub async fn download_package(url: &str, config: &PluginInstallConfig) -> Result<Vec<u8>, PluginPackageError> {
+    fetch_limited(url, config.max_package_bytes, config).await
+}
+
+/// GETs `url` under the install config's scheme and timeout rules, reading at most `limit` bytes.
+pub(crate) async fn fetch_limited(url: &str, limit: u64, config: &PluginInstallConfig) -> Result<Vec<u8>, PluginPackageError> {
+    let parsed = url::Url::parse(url).map_err(|e| PluginPackageError::InvalidUrl(e.to_string()))?;
+    match parsed.scheme() {
+        "https" => {}
+        "http" if config.allow_http => {}
+        scheme => return Err(PluginPackageError::InvalidUrl(format!("{} URLs are not allowed", scheme))),
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.download_timeout_secs))
+        .redirect(reqwest::redirect::Policy::limited(5))
+        .build()
+        .map_err(|e| PluginPackageError::Download(e.to_string()))?;
+    let mut response = client
+        .get(parsed)
+        .send()
+        .await
+        .map_err(|e| PluginPackageError::Download(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(PluginPackageError::Download(format!("server answered {}", response.status())));
+    }
+
+    if response.content_length().is_some_and(|length| length > limit) {
+        return Err(PluginPackageError::TooLarge { limit });
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| PluginPackageError::Download(e.to_string()))? {
+        if (bytes.len() + chunk.len()) as u64 > limit {
+            return Err(PluginPackageError::TooLarge { limit });
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstalledPlugin {
+    pub plugin_id: Uuid,
+    pub name: String,
+    pub version: String,
+    pub publisher_key_id: String,
+    pub package_sha256: String,
+    pub is_active: bool,
+    /// Version this install replaced, if it was an upgrade.
+    pub upgraded_from: Option<String>,
+    /// Users whose copy was switched off because the upgrade requires permissions they never consented to.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub disabled_for_users: u64,
+    /// Dependencies fetched from the registry for this install, in install order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub installed_dependencies: Vec<InstalledPlugin>,
+}
+
+/// Verifies a package, installs whatever registry dependencies it is missing,
+/// then extracts and registers it. A new version of an existing plugin
+/// replaces the old one once the database row is updated.
+pub async fn install_package(
+    db: &PgPool,
+    runtime: &PluginRuntime,
+    config: &PluginInstallConfig,
+    bytes: Vec<u8>,
+    auto_activate: bool,
+    installed_by: Option<Uuid>,
+) -> Result<InstalledPlugin, PluginPackageError> {
+    let package = verify_blocking(bytes, config).await?;
+    let root = PluginVersionInfo::from_manifest(&package.manifest)?;
+    let installed = load_installed(db).await?;
+    let steps = plan_dependencies(&installed, &root, config).await?;
+
+    let mut dependencies = Vec::new();
+    for step in steps {
+        let PlannedStep::Install { name, version, url } = step else {
+            continue;
+        };
+        let dependency = verify_blocking(download_package(&url, config).await?, config).await?;
+        if dependency.manifest.name != name || parse_version(&dependency.manifest.version)? != version {
+            return Err(DependencyError::IndexMismatch { name, version: version.to_string() }.into());
+        }
+        dependencies.push(install_verified(db, runtime, dependency, installed_by).await?);
+    }
+
+    let mut registered = install_verified(db, runtime, package, installed_by).await?;
+    if auto_activate || registered.is_active {
+        activate_with_dependencies(db, &registered.name).await?;
+        registered.is_active = true;
+        for dependency in &mut dependencies {
+            dependency.is_active = true;
+        }
+    }
+    registered.installed_dependencies = dependencies;
+    Ok(registered)
+}
+
+async fn verify_blocking(bytes: Vec<u8>, config: &PluginInstallConfig) -> Result<VerifiedPackage, PluginPackageError> {
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || verify_package(&bytes, &config))
+        .await
+        .map_err(|e| PluginPackageError::Io(std::io::Error::other(e)))?
+}
+
+/// Extracts and registers one verified package, leaving activation to the caller.
+async fn install_verified(
+    db: &PgPool,
+    runtime: &PluginRuntime,
+    package: VerifiedPackage,
+    installed_by: Option<Uuid>,
+) -> Result<InstalledPlugin, PluginPackageError> {
+    let target = version_dir(runtime, &package.manifest.name, &package.manifest.version)?;
+    let (package, extracted) = tokio::task::spawn_blocking(move || extract_package(&package, &target).map(|_| (package, target)))
+        .await
+        .map_err(|e| PluginPackageError::Io(std::io::Error::other(e)))??;
+
+    let registered = match register_plugin(db, &package, installed_by).await {
+        Ok(registered) => registered,
+        Err(e) => {
+            let _ = tokio::fs::remove_dir_all(&extracted).await;
+            return Err(e);
+        }
+    };
+
+    if let Some(previous) = registered.upgraded_from.as_deref() {
+        if let Ok(old) = version_dir(runtime, &registered.name, previous) {
+            if let Err(e) = tokio::fs::remove_dir_all(&old).await {
+                tracing::warn!("Failed to remove {} after upgrade: {}", old.display(), e);
+            }
+        }
+    }
+    Ok(registered)
+}
+
+fn version_dir(runtime: &PluginRuntime, name: &str, version: &str) -> Result<PathBuf, PluginPackageError> {
+    runtime
+        .plugin_dir(name, version)
+        .map_err(|_| PluginPackageError::UnsafePath(format!("{}/{}", name, version)))
+}
+
+fn is_zero(count: &u64) -> bool {
+    *count == 0
+}
+
+/// Required permissions of `manifest` that `previous` did not require.
+///
+/// Enabling a plugin consents to what it required at the time, so an upgrade
+/// that adds to that set needs each user's consent again.
+fn newly_required(previous: &Value, manifest: &PluginManifest) -> Vec<String> {
+    let previously: Vec<&str> = previous
+        .get("permissions")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|p| p.get("required").and_then(Value::as_bool).unwrap_or(false))
+        .filter_map(|p| p.get("name").and_then(Value::as_str))
+        .collect();
+    manifest
+        .permissions
+        .iter()
+        .filter(|p| p.required && !previously.contains(&p.name.as_str()))
+        .map(|p| p.name.clone())
+        .collect()
+}
+
+async fn register_plugin(
+    db: &PgPool,
+    package: &VerifiedPackage,
+    installed_by: Option<Uuid>,
+) -> Result<InstalledPlugin, PluginPackageError> {
+    let manifest = &package.manifest;
+    let permissions: Vec<String> = manifest.permissions.iter().map(|p| p.name.clone()).collect();
+    let manifest_json = serde_json::to_value(manifest).unwrap_or(Value::Null);
+    let display_name = manifest.display_name.clone().unwrap_or_else(|| manifest.name.clone());
+
+    let mut tx = db.begin().await?;
+    let existing = sqlx::query!(
+        r#"SELECT version, COALESCE(is_system, false) AS "is_system!", COALESCE(manifest, '{}') AS "manifest!" FROM plugins WHERE name = $1 FOR UPDATE"#,
+        manifest.name
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(existing) = &existing {
+        if existing.is_system {
+            return Err(PluginPackageError::SystemPlugin(manifest.name.clone()));
+        }
+        if existing.version == manifest.version {
+            return Err(PluginPackageError::AlreadyInstalled { name: manifest.name.clone(), version: manifest.version.clone() });
+        }
+    }
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO plugins (name, display_name, description, version, author, manifest, entry_point, permissions,
+                             is_active, created_by, publisher_key_id, package_sha256, installed_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, false, $9, $10, $11, NOW())
+        ON CONFLICT (name) DO UPDATE SET
+            display_name = EXCLUDED.display_name,
+            description = EXCLUDED.description,
+            version = EXCLUDED.version,
+            author = EXCLUDED.author,
+            manifest = EXCLUDED.manifest,
+            entry_point = EXCLUDED.entry_point,
+            permissions = EXCLUDED.permissions,
+            publisher_key_id = EXCLUDED.publisher_key_id,
+            package_sha256 = EXCLUDED.package_sha256,
+            installed_at = NOW(),
+            updated_at = NOW()
+        RETURNING id, COALESCE(is_active, false) AS "is_active!"
+        "#,
+        manifest.name,
+        display_name,
+        manifest.description,
+        manifest.version,
+        manifest.author,
+        manifest_json,
+        manifest.entry,
+        &permissions,
+        installed_by,
+        package.publisher_key_id,
+        package.sha256
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Granting a newly required permission ahead of time counts as consent; anyone else re-enables
+    let added = existing.as_ref().map(|existing| newly_required(&existing.manifest, manifest)).unwrap_or_default();
+    let disabled_for_users = if added.is_empty() {
+        0
+    } else {
+        sqlx::query!(
+            r#"
+            UPDATE user_plugin_settings
+            SET is_enabled = false, updated_at = NOW()
+            WHERE plugin_id = $1 AND COALESCE(is_enabled, false)
+              AND NOT ($2::text[] <@ COALESCE(granted_permissions, '{}'))
+            "#,
+            row.id,
+            &added
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+    };
+    tx.commit().await?;
+
+    Ok(InstalledPlugin {
+        plugin_id: row.id,
+        name: manifest.name.clone(),
+        version: manifest.version.clone(),
+        publisher_key_id: package.publisher_key_id.clone(),
+        package_sha256: package.sha256.clone(),
+        is_active: row.is_active,
+        upgraded_from: existing.map(|existing| existing.version),
+        disabled_for_users,
+        installed_dependencies: Vec::new(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct UninstalledPlugin {
+    pub plugin_id: Uuid,
+    pub name: String,
+    pub version: String,
+    /// Storage entries dropped across all users.
+    pub storage_entries_removed: u64,
+}
+
+/// Removes a plugin nothing else depends on, along with its users' settings,
+/// stored data and installed files.
+pub async fn uninstall_plugin(db: &PgPool, runtime: &PluginRuntime, plugin_id: Uuid) -> Result<UninstalledPlugin, PluginPackageError> {
+    let mut tx = db.begin().await?;
+    sqlx::query!("LOCK TABLE plugins IN SHARE ROW EXCLUSIVE MODE").execute(&mut *tx).await?;
+    let (name, version) = uninstall_target(&mut tx, plugin_id).await?;
+
+    let storage_entries_removed = clear_plugin_storage(&mut *tx, plugin_id).await?;
+    sqlx::query!("DELETE FROM plugins WHERE id = $1", plugin_id).execute(&mut *tx).await?;
+    tx.commit().await?;
+
+    // Files go last; a leftover directory is harmless once the row is gone
+    if let Ok(dir) = version_dir(runtime, &name, &version) {
+        if let Some(plugin_root) = dir.parent() {
+            if let Err(e) = tokio::fs::remove_dir_all(plugin_root).await {
+                tracing::warn!("Failed to remove {} after uninstall: {}", plugin_root.display(), e);
+            }
+        }
+    }
+
+    Ok(UninstalledPlugin { plugin_id, name, version, storage_entries_removed })
+}
+
+/// Whether a plugin could be uninstalled right now; returns its name.
+pub async fn check_uninstall(db: &PgPool, plugin_id: Uuid) -> Result<String, PluginPackageError> {
+    let mut conn = db.acquire().await?;
+    Ok(uninstall_target(&mut conn, plugin_id).await?.0)
+}
+
+/// Name and version of a plugin nothing blocks from being uninstalled.
+async fn uninstall_target(conn: &mut sqlx::PgConnection, plugin_id: Uuid) -> Result<(String, String), PluginPackageError> {
+    let plugin = sqlx::query!(
+        r#"SELECT name, version, COALESCE(is_system, false) AS "is_system!" FROM plugins WHERE id = $1"#,
+        plugin_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(DependencyError::NotFound)?;
+    if plugin.is_system {
+        return Err(PluginPackageError::SystemPlugin(plugin.name));
+    }
+    let installed = load_installed(&mut *conn).await?;
+    let dependents = installed_dependents(&plugin.name, &installed);
+    if !dependents.is_empty() {
+        return Err(DependencyError::HasDependents { name: plugin.name, dependents }.into());
+    }
+    Ok((plugin.name, plugin.version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use std::io::Write;
+    use crate::plugins::PluginPermission;
+
+    const MANIFEST: &str = r#"{
+        "name": "hello-ai",
+        "version": "1.2.0",
+        "entry": "main.wasm",
+        "permissions": [{ "name": "jean.chat", "description": "Chat with Jean", "required": true }],
+        "api_version": "v1",
+        "min_jeantrail_version": "1.0.0",
+        "resources": { "css_files": ["styles/panel.css"] }
+    }"#;
+
+    fn keypair() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    fn trusting(key: &Ed25519KeyPair) -> PluginInstallConfig {
+        PluginInstallConfig {
+            trusted_publishers: HashMap::from([("jeantrail".to_string(), BASE64.encode(key.public_key().as_ref()))]),
+            allow_http: true,
+            ..PluginInstallConfig::default()
+        }
+    }
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            writer.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Builds a package signed over `signed`, then ships `shipped` alongside the signature.
+    fn package(key: &Ed25519KeyPair, signed: &[(&str, &[u8])], shipped: &[(&str, &[u8])]) -> Vec<u8> {
+        let files: BTreeMap<String, Vec<u8>> = signed.iter().map(|(name, contents)| (name.to_string(), contents.to_vec())).collect();
+        let signature = serde_json::to_vec(&PackageSignature {
+            key_id: "jeantrail".to_string(),
+            algorithm: "ed25519".to_string(),
+            signature: BASE64.encode(key.sign(digest_listing(&files).as_bytes())),
+        })
+        .unwrap();
+        let mut entries = shipped.to_vec();
+        entries.push((SIGNATURE_FILE, &signature));
+        zip(&entries)
+    }
+
+    fn files() -> Vec<(&'static str, &'static [u8])> {
+        vec![(MANIFEST_FILE, MANIFEST.as_bytes()), ("main.wasm", b"\0asm\x01\0\0\0"), ("styles/panel.css", b"body {}")]
+    }
+
+    #[test]
+    fn signed_package_verifies() {
+        let key = keypair();
+        let verified = verify_package(&package(&key, &files(), &files()), &trusting(&key)).unwrap();
+        assert_eq!(verified.manifest.name, "hello-ai");
+        assert_eq!(verified.publisher_key_id, "jeantrail");
+        assert_eq!(verified.files.len(), 3);
+        assert_eq!(verified.sha256.len(), 64);
+    }
+
+    #[test]
+    fn upgrades_list_only_the_permissions_they_start_requiring() {
+        let mut manifest: PluginManifest = serde_json::from_str(MANIFEST).unwrap();
+        let previous = serde_json::to_value(&manifest).unwrap();
+        assert!(newly_required(&previous, &manifest).is_empty());
+        assert_eq!(newly_required(&Value::Null, &manifest), vec!["jean.chat"]);
+
+        let permission = |name: &str, required: bool| PluginPermission { name: name.to_string(), description: String::new(), required };
+        manifest.permissions.push(permission("tabs.read", true));
+        manifest.permissions.push(permission("storage.read", false));
+        assert_eq!(newly_required(&previous, &manifest), vec!["tabs.read"]);
+
+        // An optional permission turning required needs consent again too
+        let optional = serde_json::json!({ "permissions": [
+            { "name": "jean.chat", "description": "", "required": true },
+            { "name": "tabs.read", "description": "", "required": false }
+        ] });
+        assert_eq!(newly_required(&optional, &manifest), vec!["tabs.read"]);
+    }
+
+    #[test]
+    fn tampering_and_unknown_publishers_are_rejected() {
+        let key = keypair();
+        let config = trusting(&key);
+
+        let mut altered = files();
+        altered[1] = ("main.wasm", b"\0asm\x01\0\0\0evil");
+        assert!(matches!(verify_package(&package(&key, &files(), &altered), &config), Err(PluginPackageError::BadSignature)));
+
+        let mut extra = files();
+        extra.push(("extra.js", b"alert(1)"));
+        assert!(matches!(verify_package(&package(&key, &files(), &extra), &config), Err(PluginPackageError::BadSignature)));
+
+        let other = keypair();
+        assert!(matches!(verify_package(&package(&other, &files(), &files()), &config), Err(PluginPackageError::BadSignature)));
+        assert!(matches!(
+            verify_package(&package(&key, &files(), &files()), &PluginInstallConfig::default()),
+            Err(PluginPackageError::UntrustedPublisher(_))
+        ));
+        assert!(matches!(verify_package(&zip(&files()), &config), Err(PluginPackageError::Unsigned)));
+    }
+
+    #[test]
+    fn archive_paths_and_manifest_are_checked() {
+        let key = keypair();
+        let config = trusting(&key);
+
+        let mut traversal = files();
+        traversal.push(("../escape.txt", b"x"));
+        assert!(matches!(verify_package(&package(&key, &traversal, &traversal), &config), Err(PluginPackageError::UnsafePath(_))));
+
+        let without_css: Vec<_> = files().into_iter().filter(|(name, _)| !name.ends_with(".css")).collect();
+        assert!(matches!(
+            verify_package(&package(&key, &without_css, &without_css), &config),
+            Err(PluginPackageError::MissingFile(path)) if path == "styles/panel.css"
+        ));
+
+        let bad_manifest = MANIFEST.replace("hello-ai", "../hello");
+        let mut renamed = files();
+        renamed[0] = (MANIFEST_FILE, bad_manifest.as_bytes());
+        assert!(matches!(verify_package(&package(&key, &renamed, &renamed), &config), Err(PluginPackageError::InvalidManifest(_))));
+
+        let tiny = PluginInstallConfig { max_unpacked_bytes: 16, ..config };
+        assert!(matches!(verify_package(&package(&key, &files(), &files()), &tiny), Err(PluginPackageError::TooLarge { .. })));
+    }
+
+    #[test]
+    fn extraction_is_all_or_nothing() {
+        let key = keypair();
+        let verified = verify_package(&package(&key, &files(), &files()), &trusting(&key)).unwrap();
+        let root = std::env::temp_dir().join(format!("jt-packages-{}", Uuid::new_v4()));
+        let runtime = PluginRuntime::new(crate::plugins::PluginRuntimeConfig { plugins_dir: root.clone(), ..Default::default() });
+        let target = version_dir(&runtime, "hello-ai", "1.2.0").unwrap();
+
+        extract_package(&verified, &target).unwrap();
+        assert_eq!(std::fs::read(target.join("styles/panel.css")).unwrap(), b"body {}");
+        assert!(matches!(extract_package(&verified, &target), Err(PluginPackageError::AlreadyInstalled { .. })));
+        let leftovers = std::fs::read_dir(target.parent().unwrap()).unwrap().count();
+        assert_eq!(leftovers, 1);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn downloads_from_a_fixture_server() {
+        let key = keypair();
+        let config = trusting(&key);
+        let archive = package(&key, &files(), &files());
+
+        let served = archive.clone();
+        let app = Router::new()
+            .route("/hello-ai.jtplugin", get(move || async move { served }))
+            .route("/huge.jtplugin", get(|| async { vec![0u8; 64 * 1024] }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let bytes = download_package(&format!("{}/hello-ai.jtplugin", base), &config).await.unwrap();
+        assert_eq!(bytes, archive);
+        assert!(verify_package(&bytes, &config).is_ok());
+
+        let missing = download_package(&format!("{}/missing.jtplugin", base), &config).await;
+        assert!(matches!(missing, Err(PluginPackageError::Download(_))));
+
+        let small = PluginInstallConfig { max_package_bytes: 1024, ..config.clone() };
+        let huge = download_package(&format!("{}/huge.jtplugin", base), &small).await;
+        assert!(matches!(huge, Err(PluginPackageError::TooLarge { .. })));
+
+        let strict = PluginInstallConfig { allow_http: false, ..config };
+        let refused = download_package(&format!("{}/hello-ai.jtplugin", base), &strict).await;
+        assert!(matches!(refused, Err(PluginPackageError::InvalidUrl(_))));
+    }
+}
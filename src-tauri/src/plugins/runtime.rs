@@ -0,0 +1,803 @@
+//! Sandboxed WebAssembly execution of plugin commands.
+//!
+//! A plugin's manifest `entry` names a `.wasm` module inside its install
+//! directory, `<plugins_dir>/<name>/<version>/`. Guests get no WASI and no
+//! ambient imports: the only functions linked are `jeantrail.log` and the
+//! host calls whose permission the plugin holds. Every command runs in a
+//! fresh instance with its own fuel budget and memory cap.
+//!
+//! Guest ABI; every payload is UTF-8 JSON and pointers are into `memory`:
+//! - `jt_alloc(len: i32) -> i32` returns a buffer the host may fill;
+//! - `jt_run(ptr: i32, len: i32) -> i64` receives the command and returns
+//!   `(out_ptr << 32) | out_len`. A guest reports failure by returning
+//!   `{"error": {"code": .., "message": ..}}`;
+//! - host calls take `(ptr, len)` and return a packed pointer to
+//!   `{"ok": ..}` or `{"error": {"code": .., "message": ..}}`;
+//! - `log(level: i32, ptr: i32, len: i32)`, with levels 0 error to 3 debug.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Memory, Module, ResourceLimiter, Store, Trap};
+
+use super::PluginCommand;
+use crate::security::is_unsafe_path_param;
+
+/// Import module guests link host functions from.
+pub const HOST_MODULE: &str = "jeantrail";
+
+const LOG_LINE_MAX_CHARS: usize = 1024;
+const MAX_TABLE_ELEMENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginRuntimeConfig {
+    /// Holds one `<name>/<version>/` directory per installed plugin.
+    pub plugins_dir: PathBuf,
+    /// Fuel granted to each command; roughly one unit per wasm instruction.
+    pub fuel_per_command: u64,
+    /// Cap on guest linear memory, initial size included.
+    pub max_memory_bytes: usize,
+    pub max_module_bytes: u64,
+    /// Cap on the command, the result and each host call payload.
+    pub max_io_bytes: usize,
+    /// Log lines kept per command; the rest are dropped.
+    pub max_log_lines: usize,
+}
+
+impl Default for PluginRuntimeConfig {
+    fn default() -> Self {
+        Self {
+            plugins_dir: PathBuf::from("plugins"),
+            fuel_per_command: 50_000_000,
+            max_memory_bytes: 64 * 1024 * 1024,
+            max_module_bytes: 16 * 1024 * 1024,
+            max_io_bytes: 1024 * 1024,
+            max_log_lines: 100,
+        }
+    }
+}
+
+impl PluginRuntimeConfig {
+    /// Loads the config from the JSON file named by `PLUGIN_RUNTIME_CONFIG`,
+    /// falling back to the defaults.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("PLUGIN_RUNTIME_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring plugin runtime config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Host calls a guest may import from [`HOST_MODULE`], each gated by one manifest permission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostFunction {
+    JeanChat,
+    TabsRead,
+    TabsWrite,
+    StorageGet,
+    StorageList,
+    StorageSet,
+    StorageDelete,
+    StorageBatch,
+    NetworkRequest,
+    Notify,
+}
+
+impl HostFunction {
+    pub const ALL: [HostFunction; 10] = [
+        HostFunction::JeanChat,
+        HostFunction::TabsRead,
+        HostFunction::TabsWrite,
+        HostFunction::StorageGet,
+        HostFunction::StorageList,
+        HostFunction::StorageSet,
+        HostFunction::StorageDelete,
+        HostFunction::StorageBatch,
+        HostFunction::NetworkRequest,
+        HostFunction::Notify,
+    ];
+
+    pub fn import_name(self) -> &'static str {
+        match self {
+            HostFunction::JeanChat => "jean_chat",
+            HostFunction::TabsRead => "tabs_read",
+            HostFunction::TabsWrite => "tabs_write",
+            HostFunction::StorageGet => "storage_get",
+            HostFunction::StorageList => "storage_list",
+            HostFunction::StorageSet => "storage_set",
+            HostFunction::StorageDelete => "storage_delete",
+            HostFunction::StorageBatch => "storage_batch",
+            HostFunction::NetworkRequest => "network_request",
+            HostFunction::Notify => "notify",
+        }
+    }
+
+    pub fn permission(self) -> &'static str {
+        match self {
+            HostFunction::JeanChat => "jean.chat",
+            HostFunction::TabsRead => "tabs.read",
+            HostFunction::TabsWrite => "tabs.write",
+            HostFunction::StorageGet | HostFunction::StorageList => "storage.read",
+            HostFunction::StorageSet | HostFunction::StorageDelete | HostFunction::StorageBatch => "storage.write",
+            HostFunction::NetworkRequest => "network.request",
+            HostFunction::Notify => "notifications.show",
+        }
+    }
+
+    /// Name the API bridge exposes the function under, e.g. `storage.get`.
+    pub fn endpoint(self) -> &'static str {
+        match self {
+            HostFunction::JeanChat => "jean.chat",
+            HostFunction::TabsRead => "tabs.read",
+            HostFunction::TabsWrite => "tabs.write",
+            HostFunction::StorageGet => "storage.get",
+            HostFunction::StorageList => "storage.list",
+            HostFunction::StorageSet => "storage.set",
+            HostFunction::StorageDelete => "storage.delete",
+            HostFunction::StorageBatch => "storage.batch",
+            HostFunction::NetworkRequest => "network.request",
+            HostFunction::Notify => "notifications.show",
+        }
+    }
+
+    pub fn from_import(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|function| function.import_name() == name)
+    }
+
+    pub fn from_endpoint(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|function| function.endpoint() == name)
+    }
+}
+
+/// Who a guest is running for.
+#[derive(Debug, Clone)]
+pub struct PluginCallContext {
+    pub plugin_id: Uuid,
+    pub plugin_name: String,
+    pub user_id: Uuid,
+    pub command_id: Uuid,
+    /// Permissions the plugin holds for this call.
+    pub granted: Vec<String>,
+}
+
+impl PluginCallContext {
+    pub fn is_granted(&self, permission: &str) -> bool {
+        self.granted.iter().any(|granted| granted == permission)
+    }
+}
+
+/// Error handed back to the guest in a host call reply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostCallError {
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl HostCallError {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self { code: code.to_string(), message: message.into(), details: None }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// Serves the host calls a guest makes. The runtime has already checked the
+/// manifest permission by the time `call` runs; it is invoked on a blocking thread.
+pub trait PluginHost: Send + Sync {
+    fn call(&self, context: &PluginCallContext, function: HostFunction, payload: Value) -> Result<Value, HostCallError>;
+}
+
+/// Host with no capabilities wired up; every call fails.
+pub struct UnavailableHost;
+
+impl PluginHost for UnavailableHost {
+    fn call(&self, _context: &PluginCallContext, function: HostFunction, _payload: Value) -> Result<Value, HostCallError> {
+        Err(HostCallError::new("unavailable", format!("{} is not available", function.import_name())))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PluginExecution {
+    pub command_id: Uuid,
+    pub output: Value,
+    pub fuel_consumed: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PluginRuntimeError {
+    #[error("Plugin entry must be a .wasm module inside the plugin directory")]
+    InvalidEntry,
+    #[error("Plugin entry {0} was not found")]
+    EntryNotFound(String),
+    #[error("Plugin module is larger than {limit} bytes")]
+    ModuleTooLarge { limit: u64 },
+    #[error("Plugin module is invalid: {0}")]
+    InvalidModule(String),
+    #[error("Import {module}.{name} is not provided by the host")]
+    UnknownImport { module: String, name: String },
+    #[error("Import {import} requires the {permission} permission")]
+    PermissionDenied { import: String, permission: &'static str },
+    #[error("Plugin module does not export `{0}` with the expected signature")]
+    MissingExport(&'static str),
+    #[error("Plugin ran out of fuel after {fuel} units")]
+    FuelExhausted { fuel: u64 },
+    #[error("Plugin exceeded its memory limit of {limit} bytes")]
+    MemoryLimitExceeded { limit: usize },
+    #[error("Plugin trapped: {0}")]
+    Trap(String),
+    #[error("Plugin payload exceeds {limit} bytes")]
+    PayloadTooLarge { limit: usize },
+    #[error("Plugin returned invalid output: {0}")]
+    InvalidOutput(String),
+    #[error("{message}")]
+    Guest { code: String, message: String },
+    #[error("Plugin execution failed: {0}")]
+    Internal(String),
+}
+
+impl PluginRuntimeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PluginRuntimeError::InvalidEntry => "invalid_entry",
+            PluginRuntimeError::EntryNotFound(_) => "entry_not_found",
+            PluginRuntimeError::ModuleTooLarge { .. } => "module_too_large",
+            PluginRuntimeError::InvalidModule(_) => "invalid_module",
+            PluginRuntimeError::UnknownImport { .. } => "unknown_import",
+            PluginRuntimeError::PermissionDenied { .. } => "permission_denied",
+            PluginRuntimeError::MissingExport(_) => "missing_export",
+            PluginRuntimeError::FuelExhausted { .. } => "fuel_exhausted",
+            PluginRuntimeError::MemoryLimitExceeded { .. } => "memory_limit_exceeded",
+            PluginRuntimeError::Trap(_) => "trap",
+            PluginRuntimeError::PayloadTooLarge { .. } => "payload_too_large",
+            PluginRuntimeError::InvalidOutput(_) => "invalid_output",
+            PluginRuntimeError::Guest { .. } => "guest_error",
+            PluginRuntimeError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            PluginRuntimeError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+            PluginRuntimeError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            PluginRuntimeError::Guest { .. } => StatusCode::BAD_REQUEST,
+            PluginRuntimeError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut error = serde_json::json!({ "code": self.code(), "message": self.to_string() });
+        if let PluginRuntimeError::Guest { code, .. } = self {
+            error["guest_code"] = Value::String(code.clone());
+        }
+        error
+    }
+}
+
+impl IntoResponse for PluginRuntimeError {
+    fn into_response(self) -> Response {
+        (self.status_code(), Json(serde_json::json!({ "success": false, "error": self.to_json() }))).into_response()
+    }
+}
+
+/// Compiles, caches and runs plugin modules.
+#[derive(Clone)]
+pub struct PluginRuntime {
+    engine: Engine,
+    config: Arc<PluginRuntimeConfig>,
+    /// Compiled modules keyed by the SHA-256 of their bytes.
+    modules: Arc<Mutex<HashMap<String, Module>>>,
+}
+
+impl PluginRuntime {
+    pub fn new(config: PluginRuntimeConfig) -> Self {
+        let mut wasm = Config::new();
+        wasm.consume_fuel(true);
+        wasm.max_wasm_stack(512 * 1024);
+        let engine = Engine::new(&wasm).expect("static wasmtime configuration is valid");
+        Self { engine, config: Arc::new(config), modules: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    pub fn config(&self) -> &PluginRuntimeConfig {
+        &self.config
+    }
+
+    /// Install directory of one plugin version.
+    pub fn plugin_dir(&self, name: &str, version: &str) -> Result<PathBuf, PluginRuntimeError> {
+        if [name, version].iter().any(|part| part.is_empty() || part.contains('/') || is_unsafe_path_param(part)) {
+            return Err(PluginRuntimeError::InvalidEntry);
+        }
+        Ok(self.config.plugins_dir.join(name).join(version))
+    }
+
+    /// Resolves a manifest `entry` to a `.wasm` file that cannot leave the plugin directory.
+    pub fn resolve_entry(&self, name: &str, version: &str, entry: &str) -> Result<PathBuf, PluginRuntimeError> {
+        let entry = entry.strip_prefix("./").unwrap_or(entry);
+        if entry.is_empty() || is_unsafe_path_param(entry) || !entry.ends_with(".wasm") {
+            return Err(PluginRuntimeError::InvalidEntry);
+        }
+        Ok(self.plugin_dir(name, version)?.join(entry))
+    }
+
+    pub fn compile(&self, bytes: &[u8]) -> Result<Module, PluginRuntimeError> {
+        if bytes.len() as u64 > self.config.max_module_bytes {
+            return Err(PluginRuntimeError::ModuleTooLarge { limit: self.config.max_module_bytes });
+        }
+        let key = format!("{:x}", Sha256::digest(bytes));
+        if let Some(module) = self.modules.lock().unwrap().get(&key) {
+            return Ok(module.clone());
+        }
+        let module = Module::from_binary(&self.engine, bytes).map_err(|e| PluginRuntimeError::InvalidModule(format!("{:#}", e)))?;
+        self.modules.lock().unwrap().insert(key, module.clone());
+        Ok(module)
+    }
+
+    pub fn load(&self, path: &Path) -> Result<Module, PluginRuntimeError> {
+        let not_found = || PluginRuntimeError::EntryNotFound(path.display().to_string());
+        let size = std::fs::metadata(path).map_err(|_| not_found())?.len();
+        if size > self.config.max_module_bytes {
+            return Err(PluginRuntimeError::ModuleTooLarge { limit: self.config.max_module_bytes });
+        }
+        let bytes = std::fs::read(path).map_err(|_| not_found())?;
+        self.compile(&bytes)
+    }
+
+    /// Loads the module at `path` and runs `command` on a blocking thread.
+    pub async fn execute(
+        &self,
+        path: PathBuf,
+        context: PluginCallContext,
+        command: PluginCommand,
+        host: Arc<dyn PluginHost>,
+    ) -> Result<PluginExecution, PluginRuntimeError> {
+        let runtime = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let module = runtime.load(&path)?;
+            runtime.run(&module, context, &command, host)
+        })
+        .await
+        .map_err(|e| PluginRuntimeError::Internal(e.to_string()))?
+    }
+
+    /// Runs one command in a fresh instance. Blocks until the guest returns or runs out of fuel.
+    pub fn run(
+        &self,
+        module: &Module,
+        context: PluginCallContext,
+        command: &PluginCommand,
+        host: Arc<dyn PluginHost>,
+    ) -> Result<PluginExecution, PluginRuntimeError> {
+        let started = Instant::now();
+        check_imports(module, &context)?;
+
+        let max_io = self.config.max_io_bytes;
+        let input = serde_json::to_vec(command).map_err(|e| PluginRuntimeError::Internal(e.to_string()))?;
+        if input.len() > max_io {
+            return Err(PluginRuntimeError::PayloadTooLarge { limit: max_io });
+        }
+
+        let command_id = context.command_id;
+        let linker = self.linker(&context)?;
+        let mut store = Store::new(
+            &self.engine,
+            GuestState {
+                memory: MemoryCap { max_bytes: self.config.max_memory_bytes, exceeded: false },
+                host,
+                context,
+                max_io_bytes: max_io,
+                max_log_lines: self.config.max_log_lines,
+                log_lines: 0,
+            },
+        );
+        store.limiter(|state| &mut state.memory);
+        let fuel = self.config.fuel_per_command;
+        store.set_fuel(fuel).map_err(|e| PluginRuntimeError::Internal(e.to_string()))?;
+
+        let instance = linker.instantiate(&mut store, module).map_err(|e| {
+            if e.downcast_ref::<Trap>().is_some() || store.data().memory.exceeded {
+                self.classify(e, &store)
+            } else {
+                PluginRuntimeError::InvalidModule(format!("{:#}", e))
+            }
+        })?;
+        let memory = instance.get_memory(&mut store, "memory").ok_or(PluginRuntimeError::MissingExport("memory"))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "jt_alloc")
+            .map_err(|_| PluginRuntimeError::MissingExport("jt_alloc"))?;
+        let run = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "jt_run")
+            .map_err(|_| PluginRuntimeError::MissingExport("jt_run"))?;
+
+        let input_len = input.len() as i32;
+        let ptr = alloc.call(&mut store, input_len).map_err(|e| self.classify(e, &store))?;
+        memory
+            .write(&mut store, ptr as u32 as usize, &input)
+            .map_err(|_| PluginRuntimeError::InvalidOutput("jt_alloc returned a buffer outside memory".to_string()))?;
+        let packed = run.call(&mut store, (ptr, input_len)).map_err(|e| self.classify(e, &store))?;
+
+        let (out_ptr, out_len) = unpack(packed);
+        if out_len > max_io {
+            return Err(PluginRuntimeError::PayloadTooLarge { limit: max_io });
+        }
+        let bytes = memory
+            .data(&store)
+            .get(out_ptr..out_ptr + out_len)
+            .ok_or_else(|| PluginRuntimeError::InvalidOutput("result lies outside memory".to_string()))?;
+        let output: Value = serde_json::from_slice(bytes).map_err(|e| PluginRuntimeError::InvalidOutput(e.to_string()))?;
+        if let Some(error) = output.get("error") {
+            return Err(PluginRuntimeError::Guest {
+                code: error.get("code").and_then(Value::as_str).unwrap_or("error").to_string(),
+                message: error.get("message").and_then(Value::as_str).unwrap_or("Plugin reported an error").to_string(),
+            });
+        }
+
+        let fuel_consumed = fuel.saturating_sub(store.get_fuel().unwrap_or(0));
+        Ok(PluginExecution { command_id, output, fuel_consumed, duration_ms: started.elapsed().as_millis() as u64 })
+    }
+
+    /// Links `log` plus the host functions the context's permissions allow; nothing else exists for the guest.
+    fn linker(&self, context: &PluginCallContext) -> Result<Linker<GuestState>, PluginRuntimeError> {
+        let mut linker = Linker::new(&self.engine);
+        let link_error = |e: anyhow::Error| PluginRuntimeError::Internal(e.to_string());
+
+        linker
+            .func_wrap(HOST_MODULE, "log", |mut caller: Caller<'_, GuestState>, level: i32, ptr: i32, len: i32| -> anyhow::Result<()> {
+                let state = caller.data_mut();
+                if state.log_lines >= state.max_log_lines {
+                    return Ok(());
+                }
+                state.log_lines += 1;
+                let memory = guest_memory(&mut caller)?;
+                let bytes = read_guest(&memory, &caller, ptr, len, caller.data().max_io_bytes)?;
+                let line: String = String::from_utf8_lossy(&bytes).chars().take(LOG_LINE_MAX_CHARS).collect();
+                let plugin = &caller.data().context.plugin_name;
+                match level {
+                    0 => tracing::error!(plugin = %plugin, "{}", line),
+                    1 => tracing::warn!(plugin = %plugin, "{}", line),
+                    2 => tracing::info!(plugin = %plugin, "{}", line),
+                    _ => tracing::debug!(plugin = %plugin, "{}", line),
+                }
+                Ok(())
+            })
+            .map_err(link_error)?;
+
+        for function in HostFunction::ALL.into_iter().filter(|function| context.is_granted(function.permission())) {
+            linker
+                .func_wrap(HOST_MODULE, function.import_name(), move |mut caller: Caller<'_, GuestState>, ptr: i32, len: i32| -> anyhow::Result<i64> {
+                    let memory = guest_memory(&mut caller)?;
+                    let payload = read_guest(&memory, &caller, ptr, len, caller.data().max_io_bytes)?;
+                    let reply = match serde_json::from_slice::<Value>(&payload) {
+                        Ok(payload) => {
+                            let state = caller.data();
+                            match state.host.call(&state.context, function, payload) {
+                                Ok(value) => serde_json::json!({ "ok": value }),
+                                Err(e) => serde_json::json!({ "error": e }),
+                            }
+                        }
+                        Err(e) => serde_json::json!({ "error": HostCallError::new("invalid_payload", e.to_string()) }),
+                    };
+                    write_guest(&mut caller, &memory, &serde_json::to_vec(&reply)?)
+                })
+                .map_err(link_error)?;
+        }
+        Ok(linker)
+    }
+
+    fn classify(&self, error: anyhow::Error, store: &Store<GuestState>) -> PluginRuntimeError {
+        if error.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) {
+            PluginRuntimeError::FuelExhausted { fuel: self.config.fuel_per_command }
+        } else if store.data().memory.exceeded {
+            PluginRuntimeError::MemoryLimitExceeded { limit: self.config.max_memory_bytes }
+        } else {
+            PluginRuntimeError::Trap(format!("{:#}", error))
+        }
+    }
+}
+
+/// Rejects modules importing anything the host does not provide or the plugin was not granted,
+/// so a missing permission surfaces before any guest code runs.
+fn check_imports(module: &Module, context: &PluginCallContext) -> Result<(), PluginRuntimeError> {
+    for import in module.imports() {
+        let unknown = || PluginRuntimeError::UnknownImport { module: import.module().to_string(), name: import.name().to_string() };
+        if import.module() != HOST_MODULE {
+            return Err(unknown());
+        }
+        if import.name() == "log" {
+            continue;
+        }
+        let function = HostFunction::from_import(import.name()).ok_or_else(unknown)?;
+        if !context.is_granted(function.permission()) {
+            return Err(PluginRuntimeError::PermissionDenied {
+                import: format!("{}.{}", HOST_MODULE, import.name()),
+                permission: function.permission(),
+            });
+        }
+    }
+    Ok(())
+}
+
+struct GuestState {
+    memory: MemoryCap,
+    host: Arc<dyn PluginHost>,
+    context: PluginCallContext,
+    max_io_bytes: usize,
+    max_log_lines: usize,
+    log_lines: usize,
+}
+
+/// Refuses memory growth past the cap and remembers that it did, so the resulting trap can be reported as such.
+struct MemoryCap {
+    max_bytes: usize,
+    exceeded: bool,
+}
+
+impl ResourceLimiter for MemoryCap {
+    fn memory_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> anyhow::Result<bool> {
+        if desired > self.max_bytes {
+            self.exceeded = true;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn table_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> anyhow::Result<bool> {
+        Ok(desired <= MAX_TABLE_ELEMENTS)
+    }
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    let packed = packed as u64;
+    ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize)
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    (((ptr as u32 as u64) << 32) | len as u64) as i64
+}
+
+fn guest_memory(caller: &mut Caller<'_, GuestState>) -> anyhow::Result<Memory> {
+    caller.get_export("memory").and_then(Extern::into_memory).ok_or_else(|| anyhow!("guest does not export memory"))
+}
+
+fn read_guest(memory: &Memory, caller: &Caller<'_, GuestState>, ptr: i32, len: i32, max: usize) -> anyhow::Result<Vec<u8>> {
+    let (start, len) = (ptr as u32 as usize, len as u32 as usize);
+    if len > max {
+        return Err(anyhow!("host call payload exceeds {} bytes", max));
+    }
+    memory
+        .data(caller)
+        .get(start..start + len)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow!("host call payload lies outside memory"))
+}
+
+/// Copies a reply into a buffer from the guest's own allocator and returns it packed.
+fn write_guest(caller: &mut Caller<'_, GuestState>, memory: &Memory, bytes: &[u8]) -> anyhow::Result<i64> {
+    let alloc = caller
+        .get_export("jt_alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| anyhow!("guest does not export jt_alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    memory.write(&mut *caller, ptr as u32 as usize, bytes)?;
+    Ok(pack(ptr, bytes.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bump allocator shared by the sample plugins.
+    const ALLOC: &str = r#"
+        (global $next (mut i32) (i32.const 4096))
+        (func (export "jt_alloc") (param $len i32) (result i32)
+          (local $ptr i32)
+          (local.set $ptr (global.get $next))
+          (global.set $next (i32.add (global.get $next) (local.get $len)))
+          (local.get $ptr))
+        (func $pack (param $ptr i32) (param $len i32) (result i64)
+          (i64.or (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32)) (i64.extend_i32_u (local.get $len))))
+    "#;
+
+    fn sample(imports: &str, body: &str) -> Vec<u8> {
+        wat::parse_str(format!("(module {} {} {})", imports, ALLOC, body)).expect("sample plugin compiles")
+    }
+
+    const ECHO: &str = r#"
+        (memory (export "memory") 1)
+        (func (export "jt_run") (param $ptr i32) (param $len i32) (result i64)
+          (call $pack (local.get $ptr) (local.get $len)))
+    "#;
+
+    fn runtime() -> PluginRuntime {
+        PluginRuntime::new(PluginRuntimeConfig {
+            fuel_per_command: 1_000_000,
+            max_memory_bytes: 2 * 1024 * 1024,
+            ..PluginRuntimeConfig::default()
+        })
+    }
+
+    fn context(granted: &[&str]) -> PluginCallContext {
+        PluginCallContext {
+            plugin_id: Uuid::new_v4(),
+            plugin_name: "sample".to_string(),
+            user_id: Uuid::new_v4(),
+            command_id: Uuid::new_v4(),
+            granted: granted.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    fn command() -> PluginCommand {
+        PluginCommand { action: "greet".to_string(), parameters: serde_json::json!({ "name": "Jean" }) }
+    }
+
+    fn run(wasm: &[u8], granted: &[&str], host: Arc<dyn PluginHost>) -> Result<PluginExecution, PluginRuntimeError> {
+        let runtime = runtime();
+        let module = runtime.compile(wasm)?;
+        runtime.run(&module, context(granted), &command(), host)
+    }
+
+    #[derive(Default)]
+    struct RecordingHost {
+        calls: Mutex<Vec<(HostFunction, Value)>>,
+    }
+
+    impl PluginHost for RecordingHost {
+        fn call(&self, _context: &PluginCallContext, function: HostFunction, payload: Value) -> Result<Value, HostCallError> {
+            self.calls.lock().unwrap().push((function, payload));
+            Ok(serde_json::json!({ "delivered": true }))
+        }
+    }
+
+    #[test]
+    fn echo_plugin_returns_the_command() {
+        let execution = run(&sample("", ECHO), &[], Arc::new(UnavailableHost)).unwrap();
+        assert_eq!(execution.output, serde_json::json!({ "action": "greet", "parameters": { "name": "Jean" } }));
+        assert!(execution.fuel_consumed > 0);
+    }
+
+    #[test]
+    fn runaway_loop_exhausts_fuel() {
+        let wasm = sample("", r#"
+            (memory (export "memory") 1)
+            (func (export "jt_run") (param i32 i32) (result i64)
+              (loop $spin (br $spin))
+              (i64.const 0))
+        "#);
+        assert!(matches!(run(&wasm, &[], Arc::new(UnavailableHost)), Err(PluginRuntimeError::FuelExhausted { .. })));
+    }
+
+    #[test]
+    fn memory_growth_is_capped() {
+        let grows = sample("", r#"
+            (memory (export "memory") 1)
+            (func (export "jt_run") (param i32 i32) (result i64)
+              (loop $grow (br_if $grow (i32.ne (memory.grow (i32.const 16)) (i32.const -1))))
+              unreachable)
+        "#);
+        assert!(matches!(run(&grows, &[], Arc::new(UnavailableHost)), Err(PluginRuntimeError::MemoryLimitExceeded { .. })));
+
+        let oversized = sample("", r#"
+            (memory (export "memory") 100)
+            (func (export "jt_run") (param i32 i32) (result i64) (i64.const 0))
+        "#);
+        assert!(matches!(run(&oversized, &[], Arc::new(UnavailableHost)), Err(PluginRuntimeError::MemoryLimitExceeded { .. })));
+    }
+
+    #[test]
+    fn imports_need_a_granted_permission() {
+        let imports = r#"(import "jeantrail" "notify" (func $notify (param i32 i32) (result i64)))"#;
+        let body = r#"
+            (memory (export "memory") 1)
+            (func (export "jt_run") (param $ptr i32) (param $len i32) (result i64)
+              (call $notify (local.get $ptr) (local.get $len)))
+        "#;
+        let wasm = sample(imports, body);
+
+        match run(&wasm, &["storage.read"], Arc::new(UnavailableHost)) {
+            Err(PluginRuntimeError::PermissionDenied { permission, .. }) => assert_eq!(permission, "notifications.show"),
+            other => panic!("expected permission denied, got {:?}", other.map(|e| e.output)),
+        }
+
+        let host = Arc::new(RecordingHost::default());
+        let execution = run(&wasm, &["notifications.show"], host.clone()).unwrap();
+        assert_eq!(execution.output, serde_json::json!({ "ok": { "delivered": true } }));
+        let calls = host.calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, HostFunction::Notify);
+        assert_eq!(calls[0].1["action"], "greet");
+    }
+
+    #[test]
+    fn foreign_imports_are_rejected() {
+        let imports = r#"(import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))"#;
+        let wasm = sample(imports, ECHO);
+        assert!(matches!(run(&wasm, &[], Arc::new(UnavailableHost)), Err(PluginRuntimeError::UnknownImport { .. })));
+    }
+
+    #[test]
+    fn guest_errors_and_bad_output_are_structured() {
+        let error = r#"{"error":{"code":"bad_input","message":"name is required"}}"#;
+        let wasm = sample("", &format!(
+            r#"
+            (memory (export "memory") 1)
+            (data (i32.const 0) "{}")
+            (func (export "jt_run") (param i32 i32) (result i64)
+              (call $pack (i32.const 0) (i32.const {})))
+            "#,
+            error.replace('"', "\\\""),
+            error.len()
+        ));
+        match run(&wasm, &[], Arc::new(UnavailableHost)) {
+            Err(e @ PluginRuntimeError::Guest { .. }) => {
+                assert_eq!(e.status_code(), StatusCode::BAD_REQUEST);
+                assert_eq!(e.to_json()["guest_code"], "bad_input");
+                assert_eq!(e.to_string(), "name is required");
+            }
+            other => panic!("expected guest error, got {:?}", other.map(|e| e.output)),
+        }
+
+        let out_of_bounds = sample("", r#"
+            (memory (export "memory") 1)
+            (func (export "jt_run") (param i32 i32) (result i64)
+              (call $pack (i32.const 65530) (i32.const 64)))
+        "#);
+        assert!(matches!(run(&out_of_bounds, &[], Arc::new(UnavailableHost)), Err(PluginRuntimeError::InvalidOutput(_))));
+
+        let no_run = sample("", r#"(memory (export "memory") 1)"#);
+        assert!(matches!(run(&no_run, &[], Arc::new(UnavailableHost)), Err(PluginRuntimeError::MissingExport("jt_run"))));
+    }
+
+    #[test]
+    fn entries_stay_inside_the_plugin_directory() {
+        let runtime = runtime();
+        assert_eq!(
+            runtime.resolve_entry("hello-ai", "1.0.0", "./main.wasm").unwrap(),
+            PathBuf::from("plugins/hello-ai/1.0.0/main.wasm")
+        );
+        for entry in ["../other/main.wasm", "/etc/main.wasm", "index.html", "lib/../../main.wasm"] {
+            assert!(matches!(runtime.resolve_entry("hello-ai", "1.0.0", entry), Err(PluginRuntimeError::InvalidEntry)), "{}", entry);
+        }
+        assert!(runtime.plugin_dir("..", "1.0.0").is_err());
+    }
+
+    #[tokio::test]
+    async fn execute_loads_the_entry_from_disk() {
+        let dir = std::env::temp_dir().join(format!("jt-plugins-{}", Uuid::new_v4()));
+        let runtime = PluginRuntime::new(PluginRuntimeConfig { plugins_dir: dir.clone(), ..PluginRuntimeConfig::default() });
+        let path = runtime.resolve_entry("echo", "1.0.0", "main.wasm").unwrap();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, sample("", ECHO)).unwrap();
+
+        let execution = runtime.execute(path, context(&[]), command(), Arc::new(UnavailableHost)).await.unwrap();
+        assert_eq!(execution.output["action"], "greet");
+
+        let missing = runtime.resolve_entry("echo", "2.0.0", "main.wasm").unwrap();
+        let result = runtime.execute(missing, context(&[]), command(), Arc::new(UnavailableHost)).await;
+        assert!(matches!(result, Err(PluginRuntimeError::EntryNotFound(_))));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
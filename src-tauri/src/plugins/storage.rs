@@ -0,0 +1,385 @@
+//! Key-value storage for plugins, namespaced per plugin and per user.
+//!
+//! A plugin only ever sees the keys it wrote for the user it is running for.
+//! Each namespace has a quota covering the bytes of its keys and JSON values.
+//! Writes go through batches that apply in one transaction, so a batch that
+//! would break the quota leaves nothing behind. Entries hang off the user's
+//! `user_plugin_settings` row and go away with it on uninstall.
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::HostCallError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginStorageConfig {
+    pub max_key_bytes: usize,
+    pub max_value_bytes: usize,
+    /// Bytes of keys and values one user may hold for one plugin.
+    pub quota_bytes: i64,
+    pub max_keys: i64,
+    pub max_batch_ops: usize,
+    /// Cap on entries returned by one list call.
+    pub max_list_entries: i64,
+}
+
+impl Default for PluginStorageConfig {
+    fn default() -> Self {
+        Self {
+            max_key_bytes: 256,
+            max_value_bytes: 64 * 1024,
+            quota_bytes: 5 * 1024 * 1024,
+            max_keys: 10_000,
+            max_batch_ops: 100,
+            max_list_entries: 1_000,
+        }
+    }
+}
+
+impl PluginStorageConfig {
+    /// Loads the config from the JSON file named by `PLUGIN_STORAGE_CONFIG`,
+    /// falling back to the defaults.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("PLUGIN_STORAGE_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring plugin storage config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// One write in a batch.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum StorageOp {
+    Set { key: String, value: Value },
+    Delete { key: String },
+}
+
+impl StorageOp {
+    pub fn key(&self) -> &str {
+        match self {
+            StorageOp::Set { key, .. } | StorageOp::Delete { key } => key,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StorageEntry {
+    pub key: String,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StorageUsage {
+    pub bytes: i64,
+    pub keys: i64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Invalid storage key: {0}")]
+    InvalidKey(String),
+    #[error("Value for {key} exceeds {limit} bytes")]
+    ValueTooLarge { key: String, limit: usize },
+    #[error("Batch exceeds {limit} operations")]
+    BatchTooLarge { limit: usize },
+    #[error("Storage quota of {limit} bytes exceeded")]
+    QuotaExceeded { limit: i64 },
+    #[error("Storage is limited to {limit} keys")]
+    TooManyKeys { limit: i64 },
+    #[error("The plugin is not set up for this user")]
+    NotEnabled,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl StorageError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            StorageError::InvalidKey(_) => "invalid_params",
+            StorageError::ValueTooLarge { .. } | StorageError::BatchTooLarge { .. } => "payload_too_large",
+            StorageError::QuotaExceeded { .. } | StorageError::TooManyKeys { .. } => "quota_exceeded",
+            StorageError::NotEnabled => "permission_denied",
+            StorageError::Database(_) => "internal",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            StorageError::InvalidKey(_) => StatusCode::BAD_REQUEST,
+            StorageError::ValueTooLarge { .. } | StorageError::BatchTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            StorageError::QuotaExceeded { .. } | StorageError::TooManyKeys { .. } => StatusCode::INSUFFICIENT_STORAGE,
+            StorageError::NotEnabled => StatusCode::FORBIDDEN,
+            StorageError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<StorageError> for HostCallError {
+    fn from(error: StorageError) -> Self {
+        if let StorageError::Database(e) = &error {
+            tracing::error!("Plugin storage database error: {}", e);
+            return HostCallError::new(error.code(), "The host could not complete the call");
+        }
+        HostCallError::new(error.code(), error.to_string())
+    }
+}
+
+/// `LIKE` pattern matching every key that starts with `prefix`.
+pub fn like_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[derive(Clone)]
+pub struct PluginStorage {
+    db: PgPool,
+    config: Arc<PluginStorageConfig>,
+}
+
+impl PluginStorage {
+    pub fn new(db: PgPool, config: PluginStorageConfig) -> Self {
+        Self { db, config: Arc::new(config) }
+    }
+
+    pub fn config(&self) -> &PluginStorageConfig {
+        &self.config
+    }
+
+    fn check_key(&self, key: &str) -> Result<(), StorageError> {
+        if key.is_empty() || key.len() > self.config.max_key_bytes {
+            return Err(StorageError::InvalidKey(format!("keys must be 1-{} bytes", self.config.max_key_bytes)));
+        }
+        if key.chars().any(char::is_control) {
+            return Err(StorageError::InvalidKey("keys may not contain control characters".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Checks a batch before it touches the database; returns each set's stored size.
+    pub fn check_batch(&self, ops: &[StorageOp]) -> Result<Vec<i32>, StorageError> {
+        if ops.len() > self.config.max_batch_ops {
+            return Err(StorageError::BatchTooLarge { limit: self.config.max_batch_ops });
+        }
+        ops.iter()
+            .map(|op| {
+                self.check_key(op.key())?;
+                let value_bytes = match op {
+                    StorageOp::Set { value, .. } => value.to_string().len(),
+                    StorageOp::Delete { .. } => 0,
+                };
+                if value_bytes > self.config.max_value_bytes {
+                    return Err(StorageError::ValueTooLarge { key: op.key().to_string(), limit: self.config.max_value_bytes });
+                }
+                Ok((op.key().len() + value_bytes) as i32)
+            })
+            .collect()
+    }
+
+    pub async fn get(&self, plugin_id: Uuid, user_id: Uuid, key: &str) -> Result<Option<Value>, StorageError> {
+        self.check_key(key)?;
+        let value = sqlx::query_scalar!(
+            "SELECT value FROM plugin_storage WHERE plugin_id = $1 AND user_id = $2 AND key = $3",
+            plugin_id,
+            user_id,
+            key
+        )
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(value)
+    }
+
+    /// Entries whose key starts with `prefix`, in key order. Pass the last key
+    /// of a page as `after` to read the next one.
+    pub async fn list(
+        &self,
+        plugin_id: Uuid,
+        user_id: Uuid,
+        prefix: &str,
+        after: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<Vec<StorageEntry>, StorageError> {
+        if prefix.len() > self.config.max_key_bytes {
+            return Err(StorageError::InvalidKey(format!("prefixes must be at most {} bytes", self.config.max_key_bytes)));
+        }
+        let limit = limit.unwrap_or(100).clamp(1, self.config.max_list_entries);
+        let entries = sqlx::query_as!(
+            StorageEntry,
+            r#"
+            SELECT key, value FROM plugin_storage
+            WHERE plugin_id = $1 AND user_id = $2 AND key LIKE $3 ESCAPE '\'
+              AND ($4::text IS NULL OR key > $4)
+            ORDER BY key
+            LIMIT $5
+            "#,
+            plugin_id,
+            user_id,
+            like_prefix(prefix),
+            after,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(entries)
+    }
+
+    /// Applies every op or none of them. A batch may not grow a namespace past
+    /// its quota; one that shrinks it always goes through.
+    pub async fn batch(&self, plugin_id: Uuid, user_id: Uuid, ops: &[StorageOp]) -> Result<StorageUsage, StorageError> {
+        let sizes = self.check_batch(ops)?;
+        let mut tx = self.db.begin().await?;
+
+        // Serialises writers on the namespace so the quota check below holds
+        sqlx::query_scalar!(
+            "SELECT id FROM user_plugin_settings WHERE plugin_id = $1 AND user_id = $2 FOR UPDATE",
+            plugin_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(StorageError::NotEnabled)?;
+
+        let before = usage(&mut *tx, plugin_id, user_id).await?;
+        for (op, size) in ops.iter().zip(sizes) {
+            match op {
+                StorageOp::Set { key, value } => {
+                    sqlx::query!(
+                        r#"
+                        INSERT INTO plugin_storage (plugin_id, user_id, key, value, size_bytes)
+                        VALUES ($1, $2, $3, $4, $5)
+                        ON CONFLICT (plugin_id, user_id, key)
+                        DO UPDATE SET value = EXCLUDED.value, size_bytes = EXCLUDED.size_bytes, updated_at = NOW()
+                        "#,
+                        plugin_id,
+                        user_id,
+                        key,
+                        value,
+                        size
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                StorageOp::Delete { key } => {
+                    sqlx::query!(
+                        "DELETE FROM plugin_storage WHERE plugin_id = $1 AND user_id = $2 AND key = $3",
+                        plugin_id,
+                        user_id,
+                        key
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        let after = usage(&mut *tx, plugin_id, user_id).await?;
+        if after.bytes > self.config.quota_bytes && after.bytes > before.bytes {
+            return Err(StorageError::QuotaExceeded { limit: self.config.quota_bytes });
+        }
+        if after.keys > self.config.max_keys && after.keys > before.keys {
+            return Err(StorageError::TooManyKeys { limit: self.config.max_keys });
+        }
+        tx.commit().await?;
+        Ok(after)
+    }
+
+    pub async fn usage(&self, plugin_id: Uuid, user_id: Uuid) -> Result<StorageUsage, StorageError> {
+        Ok(usage(&self.db, plugin_id, user_id).await?)
+    }
+}
+
+async fn usage<'e, E>(executor: E, plugin_id: Uuid, user_id: Uuid) -> Result<StorageUsage, sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let row = sqlx::query!(
+        r#"
+        SELECT COALESCE(SUM(size_bytes), 0)::BIGINT AS "bytes!", COUNT(*) AS "keys!"
+        FROM plugin_storage
+        WHERE plugin_id = $1 AND user_id = $2
+        "#,
+        plugin_id,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(StorageUsage { bytes: row.bytes, keys: row.keys })
+}
+
+/// Drops every user's entries for a plugin; returns how many were removed.
+pub async fn clear_plugin_storage<'e, E>(executor: E, plugin_id: Uuid) -> Result<u64, sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let result = sqlx::query!("DELETE FROM plugin_storage WHERE plugin_id = $1", plugin_id)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn storage(config: PluginStorageConfig) -> PluginStorage {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        PluginStorage::new(db, config)
+    }
+
+    #[test]
+    fn prefixes_escape_like_wildcards() {
+        assert_eq!(like_prefix("notes/"), "notes/%");
+        assert_eq!(like_prefix("100%_done\\"), "100\\%\\_done\\\\%");
+        assert_eq!(like_prefix(""), "%");
+    }
+
+    #[tokio::test]
+    async fn batches_are_checked_before_they_run() {
+        let storage = storage(PluginStorageConfig { max_key_bytes: 8, max_value_bytes: 16, max_batch_ops: 2, ..PluginStorageConfig::default() });
+        let set = |key: &str, value: Value| StorageOp::Set { key: key.to_string(), value };
+
+        let sizes = storage.check_batch(&[set("a", serde_json::json!("xyz")), StorageOp::Delete { key: "b".to_string() }]).unwrap();
+        assert_eq!(sizes, vec![1 + 5, 1]);
+
+        let too_many = vec![StorageOp::Delete { key: "a".to_string() }; 3];
+        assert!(matches!(storage.check_batch(&too_many), Err(StorageError::BatchTooLarge { limit: 2 })));
+        assert!(matches!(storage.check_batch(&[set("", Value::Null)]), Err(StorageError::InvalidKey(_))));
+        assert!(matches!(storage.check_batch(&[set("too-long-key", Value::Null)]), Err(StorageError::InvalidKey(_))));
+        assert!(matches!(storage.check_batch(&[set("tab\t", Value::Null)]), Err(StorageError::InvalidKey(_))));
+        let big = serde_json::json!("0123456789abcdef");
+        assert!(matches!(storage.check_batch(&[set("a", big)]), Err(StorageError::ValueTooLarge { .. })));
+    }
+
+    #[test]
+    fn ops_parse_by_tag_and_errors_map_to_host_codes() {
+        let ops: Vec<StorageOp> = serde_json::from_value(serde_json::json!([
+            { "op": "set", "key": "theme", "value": { "dark": true } },
+            { "op": "delete", "key": "draft" }
+        ]))
+        .unwrap();
+        assert_eq!(ops.iter().map(StorageOp::key).collect::<Vec<_>>(), ["theme", "draft"]);
+
+        let error: HostCallError = StorageError::QuotaExceeded { limit: 10 }.into();
+        assert_eq!(error.code, "quota_exceeded");
+        assert_eq!(StorageError::QuotaExceeded { limit: 10 }.status_code(), StatusCode::INSUFFICIENT_STORAGE);
+    }
+}
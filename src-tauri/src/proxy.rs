@@ -1,7 +1,9 @@
-use axum::{Json, extract::{Path, State}};
+use axum::{Extension, Json, extract::{Path, State}};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use crate::plugins::{PluginEvent, PluginEventBus};
+use crate::security::UserContext;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProxyNode {
@@ -74,11 +76,14 @@ pub async fn list_sessions() -> Result<Json<Vec<ProxySession>>, axum::http::Stat
 }
 
 pub async fn start_session(
+    user: Option<Extension<UserContext>>,
+    events: Option<Extension<PluginEventBus>>,
     Json(request): Json<StartSessionRequest>,
 ) -> Result<Json<ProxySession>, axum::http::StatusCode> {
+    let user_id = user.map(|Extension(user)| user.user_id);
     let session = ProxySession {
         id: Uuid::new_v4(),
-        user_id: Uuid::new_v4(),
+        user_id: user_id.unwrap_or_else(Uuid::new_v4),
         node_id: request.node_id,
         started_at: Utc::now(),
         ended_at: None,
@@ -87,5 +92,12 @@ pub async fn start_session(
     };
 
     // Would save to DB and start actual proxy
+    if let (Some(user_id), Some(Extension(events))) = (user_id, events) {
+        events.publish(
+            PluginEvent::ProxySessionStarted,
+            user_id,
+            serde_json::json!({ "session_id": session.id, "node_id": session.node_id }),
+        );
+    }
     Ok(Json(session))
 }
\ No newline at end of file
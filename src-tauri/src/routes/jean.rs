@@ -10,10 +10,11 @@ use std::collections::HashMap;
 
 use crate::commands::AppState;
 use crate::routes::UserContext;
-use crate::jean_core::{JeanOrchestrator, JeanRequest, JeanResponse, JeanAction};
+use crate::jean_core::{action_risk_level, JeanOrchestrator, JeanRequest, JeanResponse, JeanAction, RiskLevel};
 use crate::jean_memory::JeanMemoryStore;
 use crate::jean_permissions::JeanPermissions;
 use crate::docker_monitor::DockerMonitor;
+use crate::security::{ConsentGate, ConsentPurpose};
 
 #[derive(Debug, Deserialize)]
 pub struct ExecuteActionRequest {
@@ -103,14 +104,19 @@ pub async fn execute_action(
         Err(_) => return Err(StatusCode::NOT_FOUND),
     };
 
-    // Check if action is already executed
-    if action.status != "pending" {
+    // High-risk actions only run once approved with a second factor
+    let awaiting_step_up = action.status == "pending" && action_risk_level(&action.action_type) == RiskLevel::High;
+    if awaiting_step_up || !matches!(action.status.as_str(), "pending" | "approved" | "confirmed") {
         return Ok(Json(ActionResponse {
             success: false,
             action_id: request.action_id,
             status: action.status,
             result: action.result,
-            message: format!("Action is already {}", action.status),
+            message: if awaiting_step_up {
+                "Action requires approval with step-up authentication".to_string()
+            } else {
+                format!("Action is already {}", action.status)
+            },
         }));
     }
 
@@ -189,41 +195,6 @@ pub async fn execute_action(
     }
 }
 
-// Approve action
-pub async fn approve_action(
-    State(state): State<AppState>,
-    user_context: UserContext,
-    Path(action_id): Path<Uuid>,
-) -> Result<Json<ActionResponse>, StatusCode> {
-    // Update action to approved status
-    let rows_affected = sqlx::query!(
-        r#"
-        UPDATE jean_actions 
-        SET status = 'approved', approved_by = $1, approved_at = NOW()
-        WHERE id = $2 AND user_id = $3 AND status = 'pending'
-        "#,
-        user_context.user_id,
-        action_id,
-        user_context.user_id
-    )
-    .execute(&state.db_pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .rows_affected();
-
-    if rows_affected == 0 {
-        return Err(StatusCode::NOT_FOUND);
-    }
-
-    Ok(Json(ActionResponse {
-        success: true,
-        action_id,
-        status: "approved".to_string(),
-        result: None,
-        message: "Action approved successfully".to_string(),
-    }))
-}
-
 // Execute action by ID (auto-execute for safe actions)
 pub async fn execute_action_by_id(
     State(state): State<AppState>,
@@ -346,6 +317,11 @@ pub async fn save_memory(
     user_context: UserContext,
     Json(request): AxumJson<SaveMemoryRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
+    ConsentGate::new(state.db_pool.clone())
+        .require(Some(user_context.user_id), ConsentPurpose::MemoryPersonalization)
+        .await
+        .map_err(|e| e.status_code())?;
+
     let memory_store = JeanMemoryStore::new(state.db_pool.clone());
     
     let memory_id = memory_store.save_memory(
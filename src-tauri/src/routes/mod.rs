@@ -27,7 +27,6 @@ pub fn create_routes(state: AppState) -> Router {
     Router::new()
         // Jean Orchestrator Routes
         .route("/api/jean/action", post(jean::execute_action))
-        .route("/api/jean/action/:action_id/approve", post(jean::approve_action))
         .route("/api/jean/action/:action_id/execute", post(jean::execute_action_by_id))
         .route("/api/jean/memory/search", get(jean::search_memory))
         .route("/api/jean/memory/save", post(jean::save_memory))
@@ -262,12 +261,12 @@ pub async fn auth_middleware(
                 let api_key = &auth_str[7..];
                 
                 // Validate API key against database
-                let validation_result = validate_api_key(&state.db_pool, api_key).await;
+                let client_ip = crate::security::ClientInfo::from_request_parts(request.headers(), request.extensions()).ip_address;
+                let validation_result = validate_api_key(&state.db_pool, api_key, client_ip).await;
                 
-                if validation_result.is_ok() {
+                if let Ok(context) = validation_result {
                     // Add user context to request extensions
-                    let (user_id, permissions) = validation_result.unwrap();
-                    request.extensions_mut().insert(UserContext { user_id, permissions });
+                    request.extensions_mut().insert(context);
                     return Ok(next.run(request).await);
                 }
             }
@@ -277,42 +276,17 @@ pub async fn auth_middleware(
     Err(StatusCode::UNAUTHORIZED)
 }
 
-// User context for authenticated requests
-#[derive(Debug, Clone)]
-pub struct UserContext {
-    pub user_id: uuid::Uuid,
-    pub permissions: Vec<String>,
-}
+pub use crate::security::UserContext;
 
 // API key validation
 async fn validate_api_key(
     pool: &sqlx::PgPool,
     api_key: &str,
-) -> Result<(uuid::Uuid, Vec<String>), Box<dyn std::error::Error>> {
-    let result = sqlx::query!(
-        r#"
-        SELECT ak.user_id, ak.permissions, u.is_active
-        FROM api_keys ak
-        JOIN users u ON ak.user_id = u.id
-        WHERE ak.key_hash = crypt($1, ak.key_hash)
-            AND ak.is_active = true
-            AND u.is_active = true
-            AND (ak.expires_at IS NULL OR ak.expires_at > NOW())
-        "#,
-        api_key
-    )
-    .fetch_optional(pool)
-    .await?;
-
-    if let Some(record) = result {
-        if !record.is_active {
-            return Err("User account is inactive".into());
-        }
-        
-        let permissions: Vec<String> = serde_json::from_value(record.permissions.unwrap_or_default())?;
-        Ok((record.user_id, permissions))
-    } else {
-        Err("Invalid API key".into())
+    client_ip: Option<std::net::IpAddr>,
+) -> Result<UserContext, Box<dyn std::error::Error>> {
+    match crate::security::authenticate_api_key(pool, api_key, client_ip).await? {
+        Some(context) => Ok(context),
+        None => Err("Invalid API key".into()),
     }
 }
 
@@ -0,0 +1,595 @@
+// API key lifecycle: issue, list, scope, rotate and revoke
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+use super::{
+    db_error, generate_token, hash_secret, query_audit_logs, record_audit_event, require_self, validate_api_key,
+    verify_secret, AuditLog, ClientInfo, CryptoKeys, GetAuditLogsQuery, RiskContext, SecurityState, UserContext,
+};
+use crate::plugins::is_valid_permission;
+
+pub const API_KEY_PREFIX: &str = "jt_";
+/// Header carrying an API key, as an alternative to `Authorization: Bearer`.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+const API_KEY_RESOURCE: &str = "api_key";
+/// Characters of the secret kept in clear for display.
+const DISPLAY_PREFIX_LEN: usize = 11;
+/// Last-used timestamps are only rewritten once per interval.
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+/// A key as stored; the secret itself is never returned after creation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub is_active: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub last_used_ip: Option<String>,
+    pub rotated_from: Option<Uuid>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Returned once, when a key is created or rotated.
+#[derive(Debug, Serialize)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateApiKeyScopesRequest {
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RotateApiKeyRequest {
+    /// How long the old secret keeps working; it is revoked at once when absent.
+    pub grace_period_seconds: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListApiKeysQuery {
+    #[serde(default)]
+    pub include_revoked: bool,
+}
+
+/// Resolves a presented secret to its owner, recording when it was last used.
+pub async fn authenticate_api_key(
+    db: &PgPool,
+    secret: &str,
+    client_ip: Option<IpAddr>,
+) -> Result<Option<UserContext>, sqlx::Error> {
+    if !validate_api_key(secret) {
+        return Ok(None);
+    }
+
+    let Some(record) = sqlx::query!(
+        r#"
+        SELECT ak.id, ak.user_id, ak.key_hash, ak.scopes, u.is_admin
+        FROM api_keys ak
+        JOIN users u ON ak.user_id = u.id
+        WHERE ak.lookup_hash = $1
+            AND ak.is_active = TRUE
+            AND ak.revoked_at IS NULL
+            AND (ak.expires_at IS NULL OR ak.expires_at > NOW())
+            AND u.is_active = TRUE
+        "#,
+        CryptoKeys::global().keyed_hash(secret)
+    )
+    .fetch_optional(db)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    if !verify_secret(secret, &record.key_hash) {
+        return Ok(None);
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE api_keys SET last_used_at = NOW(), last_used_ip = $2::text::inet
+        WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - make_interval(secs => $3))
+        "#,
+        record.id,
+        client_ip.map(|ip| ip.to_string()),
+        LAST_USED_RESOLUTION_SECONDS as f64
+    )
+    .execute(db)
+    .await?;
+
+    Ok(Some(UserContext { user_id: record.user_id, permissions: record.scopes, is_admin: record.is_admin }))
+}
+
+/// The API key a request presents, from `x-api-key` or a bearer token.
+pub fn presented_api_key(headers: &HeaderMap) -> Option<&str> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    header(API_KEY_HEADER)
+        .or_else(|| header(AUTHORIZATION.as_str()).and_then(|value| value.strip_prefix("Bearer ")))
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// Puts the caller's `UserContext` on the request for every layer and handler
+/// below. Requests without a key carry on anonymously and are refused by
+/// handlers that need a user; a key that does not authenticate is refused here.
+pub async fn api_key_auth_middleware(
+    State(state): State<SecurityState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let Some(secret) = presented_api_key(request.headers()).map(str::to_string) else {
+        return Ok(next.run(request).await);
+    };
+    let client = ClientInfo::from_request_parts(request.headers(), request.extensions());
+    match authenticate_api_key(&state.db, &secret, client.ip_address).await.map_err(db_error)? {
+        Some(context) => {
+            request.extensions_mut().insert(context);
+            Ok(next.run(request).await)
+        }
+        None => {
+            reject_credentials(&state, request.method(), request.uri().path(), &client).await;
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+/// Rejected keys never reach the audit layer, so they are recorded here and
+/// counted towards the address's failed-authentication streak.
+async fn reject_credentials(state: &SecurityState, method: &Method, path: &str, client: &ClientInfo) {
+    let context = RiskContext {
+        user_id: None,
+        ip_address: client.ip_address,
+        user_agent: client.user_agent.clone(),
+        method: method.clone(),
+        path: path.to_string(),
+        resource_type: None,
+        at: Utc::now(),
+    };
+    state.risk.record_outcome(&context, StatusCode::UNAUTHORIZED);
+    record_audit_event(&state.db, None, "api_key.rejected", API_KEY_RESOURCE, path, serde_json::json!({
+        "method": method.as_str()
+    }), Some(client)).await;
+}
+
+pub async fn create_api_key(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<Json<IssuedApiKey>, StatusCode> {
+    let caller = key_owner(user, user_id)?;
+    let name = request.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let scopes = normalize_scopes(request.scopes)?;
+    ensure_scopes_held(&caller, &scopes)?;
+    if request.expires_at.is_some_and(|at| at <= Utc::now()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (secret, lookup_hash, key_hash) = new_secret()?;
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (user_id, name, key_prefix, lookup_hash, key_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, user_id, name, key_prefix, scopes, is_active, expires_at, last_used_at,
+                  host(last_used_ip) AS last_used_ip, rotated_from, revoked_at, created_at
+        "#,
+        user_id,
+        name,
+        display_prefix(&secret),
+        lookup_hash,
+        key_hash,
+        &scopes,
+        request.expires_at
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    audit_key_event(&state.db, &key, "api_key.created", serde_json::json!({
+        "name": key.name,
+        "scopes": key.scopes,
+        "expires_at": key.expires_at
+    }), &client).await;
+
+    Ok(Json(IssuedApiKey { key, secret }))
+}
+
+pub async fn list_api_keys(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    Query(params): Query<ListApiKeysQuery>,
+) -> Result<Json<Vec<ApiKey>>, StatusCode> {
+    require_self(user, user_id)?;
+    let keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, user_id, name, key_prefix, scopes, is_active, expires_at, last_used_at,
+               host(last_used_ip) AS last_used_ip, rotated_from, revoked_at, created_at
+        FROM api_keys
+        WHERE user_id = $1 AND ($2 OR revoked_at IS NULL)
+        ORDER BY created_at DESC
+        "#,
+        user_id,
+        params.include_revoked
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(keys))
+}
+
+pub async fn get_api_key(
+    State(state): State<SecurityState>,
+    Path((user_id, key_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<ApiKey>, StatusCode> {
+    require_self(user, user_id)?;
+    fetch_key(&state.db, user_id, key_id).await.map(Json)
+}
+
+pub async fn update_api_key_scopes(
+    State(state): State<SecurityState>,
+    Path((user_id, key_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<UpdateApiKeyScopesRequest>,
+) -> Result<Json<ApiKey>, StatusCode> {
+    let caller = key_owner(user, user_id)?;
+    let scopes = normalize_scopes(request.scopes)?;
+    ensure_scopes_held(&caller, &scopes)?;
+    let previous = fetch_key(&state.db, user_id, key_id).await?;
+    if previous.revoked_at.is_some() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        UPDATE api_keys SET scopes = $3, updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, name, key_prefix, scopes, is_active, expires_at, last_used_at,
+                  host(last_used_ip) AS last_used_ip, rotated_from, revoked_at, created_at
+        "#,
+        key_id,
+        user_id,
+        &scopes
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    audit_key_event(&state.db, &key, "api_key.scopes_updated", serde_json::json!({
+        "previous_scopes": previous.scopes,
+        "scopes": key.scopes
+    }), &client).await;
+
+    Ok(Json(key))
+}
+
+/// Issues a replacement secret with the same name, scopes and expiry.
+pub async fn rotate_api_key(
+    State(state): State<SecurityState>,
+    Path((user_id, key_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    request: Option<Json<RotateApiKeyRequest>>,
+) -> Result<Json<IssuedApiKey>, StatusCode> {
+    let caller = key_owner(user, user_id)?;
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    let grace_period = grace_period_seconds(&request)?;
+
+    let old = fetch_key(&state.db, user_id, key_id).await?;
+    ensure_rotatable(&old, Utc::now())?;
+    // The replacement secret carries the old scopes, so the caller must hold them too
+    ensure_scopes_held(&caller, &old.scopes)?;
+
+    let (secret, lookup_hash, key_hash) = new_secret()?;
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (user_id, name, key_prefix, lookup_hash, key_hash, scopes, expires_at, rotated_from)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, user_id, name, key_prefix, scopes, is_active, expires_at, last_used_at,
+                  host(last_used_ip) AS last_used_ip, rotated_from, revoked_at, created_at
+        "#,
+        user_id,
+        old.name,
+        display_prefix(&secret),
+        lookup_hash,
+        key_hash,
+        &old.scopes,
+        old.expires_at,
+        old.id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    let old_expires_at = if grace_period > 0 {
+        let grace_end = Utc::now() + Duration::seconds(grace_period);
+        sqlx::query!(
+            "UPDATE api_keys SET expires_at = $2, updated_at = NOW() WHERE id = $1",
+            old.id,
+            old.expires_at.map_or(grace_end, |at| at.min(grace_end))
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        Some(grace_end)
+    } else {
+        sqlx::query!(
+            "UPDATE api_keys SET is_active = FALSE, revoked_at = NOW(), updated_at = NOW() WHERE id = $1",
+            old.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        None
+    };
+
+    tx.commit().await.map_err(db_error)?;
+
+    audit_key_event(&state.db, &old, "api_key.rotated", serde_json::json!({
+        "replaced_by": key.id,
+        "grace_period_seconds": grace_period,
+        "expires_at": old_expires_at
+    }), &client).await;
+    audit_key_event(&state.db, &key, "api_key.created", serde_json::json!({
+        "name": key.name,
+        "scopes": key.scopes,
+        "expires_at": key.expires_at,
+        "rotated_from": old.id
+    }), &client).await;
+
+    Ok(Json(IssuedApiKey { key, secret }))
+}
+
+pub async fn revoke_api_key(
+    State(state): State<SecurityState>,
+    Path((user_id, key_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Json<ApiKey>, StatusCode> {
+    require_self(user, user_id)?;
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        UPDATE api_keys SET is_active = FALSE, revoked_at = COALESCE(revoked_at, NOW()), updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, user_id, name, key_prefix, scopes, is_active, expires_at, last_used_at,
+                  host(last_used_ip) AS last_used_ip, rotated_from, revoked_at, created_at
+        "#,
+        key_id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    audit_key_event(&state.db, &key, "api_key.revoked", serde_json::json!({ "name": key.name }), &client).await;
+
+    Ok(Json(key))
+}
+
+/// Lifecycle events recorded for one key, newest first.
+pub async fn get_api_key_audit_log(
+    State(state): State<SecurityState>,
+    Path((user_id, key_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<Vec<AuditLog>>, StatusCode> {
+    require_self(user, user_id)?;
+    fetch_key(&state.db, user_id, key_id).await?;
+
+    let params = GetAuditLogsQuery {
+        resource_type: Some(API_KEY_RESOURCE.to_string()),
+        resource_id: Some(key_id.to_string()),
+        limit: Some(1000),
+        ..Default::default()
+    };
+    query_audit_logs(&state.db, &params).await.map(Json).map_err(db_error)
+}
+
+async fn fetch_key(db: &PgPool, user_id: Uuid, key_id: Uuid) -> Result<ApiKey, StatusCode> {
+    sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, user_id, name, key_prefix, scopes, is_active, expires_at, last_used_at,
+               host(last_used_ip) AS last_used_ip, rotated_from, revoked_at, created_at
+        FROM api_keys
+        WHERE id = $1 AND user_id = $2
+        "#,
+        key_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// The caller, who must own the keys being managed.
+fn key_owner(user: Option<Extension<UserContext>>, user_id: Uuid) -> Result<UserContext, StatusCode> {
+    require_self(user.clone(), user_id)?;
+    user.map(|Extension(context)| context).ok_or(StatusCode::UNAUTHORIZED)
+}
+
+/// A key can hand out no scope it does not hold itself.
+fn ensure_scopes_held(caller: &UserContext, scopes: &[String]) -> Result<(), StatusCode> {
+    match scopes.iter().find(|scope| !caller.permissions.contains(scope)) {
+        Some(scope) => {
+            tracing::debug!("API key for {} cannot grant scope {}", caller.user_id, scope);
+            Err(StatusCode::FORBIDDEN)
+        }
+        None => Ok(()),
+    }
+}
+
+/// How long the old secret keeps working after a rotation, at most a week.
+fn grace_period_seconds(request: &RotateApiKeyRequest) -> Result<i64, StatusCode> {
+    let grace_period = request.grace_period_seconds.unwrap_or(0);
+    if (0..=7 * 24 * 3600).contains(&grace_period) {
+        Ok(grace_period)
+    } else {
+        Err(StatusCode::BAD_REQUEST)
+    }
+}
+
+/// Revoked, deactivated and expired keys cannot be rotated back to life.
+fn ensure_rotatable(key: &ApiKey, now: DateTime<Utc>) -> Result<(), StatusCode> {
+    if key.revoked_at.is_some() || !key.is_active || key.expires_at.is_some_and(|at| at <= now) {
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(())
+}
+
+/// Rejects unknown scopes and returns the rest sorted and de-duplicated.
+fn normalize_scopes(scopes: Vec<String>) -> Result<Vec<String>, StatusCode> {
+    let mut scopes: Vec<String> = scopes.into_iter().map(|s| s.trim().to_string()).collect();
+    if let Some(unknown) = scopes.iter().find(|s| !is_valid_permission(s)) {
+        tracing::debug!("Rejected unknown API key scope {}", unknown);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    scopes.sort();
+    scopes.dedup();
+    Ok(scopes)
+}
+
+/// Generates a secret with its lookup and verification hashes.
+fn new_secret() -> Result<(String, String, String), StatusCode> {
+    let secret = format!("{}{}", API_KEY_PREFIX, generate_token(CryptoKeys::global().token_bytes));
+    let key_hash = hash_secret(&secret).map_err(|e| {
+        tracing::error!("Failed to hash API key: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let lookup_hash = CryptoKeys::global().keyed_hash(&secret);
+    Ok((secret, lookup_hash, key_hash))
+}
+
+fn display_prefix(secret: &str) -> String {
+    secret.chars().take(DISPLAY_PREFIX_LEN).collect()
+}
+
+async fn audit_key_event(db: &PgPool, key: &ApiKey, action: &str, details: Value, client: &ClientInfo) {
+    record_audit_event(db, Some(key.user_id), action, API_KEY_RESOURCE, &key.id.to_string(), details, Some(client)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn caller(scopes: &[&str]) -> UserContext {
+        UserContext { user_id: Uuid::new_v4(), permissions: scopes.iter().map(|s| s.to_string()).collect(), is_admin: false }
+    }
+
+    fn key(revoked: bool, active: bool, expires_at: Option<DateTime<Utc>>) -> ApiKey {
+        ApiKey {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "ci".to_string(),
+            key_prefix: "jt_abcdefgh".to_string(),
+            scopes: vec!["tabs.read".to_string()],
+            is_active: active,
+            expires_at,
+            last_used_at: None,
+            last_used_ip: None,
+            rotated_from: None,
+            revoked_at: revoked.then(Utc::now),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn scopes_are_validated_and_cannot_exceed_the_caller() {
+        let scopes = normalize_scopes(vec![" tabs.read".to_string(), "jean.chat".to_string(), "tabs.read".to_string()]).unwrap();
+        assert_eq!(scopes, vec!["jean.chat".to_string(), "tabs.read".to_string()]);
+        assert_eq!(normalize_scopes(vec!["root".to_string()]), Err(StatusCode::BAD_REQUEST));
+
+        let holder = caller(&["jean.chat", "tabs.read"]);
+        assert_eq!(ensure_scopes_held(&holder, &scopes), Ok(()));
+        assert_eq!(ensure_scopes_held(&caller(&["tabs.read"]), &scopes), Err(StatusCode::FORBIDDEN));
+        assert_eq!(ensure_scopes_held(&caller(&[]), &[]), Ok(()));
+    }
+
+    #[test]
+    fn only_the_owner_manages_keys() {
+        let owner = caller(&[]);
+        let user_id = owner.user_id;
+        assert_eq!(key_owner(Some(Extension(owner)), user_id).map(|c| c.user_id), Ok(user_id));
+        assert_eq!(key_owner(Some(Extension(caller(&[]))), user_id).map(|c| c.user_id), Err(StatusCode::FORBIDDEN));
+        assert_eq!(key_owner(None, user_id).map(|c| c.user_id), Err(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn rotation_needs_a_live_key_and_a_bounded_grace_period() {
+        let now = Utc::now();
+        assert_eq!(ensure_rotatable(&key(false, true, None), now), Ok(()));
+        assert_eq!(ensure_rotatable(&key(true, false, None), now), Err(StatusCode::CONFLICT));
+        assert_eq!(ensure_rotatable(&key(false, false, None), now), Err(StatusCode::CONFLICT));
+        assert_eq!(ensure_rotatable(&key(false, true, Some(now - Duration::seconds(1))), now), Err(StatusCode::CONFLICT));
+
+        let request = |seconds| RotateApiKeyRequest { grace_period_seconds: seconds };
+        assert_eq!(grace_period_seconds(&request(None)), Ok(0));
+        assert_eq!(grace_period_seconds(&request(Some(3600))), Ok(3600));
+        assert_eq!(grace_period_seconds(&request(Some(-1))), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(grace_period_seconds(&request(Some(8 * 24 * 3600))), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn issued_secrets_pass_the_format_check_and_show_only_a_prefix() {
+        let (secret, lookup_hash, key_hash) = new_secret().unwrap();
+        assert!(validate_api_key(&secret));
+        assert!(verify_secret(&secret, &key_hash));
+        assert_eq!(lookup_hash, CryptoKeys::global().keyed_hash(&secret));
+        assert_eq!(display_prefix(&secret).len(), DISPLAY_PREFIX_LEN);
+        assert!(secret.starts_with(&display_prefix(&secret)));
+    }
+
+    #[test]
+    fn keys_are_read_from_either_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(presented_api_key(&headers), None);
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Basic dXNlcjpwYXNz"));
+        assert_eq!(presented_api_key(&headers), None);
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer jt_bearer"));
+        assert_eq!(presented_api_key(&headers), Some("jt_bearer"));
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static(" jt_header "));
+        assert_eq!(presented_api_key(&headers), Some("jt_header"));
+    }
+}
@@ -0,0 +1,450 @@
+// Tamper-evident SHA-256 hash chain over persisted audit log entries
+use axum::{extract::State, Extension, Json};
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::{require_admin, AuditLog, SecurityState, UserContext};
+
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const VERIFY_BATCH_SIZE: i64 = 1000;
+
+/// The exact fields covered by an entry's hash, in a fixed order.
+#[derive(Serialize)]
+struct ChainedFields<'a> {
+    sequence: i64,
+    prev_hash: &'a str,
+    id: Uuid,
+    user_id: Option<Uuid>,
+    action: &'a str,
+    resource_type: Option<&'a str>,
+    resource_id: Option<&'a str>,
+    details: Value,
+    ip_address: Option<String>,
+    user_agent: Option<&'a str>,
+    session_id: Option<&'a str>,
+    risk_score: i32,
+    status: &'a str,
+    created_at: String,
+}
+
+/// Hashes an entry together with its position and predecessor.
+pub fn compute_entry_hash(log: &AuditLog, sequence: i64, prev_hash: &str) -> String {
+    let fields = ChainedFields {
+        sequence,
+        prev_hash,
+        id: log.id,
+        user_id: log.user_id,
+        action: &log.action,
+        resource_type: log.resource_type.as_deref(),
+        resource_id: log.resource_id.as_deref(),
+        // JSONB does not keep key order, so keys are sorted before hashing
+        details: canonical_json(&log.details),
+        ip_address: log.ip_address.map(|ip| ip.to_string()),
+        user_agent: log.user_agent.as_deref(),
+        session_id: log.session_id.as_deref(),
+        risk_score: log.risk_score,
+        status: &log.status,
+        created_at: log.created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+    };
+    let canonical = serde_json::to_string(&fields).unwrap_or_default();
+    format!("{:x}", Sha256::digest(canonical.as_bytes()))
+}
+
+/// Copies a value with every object's keys in sorted order, whatever map
+/// ordering serde_json was built with.
+fn canonical_json(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(entries.into_iter().map(|(key, value)| (key.clone(), canonical_json(value))).collect::<Map<String, Value>>())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical_json).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Persists an entry at the head of the chain.
+pub async fn append_audit_log(db: &PgPool, mut log: AuditLog) -> Result<AuditLog, sqlx::Error> {
+    // Postgres keeps microseconds; hash what will be read back
+    log.created_at = log.created_at.trunc_subsecs(6);
+
+    let mut tx = db.begin().await?;
+
+    // Row lock serialises appends so every entry links to the one before it
+    let head = sqlx::query!(
+        r#"SELECT last_sequence, last_hash AS "last_hash!" FROM audit_chain_state WHERE id = TRUE FOR UPDATE"#
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let sequence = head.last_sequence + 1;
+    let entry_hash = compute_entry_hash(&log, sequence, &head.last_hash);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_logs
+            (id, user_id, action, resource_type, resource_id, details, ip_address, user_agent,
+             session_id, risk_score, status, created_at, sequence, prev_hash, entry_hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7::text::inet, $8, $9, $10, $11, $12, $13, $14, $15)
+        "#,
+        log.id,
+        log.user_id,
+        log.action,
+        log.resource_type,
+        log.resource_id,
+        log.details,
+        log.ip_address.map(|ip| ip.to_string()),
+        log.user_agent,
+        log.session_id,
+        log.risk_score,
+        log.status,
+        log.created_at,
+        sequence,
+        head.last_hash,
+        entry_hash
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE audit_chain_state SET last_sequence = $1, last_hash = $2, updated_at = NOW() WHERE id = TRUE",
+        sequence,
+        entry_hash
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    log.sequence = Some(sequence);
+    log.prev_hash = Some(head.last_hash);
+    log.entry_hash = Some(entry_hash);
+    Ok(log)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainVerificationReport {
+    pub verified: bool,
+    pub entries_checked: i64,
+    pub first_sequence: Option<i64>,
+    pub last_sequence: Option<i64>,
+    pub head_sequence: i64,
+    pub head_hash: String,
+    pub issues: Vec<ChainIssue>,
+    pub verified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainIssue {
+    pub sequence: i64,
+    pub entry_id: Option<Uuid>,
+    pub kind: ChainIssueKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainIssueKind {
+    /// The entry's content no longer matches its stored hash.
+    Altered,
+    /// Sequence numbers are missing between two surviving entries.
+    Missing,
+    /// The entry does not point at the hash of its predecessor.
+    BrokenLink,
+    /// Entries at the end of the chain were removed.
+    Truncated,
+    /// An archived entry is missing from its archive, or the archive is unreadable.
+    ArchiveMismatch,
+}
+
+/// Walks the chain in sequence order, one entry at a time.
+pub struct ChainVerifier {
+    expected_sequence: i64,
+    expected_prev_hash: String,
+    first_sequence: Option<i64>,
+    entries_checked: i64,
+    issues: Vec<ChainIssue>,
+    /// Archived copies of tombstoned entries not yet reached, by entry id.
+    archived: HashMap<Uuid, AuditLog>,
+    unreadable_archives: HashSet<Uuid>,
+}
+
+impl ChainVerifier {
+    /// Starts after the last entry retention has legitimately pruned.
+    pub fn new(pruned_through_sequence: i64, pruned_through_hash: &str) -> Self {
+        Self {
+            expected_sequence: pruned_through_sequence + 1,
+            expected_prev_hash: pruned_through_hash.to_string(),
+            first_sequence: None,
+            entries_checked: 0,
+            issues: Vec::new(),
+            archived: HashMap::new(),
+            unreadable_archives: HashSet::new(),
+        }
+    }
+
+    /// Supplies the entries read back from an archive file.
+    pub fn add_archive(&mut self, entries: Vec<AuditLog>) {
+        self.archived.extend(entries.into_iter().filter(|e| e.sequence.is_some()).map(|e| (e.id, e)));
+    }
+
+    /// Records an archive that could not be read or failed its digest check.
+    pub fn archive_unreadable(&mut self, archive_id: Uuid, sequence: i64, detail: String) {
+        if self.unreadable_archives.insert(archive_id) {
+            self.issues.push(ChainIssue {
+                sequence,
+                entry_id: None,
+                kind: ChainIssueKind::ArchiveMismatch,
+                detail: format!("archive {}: {}", archive_id, detail),
+            });
+        }
+    }
+
+    pub fn check(&mut self, log: &AuditLog) {
+        let (Some(sequence), Some(prev_hash), Some(entry_hash)) = (log.sequence, log.prev_hash.as_deref(), log.entry_hash.as_deref()) else {
+            return;
+        };
+        self.first_sequence.get_or_insert(sequence);
+        self.entries_checked += 1;
+
+        if sequence > self.expected_sequence {
+            self.issues.push(ChainIssue {
+                sequence: self.expected_sequence,
+                entry_id: None,
+                kind: ChainIssueKind::Missing,
+                detail: format!("entries {} to {} are missing", self.expected_sequence, sequence - 1),
+            });
+        }
+        if prev_hash != self.expected_prev_hash {
+            self.issues.push(ChainIssue {
+                sequence,
+                entry_id: Some(log.id),
+                kind: ChainIssueKind::BrokenLink,
+                detail: "previous hash does not match the preceding entry".to_string(),
+            });
+        }
+        match log.archive_id {
+            None => {
+                if compute_entry_hash(log, sequence, prev_hash) != entry_hash {
+                    self.issues.push(ChainIssue {
+                        sequence,
+                        entry_id: Some(log.id),
+                        kind: ChainIssueKind::Altered,
+                        detail: "entry content does not match its hash".to_string(),
+                    });
+                }
+            }
+            // Tombstones no longer hold the hashed content, so it is checked from the archived copy
+            Some(archive_id) => match self.archived.remove(&log.id) {
+                Some(archived) if compute_entry_hash(&archived, sequence, prev_hash) == entry_hash => {}
+                Some(_) => self.issues.push(ChainIssue {
+                    sequence,
+                    entry_id: Some(log.id),
+                    kind: ChainIssueKind::Altered,
+                    detail: format!("archived copy in {} does not match the entry hash", archive_id),
+                }),
+                None if self.unreadable_archives.contains(&archive_id) => {}
+                None => self.issues.push(ChainIssue {
+                    sequence,
+                    entry_id: Some(log.id),
+                    kind: ChainIssueKind::ArchiveMismatch,
+                    detail: format!("entry is missing from archive {}", archive_id),
+                }),
+            },
+        }
+
+        self.expected_sequence = sequence + 1;
+        self.expected_prev_hash = entry_hash.to_string();
+    }
+
+    /// Compares the end of the walk with the recorded chain head.
+    pub fn finish(mut self, head_sequence: i64, head_hash: &str) -> ChainVerificationReport {
+        let last_sequence = self.expected_sequence - 1;
+        if last_sequence < head_sequence {
+            self.issues.push(ChainIssue {
+                sequence: self.expected_sequence,
+                entry_id: None,
+                kind: ChainIssueKind::Truncated,
+                detail: format!("entries {} to {} were removed from the end of the log", self.expected_sequence, head_sequence),
+            });
+        } else if self.expected_prev_hash != head_hash {
+            self.issues.push(ChainIssue {
+                sequence: last_sequence,
+                entry_id: None,
+                kind: ChainIssueKind::BrokenLink,
+                detail: "last entry does not match the recorded chain head".to_string(),
+            });
+        }
+
+        ChainVerificationReport {
+            verified: self.issues.is_empty(),
+            entries_checked: self.entries_checked,
+            first_sequence: self.first_sequence,
+            last_sequence: self.first_sequence.map(|_| last_sequence),
+            head_sequence,
+            head_hash: head_hash.to_string(),
+            issues: self.issues,
+            verified_at: Utc::now(),
+        }
+    }
+}
+
+/// Recomputes every hash in the chain and reports deleted or altered entries.
+pub async fn verify_audit_chain(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<ChainVerificationReport>, axum::http::StatusCode> {
+    require_admin(user)?;
+    let head = sqlx::query!(
+        r#"
+        SELECT last_sequence, last_hash AS "last_hash!",
+               pruned_through_sequence, pruned_through_hash AS "pruned_through_hash!"
+        FROM audit_chain_state WHERE id = TRUE
+        "#
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(super::db_error)?;
+
+    let mut verifier = ChainVerifier::new(head.pruned_through_sequence, &head.pruned_through_hash);
+    let mut after = head.pruned_through_sequence;
+    let mut loaded_archives = HashSet::new();
+
+    // Keyset batches keep memory flat on long chains
+    loop {
+        let batch = super::fetch_chained_logs(&state.db, after, VERIFY_BATCH_SIZE).await.map_err(super::db_error)?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after = last.sequence.unwrap_or(after);
+        for log in &batch {
+            if let Some(archive_id) = log.archive_id.filter(|id| loaded_archives.insert(*id)) {
+                match super::read_archive(&state.db, archive_id).await {
+                    Ok(entries) => verifier.add_archive(entries),
+                    Err(e) => verifier.archive_unreadable(archive_id, log.sequence.unwrap_or(after), e.to_string()),
+                }
+            }
+            verifier.check(log);
+        }
+        if (batch.len() as i64) < VERIFY_BATCH_SIZE {
+            break;
+        }
+    }
+
+    let report = verifier.finish(head.last_sequence, &head.last_hash);
+    if !report.verified {
+        tracing::warn!("Audit chain verification found {} issues", report.issues.len());
+    }
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    fn chain(length: i64) -> Vec<AuditLog> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        (1..=length)
+            .map(|sequence| {
+                let mut log = AuditLog {
+                    id: Uuid::new_v4(),
+                    user_id: Some(Uuid::new_v4()),
+                    action: "api_key.revoked".to_string(),
+                    resource_type: Some("api_key".to_string()),
+                    resource_id: Some(sequence.to_string()),
+                    details: serde_json::json!({ "scopes": ["read"], "reason": "rotated" }),
+                    ip_address: Some("192.0.2.10".parse().unwrap()),
+                    user_agent: Some("curl/8.0".to_string()),
+                    session_id: None,
+                    risk_score: 10,
+                    status: "success".to_string(),
+                    created_at: Utc::now().trunc_subsecs(6),
+                    sequence: Some(sequence),
+                    prev_hash: Some(prev_hash.clone()),
+                    entry_hash: None,
+                    archive_id: None,
+                };
+                let entry_hash = compute_entry_hash(&log, sequence, &prev_hash);
+                log.entry_hash = Some(entry_hash.clone());
+                prev_hash = entry_hash;
+                log
+            })
+            .collect()
+    }
+
+    fn verify(logs: &[AuditLog], head: &AuditLog) -> ChainVerificationReport {
+        let mut verifier = ChainVerifier::new(0, GENESIS_HASH);
+        for log in logs {
+            verifier.check(log);
+        }
+        verifier.finish(head.sequence.unwrap(), head.entry_hash.as_deref().unwrap())
+    }
+
+    fn kinds(report: &ChainVerificationReport) -> Vec<(i64, ChainIssueKind)> {
+        report.issues.iter().map(|issue| (issue.sequence, issue.kind)).collect()
+    }
+
+    #[test]
+    fn intact_chain_verifies() {
+        let logs = chain(4);
+        let report = verify(&logs, &logs[3]);
+        assert!(report.verified, "{:?}", report.issues);
+        assert_eq!((report.entries_checked, report.first_sequence, report.last_sequence), (4, Some(1), Some(4)));
+    }
+
+    #[test]
+    fn edited_entry_is_reported_as_altered() {
+        let mut logs = chain(4);
+        logs[1].details["reason"] = serde_json::json!("compromised");
+        let head = logs[3].clone();
+        assert_eq!(kinds(&verify(&logs, &head)), [(2, ChainIssueKind::Altered)]);
+    }
+
+    #[test]
+    fn rehashed_entry_breaks_the_next_link() {
+        let mut logs = chain(4);
+        logs[1].status = "failed".to_string();
+        logs[1].entry_hash = Some(compute_entry_hash(&logs[1], 2, logs[1].prev_hash.as_deref().unwrap()));
+        let head = logs[3].clone();
+        assert_eq!(kinds(&verify(&logs, &head)), [(3, ChainIssueKind::BrokenLink)]);
+    }
+
+    #[test]
+    fn deleted_entries_are_reported_as_missing_or_truncated() {
+        let mut logs = chain(4);
+        let head = logs[3].clone();
+        logs.remove(1);
+        assert_eq!(kinds(&verify(&logs, &head)), [(2, ChainIssueKind::Missing), (3, ChainIssueKind::BrokenLink)]);
+
+        let mut logs = chain(4);
+        let head = logs.pop().unwrap();
+        assert_eq!(kinds(&verify(&logs, &head)), [(4, ChainIssueKind::Truncated)]);
+    }
+
+    #[test]
+    fn hash_ignores_object_key_order() {
+        let logs = chain(1);
+        let mut reordered = logs[0].clone();
+        reordered.details = serde_json::from_str(r#"{"reason":"rotated","scopes":["read"]}"#).unwrap();
+        let prev_hash = logs[0].prev_hash.as_deref().unwrap();
+        assert_eq!(compute_entry_hash(&reordered, 1, prev_hash), logs[0].entry_hash.clone().unwrap());
+    }
+
+    #[tokio::test]
+    async fn verification_is_refused_without_an_admin() {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let state = SecurityState::new(db);
+        let caller = UserContext { user_id: Uuid::new_v4(), permissions: Vec::new(), is_admin: false };
+
+        assert_eq!(verify_audit_chain(State(state.clone()), None).await.err(), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(verify_audit_chain(State(state), Some(Extension(caller))).await.err(), Some(StatusCode::FORBIDDEN));
+    }
+}
@@ -0,0 +1,455 @@
+// Audit retention: archive expired entries to compressed files without breaking the hash chain
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::{db_error, record_audit_event, require_admin, AuditLog, AuditLogRow, ClientInfo, SecurityState, UserContext};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditRetentionConfig {
+    /// Days kept for resource types without their own entry.
+    pub default_days: u32,
+    /// Days kept per resource type; `null` keeps that type forever.
+    pub resource_types: HashMap<String, Option<u32>>,
+    /// Directory the gzipped JSON Lines archives are written to.
+    pub archive_dir: PathBuf,
+    /// Most entries archived per run.
+    pub batch_size: i64,
+}
+
+impl Default for AuditRetentionConfig {
+    fn default() -> Self {
+        let resource_types = [
+            ("api_key", Some(365)),
+            ("consent", Some(365 * 3)),
+            ("dsar_request", Some(365 * 3)),
+        ]
+        .into_iter()
+        .map(|(resource_type, days)| (resource_type.to_string(), days))
+        .collect();
+        Self {
+            default_days: 90,
+            resource_types,
+            archive_dir: PathBuf::from("audit-archive"),
+            batch_size: 10_000,
+        }
+    }
+}
+
+impl AuditRetentionConfig {
+    /// Loads the config from the JSON file named by `AUDIT_RETENTION_CONFIG`,
+    /// falling back to the defaults.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("AUDIT_RETENTION_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring audit retention config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Parallel arrays for SQL: types with a limit and their days, plus types kept forever.
+    fn sql_rules(&self) -> (Vec<String>, Vec<i32>, Vec<String>) {
+        let mut limited_types = Vec::new();
+        let mut limited_days = Vec::new();
+        let mut kept = Vec::new();
+        for (resource_type, days) in &self.resource_types {
+            match days {
+                Some(days) => {
+                    limited_types.push(resource_type.clone());
+                    limited_days.push(*days as i32);
+                }
+                None => kept.push(resource_type.clone()),
+            }
+        }
+        (limited_types, limited_days, kept)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditArchiveError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Archive write failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Archive entry is not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Archive file does not match its recorded SHA-256")]
+    DigestMismatch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditArchive {
+    pub id: Uuid,
+    pub file_path: String,
+    /// SHA-256 of the archive file as written.
+    pub sha256: String,
+    pub entries: i64,
+    pub first_created_at: DateTime<Utc>,
+    pub last_created_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionReport {
+    pub archive: Option<AuditArchive>,
+    pub archived: u64,
+    /// Entries removed outright: tombstones below the new chain start and
+    /// legacy entries written before the chain existed.
+    pub deleted: u64,
+    pub pruned_through_sequence: i64,
+}
+
+/// Archives entries past their retention, then drops archived entries from
+/// the start of the chain.
+///
+/// Chained entries are archived in place: their content is cleared but their
+/// sequence and hashes stay, so links across them still verify. Once every
+/// entry up to some sequence is archived, the chain start advances past it
+/// and those rows are deleted.
+pub async fn run_audit_retention(db: &PgPool, config: &AuditRetentionConfig) -> Result<RetentionReport, AuditArchiveError> {
+    let (limited_types, limited_days, kept_types) = config.sql_rules();
+    let rows = sqlx::query_as!(
+        AuditLogRow,
+        r#"
+        SELECT a.id, a.user_id, a.action, a.resource_type, a.resource_id,
+               a.details AS "details!", host(a.ip_address) AS ip_address, a.user_agent, a.session_id,
+               a.risk_score AS "risk_score!", a.status AS "status!", a.created_at AS "created_at!",
+               a.sequence, a.prev_hash, a.entry_hash, a.archive_id
+        FROM audit_logs a
+        LEFT JOIN UNNEST($1::text[], $2::int[]) AS rule(resource_type, days)
+               ON rule.resource_type = a.resource_type
+        WHERE a.archive_id IS NULL
+          AND NOT (COALESCE(a.resource_type, '') = ANY($3))
+          AND a.created_at < NOW() - make_interval(days => COALESCE(rule.days, $4))
+        ORDER BY a.sequence ASC NULLS FIRST, a.created_at ASC
+        LIMIT $5
+        "#,
+        &limited_types,
+        &limited_days,
+        &kept_types,
+        config.default_days as i32,
+        config.batch_size.max(1)
+    )
+    .fetch_all(db)
+    .await?;
+    let entries: Vec<AuditLog> = rows.into_iter().map(AuditLog::from).collect();
+
+    let mut report = RetentionReport::default();
+    if let Some(archive) = write_archive(config, &entries).await? {
+        let chained: Vec<Uuid> = entries.iter().filter(|e| e.sequence.is_some()).map(|e| e.id).collect();
+        let unchained: Vec<Uuid> = entries.iter().filter(|e| e.sequence.is_none()).map(|e| e.id).collect();
+
+        let mut tx = db.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_log_archives (id, file_path, sha256, entries, first_created_at, last_created_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            archive.id,
+            archive.file_path,
+            archive.sha256,
+            archive.entries,
+            archive.first_created_at,
+            archive.last_created_at,
+            archive.created_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // Tombstones keep only what the chain and the retention job need
+        report.archived = sqlx::query!(
+            r#"
+            UPDATE audit_logs
+            SET archive_id = $2, action = 'archived', details = '{}'::jsonb, user_id = NULL, resource_id = NULL,
+                ip_address = NULL, user_agent = NULL, session_id = NULL
+            WHERE id = ANY($1)
+            "#,
+            &chained,
+            archive.id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        report.deleted += sqlx::query!("DELETE FROM audit_logs WHERE id = ANY($1)", &unchained)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        report.archived += unchained.len() as u64;
+        tx.commit().await?;
+        report.archive = Some(archive);
+    }
+
+    let (pruned_through, pruned) = advance_chain_start(db).await?;
+    report.deleted += pruned;
+    report.pruned_through_sequence = pruned_through;
+    Ok(report)
+}
+
+/// Moves the verified chain start past leading tombstones and deletes them.
+async fn advance_chain_start(db: &PgPool) -> Result<(i64, u64), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let state = sqlx::query!("SELECT pruned_through_sequence FROM audit_chain_state WHERE id = TRUE FOR UPDATE")
+        .fetch_one(&mut *tx)
+        .await?;
+
+    // The last tombstone before the first live entry becomes the new start
+    let boundary = sqlx::query!(
+        r#"
+        SELECT sequence AS "sequence!", entry_hash AS "entry_hash!"
+        FROM audit_logs
+        WHERE sequence > $1
+          AND archive_id IS NOT NULL
+          AND sequence < COALESCE(
+              (SELECT MIN(sequence) FROM audit_logs WHERE sequence > $1 AND archive_id IS NULL),
+              9223372036854775807
+          )
+        ORDER BY sequence DESC
+        LIMIT 1
+        "#,
+        state.pruned_through_sequence
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(boundary) = boundary else {
+        tx.commit().await?;
+        return Ok((state.pruned_through_sequence, 0));
+    };
+
+    sqlx::query!(
+        "UPDATE audit_chain_state SET pruned_through_sequence = $1, pruned_through_hash = $2, updated_at = NOW() WHERE id = TRUE",
+        boundary.sequence,
+        boundary.entry_hash
+    )
+    .execute(&mut *tx)
+    .await?;
+    let deleted = sqlx::query!("DELETE FROM audit_logs WHERE sequence <= $1 AND archive_id IS NOT NULL", boundary.sequence)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+
+    Ok((boundary.sequence, deleted))
+}
+
+/// Writes entries as gzipped JSON Lines; `None` when there is nothing to archive.
+async fn write_archive(config: &AuditRetentionConfig, entries: &[AuditLog]) -> Result<Option<AuditArchive>, std::io::Error> {
+    let (Some(first), Some(last)) = (
+        entries.iter().map(|e| e.created_at).min(),
+        entries.iter().map(|e| e.created_at).max(),
+    ) else {
+        return Ok(None);
+    };
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for entry in entries {
+        serde_json::to_writer(&mut encoder, entry)?;
+        encoder.write_all(b"\n")?;
+    }
+    let compressed = encoder.finish()?;
+
+    let id = Uuid::new_v4();
+    let created_at = Utc::now();
+    let path = config
+        .archive_dir
+        .join(format!("audit-{}-{}.jsonl.gz", created_at.format("%Y%m%dT%H%M%SZ"), id.simple()));
+    tokio::fs::create_dir_all(&config.archive_dir).await?;
+    tokio::fs::write(&path, &compressed).await?;
+
+    Ok(Some(AuditArchive {
+        id,
+        file_path: path.to_string_lossy().into_owned(),
+        sha256: format!("{:x}", Sha256::digest(&compressed)),
+        entries: entries.len() as i64,
+        first_created_at: first,
+        last_created_at: last,
+        created_at,
+    }))
+}
+
+/// Reads an archive back, refusing it if the file no longer matches its digest.
+pub async fn read_archive(db: &PgPool, archive_id: Uuid) -> Result<Vec<AuditLog>, AuditArchiveError> {
+    let archive = sqlx::query!("SELECT file_path, sha256 FROM audit_log_archives WHERE id = $1", archive_id)
+        .fetch_one(db)
+        .await?;
+    let compressed = tokio::fs::read(&archive.file_path).await?;
+    parse_archive(&compressed, &archive.sha256)
+}
+
+fn parse_archive(compressed: &[u8], sha256: &str) -> Result<Vec<AuditLog>, AuditArchiveError> {
+    if format!("{:x}", Sha256::digest(compressed)) != sha256 {
+        return Err(AuditArchiveError::DigestMismatch);
+    }
+    let mut entries = Vec::new();
+    for line in BufReader::new(GzDecoder::new(compressed)).lines() {
+        entries.push(serde_json::from_str(&line?)?);
+    }
+    Ok(entries)
+}
+
+pub async fn run_audit_retention_now(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Json<RetentionReport>, StatusCode> {
+    let admin = require_admin(user)?;
+    let report = run_audit_retention(&state.db, &AuditRetentionConfig::load()).await.map_err(|e| {
+        tracing::error!("Audit retention failed: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let archive_id = report.archive.as_ref().map(|a| a.id.to_string()).unwrap_or_default();
+    record_audit_event(&state.db, Some(admin.user_id), "audit_logs.retention_run", "audit_log_archive", &archive_id, serde_json::json!({
+        "archived": report.archived,
+        "deleted": report.deleted,
+        "pruned_through_sequence": report.pruned_through_sequence
+    }), Some(&client)).await;
+
+    Ok(Json(report))
+}
+
+pub async fn list_audit_archives(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<Vec<AuditArchive>>, StatusCode> {
+    require_admin(user)?;
+    sqlx::query_as!(
+        AuditArchive,
+        r#"
+        SELECT id, file_path, sha256, entries, first_created_at, last_created_at, created_at
+        FROM audit_log_archives
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .map(Json)
+    .map_err(db_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::{compute_entry_hash, ChainIssueKind, ChainVerifier};
+    use chrono::SubsecRound;
+
+    fn chained(sequence: i64, prev_hash: &str) -> AuditLog {
+        let mut log = AuditLog {
+            id: Uuid::new_v4(),
+            user_id: Some(Uuid::new_v4()),
+            action: "GET_/api/tabs".to_string(),
+            resource_type: Some("tab_operation".to_string()),
+            resource_id: None,
+            details: serde_json::json!({ "status": 200 }),
+            ip_address: Some("198.51.100.4".parse().unwrap()),
+            user_agent: None,
+            session_id: None,
+            risk_score: 0,
+            status: "success".to_string(),
+            created_at: Utc::now().trunc_subsecs(6),
+            sequence: Some(sequence),
+            prev_hash: Some(prev_hash.to_string()),
+            entry_hash: None,
+            archive_id: None,
+        };
+        log.entry_hash = Some(compute_entry_hash(&log, sequence, prev_hash));
+        log
+    }
+
+    #[tokio::test]
+    async fn archive_is_gzipped_json_lines_with_its_digest() {
+        let config = AuditRetentionConfig {
+            archive_dir: std::env::temp_dir().join(format!("jeantrail-audit-archive-{}", Uuid::new_v4())),
+            ..AuditRetentionConfig::default()
+        };
+        let first = chained(1, &"0".repeat(64));
+        let second = chained(2, first.entry_hash.as_deref().unwrap());
+        let archive = write_archive(&config, &[first, second]).await.unwrap().unwrap();
+
+        let mut compressed = std::fs::read(&archive.file_path).unwrap();
+        let restored = parse_archive(&compressed, &archive.sha256).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored[1].sequence, Some(2));
+        assert_eq!(archive.entries, 2);
+
+        assert!(write_archive(&config, &[]).await.unwrap().is_none());
+        std::fs::remove_dir_all(&config.archive_dir).unwrap();
+
+        let last = compressed.len() - 1;
+        compressed[last] ^= 1;
+        assert!(matches!(parse_archive(&compressed, &archive.sha256), Err(AuditArchiveError::DigestMismatch)));
+    }
+
+    /// What the retention job leaves behind for an archived entry.
+    fn tombstone(log: &AuditLog) -> AuditLog {
+        AuditLog {
+            archive_id: Some(Uuid::new_v4()),
+            action: "archived".to_string(),
+            details: serde_json::json!({}),
+            user_id: None,
+            ip_address: None,
+            ..log.clone()
+        }
+    }
+
+    fn verify(archived: Vec<AuditLog>, chain: &[&AuditLog]) -> Vec<ChainIssueKind> {
+        let mut verifier = ChainVerifier::new(0, &"0".repeat(64));
+        verifier.add_archive(archived);
+        for log in chain {
+            verifier.check(log);
+        }
+        let head = chain.last().unwrap();
+        let report = verifier.finish(head.sequence.unwrap(), head.entry_hash.as_deref().unwrap());
+        report.issues.into_iter().map(|issue| issue.kind).collect()
+    }
+
+    #[test]
+    fn tombstones_verify_against_their_archived_copy() {
+        let first = chained(1, &"0".repeat(64));
+        let second = chained(2, first.entry_hash.as_deref().unwrap());
+        let third = chained(3, second.entry_hash.as_deref().unwrap());
+        let archived = tombstone(&second);
+
+        assert_eq!(verify(vec![second.clone()], &[&first, &archived, &third]), []);
+
+        let mut edited = second.clone();
+        edited.details = serde_json::json!({ "status": 500 });
+        assert_eq!(verify(vec![edited], &[&first, &archived, &third]), [ChainIssueKind::Altered]);
+        assert_eq!(verify(vec![], &[&first, &archived, &third]), [ChainIssueKind::ArchiveMismatch]);
+
+        // Stripping content without marking the entry archived is tampering
+        let stripped = AuditLog { archive_id: None, ..archived };
+        assert_eq!(verify(vec![second], &[&first, &stripped, &third]), [ChainIssueKind::Altered]);
+    }
+
+    fn caller(is_admin: bool) -> Option<Extension<UserContext>> {
+        Some(Extension(UserContext { user_id: Uuid::new_v4(), permissions: Vec::new(), is_admin }))
+    }
+
+    #[tokio::test]
+    async fn archive_listing_and_manual_runs_are_refused_without_an_admin() {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let state = SecurityState::new(db);
+
+        for (user, expected) in [(None, StatusCode::UNAUTHORIZED), (caller(false), StatusCode::FORBIDDEN)] {
+            let listed = list_audit_archives(State(state.clone()), user.clone()).await;
+            assert_eq!(listed.err(), Some(expected));
+            let run = run_audit_retention_now(State(state.clone()), user, ClientInfo::default()).await;
+            assert_eq!(run.err(), Some(expected));
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// Client address resolution: forwarding headers only count when they come from a trusted proxy
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, Extensions, HeaderMap},
+};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+
+/// An address range such as `10.0.0.0/8` or `fd00::/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Accepts `addr/prefix` or a bare address, which covers just that host.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix) = match value.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+            None => (value.trim(), None),
+        };
+        let network = addr.parse::<IpAddr>().ok()?.to_canonical();
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix)).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix)).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Proxies whose `Forwarded`, `X-Forwarded-For` and `X-Real-IP` headers are believed.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<Cidr>,
+}
+
+impl TrustedProxies {
+    pub fn new(ranges: Vec<Cidr>) -> Self {
+        Self { ranges }
+    }
+
+    /// Reads comma-separated CIDRs from `TRUSTED_PROXY_CIDRS`. With none set,
+    /// no proxy is trusted and the socket peer is always the client.
+    pub fn from_env() -> Self {
+        let Ok(raw) = std::env::var("TRUSTED_PROXY_CIDRS") else {
+            return Self::default();
+        };
+        let ranges = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let cidr = Cidr::parse(entry);
+                if cidr.is_none() {
+                    tracing::warn!("Ignoring invalid trusted proxy range {:?}", entry);
+                }
+                cidr
+            })
+            .collect();
+        Self { ranges }
+    }
+
+    /// Process-wide set, read from the environment on first use.
+    pub fn global() -> &'static TrustedProxies {
+        static PROXIES: OnceLock<TrustedProxies> = OnceLock::new();
+        PROXIES.get_or_init(Self::from_env)
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+}
+
+/// One hop of a forwarding chain; `None` for `unknown` or obfuscated identifiers.
+type Hop = Option<IpAddr>;
+
+/// Finds the client behind `peer`. Hops are read right to left and trusted
+/// proxies skipped; the first untrusted hop is the client. Anything a proxy
+/// did not itself append, such as entries a client forged before the first
+/// trusted hop, is never reached. Without a peer nothing can be trusted.
+pub fn resolve_client_ip(headers: &HeaderMap, peer: Option<IpAddr>, trusted: &TrustedProxies) -> Option<IpAddr> {
+    let peer = peer?.to_canonical();
+    if !trusted.is_trusted(peer) {
+        return Some(peer);
+    }
+
+    // Forwarded (RFC 7239) supersedes the de facto headers when present
+    let chain = forwarded_chain(headers)
+        .or_else(|| x_forwarded_for_chain(headers))
+        .or_else(|| header_values(headers, "x-real-ip").last().map(|value| vec![parse_node(value)]))
+        .unwrap_or_default();
+
+    let mut client = peer;
+    for hop in chain.iter().rev() {
+        // The trusted proxy in front of this hop could not name it
+        let Some(ip) = hop else { break };
+        client = ip.to_canonical();
+        if !trusted.is_trusted(client) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+fn header_values<'a>(headers: &'a HeaderMap, name: &str) -> Vec<&'a str> {
+    headers.get_all(name).iter().filter_map(|value| value.to_str().ok()).collect()
+}
+
+/// `for=` parameters from every `Forwarded` header, in order.
+fn forwarded_chain(headers: &HeaderMap) -> Option<Vec<Hop>> {
+    let values = header_values(headers, "forwarded");
+    if values.is_empty() {
+        return None;
+    }
+    let hops = values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                key.trim().eq_ignore_ascii_case("for").then(|| parse_node(value))
+            })
+        })
+        .collect();
+    Some(hops)
+}
+
+fn x_forwarded_for_chain(headers: &HeaderMap) -> Option<Vec<Hop>> {
+    let values = header_values(headers, "x-forwarded-for");
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().flat_map(|value| value.split(',')).map(parse_node).collect())
+}
+
+/// Parses a node as written by proxies: `1.2.3.4`, `1.2.3.4:80`, `::1`,
+/// `[::1]:80`, optionally quoted.
+fn parse_node(value: &str) -> Hop {
+    let value = value.trim().trim_matches('"');
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    value.parse().ok().or_else(|| {
+        // IPv4 with a port; bare IPv6 has more than one colon and parsed above
+        let (host, port) = value.rsplit_once(':')?;
+        port.parse::<u16>().ok()?;
+        host.parse::<std::net::Ipv4Addr>().ok().map(IpAddr::V4)
+    })
+}
+
+/// Where a request came from, as far as the server can tell. The user is not
+/// part of this; identity only comes from an authenticated `UserContext`.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub session_id: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_request_parts(headers: &HeaderMap, extensions: &Extensions) -> Self {
+        let peer = extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+        let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok()).map(str::to_string);
+        Self {
+            ip_address: resolve_client_ip(headers, peer, TrustedProxies::global()),
+            user_agent: header("user-agent"),
+            session_id: header("x-session-id"),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_request_parts(&parts.headers, &parts.extensions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn proxies(ranges: &[&str]) -> TrustedProxies {
+        TrustedProxies::new(ranges.iter().map(|r| Cidr::parse(r).unwrap()).collect())
+    }
+
+    #[test]
+    fn forged_headers_from_untrusted_peer_are_ignored() {
+        let forged = headers(&[
+            ("x-forwarded-for", "10.0.0.1"),
+            ("x-real-ip", "10.0.0.2"),
+            ("forwarded", "for=10.0.0.3"),
+        ]);
+        let resolved = resolve_client_ip(&forged, Some(ip("203.0.113.9")), &proxies(&["10.0.0.0/8"]));
+        assert_eq!(resolved, Some(ip("203.0.113.9")));
+    }
+
+    #[test]
+    fn nothing_is_trusted_by_default() {
+        let forged = headers(&[("x-forwarded-for", "198.51.100.1")]);
+        let resolved = resolve_client_ip(&forged, Some(ip("127.0.0.1")), &TrustedProxies::default());
+        assert_eq!(resolved, Some(ip("127.0.0.1")));
+    }
+
+    #[test]
+    fn no_peer_means_no_address() {
+        let forged = headers(&[("x-forwarded-for", "198.51.100.1")]);
+        assert_eq!(resolve_client_ip(&forged, None, &proxies(&["0.0.0.0/0"])), None);
+    }
+
+    #[test]
+    fn forged_entries_left_of_the_client_are_skipped() {
+        // The client sent "1.1.1.1" itself; the edge proxy appended the real address
+        let chain = headers(&[("x-forwarded-for", "1.1.1.1, 198.51.100.7, 10.0.0.5")]);
+        let resolved = resolve_client_ip(&chain, Some(ip("10.0.0.6")), &proxies(&["10.0.0.0/8"]));
+        assert_eq!(resolved, Some(ip("198.51.100.7")));
+    }
+
+    #[test]
+    fn repeated_forwarding_headers_form_one_chain() {
+        let chain = headers(&[("x-forwarded-for", "1.1.1.1"), ("x-forwarded-for", "198.51.100.7")]);
+        let resolved = resolve_client_ip(&chain, Some(ip("10.0.0.6")), &proxies(&["10.0.0.0/8"]));
+        assert_eq!(resolved, Some(ip("198.51.100.7")));
+    }
+
+    #[test]
+    fn forwarded_takes_precedence_and_handles_ports_and_ipv6() {
+        let chain = headers(&[
+            ("x-forwarded-for", "1.1.1.1"),
+            ("forwarded", r#"for="[2001:db8:cafe::17]:4711";proto=https, For="10.0.0.5:8080";by=10.0.0.6"#),
+        ]);
+        let resolved = resolve_client_ip(&chain, Some(ip("10.0.0.6")), &proxies(&["10.0.0.0/8"]));
+        assert_eq!(resolved, Some(ip("2001:db8:cafe::17")));
+    }
+
+    #[test]
+    fn unknown_hop_stops_at_the_nearest_known_address() {
+        let chain = headers(&[("forwarded", "for=198.51.100.1, for=unknown, for=10.0.0.5")]);
+        let resolved = resolve_client_ip(&chain, Some(ip("10.0.0.6")), &proxies(&["10.0.0.0/8"]));
+        assert_eq!(resolved, Some(ip("10.0.0.5")));
+    }
+
+    #[test]
+    fn x_real_ip_is_used_only_behind_a_trusted_proxy() {
+        let real_ip = headers(&[("x-real-ip", "198.51.100.7")]);
+        let trusted = proxies(&["127.0.0.1"]);
+        assert_eq!(resolve_client_ip(&real_ip, Some(ip("127.0.0.1")), &trusted), Some(ip("198.51.100.7")));
+        assert_eq!(resolve_client_ip(&real_ip, Some(ip("127.0.0.2")), &trusted), Some(ip("127.0.0.2")));
+    }
+
+    #[test]
+    fn cidr_matching_covers_mapped_addresses() {
+        let range = Cidr::parse("192.168.0.0/16").unwrap();
+        assert!(range.contains(ip("192.168.4.2")));
+        assert!(range.contains(ip("::ffff:192.168.4.2")));
+        assert!(!range.contains(ip("192.169.0.1")));
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(ip("8.8.8.8")));
+        assert!(Cidr::parse("fd00::/8").unwrap().contains(ip("fd12::1")));
+        assert_eq!(Cidr::parse("10.0.0.0/33"), None);
+        assert_eq!(Cidr::parse("not-an-ip"), None);
+    }
+}
@@ -0,0 +1,384 @@
+// Consent gate: modules ask here before processing personal data for a purpose
+use axum::http::StatusCode;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::{record_audit_event, table_exists};
+
+/// Processing purposes that modules gate on. Other consent types can still
+/// be recorded, they just have nothing enforcing them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentPurpose {
+    /// Storing assistant memories and AI context about the user.
+    MemoryPersonalization,
+    /// Logging browser traffic for Auto-API discovery.
+    TrafficCapture,
+}
+
+impl ConsentPurpose {
+    pub const ALL: [ConsentPurpose; 2] = [ConsentPurpose::MemoryPersonalization, ConsentPurpose::TrafficCapture];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentPurpose::MemoryPersonalization => "memory_personalization",
+            ConsentPurpose::TrafficCapture => "traffic_capture",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|purpose| purpose.as_str() == value)
+    }
+
+    /// Tables, keyed by `user_id`, holding data collected under this purpose.
+    /// Dependent tables come first.
+    fn data_tables(&self) -> &'static [&'static str] {
+        match self {
+            ConsentPurpose::MemoryPersonalization => &["jean_memory", "user_ai_context"],
+            ConsentPurpose::TrafficCapture => &["api_stream_messages", "api_discovery_logs"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsentPolicy {
+    /// Days a grant stays valid before the user has to confirm it again.
+    pub validity_days: Option<i64>,
+    /// Whether requests without a user may be processed at all.
+    pub allow_anonymous: bool,
+    /// Delete the purpose's data when consent is withdrawn or expires.
+    pub purge_on_withdrawal: bool,
+}
+
+impl Default for ConsentPolicy {
+    fn default() -> Self {
+        Self {
+            validity_days: Some(365),
+            allow_anonymous: false,
+            purge_on_withdrawal: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsentConfig {
+    /// Used for consent types without an entry in `policies`.
+    pub default_policy: ConsentPolicy,
+    /// Keyed by consent type, e.g. `traffic_capture`.
+    pub policies: HashMap<String, ConsentPolicy>,
+}
+
+impl ConsentConfig {
+    /// Loads the config from the JSON file named by `CONSENT_POLICY_CONFIG`,
+    /// falling back to the defaults.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("CONSENT_POLICY_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring consent policy config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn policy(&self, consent_type: &str) -> &ConsentPolicy {
+        self.policies.get(consent_type).unwrap_or(&self.default_policy)
+    }
+}
+
+/// Where a user stands on a purpose, based on their latest consent record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ConsentStatus {
+    Granted { version: String, expires_at: Option<DateTime<Utc>> },
+    /// No user to ask, and the policy allows anonymous processing.
+    NotRequired,
+    Missing,
+    Withdrawn { at: DateTime<Utc> },
+    Expired { at: DateTime<Utc> },
+}
+
+impl ConsentStatus {
+    pub fn allows(&self) -> bool {
+        matches!(self, ConsentStatus::Granted { .. } | ConsentStatus::NotRequired)
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ConsentStatus::Granted { .. } => "granted",
+            ConsentStatus::NotRequired => "not required",
+            ConsentStatus::Missing => "missing",
+            ConsentStatus::Withdrawn { .. } => "withdrawn",
+            ConsentStatus::Expired { .. } => "expired",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConsentError {
+    #[error("{} consent is {}", .purpose.as_str(), .status.label())]
+    NotGranted { purpose: ConsentPurpose, status: ConsentStatus },
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl ConsentError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ConsentError::NotGranted { .. } => StatusCode::FORBIDDEN,
+            ConsentError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// A user's latest consent record for one purpose.
+struct LatestConsent {
+    granted: bool,
+    version: String,
+    granted_at: DateTime<Utc>,
+    revoked_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl LatestConsent {
+    fn status(self, now: DateTime<Utc>) -> ConsentStatus {
+        // Expiry is checked before revocation because the cleanup job marks
+        // expired grants as revoked at their expiry time
+        if !self.granted {
+            ConsentStatus::Withdrawn { at: self.granted_at }
+        } else if let Some(at) = self.expires_at.filter(|at| *at <= now) {
+            ConsentStatus::Expired { at }
+        } else if let Some(at) = self.revoked_at {
+            ConsentStatus::Withdrawn { at }
+        } else {
+            ConsentStatus::Granted { version: self.version, expires_at: self.expires_at }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsentGate {
+    db: PgPool,
+    config: Arc<ConsentConfig>,
+}
+
+impl ConsentGate {
+    pub fn new(db: PgPool) -> Self {
+        Self::with_config(db, ConsentConfig::load())
+    }
+
+    pub fn with_config(db: PgPool, config: ConsentConfig) -> Self {
+        Self { db, config: Arc::new(config) }
+    }
+
+    pub fn config(&self) -> &ConsentConfig {
+        &self.config
+    }
+
+    /// When a grant recorded now for `consent_type` stops being valid.
+    pub fn expiry_for(&self, consent_type: &str, granted_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.config
+            .policy(consent_type)
+            .validity_days
+            .map(|days| granted_at + Duration::days(days))
+    }
+
+    pub async fn status(&self, user_id: Option<Uuid>, purpose: ConsentPurpose) -> Result<ConsentStatus, sqlx::Error> {
+        let Some(user_id) = user_id else {
+            return Ok(self.anonymous_status(purpose));
+        };
+
+        let latest = sqlx::query_as!(
+            LatestConsent,
+            r#"
+            SELECT granted, version, granted_at AS "granted_at!", revoked_at, expires_at
+            FROM consent_records
+            WHERE user_id = $1 AND consent_type = $2
+            ORDER BY granted_at DESC
+            LIMIT 1
+            "#,
+            user_id,
+            purpose.as_str()
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(latest.map_or(ConsentStatus::Missing, |record| record.status(Utc::now())))
+    }
+
+    fn anonymous_status(&self, purpose: ConsentPurpose) -> ConsentStatus {
+        if self.config.policy(purpose.as_str()).allow_anonymous {
+            ConsentStatus::NotRequired
+        } else {
+            ConsentStatus::Missing
+        }
+    }
+
+    /// Whether data collected under `purpose` goes once the user stands at `status`.
+    fn purges(&self, purpose: ConsentPurpose, status: &ConsentStatus) -> bool {
+        self.config.policy(purpose.as_str()).purge_on_withdrawal && !status.allows()
+    }
+
+    /// For callers that degrade: true only when processing may go ahead.
+    /// Fails closed when the status cannot be read.
+    pub async fn check(&self, user_id: Option<Uuid>, purpose: ConsentPurpose) -> bool {
+        match self.status(user_id, purpose).await {
+            Ok(status) => status.allows(),
+            Err(e) => {
+                tracing::error!("Consent check for {} failed: {}", purpose.as_str(), e);
+                false
+            }
+        }
+    }
+
+    /// For callers that block: errors unless processing may go ahead.
+    pub async fn require(&self, user_id: Option<Uuid>, purpose: ConsentPurpose) -> Result<(), ConsentError> {
+        let status = self.status(user_id, purpose).await?;
+        if status.allows() {
+            Ok(())
+        } else {
+            Err(ConsentError::NotGranted { purpose, status })
+        }
+    }
+
+    /// Deletes what was collected under `purpose` once the user has no valid
+    /// grant for it. Returns the number of rows removed.
+    pub async fn purge_purpose_data(&self, user_id: Uuid, purpose: ConsentPurpose) -> Result<u64, sqlx::Error> {
+        let status = self.status(Some(user_id), purpose).await?;
+        if !self.purges(purpose, &status) {
+            return Ok(0);
+        }
+
+        let mut tx = self.db.begin().await?;
+        let mut purged = 0;
+        for table in purpose.data_tables() {
+            if !table_exists(&mut *tx, table).await? {
+                continue;
+            }
+            // Table names come from ConsentPurpose, never from the request
+            let sql = format!("DELETE FROM {} WHERE user_id = $1", table);
+            purged += sqlx::query(&sql).bind(user_id).execute(&mut *tx).await?.rows_affected();
+        }
+        tx.commit().await?;
+
+        record_audit_event(&self.db, Some(user_id), "consent.data_purged", "consent", purpose.as_str(), json!({ "rows": purged }), None).await;
+        Ok(purged)
+    }
+
+    /// Closes grants past their expiry and purges the data they covered.
+    /// Returns the number of grants that expired.
+    pub async fn cleanup_expired(&self) -> Result<u64, sqlx::Error> {
+        let expired = sqlx::query!(
+            r#"
+            UPDATE consent_records
+            SET revoked_at = expires_at
+            WHERE granted AND revoked_at IS NULL AND expires_at <= NOW()
+            RETURNING user_id, consent_type
+            "#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let affected: HashSet<(Uuid, ConsentPurpose)> = expired
+            .iter()
+            .filter_map(|row| Some((row.user_id?, ConsentPurpose::parse(&row.consent_type)?)))
+            .collect();
+        for (user_id, purpose) in affected {
+            // A newer grant keeps the data; purge_purpose_data checks for it
+            if let Err(e) = self.purge_purpose_data(user_id, purpose).await {
+                tracing::error!("Failed to purge {} data for {}: {}", purpose.as_str(), user_id, e);
+            }
+        }
+
+        if !expired.is_empty() {
+            tracing::info!("Expired {} consent grants", expired.len());
+        }
+        Ok(expired.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gate(policy: ConsentPolicy) -> ConsentGate {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        ConsentGate::with_config(db, ConsentConfig { default_policy: policy, policies: HashMap::new() })
+    }
+
+    fn grant(granted_at: DateTime<Utc>, expires_at: Option<DateTime<Utc>>) -> LatestConsent {
+        LatestConsent { granted: true, version: "2".to_string(), granted_at, revoked_at: None, expires_at }
+    }
+
+    #[test]
+    fn latest_record_decides_the_status() {
+        let now = Utc::now();
+        let granted_at = now - Duration::days(10);
+
+        let granted = grant(granted_at, Some(now + Duration::days(1))).status(now);
+        assert_eq!(granted, ConsentStatus::Granted { version: "2".to_string(), expires_at: Some(now + Duration::days(1)) });
+        assert!(granted.allows());
+
+        let withdrawn = LatestConsent { granted: false, ..grant(granted_at, None) }.status(now);
+        assert_eq!(withdrawn, ConsentStatus::Withdrawn { at: granted_at });
+        assert!(!withdrawn.allows());
+    }
+
+    #[test]
+    fn expired_grant_stays_expired_after_cleanup_closes_it() {
+        let now = Utc::now();
+        let expired_at = now - Duration::days(1);
+        let open = grant(now - Duration::days(30), Some(expired_at));
+        assert_eq!(open.status(now), ConsentStatus::Expired { at: expired_at });
+
+        let closed = LatestConsent { revoked_at: Some(expired_at), ..grant(now - Duration::days(30), Some(expired_at)) };
+        assert_eq!(closed.status(now), ConsentStatus::Expired { at: expired_at });
+    }
+
+    #[tokio::test]
+    async fn anonymous_requests_follow_the_policy() {
+        let strict = gate(ConsentPolicy::default());
+        assert_eq!(strict.anonymous_status(ConsentPurpose::MemoryPersonalization), ConsentStatus::Missing);
+        assert!(!strict.check(None, ConsentPurpose::MemoryPersonalization).await);
+        assert!(matches!(
+            strict.require(None, ConsentPurpose::MemoryPersonalization).await,
+            Err(ConsentError::NotGranted { status: ConsentStatus::Missing, .. })
+        ));
+
+        let open = gate(ConsentPolicy { allow_anonymous: true, ..ConsentPolicy::default() });
+        assert!(open.check(None, ConsentPurpose::TrafficCapture).await);
+    }
+
+    #[tokio::test]
+    async fn lapsed_consent_purges_unless_the_policy_keeps_data() {
+        let now = Utc::now();
+        let purging = gate(ConsentPolicy::default());
+        let purpose = ConsentPurpose::MemoryPersonalization;
+
+        assert!(purging.purges(purpose, &ConsentStatus::Expired { at: now }));
+        assert!(purging.purges(purpose, &ConsentStatus::Withdrawn { at: now }));
+        assert!(purging.purges(purpose, &ConsentStatus::Missing));
+        assert!(!purging.purges(purpose, &ConsentStatus::Granted { version: "1".to_string(), expires_at: None }));
+
+        let keeping = gate(ConsentPolicy { purge_on_withdrawal: false, ..ConsentPolicy::default() });
+        assert!(!keeping.purges(purpose, &ConsentStatus::Expired { at: now }));
+        assert_eq!(purpose.data_tables(), ["jean_memory", "user_ai_context"]);
+    }
+
+    #[tokio::test]
+    async fn grants_expire_after_the_policy_validity() {
+        let granted_at = Utc::now();
+        assert_eq!(gate(ConsentPolicy::default()).expiry_for("memory_personalization", granted_at), Some(granted_at + Duration::days(365)));
+        assert_eq!(gate(ConsentPolicy { validity_days: None, ..ConsentPolicy::default() }).expiry_for("memory_personalization", granted_at), None);
+    }
+}
@@ -0,0 +1,395 @@
+// Password hashing, keyed hashes, tokens and envelope encryption for stored secrets
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const ENVELOPE_VERSION: &str = "v1";
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+pub const DEFAULT_TOKEN_BYTES: usize = 32;
+/// Tokens shorter than 128 bits are refused.
+pub const MIN_TOKEN_BYTES: usize = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    #[error("Hashing failed: {0}")]
+    Hashing(String),
+
+    #[error("Invalid key material: {0}")]
+    InvalidKey(String),
+
+    #[error("Unknown encryption key: {0}")]
+    UnknownKey(String),
+
+    #[error("Malformed envelope: {0}")]
+    Malformed(String),
+
+    #[error("Encryption failed")]
+    Encryption,
+
+    #[error("Decryption failed")]
+    Decryption,
+}
+
+static GLOBAL_KEYS: OnceLock<CryptoKeys> = OnceLock::new();
+
+/// Key material for keyed hashes and secret encryption.
+///
+/// Encryption keys are key-encryption keys (KEKs) addressed by id. New
+/// envelopes are wrapped with the active key; older keys stay loaded so
+/// existing envelopes can be read and re-wrapped during rotation.
+pub struct CryptoKeys {
+    active_key_id: String,
+    encryption_keys: HashMap<String, [u8; KEY_LEN]>,
+    hmac_key: Vec<u8>,
+    pub token_bytes: usize,
+}
+
+impl CryptoKeys {
+    pub fn new(active_key_id: &str, encryption_keys: HashMap<String, [u8; KEY_LEN]>, hmac_key: Vec<u8>) -> Result<Self, CryptoError> {
+        if let Some(id) = encryption_keys.keys().find(|id| id.is_empty() || id.contains(':')) {
+            return Err(CryptoError::InvalidKey(format!("key id {:?} must be non-empty and contain no ':'", id)));
+        }
+        if !encryption_keys.contains_key(active_key_id) {
+            return Err(CryptoError::UnknownKey(active_key_id.to_string()));
+        }
+        if hmac_key.len() < MIN_TOKEN_BYTES {
+            return Err(CryptoError::InvalidKey("HMAC key must be at least 16 bytes".to_string()));
+        }
+        Ok(Self {
+            active_key_id: active_key_id.to_string(),
+            encryption_keys,
+            hmac_key,
+            token_bytes: DEFAULT_TOKEN_BYTES,
+        })
+    }
+
+    /// Reads keys from the environment:
+    /// - `SECRET_ENCRYPTION_KEYS`: comma-separated `id:base64` AES-256 keys
+    /// - `SECRET_ENCRYPTION_ACTIVE_KEY`: id used for new envelopes, defaults to the last key listed
+    /// - `HMAC_SECRET_KEY`: base64 key for keyed hashes
+    /// - `TOKEN_ENTROPY_BYTES`: random bytes per generated token
+    pub fn from_env() -> Result<Self, CryptoError> {
+        let raw_keys = std::env::var("SECRET_ENCRYPTION_KEYS")
+            .map_err(|_| CryptoError::InvalidKey("SECRET_ENCRYPTION_KEYS is not set".to_string()))?;
+        let mut encryption_keys = HashMap::new();
+        let mut last_id = None;
+        for entry in raw_keys.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, encoded) = entry
+                .split_once(':')
+                .ok_or_else(|| CryptoError::InvalidKey(format!("expected id:base64, got {}", entry.split(':').next().unwrap_or_default())))?;
+            encryption_keys.insert(id.to_string(), decode_key(id, encoded)?);
+            last_id = Some(id.to_string());
+        }
+        let active_key_id = std::env::var("SECRET_ENCRYPTION_ACTIVE_KEY")
+            .ok()
+            .or(last_id)
+            .ok_or_else(|| CryptoError::InvalidKey("SECRET_ENCRYPTION_KEYS is empty".to_string()))?;
+
+        let hmac_key = std::env::var("HMAC_SECRET_KEY")
+            .map_err(|_| CryptoError::InvalidKey("HMAC_SECRET_KEY is not set".to_string()))
+            .and_then(|encoded| STANDARD.decode(encoded.trim()).map_err(|e| CryptoError::InvalidKey(format!("HMAC_SECRET_KEY: {}", e))))?;
+
+        let mut keys = Self::new(&active_key_id, encryption_keys, hmac_key)?;
+        if let Some(bytes) = std::env::var("TOKEN_ENTROPY_BYTES").ok().and_then(|v| v.parse().ok()) {
+            keys.token_bytes = usize::max(bytes, MIN_TOKEN_BYTES);
+        }
+        Ok(keys)
+    }
+
+    /// Random keys that only live as long as the process.
+    pub fn ephemeral() -> Self {
+        let mut encryption_keys = HashMap::new();
+        encryption_keys.insert("ephemeral".to_string(), random_key());
+        Self {
+            active_key_id: "ephemeral".to_string(),
+            encryption_keys,
+            hmac_key: random_key().to_vec(),
+            token_bytes: DEFAULT_TOKEN_BYTES,
+        }
+    }
+
+    /// Environment keys, or ephemeral ones in debug and test builds so local
+    /// runs work without configuration. Release builds refuse to start without keys.
+    fn load() -> Result<Self, CryptoError> {
+        Self::from_env().or_else(|e| {
+            if !cfg!(any(debug_assertions, test)) {
+                return Err(e);
+            }
+            tracing::warn!("{}; using ephemeral keys, encrypted secrets will not survive a restart", e);
+            Ok(Self::ephemeral())
+        })
+    }
+
+    /// Loads the process-wide keys. Called at startup so missing keys stop
+    /// the server instead of failing the first request that needs them.
+    pub fn init() -> Result<&'static CryptoKeys, CryptoError> {
+        if let Some(keys) = GLOBAL_KEYS.get() {
+            return Ok(keys);
+        }
+        let keys = Self::load()?;
+        Ok(GLOBAL_KEYS.get_or_init(|| keys))
+    }
+
+    /// Process-wide keys, loaded by `init` at startup.
+    pub fn global() -> &'static CryptoKeys {
+        GLOBAL_KEYS.get_or_init(|| Self::load().unwrap_or_else(|e| panic!("encryption keys are not configured: {}", e)))
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    /// Hex HMAC-SHA256 of `data`, for lookups that must not reveal the input.
+    pub fn keyed_hash(&self, data: &str) -> String {
+        format!("{:x}", self.mac(data).finalize().into_bytes())
+    }
+
+    /// Constant-time comparison against a stored `keyed_hash`.
+    pub fn verify_keyed_hash(&self, data: &str, expected_hex: &str) -> bool {
+        let Some(expected) = unhex(expected_hex) else {
+            return false;
+        };
+        self.mac(data).verify_slice(&expected).is_ok()
+    }
+
+    fn mac(&self, data: &str) -> HmacSha256 {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.hmac_key).expect("HMAC accepts keys of any length");
+        mac.update(data.as_bytes());
+        mac
+    }
+
+    /// Encrypts a secret under a fresh data key, wrapped with the active key.
+    ///
+    /// `context` is bound as associated data, so an envelope copied into a
+    /// different column or row fails to decrypt.
+    pub fn encrypt_secret(&self, plaintext: &str, context: &str) -> Result<String, CryptoError> {
+        let data_key = random_key();
+        let data_cipher = Aes256Gcm::new_from_slice(&data_key).map_err(|e| CryptoError::InvalidKey(e.to_string()))?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = data_cipher
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: context.as_bytes() })
+            .map_err(|_| CryptoError::Encryption)?;
+        let wrapped_key = self.wrap_key(&self.active_key_id, &data_key)?;
+
+        Ok(format!(
+            "{}:{}:{}:{}",
+            ENVELOPE_VERSION,
+            self.active_key_id,
+            STANDARD.encode(wrapped_key),
+            STANDARD.encode([nonce.as_slice(), &ciphertext].concat())
+        ))
+    }
+
+    pub fn decrypt_secret(&self, envelope: &str, context: &str) -> Result<String, CryptoError> {
+        let parsed = Envelope::parse(envelope)?;
+        let data_key = self.unwrap_key(parsed.key_id, &parsed.wrapped_key)?;
+        let data_cipher = Aes256Gcm::new_from_slice(&data_key).map_err(|e| CryptoError::InvalidKey(e.to_string()))?;
+        let (nonce, ciphertext) = split_nonce(&parsed.payload)?;
+        let plaintext = data_cipher
+            .decrypt(nonce, Payload { msg: ciphertext, aad: context.as_bytes() })
+            .map_err(|_| CryptoError::Decryption)?;
+        String::from_utf8(plaintext).map_err(|_| CryptoError::Decryption)
+    }
+
+    /// True for values produced by `encrypt_secret`, as opposed to legacy plaintext.
+    pub fn is_envelope(value: &str) -> bool {
+        Envelope::parse(value).is_ok()
+    }
+
+    /// True when the envelope was wrapped with a key other than the active one.
+    pub fn needs_rewrap(&self, envelope: &str) -> bool {
+        Envelope::parse(envelope).is_ok_and(|parsed| parsed.key_id != self.active_key_id)
+    }
+
+    /// Re-wraps the envelope's data key with the active key. The encrypted
+    /// secret itself is untouched, so rotation never handles plaintext.
+    pub fn rewrap_secret(&self, envelope: &str) -> Result<String, CryptoError> {
+        let parsed = Envelope::parse(envelope)?;
+        if parsed.key_id == self.active_key_id {
+            return Ok(envelope.to_string());
+        }
+        let data_key = self.unwrap_key(parsed.key_id, &parsed.wrapped_key)?;
+        let wrapped_key = self.wrap_key(&self.active_key_id, &data_key)?;
+        Ok(format!(
+            "{}:{}:{}:{}",
+            ENVELOPE_VERSION,
+            self.active_key_id,
+            STANDARD.encode(wrapped_key),
+            STANDARD.encode(&parsed.payload)
+        ))
+    }
+
+    fn wrap_key(&self, key_id: &str, data_key: &[u8; KEY_LEN]) -> Result<Vec<u8>, CryptoError> {
+        let cipher = self.key_cipher(key_id)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let wrapped = cipher
+            .encrypt(&nonce, Payload { msg: data_key, aad: key_id.as_bytes() })
+            .map_err(|_| CryptoError::Encryption)?;
+        Ok([nonce.as_slice(), &wrapped].concat())
+    }
+
+    fn unwrap_key(&self, key_id: &str, wrapped: &[u8]) -> Result<[u8; KEY_LEN], CryptoError> {
+        let cipher = self.key_cipher(key_id)?;
+        let (nonce, wrapped) = split_nonce(wrapped)?;
+        let data_key = cipher
+            .decrypt(nonce, Payload { msg: wrapped, aad: key_id.as_bytes() })
+            .map_err(|_| CryptoError::Decryption)?;
+        data_key.try_into().map_err(|_| CryptoError::Decryption)
+    }
+
+    fn key_cipher(&self, key_id: &str) -> Result<Aes256Gcm, CryptoError> {
+        let key = self.encryption_keys.get(key_id).ok_or_else(|| CryptoError::UnknownKey(key_id.to_string()))?;
+        Aes256Gcm::new_from_slice(key).map_err(|e| CryptoError::InvalidKey(e.to_string()))
+    }
+}
+
+/// `v1:<key id>:<base64 wrapped data key>:<base64 nonce + ciphertext>`
+struct Envelope<'a> {
+    key_id: &'a str,
+    wrapped_key: Vec<u8>,
+    payload: Vec<u8>,
+}
+
+impl<'a> Envelope<'a> {
+    fn parse(envelope: &'a str) -> Result<Self, CryptoError> {
+        let mut parts = envelope.splitn(4, ':');
+        let (Some(version), Some(key_id), Some(wrapped_key), Some(payload)) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(CryptoError::Malformed("expected four fields".to_string()));
+        };
+        if version != ENVELOPE_VERSION {
+            return Err(CryptoError::Malformed(format!("unsupported version {}", version)));
+        }
+        let decode = |field: &str| STANDARD.decode(field).map_err(|e| CryptoError::Malformed(e.to_string()));
+        Ok(Self {
+            key_id,
+            wrapped_key: decode(wrapped_key)?,
+            payload: decode(payload)?,
+        })
+    }
+}
+
+/// Argon2id hash in PHC string format, salted per call.
+pub fn hash_secret(secret: &str) -> Result<String, CryptoError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(secret.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| CryptoError::Hashing(e.to_string()))
+}
+
+pub fn verify_secret(secret: &str, phc_hash: &str) -> bool {
+    PasswordHash::new(phc_hash).is_ok_and(|parsed| Argon2::default().verify_password(secret.as_bytes(), &parsed).is_ok())
+}
+
+/// URL-safe token drawn from the OS CSPRNG, with at least 128 bits of entropy.
+pub fn generate_token(entropy_bytes: usize) -> String {
+    let mut bytes = vec![0u8; entropy_bytes.max(MIN_TOKEN_BYTES)];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn random_key() -> [u8; KEY_LEN] {
+    let mut key = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+fn decode_key(id: &str, encoded: &str) -> Result<[u8; KEY_LEN], CryptoError> {
+    STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| CryptoError::InvalidKey(format!("{}: {}", id, e)))?
+        .try_into()
+        .map_err(|_| CryptoError::InvalidKey(format!("{}: expected a 32-byte key", id)))
+}
+
+fn split_nonce(bytes: &[u8]) -> Result<(&Nonce<aes_gcm::aead::consts::U12>, &[u8]), CryptoError> {
+    if bytes.len() <= NONCE_LEN {
+        return Err(CryptoError::Malformed("ciphertext too short".to_string()));
+    }
+    let (nonce, rest) = bytes.split_at(NONCE_LEN);
+    Ok((Nonce::from_slice(nonce), rest))
+}
+
+fn unhex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len()).step_by(2).map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys_with(active: &str, ids: &[(&str, [u8; KEY_LEN])]) -> CryptoKeys {
+        let encryption_keys = ids.iter().map(|(id, key)| (id.to_string(), *key)).collect();
+        CryptoKeys::new(active, encryption_keys, vec![7u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn encrypted_secrets_round_trip() {
+        let keys = keys_with("k1", &[("k1", random_key())]);
+        let envelope = keys.encrypt_secret("sk-live-123", "ai_models:1").unwrap();
+
+        assert!(CryptoKeys::is_envelope(&envelope));
+        assert!(!envelope.contains("sk-live-123"));
+        assert_eq!(keys.decrypt_secret(&envelope, "ai_models:1").unwrap(), "sk-live-123");
+    }
+
+    #[test]
+    fn envelopes_do_not_decrypt_under_another_context() {
+        let keys = keys_with("k1", &[("k1", random_key())]);
+        let envelope = keys.encrypt_secret("sk-live-123", "ai_models:1").unwrap();
+
+        assert!(matches!(keys.decrypt_secret(&envelope, "ai_models:2"), Err(CryptoError::Decryption)));
+    }
+
+    #[test]
+    fn rewrap_moves_envelopes_to_the_active_key() {
+        let old_key = random_key();
+        let before = keys_with("old", &[("old", old_key)]);
+        let envelope = before.encrypt_secret("secret", "ctx").unwrap();
+
+        let after = keys_with("new", &[("old", old_key), ("new", random_key())]);
+        assert!(after.needs_rewrap(&envelope));
+        let rewrapped = after.rewrap_secret(&envelope).unwrap();
+
+        assert!(!after.needs_rewrap(&rewrapped));
+        assert!(rewrapped.starts_with("v1:new:"));
+        assert_eq!(after.decrypt_secret(&rewrapped, "ctx").unwrap(), "secret");
+        assert_eq!(after.rewrap_secret(&rewrapped).unwrap(), rewrapped);
+    }
+
+    #[test]
+    fn envelopes_under_unknown_keys_are_refused() {
+        let envelope = keys_with("gone", &[("gone", random_key())]).encrypt_secret("secret", "ctx").unwrap();
+        let keys = keys_with("k1", &[("k1", random_key())]);
+
+        assert!(matches!(keys.decrypt_secret(&envelope, "ctx"), Err(CryptoError::UnknownKey(id)) if id == "gone"));
+        assert!(matches!(keys.rewrap_secret(&envelope), Err(CryptoError::UnknownKey(_))));
+    }
+
+    #[test]
+    fn active_key_must_be_loaded() {
+        let encryption_keys = HashMap::from([("k1".to_string(), random_key())]);
+        assert!(matches!(CryptoKeys::new("k2", encryption_keys, vec![7u8; 32]), Err(CryptoError::UnknownKey(_))));
+    }
+}
@@ -0,0 +1,462 @@
+// Security dashboard aggregates over the audit store, plus a live feed of high-risk events
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{sse::{Event, KeepAlive}, Sse},
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+use super::{db_error, require_admin, AuditLog, RiskAssessment, SecurityState, UserContext};
+
+/// Audit actions counted as permission grants on the dashboard.
+pub const PERMISSION_GRANT_ACTIONS: &[&str] = &[
+    "consent.granted",
+    "api_key.created",
+    "api_key.scopes_updated",
+    "plugin.permission_granted",
+];
+
+/// Audit actions counted as permission revocations on the dashboard.
+pub const PERMISSION_REVOKE_ACTIONS: &[&str] = &[
+    "consent.withdrawn",
+    "api_key.revoked",
+    "plugin.permission_denied",
+];
+
+const DEFAULT_WINDOW_HOURS: i64 = 24;
+const MAX_WINDOW_HOURS: i64 = 24 * 90;
+const ALERT_FEED_CAPACITY: usize = 256;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DashboardQuery {
+    /// How far back to aggregate, in hours.
+    pub window_hours: Option<i64>,
+    /// `hour` or `day`; defaults to hourly for windows up to two days.
+    pub bucket: Option<String>,
+    /// Rows in the top users and top IPs lists.
+    pub top: Option<i64>,
+}
+
+impl DashboardQuery {
+    /// Validated window length in hours, bucket unit and list size.
+    fn resolve(&self) -> Result<(i64, &'static str, i64), StatusCode> {
+        let window_hours = self.window_hours.unwrap_or(DEFAULT_WINDOW_HOURS);
+        if !(1..=MAX_WINDOW_HOURS).contains(&window_hours) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let bucket = match self.bucket.as_deref() {
+            Some("hour") => "hour",
+            Some("day") => "day",
+            Some(_) => return Err(StatusCode::BAD_REQUEST),
+            None if window_hours <= 48 => "hour",
+            None => "day",
+        };
+        Ok((window_hours, bucket, self.top.unwrap_or(10).clamp(1, 100)))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CountBy {
+    pub key: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RiskySubject {
+    /// User id or IP address.
+    pub subject: String,
+    pub events: i64,
+    pub high_risk_events: i64,
+    pub max_risk_score: i32,
+    pub avg_risk_score: f64,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrendPoint {
+    pub bucket: DateTime<Utc>,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PermissionTrendPoint {
+    pub bucket: DateTime<Utc>,
+    pub grants: i64,
+    pub revocations: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecurityDashboard {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub bucket: String,
+    pub high_risk_threshold: i32,
+    pub total_events: i64,
+    pub high_risk_events: i64,
+    pub distinct_users: i64,
+    pub distinct_ips: i64,
+    pub by_status: Vec<CountBy>,
+    pub by_resource_type: Vec<CountBy>,
+    pub top_risky_users: Vec<RiskySubject>,
+    pub top_risky_ips: Vec<RiskySubject>,
+    pub auth_failures: Vec<TrendPoint>,
+    pub permission_changes: Vec<PermissionTrendPoint>,
+}
+
+/// One high-risk request, as pushed to dashboard subscribers.
+#[derive(Debug, Clone, Serialize)]
+pub struct SecurityAlert {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub action: String,
+    pub resource_type: Option<String>,
+    pub ip_address: Option<String>,
+    pub risk_score: i32,
+    pub status: String,
+    pub reasons: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SecurityAlert {
+    pub fn from_audit(log: &AuditLog, assessment: &RiskAssessment) -> Self {
+        Self {
+            id: log.id,
+            user_id: log.user_id,
+            action: log.action.clone(),
+            resource_type: log.resource_type.clone(),
+            ip_address: log.ip_address.map(|ip| ip.to_string()),
+            risk_score: log.risk_score,
+            status: log.status.clone(),
+            reasons: assessment.fired.iter().map(|fired| fired.reason.clone()).collect(),
+            created_at: log.created_at,
+        }
+    }
+}
+
+/// In-process fan-out of high-risk events to connected dashboards.
+#[derive(Clone)]
+pub struct AlertFeed {
+    sender: broadcast::Sender<SecurityAlert>,
+}
+
+impl AlertFeed {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(ALERT_FEED_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn publish(&self, alert: SecurityAlert) {
+        // No subscribers is the normal case when no dashboard is open
+        let _ = self.sender.send(alert);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SecurityAlert> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for AlertFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub async fn get_security_dashboard(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+    Query(params): Query<DashboardQuery>,
+) -> Result<Json<SecurityDashboard>, StatusCode> {
+    require_admin(user)?;
+    let (window_hours, bucket, top) = params.resolve()?;
+    let threshold = state.risk.step_up_threshold();
+    let window_end = Utc::now();
+    let window_start = window_end - Duration::hours(window_hours);
+
+    let totals = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "total!",
+               COUNT(*) FILTER (WHERE risk_score >= $2) AS "high_risk!",
+               COUNT(DISTINCT user_id) AS "users!",
+               COUNT(DISTINCT ip_address) AS "ips!"
+        FROM audit_logs
+        WHERE created_at >= $1
+        "#,
+        window_start,
+        threshold
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let by_status = sqlx::query_as!(
+        CountBy,
+        r#"
+        SELECT status AS "key!", COUNT(*) AS "count!"
+        FROM audit_logs
+        WHERE created_at >= $1
+        GROUP BY status
+        ORDER BY 2 DESC
+        "#,
+        window_start
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let by_resource_type = sqlx::query_as!(
+        CountBy,
+        r#"
+        SELECT COALESCE(resource_type, 'other') AS "key!", COUNT(*) AS "count!"
+        FROM audit_logs
+        WHERE created_at >= $1
+        GROUP BY 1
+        ORDER BY 2 DESC
+        "#,
+        window_start
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let top_risky_users = sqlx::query_as!(
+        RiskySubject,
+        r#"
+        SELECT user_id::text AS "subject!",
+               COUNT(*) AS "events!",
+               COUNT(*) FILTER (WHERE risk_score >= $2) AS "high_risk_events!",
+               MAX(risk_score) AS "max_risk_score!",
+               AVG(risk_score)::float8 AS "avg_risk_score!",
+               MAX(created_at) AS "last_seen!"
+        FROM audit_logs
+        WHERE created_at >= $1 AND user_id IS NOT NULL
+        GROUP BY user_id
+        HAVING MAX(risk_score) > 0
+        ORDER BY 3 DESC, 4 DESC, 2 DESC
+        LIMIT $3
+        "#,
+        window_start,
+        threshold,
+        top
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let top_risky_ips = sqlx::query_as!(
+        RiskySubject,
+        r#"
+        SELECT host(ip_address) AS "subject!",
+               COUNT(*) AS "events!",
+               COUNT(*) FILTER (WHERE risk_score >= $2) AS "high_risk_events!",
+               MAX(risk_score) AS "max_risk_score!",
+               AVG(risk_score)::float8 AS "avg_risk_score!",
+               MAX(created_at) AS "last_seen!"
+        FROM audit_logs
+        WHERE created_at >= $1 AND ip_address IS NOT NULL
+        GROUP BY ip_address
+        HAVING MAX(risk_score) > 0
+        ORDER BY 3 DESC, 4 DESC, 2 DESC
+        LIMIT $3
+        "#,
+        window_start,
+        threshold,
+        top
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    // Request entries carry the response code in details.status
+    let auth_failures = sqlx::query_as!(
+        TrendPoint,
+        r#"
+        SELECT date_trunc($2, created_at) AS "bucket!", COUNT(*) AS "count!"
+        FROM audit_logs
+        WHERE created_at >= $1 AND details->>'status' IN ('401', '403')
+        GROUP BY 1
+        ORDER BY 1
+        "#,
+        window_start,
+        bucket
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let grant_actions: Vec<String> = PERMISSION_GRANT_ACTIONS.iter().map(|a| a.to_string()).collect();
+    let revoke_actions: Vec<String> = PERMISSION_REVOKE_ACTIONS.iter().map(|a| a.to_string()).collect();
+    let permission_changes = sqlx::query_as!(
+        PermissionTrendPoint,
+        r#"
+        SELECT date_trunc($2, created_at) AS "bucket!",
+               COUNT(*) FILTER (WHERE action = ANY($3)) AS "grants!",
+               COUNT(*) FILTER (WHERE action = ANY($4)) AS "revocations!"
+        FROM audit_logs
+        WHERE created_at >= $1 AND (action = ANY($3) OR action = ANY($4))
+        GROUP BY 1
+        ORDER BY 1
+        "#,
+        window_start,
+        bucket,
+        &grant_actions,
+        &revoke_actions
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(SecurityDashboard {
+        window_start,
+        window_end,
+        bucket: bucket.to_string(),
+        high_risk_threshold: threshold,
+        total_events: totals.total,
+        high_risk_events: totals.high_risk,
+        distinct_users: totals.users,
+        distinct_ips: totals.ips,
+        by_status,
+        by_resource_type,
+        top_risky_users,
+        top_risky_ips,
+        auth_failures: fill_trend(auth_failures, window_start, window_end, bucket),
+        permission_changes: fill_permission_trend(permission_changes, window_start, window_end, bucket),
+    }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AlertStreamQuery {
+    /// Only forward alerts at or above this score.
+    pub min_risk_score: Option<i32>,
+}
+
+/// Server-sent events feed of high-risk requests as they are audited.
+pub async fn stream_security_alerts(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+    Query(params): Query<AlertStreamQuery>,
+) -> Result<Sse<ReceiverStream<Result<Event, Infallible>>>, StatusCode> {
+    require_admin(user)?;
+    let min_risk_score = params.min_risk_score.unwrap_or(0);
+    let mut alerts = state.alerts.subscribe();
+    let (tx, rx) = mpsc::channel(32);
+
+    tokio::spawn(async move {
+        loop {
+            let event = match alerts.recv().await {
+                Ok(alert) if alert.risk_score < min_risk_score => continue,
+                Ok(alert) => Event::default()
+                    .event("alert")
+                    .id(alert.id.to_string())
+                    .json_data(&alert)
+                    .unwrap_or_default(),
+                // Tell the client it missed events rather than silently dropping them
+                Err(broadcast::error::RecvError::Lagged(skipped)) => Event::default().event("lagged").data(skipped.to_string()),
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            if tx.send(Ok(event)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default()))
+}
+
+/// Start of every bucket in the window, so quiet periods chart as zero.
+fn bucket_starts(window_start: DateTime<Utc>, window_end: DateTime<Utc>, bucket: &str) -> Vec<DateTime<Utc>> {
+    let (step, truncated) = match bucket {
+        "day" => (Duration::days(1), window_start.duration_trunc(Duration::days(1))),
+        _ => (Duration::hours(1), window_start.duration_trunc(Duration::hours(1))),
+    };
+    let mut starts = Vec::new();
+    let mut current = truncated.unwrap_or(window_start);
+    while current <= window_end {
+        starts.push(current);
+        current += step;
+    }
+    starts
+}
+
+fn fill_trend(points: Vec<TrendPoint>, window_start: DateTime<Utc>, window_end: DateTime<Utc>, bucket: &str) -> Vec<TrendPoint> {
+    let counts: HashMap<DateTime<Utc>, i64> = points.into_iter().map(|p| (p.bucket, p.count)).collect();
+    bucket_starts(window_start, window_end, bucket)
+        .into_iter()
+        .map(|bucket| TrendPoint { bucket, count: counts.get(&bucket).copied().unwrap_or(0) })
+        .collect()
+}
+
+fn fill_permission_trend(
+    points: Vec<PermissionTrendPoint>,
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    bucket: &str,
+) -> Vec<PermissionTrendPoint> {
+    let counts: HashMap<DateTime<Utc>, (i64, i64)> = points.into_iter().map(|p| (p.bucket, (p.grants, p.revocations))).collect();
+    bucket_starts(window_start, window_end, bucket)
+        .into_iter()
+        .map(|bucket| {
+            let (grants, revocations) = counts.get(&bucket).copied().unwrap_or((0, 0));
+            PermissionTrendPoint { bucket, grants, revocations }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, TimeZone, Timelike};
+
+    fn query(window_hours: Option<i64>, bucket: Option<&str>, top: Option<i64>) -> DashboardQuery {
+        DashboardQuery { window_hours, bucket: bucket.map(str::to_string), top }
+    }
+
+    #[test]
+    fn query_defaults_to_hourly_buckets_for_short_windows() {
+        assert_eq!(query(None, None, None).resolve().unwrap(), (24, "hour", 10));
+        assert_eq!(query(Some(72), None, Some(500)).resolve().unwrap(), (72, "day", 100));
+        assert_eq!(query(Some(72), Some("hour"), Some(0)).resolve().unwrap(), (72, "hour", 1));
+    }
+
+    #[test]
+    fn query_rejects_out_of_range_windows_and_unknown_buckets() {
+        assert_eq!(query(Some(0), None, None).resolve(), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(query(Some(MAX_WINDOW_HOURS + 1), None, None).resolve(), Err(StatusCode::BAD_REQUEST));
+        assert_eq!(query(None, Some("week"), None).resolve(), Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn trends_cover_every_bucket_in_the_window() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 1, 10, 30, 0).unwrap();
+        let end = start + Duration::hours(3);
+        let busy = Utc.with_ymd_and_hms(2026, 1, 1, 11, 0, 0).unwrap();
+
+        let filled = fill_trend(vec![TrendPoint { bucket: busy, count: 4 }], start, end, "hour");
+        let counts: Vec<(u32, i64)> = filled.iter().map(|p| (p.bucket.hour(), p.count)).collect();
+        assert_eq!(counts, [(10, 0), (11, 4), (12, 0), (13, 0)]);
+    }
+
+    #[test]
+    fn permission_trends_keep_grants_and_revocations_per_bucket() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 1, 10, 0, 0).unwrap();
+        let end = start + Duration::days(2);
+        let day = Utc.with_ymd_and_hms(2026, 1, 2, 0, 0, 0).unwrap();
+
+        let filled = fill_permission_trend(vec![PermissionTrendPoint { bucket: day, grants: 2, revocations: 1 }], start, end, "day");
+        let counts: Vec<(u32, i64, i64)> = filled.iter().map(|p| (p.bucket.day(), p.grants, p.revocations)).collect();
+        assert_eq!(counts, [(1, 0, 0), (2, 2, 1), (3, 0, 0)]);
+    }
+
+    #[test]
+    fn plugin_permission_decisions_are_counted() {
+        assert!(PERMISSION_GRANT_ACTIONS.contains(&"plugin.permission_granted"));
+        assert!(PERMISSION_REVOKE_ACTIONS.contains(&"plugin.permission_denied"));
+        assert!(PERMISSION_GRANT_ACTIONS.iter().all(|action| !PERMISSION_REVOKE_ACTIONS.contains(action)));
+    }
+}
@@ -0,0 +1,678 @@
+// GDPR data subject requests: export everything tied to a user, or erase it
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Acquire, PgPool, Postgres, Transaction};
+use std::io::Write;
+use uuid::Uuid;
+
+use super::{db_error, record_audit_event, require_self, ClientInfo, SecurityState, UserContext};
+
+const DSAR_RESOURCE: &str = "dsar_request";
+/// Export archives can be downloaded for this long.
+const ARCHIVE_TTL_DAYS: i64 = 7;
+
+/// What erasure does to a category's rows.
+#[derive(Debug, Clone, Copy)]
+pub enum ErasureAction {
+    Delete,
+    /// Keeps the rows but overwrites personal columns with this `SET` clause.
+    Pseudonymize { set: &'static str, reason: &'static str },
+    Retain { reason: &'static str },
+}
+
+/// One table holding personal data, keyed by a user column.
+#[derive(Debug, Clone, Copy)]
+pub struct DataCategory {
+    pub name: &'static str,
+    pub table: &'static str,
+    pub user_column: &'static str,
+    /// Columns left out of exports, such as credential hashes.
+    pub exclude_columns: &'static [&'static str],
+    pub erasure: ErasureAction,
+}
+
+/// Every module's user data, in erasure order: dependents first, the profile last.
+pub const DATA_CATEGORIES: &[DataCategory] = &[
+    DataCategory { name: "memories", table: "jean_memory", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "chat_history", table: "ai_chat_history", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "ai_conversations", table: "ai_conversations", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "ai_context", table: "user_ai_context", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "ai_preferences", table: "ai_user_preferences", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "ai_generated_content", table: "ai_generated_content", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "assistant_sessions", table: "jean_sessions", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "assistant_actions", table: "jean_actions_log", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "assistant_permissions", table: "jean_permissions", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "assistant_preferences", table: "jean_preferences", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "integration_logs", table: "jean_integration_logs", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "scheduled_tasks", table: "jean_scheduled_tasks", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "preferences", table: "user_preferences", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "workspaces", table: "workspaces", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "backlog_items", table: "backlog_items", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "loyalty_ledger", table: "loyalty_ledger", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "loyalty_rewards", table: "user_rewards", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory {
+        name: "orders",
+        table: "orders",
+        user_column: "customer_id",
+        exclude_columns: &["internal_notes"],
+        erasure: ErasureAction::Pseudonymize {
+            set: "shipping_address = '{}'::jsonb, billing_address = NULL, customer_email = 'erased@invalid', customer_phone = NULL, notes = NULL",
+            reason: "order totals are kept for accounting",
+        },
+    },
+    DataCategory { name: "addresses", table: "customer_addresses", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "shopping_cart", table: "shopping_cart", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory {
+        name: "affiliate_account",
+        table: "affiliates",
+        user_column: "user_id",
+        exclude_columns: &[],
+        erasure: ErasureAction::Retain { reason: "commission records are kept for accounting" },
+    },
+    DataCategory { name: "plugin_settings", table: "user_plugin_settings", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "plugin_permission_requests", table: "plugin_permission_requests", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "plugin_notifications", table: "plugin_notifications", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "plugin_storage", table: "plugin_storage", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "video_projects", table: "video_projects", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "local_hub_participation", table: "local_hub_participants", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "captured_api_traffic", table: "api_discovery_logs", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory { name: "captured_stream_messages", table: "api_stream_messages", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory {
+        name: "delivery_driver_profile",
+        table: "delivery_drivers",
+        user_column: "user_id",
+        exclude_columns: &[],
+        erasure: ErasureAction::Pseudonymize {
+            set: "name = 'Erased driver', phone = NULL, email = NULL, license_number = NULL, vehicle_plate = NULL, current_location = NULL, is_active = FALSE, user_id = NULL",
+            reason: "delivery history is kept for operations",
+        },
+    },
+    DataCategory { name: "api_keys", table: "api_keys", user_column: "user_id", exclude_columns: &["key_hash", "lookup_hash"], erasure: ErasureAction::Delete },
+    DataCategory { name: "two_factor", table: "user_totp", user_column: "user_id", exclude_columns: &["secret_encrypted"], erasure: ErasureAction::Delete },
+    DataCategory { name: "backup_codes", table: "user_backup_codes", user_column: "user_id", exclude_columns: &["code_hash"], erasure: ErasureAction::Delete },
+    DataCategory { name: "elevated_sessions", table: "elevated_sessions", user_column: "user_id", exclude_columns: &["token_hash"], erasure: ErasureAction::Delete },
+    DataCategory {
+        name: "data_requests",
+        table: "dsar_requests",
+        user_column: "user_id",
+        exclude_columns: &["archive"],
+        erasure: ErasureAction::Pseudonymize {
+            set: "archive = NULL, archive_expires_at = NULL",
+            reason: "request history is kept as proof the request was handled; export archives are dropped",
+        },
+    },
+    DataCategory { name: "privacy_settings", table: "privacy_settings", user_column: "user_id", exclude_columns: &[], erasure: ErasureAction::Delete },
+    DataCategory {
+        name: "consents",
+        table: "consent_records",
+        user_column: "user_id",
+        exclude_columns: &[],
+        erasure: ErasureAction::Pseudonymize {
+            set: "ip_address = NULL, user_agent = NULL",
+            reason: "consent decisions are kept as proof of lawful processing",
+        },
+    },
+    DataCategory {
+        name: "audit_logs",
+        table: "audit_logs",
+        user_column: "user_id",
+        exclude_columns: &[],
+        erasure: ErasureAction::Retain { reason: "tamper-evident security log, removed by audit retention" },
+    },
+    DataCategory {
+        name: "profile",
+        table: "users",
+        user_column: "id",
+        exclude_columns: &["password_hash"],
+        erasure: ErasureAction::Pseudonymize {
+            set: "username = 'erased-' || left(md5(id::text), 12), email = 'erased-' || id || '@invalid', password_hash = '', \
+                  first_name = NULL, last_name = NULL, avatar_url = NULL, is_active = FALSE, last_login = NULL",
+            reason: "the account row anchors retained orders and audit entries",
+        },
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DsarKind {
+    Export,
+    Erasure,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DsarRequest {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub status: String, // pending, running, completed, failed
+    pub report: Option<Value>,
+    pub error: Option<String>,
+    pub archive_expires_at: Option<DateTime<Utc>>,
+    pub requested_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateErasureRequest {
+    /// Must be true; erasure cannot be undone.
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryOutcomeKind {
+    Exported,
+    Deleted,
+    Pseudonymized,
+    Retained,
+    /// The module's table does not exist in this deployment.
+    NotPresent,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryOutcome {
+    pub category: String,
+    pub table: String,
+    pub outcome: CategoryOutcomeKind,
+    pub rows: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Stored on the request once the job finishes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DsarReport {
+    pub user_id: Uuid,
+    pub kind: DsarKind,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    pub succeeded: bool,
+    pub categories: Vec<CategoryOutcome>,
+}
+
+pub async fn request_data_export(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Json<DsarRequest>, StatusCode> {
+    require_self(user, user_id)?;
+    let request = create_request(&state.db, user_id, DsarKind::Export).await?;
+    record_audit_event(&state.db, Some(user_id), "dsar.export_requested", DSAR_RESOURCE, &request.id.to_string(), Value::Null, Some(&client)).await;
+
+    let db = state.db.clone();
+    tokio::spawn(async move { run_export(db, request.id, user_id).await });
+    Ok(Json(request))
+}
+
+pub async fn request_data_erasure(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(body): Json<CreateErasureRequest>,
+) -> Result<Json<DsarRequest>, StatusCode> {
+    require_self(user, user_id)?;
+    if !body.confirm {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let request = create_request(&state.db, user_id, DsarKind::Erasure).await?;
+    record_audit_event(&state.db, Some(user_id), "dsar.erasure_requested", DSAR_RESOURCE, &request.id.to_string(), Value::Null, Some(&client)).await;
+
+    let db = state.db.clone();
+    tokio::spawn(async move { run_erasure(db, request.id, user_id).await });
+    Ok(Json(request))
+}
+
+pub async fn list_dsar_requests(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<Vec<DsarRequest>>, StatusCode> {
+    require_self(user, user_id)?;
+    let requests = sqlx::query_as!(
+        DsarRequest,
+        r#"
+        SELECT id, user_id, kind, status, report, error, archive_expires_at, requested_at, completed_at
+        FROM dsar_requests
+        WHERE user_id = $1
+        ORDER BY requested_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(requests))
+}
+
+pub async fn get_dsar_request(
+    State(state): State<SecurityState>,
+    Path((user_id, request_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<DsarRequest>, StatusCode> {
+    require_self(user, user_id)?;
+    sqlx::query_as!(
+        DsarRequest,
+        r#"
+        SELECT id, user_id, kind, status, report, error, archive_expires_at, requested_at, completed_at
+        FROM dsar_requests
+        WHERE id = $1 AND user_id = $2
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .map(Json)
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Streams a finished export as a zip archive.
+pub async fn download_data_export(
+    State(state): State<SecurityState>,
+    Path((user_id, request_id)): Path<(Uuid, Uuid)>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Response, StatusCode> {
+    require_self(user, user_id)?;
+    let row = sqlx::query!(
+        r#"
+        SELECT status, archive, archive_expires_at
+        FROM dsar_requests
+        WHERE id = $1 AND user_id = $2 AND kind = 'export'
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if row.status != "completed" {
+        return Err(StatusCode::CONFLICT);
+    }
+    let (Some(archive), Some(expires_at)) = (row.archive, row.archive_expires_at) else {
+        return Err(StatusCode::GONE);
+    };
+    if expires_at <= Utc::now() {
+        return Err(StatusCode::GONE);
+    }
+
+    record_audit_event(&state.db, Some(user_id), "dsar.export_downloaded", DSAR_RESOURCE, &request_id.to_string(), Value::Null, Some(&client)).await;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"jeantrail-data-{}.zip\"", user_id)),
+        ],
+        archive,
+    )
+        .into_response())
+}
+
+/// Drops archives whose download window has passed.
+pub async fn purge_expired_exports(db: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("UPDATE dsar_requests SET archive = NULL WHERE archive IS NOT NULL AND archive_expires_at <= NOW()")
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+async fn create_request(db: &PgPool, user_id: Uuid, kind: DsarKind) -> Result<DsarRequest, StatusCode> {
+    // One open request of each kind per user
+    let open = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM dsar_requests WHERE user_id = $1 AND kind = $2 AND status IN ('pending', 'running'))",
+        user_id,
+        kind as DsarKind
+    )
+    .fetch_one(db)
+    .await
+    .map_err(db_error)?;
+    if open.unwrap_or(false) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    sqlx::query_as!(
+        DsarRequest,
+        r#"
+        INSERT INTO dsar_requests (user_id, kind, status)
+        VALUES ($1, $2, 'pending')
+        RETURNING id, user_id, kind, status, report, error, archive_expires_at, requested_at, completed_at
+        "#,
+        user_id,
+        kind as DsarKind
+    )
+    .fetch_one(db)
+    .await
+    .map_err(db_error)
+}
+
+async fn run_export(db: PgPool, request_id: Uuid, user_id: Uuid) {
+    let started_at = Utc::now();
+    if let Err(e) = mark_running(&db, request_id).await {
+        tracing::error!("Failed to start data export {}: {}", request_id, e);
+        return;
+    }
+
+    let mut categories = Vec::new();
+    let mut files = Vec::new();
+    for category in DATA_CATEGORIES {
+        let outcome = match export_category(&db, category, user_id).await {
+            Ok(Some(rows)) => {
+                let count = rows.as_array().map_or(0, |r| r.len() as u64);
+                files.push((format!("data/{}.json", category.name), rows));
+                outcome(category, CategoryOutcomeKind::Exported, count, None)
+            }
+            Ok(None) => outcome(category, CategoryOutcomeKind::NotPresent, 0, None),
+            Err(e) => {
+                tracing::error!("Data export {} failed on {}: {}", request_id, category.table, e);
+                outcome(category, CategoryOutcomeKind::Failed, 0, Some(e.to_string()))
+            }
+        };
+        categories.push(outcome);
+    }
+
+    let report = DsarReport {
+        user_id,
+        kind: DsarKind::Export,
+        started_at,
+        completed_at: Utc::now(),
+        succeeded: categories.iter().all(|c| c.outcome != CategoryOutcomeKind::Failed),
+        categories,
+    };
+
+    let result = match build_archive(&report, &files) {
+        Ok(archive) => {
+            let report_json = serde_json::to_value(&report).unwrap_or(Value::Null);
+            sqlx::query!(
+                r#"
+                UPDATE dsar_requests d
+                SET status = $2, report = $3, completed_at = NOW(),
+                    -- An erasure requested meanwhile wins; this archive would hold the erased data
+                    archive = CASE WHEN erased.at IS NULL THEN $4 END,
+                    archive_expires_at = CASE WHEN erased.at IS NULL THEN $5 END
+                FROM (
+                    SELECT MAX(e.requested_at) AS at
+                    FROM dsar_requests e JOIN dsar_requests x ON x.id = $1
+                    WHERE e.user_id = x.user_id AND e.kind = 'erasure' AND e.requested_at > x.requested_at
+                ) erased
+                WHERE d.id = $1
+                "#,
+                request_id,
+                if report.succeeded { "completed" } else { "failed" },
+                report_json,
+                archive,
+                Utc::now() + Duration::days(ARCHIVE_TTL_DAYS)
+            )
+            .execute(&db)
+            .await
+            .map(|_| ())
+        }
+        Err(e) => mark_failed(&db, request_id, &format!("archive: {}", e)).await,
+    };
+    if let Err(e) = result {
+        tracing::error!("Failed to store data export {}: {}", request_id, e);
+    }
+
+    record_audit_event(&db, Some(user_id), "dsar.export_completed", DSAR_RESOURCE, &request_id.to_string(), serde_json::json!({
+        "succeeded": report.succeeded,
+        "categories": report.categories.len()
+    }), None).await;
+}
+
+async fn run_erasure(db: PgPool, request_id: Uuid, user_id: Uuid) {
+    let started_at = Utc::now();
+    if let Err(e) = mark_running(&db, request_id).await {
+        tracing::error!("Failed to start data erasure {}: {}", request_id, e);
+        return;
+    }
+
+    let result: Result<Vec<CategoryOutcome>, sqlx::Error> = async {
+        let mut tx = db.begin().await?;
+        let mut categories = Vec::new();
+        for category in DATA_CATEGORIES {
+            // Each category runs in a savepoint so one failure does not undo the rest
+            let mut savepoint = tx.begin().await?;
+            let outcome = match erase_category(&mut savepoint, category, user_id).await {
+                Ok(outcome) => {
+                    savepoint.commit().await?;
+                    outcome
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    tracing::error!("Data erasure {} failed on {}: {}", request_id, category.table, e);
+                    outcome(category, CategoryOutcomeKind::Failed, 0, Some(e.to_string()))
+                }
+            };
+            categories.push(outcome);
+        }
+        tx.commit().await?;
+        Ok(categories)
+    }
+    .await;
+
+    let categories = match result {
+        Ok(categories) => categories,
+        Err(e) => {
+            tracing::error!("Data erasure {} aborted: {}", request_id, e);
+            if let Err(e) = mark_failed(&db, request_id, &e.to_string()).await {
+                tracing::error!("Failed to record data erasure {} failure: {}", request_id, e);
+            }
+            return;
+        }
+    };
+
+    let report = DsarReport {
+        user_id,
+        kind: DsarKind::Erasure,
+        started_at,
+        completed_at: Utc::now(),
+        succeeded: categories.iter().all(|c| c.outcome != CategoryOutcomeKind::Failed),
+        categories,
+    };
+    let report_json = serde_json::to_value(&report).unwrap_or(Value::Null);
+
+    if let Err(e) = sqlx::query!(
+        "UPDATE dsar_requests SET status = $2, report = $3, completed_at = NOW() WHERE id = $1",
+        request_id,
+        if report.succeeded { "completed" } else { "failed" },
+        report_json
+    )
+    .execute(&db)
+    .await
+    {
+        tracing::error!("Failed to store data erasure report {}: {}", request_id, e);
+    }
+
+    record_audit_event(&db, Some(user_id), "dsar.erasure_completed", DSAR_RESOURCE, &request_id.to_string(), report_json, None).await;
+}
+
+/// `None` when the category's table is not part of this deployment.
+async fn export_category(db: &PgPool, category: &DataCategory, user_id: Uuid) -> Result<Option<Value>, sqlx::Error> {
+    if !table_exists(db, category.table).await? {
+        return Ok(None);
+    }
+    // Identifiers come from DATA_CATEGORIES, never from the request
+    let sql = format!(
+        "SELECT COALESCE(jsonb_agg(to_jsonb(t) - $2::text[]), '[]'::jsonb) FROM {} t WHERE t.{} = $1",
+        category.table, category.user_column
+    );
+    let exclude: Vec<String> = category.exclude_columns.iter().map(|c| c.to_string()).collect();
+    let rows: Value = sqlx::query_scalar(&sql).bind(user_id).bind(&exclude).fetch_one(db).await?;
+    Ok(Some(rows))
+}
+
+async fn erase_category(
+    tx: &mut Transaction<'_, Postgres>,
+    category: &DataCategory,
+    user_id: Uuid,
+) -> Result<CategoryOutcome, sqlx::Error> {
+    if !table_exists(&mut **tx, category.table).await? {
+        return Ok(outcome(category, CategoryOutcomeKind::NotPresent, 0, None));
+    }
+
+    Ok(match category.erasure {
+        ErasureAction::Delete => {
+            let sql = format!("DELETE FROM {} WHERE {} = $1", category.table, category.user_column);
+            let result = sqlx::query(&sql).bind(user_id).execute(&mut **tx).await?;
+            outcome(category, CategoryOutcomeKind::Deleted, result.rows_affected(), None)
+        }
+        ErasureAction::Pseudonymize { set, reason } => {
+            let sql = format!("UPDATE {} SET {} WHERE {} = $1", category.table, set, category.user_column);
+            let result = sqlx::query(&sql).bind(user_id).execute(&mut **tx).await?;
+            outcome(category, CategoryOutcomeKind::Pseudonymized, result.rows_affected(), Some(reason.to_string()))
+        }
+        ErasureAction::Retain { reason } => {
+            let sql = format!("SELECT COUNT(*) FROM {} WHERE {} = $1", category.table, category.user_column);
+            let count: i64 = sqlx::query_scalar(&sql).bind(user_id).fetch_one(&mut **tx).await?;
+            outcome(category, CategoryOutcomeKind::Retained, count as u64, Some(reason.to_string()))
+        }
+    })
+}
+
+pub(crate) async fn table_exists<'e, E>(executor: E, table: &str) -> Result<bool, sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = Postgres>,
+{
+    sqlx::query_scalar!("SELECT to_regclass($1) IS NOT NULL", table)
+        .fetch_one(executor)
+        .await
+        .map(|exists| exists.unwrap_or(false))
+}
+
+async fn mark_running(db: &PgPool, request_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE dsar_requests SET status = 'running' WHERE id = $1", request_id)
+        .execute(db)
+        .await
+        .map(|_| ())
+}
+
+async fn mark_failed(db: &PgPool, request_id: Uuid, error: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE dsar_requests SET status = 'failed', error = $2, completed_at = NOW() WHERE id = $1",
+        request_id,
+        error
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+fn outcome(category: &DataCategory, kind: CategoryOutcomeKind, rows: u64, note: Option<String>) -> CategoryOutcome {
+    CategoryOutcome {
+        category: category.name.to_string(),
+        table: category.table.to_string(),
+        outcome: kind,
+        rows,
+        note,
+    }
+}
+
+/// Zip with a manifest, one JSON file per category and a short readme.
+pub fn build_archive(report: &DsarReport, files: &[(String, Value)]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    writer.start_file("README.txt", options)?;
+    writer.write_all(
+        format!(
+            "Personal data held by JeanTrail for user {}, exported {}.\n\
+             manifest.json lists every category, how many records it holds and whether it exported cleanly.\n\
+             Each file under data/ is a JSON array of that category's records.\n",
+            report.user_id,
+            report.completed_at.to_rfc3339()
+        )
+        .as_bytes(),
+    )?;
+
+    writer.start_file("manifest.json", options)?;
+    writer.write_all(&serde_json::to_vec_pretty(report).unwrap_or_default())?;
+
+    for (name, rows) in files {
+        writer.start_file(name.as_str(), options)?;
+        writer.write_all(&serde_json::to_vec_pretty(rows).unwrap_or_default())?;
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn report(user_id: Uuid) -> DsarReport {
+        DsarReport {
+            user_id,
+            kind: DsarKind::Export,
+            started_at: Utc::now(),
+            completed_at: Utc::now(),
+            succeeded: true,
+            categories: vec![CategoryOutcome {
+                category: "memories".to_string(),
+                table: "jean_memory".to_string(),
+                outcome: CategoryOutcomeKind::Exported,
+                rows: 2,
+                note: None,
+            }],
+        }
+    }
+
+    fn read_entry(archive: &mut zip::ZipArchive<std::io::Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut contents = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn archive_holds_readme_manifest_and_category_files() {
+        let user_id = Uuid::new_v4();
+        let rows = serde_json::json!([{"content": "first"}, {"content": "second"}]);
+        let bytes = build_archive(&report(user_id), &[("data/memories.json".to_string(), rows.clone())]).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, ["README.txt", "data/memories.json", "manifest.json"]);
+
+        assert!(read_entry(&mut archive, "README.txt").contains(&user_id.to_string()));
+        let manifest: DsarReport = serde_json::from_str(&read_entry(&mut archive, "manifest.json")).unwrap();
+        assert_eq!(manifest.user_id, user_id);
+        assert_eq!(manifest.categories[0].outcome, CategoryOutcomeKind::Exported);
+        let exported: Value = serde_json::from_str(&read_entry(&mut archive, "data/memories.json")).unwrap();
+        assert_eq!(exported, rows);
+    }
+
+    #[test]
+    fn erasure_drops_export_archives() {
+        let requests = DATA_CATEGORIES.iter().find(|c| c.table == "dsar_requests").expect("dsar_requests is erased");
+        assert!(requests.exclude_columns.contains(&"archive"));
+        match requests.erasure {
+            ErasureAction::Pseudonymize { set, .. } => {
+                assert!(set.contains("archive = NULL") && set.contains("archive_expires_at = NULL"))
+            }
+            other => panic!("export archives must not survive erasure: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn archive_without_categories_still_has_a_manifest() {
+        let bytes = build_archive(&report(Uuid::new_v4()), &[]).unwrap();
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 2);
+    }
+}
@@ -0,0 +1,475 @@
+// Hardened HTTP layer for the embedded API: CORS allowlist, security headers and request sanitisation
+use axum::{
+    async_trait,
+    body::Body,
+    extract::{FromRequestParts, MatchedPath, Path, RawPathParams, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::{LengthLimitError, Limited};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use super::ELEVATED_SESSION_HEADER;
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSecurityConfig {
+    /// Exact origins allowed to make cross-origin requests. `*` is not accepted.
+    pub allowed_origins: Vec<String>,
+    pub allow_credentials: bool,
+    /// Policy sent on every response; `frame-ancestors` is appended from `frame_ancestors`.
+    pub content_security_policy: String,
+    pub frame_ancestors: Vec<String>,
+    /// `Strict-Transport-Security` max-age; zero leaves the header off.
+    pub hsts_max_age_secs: u64,
+    pub hsts_include_subdomains: bool,
+    /// Body limit in bytes for routes without their own entry.
+    pub default_body_limit: usize,
+    /// Body limits keyed by route pattern, e.g. `/api/video-studio/projects/:id/upload`.
+    pub route_body_limits: HashMap<String, usize>,
+    /// Deepest array/object nesting accepted in JSON bodies.
+    pub max_json_depth: usize,
+}
+
+impl Default for HttpSecurityConfig {
+    fn default() -> Self {
+        const MIB: usize = 1024 * 1024;
+        let route_body_limits = [
+            ("/api/auto-api/log", 4 * MIB),
+            ("/api/auto-api/streams/log", 4 * MIB),
+            ("/api/backlog/import", 5 * MIB),
+            ("/api/plugins/install/upload", 32 * MIB),
+            ("/api/video-studio/projects/:id/upload", 25 * MIB),
+        ]
+        .into_iter()
+        .map(|(route, limit)| (route.to_string(), limit))
+        .collect();
+        Self {
+            // Tauri webview origins on each platform, plus the dev server
+            allowed_origins: vec![
+                "tauri://localhost".to_string(),
+                "https://tauri.localhost".to_string(),
+                "http://localhost:1420".to_string(),
+            ],
+            allow_credentials: false,
+            content_security_policy: "default-src 'none'; base-uri 'none'; form-action 'none'".to_string(),
+            frame_ancestors: vec!["'none'".to_string()],
+            hsts_max_age_secs: 63_072_000,
+            hsts_include_subdomains: true,
+            default_body_limit: MIB,
+            route_body_limits,
+            max_json_depth: 64,
+        }
+    }
+}
+
+impl HttpSecurityConfig {
+    /// Loads the config from the JSON file named by `HTTP_SECURITY_CONFIG`,
+    /// falling back to the defaults.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("HTTP_SECURITY_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring HTTP security config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn body_limit_for(&self, route: Option<&str>) -> usize {
+        route
+            .and_then(|route| self.route_body_limits.get(route))
+            .copied()
+            .unwrap_or(self.default_body_limit)
+    }
+
+    /// Headers added to every response that does not already set them.
+    pub fn response_headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+        let frame_ancestors = self.frame_ancestors.join(" ");
+        let csp = format!(
+            "{}; frame-ancestors {}",
+            self.content_security_policy.trim().trim_end_matches(';'),
+            frame_ancestors
+        );
+        let mut headers = vec![
+            (header::CONTENT_SECURITY_POLICY, csp),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (header::REFERRER_POLICY, "no-referrer".to_string()),
+        ];
+        // Older browsers only understand X-Frame-Options
+        match frame_ancestors.as_str() {
+            "'none'" => headers.push((header::X_FRAME_OPTIONS, "DENY".to_string())),
+            "'self'" => headers.push((header::X_FRAME_OPTIONS, "SAMEORIGIN".to_string())),
+            _ => {}
+        }
+        if self.hsts_max_age_secs > 0 {
+            let mut hsts = format!("max-age={}", self.hsts_max_age_secs);
+            if self.hsts_include_subdomains {
+                hsts.push_str("; includeSubDomains");
+            }
+            headers.push((header::STRICT_TRANSPORT_SECURITY, hsts));
+        }
+
+        headers
+            .into_iter()
+            .filter_map(|(name, value)| match HeaderValue::from_str(&value) {
+                Ok(value) => Some((name, value)),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid {} header value {:?}", name, value);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// CORS restricted to the configured origins.
+    pub fn cors_layer(&self) -> CorsLayer {
+        let origins: Vec<HeaderValue> = self
+            .allowed_origins
+            .iter()
+            .filter_map(|origin| match HeaderValue::from_str(origin) {
+                Ok(value) if origin != "*" => Some(value),
+                _ => {
+                    tracing::warn!("Ignoring CORS origin {:?}", origin);
+                    None
+                }
+            })
+            .collect();
+
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+            .allow_headers([
+                header::CONTENT_TYPE,
+                header::AUTHORIZATION,
+                HeaderName::from_static("x-api-key"),
+                HeaderName::from_static("x-session-id"),
+                HeaderName::from_static(ELEVATED_SESSION_HEADER),
+                HeaderName::from_static("x-mock-replay-latency"),
+                HeaderName::from_static("x-mock-source"),
+            ])
+            .expose_headers([
+                header::CONTENT_DISPOSITION,
+                header::RETRY_AFTER,
+                HeaderName::from_static("x-ratelimit-limit"),
+                HeaderName::from_static("x-ratelimit-remaining"),
+                HeaderName::from_static("x-ratelimit-reset"),
+            ])
+            .allow_credentials(self.allow_credentials)
+            .max_age(Duration::from_secs(600))
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpSecurityState {
+    pub config: Arc<HttpSecurityConfig>,
+    headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl HttpSecurityState {
+    pub fn new(config: HttpSecurityConfig) -> Self {
+        Self {
+            headers: Arc::new(config.response_headers()),
+            config: Arc::new(config),
+        }
+    }
+}
+
+pub async fn security_headers_middleware(
+    State(state): State<HttpSecurityState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    for (name, value) in state.headers.iter() {
+        headers.entry(name.clone()).or_insert_with(|| value.clone());
+    }
+    response
+}
+
+/// Enforces the route's body limit and, for JSON bodies, the nesting limit.
+/// Must be added with `Router::layer` so the matched route is known.
+pub async fn request_limits_middleware(
+    State(state): State<HttpSecurityState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limit = state
+        .config
+        .body_limit_for(request.extensions().get::<MatchedPath>().map(MatchedPath::as_str));
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > limit as u64) {
+        return AppError::PayloadTooLarge { limit }.into_response();
+    }
+
+    if !is_json(request.headers()) {
+        // Streamed bodies are cut off at the limit; extractors report 413
+        return next.run(request.map(|body| Body::new(Limited::new(body, limit)))).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, limit).await {
+        Ok(bytes) => bytes,
+        Err(e) if std::error::Error::source(&e).is_some_and(|source| source.is::<LengthLimitError>()) => {
+            return AppError::PayloadTooLarge { limit }.into_response();
+        }
+        Err(_) => return AppError::InvalidRequest("Failed to read request body".to_string()).into_response(),
+    };
+    let max_depth = state.config.max_json_depth;
+    if json_depth_exceeds(&bytes, max_depth) {
+        return AppError::InvalidRequest(format!("JSON nesting exceeds {} levels", max_depth)).into_response();
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
+
+/// Scans nesting without parsing, so hostile input never reaches a recursive parser.
+pub fn json_depth_exceeds(body: &[u8], max_depth: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for &byte in body {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                if depth > max_depth {
+                    return true;
+                }
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    false
+}
+
+/// True for decoded path parameters that could escape a directory or key
+/// namespace: `.`/`..` segments, absolute paths, backslashes and control characters.
+pub fn is_unsafe_path_param(value: &str) -> bool {
+    value.starts_with('/')
+        || value.contains('\\')
+        || value.chars().any(char::is_control)
+        || value.split('/').any(|segment| segment == "." || segment == "..")
+}
+
+/// `Path` that rejects traversal attempts in any string parameter of the route.
+#[derive(Debug)]
+pub struct SafePath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for SafePath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let params = RawPathParams::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        if let Some((name, _)) = params.iter().find(|(_, value)| is_unsafe_path_param(value)) {
+            tracing::warn!("Rejected unsafe value for path parameter {}", name);
+            return Err(AppError::InvalidRequest(format!("Invalid path parameter: {}", name)).into_response());
+        }
+        let Path(value) = Path::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::DefaultBodyLimit, http::StatusCode, middleware, routing::{get, post}, Router};
+    use tower::ServiceExt;
+
+    fn app(config: HttpSecurityConfig) -> Router {
+        let state = HttpSecurityState::new(config);
+        let cors = state.config.cors_layer();
+        Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/echo", post(|body: String| async move { body }))
+            .route("/upload", post(|body: axum::body::Bytes| async move { body.len().to_string() }))
+            .route("/files/:name", get(|SafePath(name): SafePath<String>| async move { name }))
+            .route("/mock/:domain/*path", get(|SafePath((_, path)): SafePath<(String, String)>| async move { path }))
+            .layer(middleware::from_fn_with_state(state.clone(), request_limits_middleware))
+            .layer(DefaultBodyLimit::disable())
+            .layer(cors)
+            .layer(middleware::from_fn_with_state(state, security_headers_middleware))
+    }
+
+    fn get_request(uri: &str) -> Request {
+        Request::builder().uri(uri).body(Body::empty()).unwrap()
+    }
+
+    fn post_request(uri: &str, content_type: &str, body: impl Into<Body>) -> Request {
+        Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(body.into())
+            .unwrap()
+    }
+
+    fn assert_security_headers(response: &Response) {
+        let headers = response.headers();
+        assert_eq!(
+            headers[header::CONTENT_SECURITY_POLICY],
+            "default-src 'none'; base-uri 'none'; form-action 'none'; frame-ancestors 'none'"
+        );
+        assert_eq!(headers[header::STRICT_TRANSPORT_SECURITY], "max-age=63072000; includeSubDomains");
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::REFERRER_POLICY], "no-referrer");
+    }
+
+    #[tokio::test]
+    async fn security_headers_are_on_every_response() {
+        let response = app(HttpSecurityConfig::default()).oneshot(get_request("/ok")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_security_headers(&response);
+
+        let response = app(HttpSecurityConfig::default()).oneshot(get_request("/missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_security_headers(&response);
+
+        let response = app(HttpSecurityConfig::default()).oneshot(get_request("/files/..%2Fsecrets")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_security_headers(&response);
+    }
+
+    #[tokio::test]
+    async fn configured_frame_ancestors_and_hsts_are_applied() {
+        let config = HttpSecurityConfig {
+            frame_ancestors: vec!["'self'".to_string(), "tauri://localhost".to_string()],
+            hsts_max_age_secs: 0,
+            ..HttpSecurityConfig::default()
+        };
+        let response = app(config).oneshot(get_request("/ok")).await.unwrap();
+        let csp = response.headers()[header::CONTENT_SECURITY_POLICY].to_str().unwrap();
+        assert!(csp.ends_with("; frame-ancestors 'self' tauri://localhost"));
+        assert!(!response.headers().contains_key(header::X_FRAME_OPTIONS));
+        assert!(!response.headers().contains_key(header::STRICT_TRANSPORT_SECURITY));
+    }
+
+    #[tokio::test]
+    async fn cors_only_answers_allowlisted_origins() {
+        let preflight = |origin: &str| {
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/echo")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app(HttpSecurityConfig::default()).oneshot(preflight("tauri://localhost")).await.unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "tauri://localhost");
+        assert_security_headers(&response);
+
+        let response = app(HttpSecurityConfig::default()).oneshot(preflight("https://evil.example")).await.unwrap();
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        let config = HttpSecurityConfig {
+            allowed_origins: vec!["*".to_string()],
+            ..HttpSecurityConfig::default()
+        };
+        let response = app(config).oneshot(preflight("https://evil.example")).await.unwrap();
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn body_limits_apply_per_route() {
+        let config = HttpSecurityConfig {
+            default_body_limit: 16,
+            route_body_limits: HashMap::from([("/upload".to_string(), 64)]),
+            ..HttpSecurityConfig::default()
+        };
+        let body = "x".repeat(32);
+
+        let response = app(config.clone()).oneshot(post_request("/echo", "text/plain", body.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_security_headers(&response);
+
+        let response = app(config.clone()).oneshot(post_request("/upload", "application/octet-stream", body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // No Content-Length: the limit is enforced while streaming
+        let stream = Body::from_stream(tokio_stream::iter(vec![Ok::<_, std::io::Error>("x".repeat(100))]));
+        let response = app(config).oneshot(post_request("/upload", "application/octet-stream", stream)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn deeply_nested_json_is_rejected() {
+        let config = HttpSecurityConfig {
+            max_json_depth: 4,
+            ..HttpSecurityConfig::default()
+        };
+        let response = app(config.clone())
+            .oneshot(post_request("/echo", "application/json", r#"{"a":[{"b":["[[[[[["]}]}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app(config)
+            .oneshot(post_request("/echo", "application/merge-patch+json", "[[[[[1]]]]]"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn path_parameters_cannot_traverse() {
+        for uri in ["/files/..", "/files/%2E%2E", "/files/%2Fetc%2Fpasswd", "/files/a%5C..%5Cb", "/files/a%00b", "/mock/api.example/v1/../../admin"] {
+            let response = app(HttpSecurityConfig::default()).oneshot(get_request(uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+
+        let response = app(HttpSecurityConfig::default()).oneshot(get_request("/files/report.v2.json")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app(HttpSecurityConfig::default()).oneshot(get_request("/mock/api.example/v1/users")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn depth_scanner_ignores_brackets_in_strings() {
+        assert!(!json_depth_exceeds(br#"{"a":"[[[[{{{{\"]]]"}"#, 1));
+        assert!(json_depth_exceeds(br#"{"a":{"b":1}}"#, 1));
+        assert!(!json_depth_exceeds(br#"{"a":{"b":1}}"#, 2));
+    }
+}
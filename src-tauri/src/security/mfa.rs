@@ -0,0 +1,650 @@
+// Second-factor step-up: TOTP enrolment, backup codes and short-lived elevated sessions
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{db_error, generate_token, record_audit_event, ClientInfo, CryptoKeys, SecurityState, UserContext};
+
+/// Header carrying an elevated-session token to endpoints that need one.
+pub const ELEVATED_SESSION_HEADER: &str = "x-elevated-token";
+pub const ELEVATED_TOKEN_PREFIX: &str = "jtel_";
+pub const ELEVATED_SESSION_TTL_SECONDS: i64 = 300;
+
+const MFA_RESOURCE: &str = "mfa";
+const TOTP_ISSUER: &str = "JeanTrail";
+const TOTP_PERIOD_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+/// Steps accepted either side of now, for clock drift.
+const TOTP_SKEW_STEPS: i64 = 1;
+const TOTP_SECRET_BYTES: usize = 20;
+const BACKUP_CODE_COUNT: usize = 10;
+const BACKUP_CODE_LEN: usize = 10;
+/// Failed verifications before the factor is locked.
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+const LOCKOUT_MINUTES: i64 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FactorMethod {
+    Totp,
+    BackupCode,
+}
+
+impl FactorMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FactorMethod::Totp => "totp",
+            FactorMethod::BackupCode => "backup_code",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MfaError {
+    #[error("Two-factor authentication is not enabled")]
+    NotEnrolled,
+    #[error("Two-factor authentication is already enabled")]
+    AlreadyEnrolled,
+    #[error("Invalid verification code")]
+    InvalidCode,
+    #[error("Too many failed attempts, locked until {until}")]
+    LockedOut { until: DateTime<Utc> },
+    #[error("Stored secret could not be used")]
+    Secret,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl MfaError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            MfaError::NotEnrolled => StatusCode::PRECONDITION_FAILED,
+            MfaError::AlreadyEnrolled => StatusCode::CONFLICT,
+            MfaError::InvalidCode => StatusCode::UNAUTHORIZED,
+            MfaError::LockedOut { .. } => StatusCode::TOO_MANY_REQUESTS,
+            MfaError::Secret | MfaError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<MfaError> for StatusCode {
+    fn from(error: MfaError) -> Self {
+        if let MfaError::Database(e) = &error {
+            tracing::error!("Database error: {}", e);
+        }
+        error.status_code()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaStatus {
+    pub totp_enabled: bool,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub backup_codes_remaining: i64,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// Returned once, when enrolment starts.
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+    pub digits: u32,
+    pub period: i64,
+}
+
+/// Returned once, when codes are generated.
+#[derive(Debug, Serialize)]
+pub struct BackupCodes {
+    pub codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyCodeRequest {
+    pub code: String,
+}
+
+/// Either factor proves presence; backup codes are spent on use.
+#[derive(Debug, Deserialize)]
+pub struct StepUpRequest {
+    pub code: Option<String>,
+    pub backup_code: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ElevatedSession {
+    pub token: String,
+    pub method: FactorMethod,
+    pub expires_at: DateTime<Utc>,
+    pub header: &'static str,
+}
+
+/// RFC 6238 code for one time step, HMAC-SHA1 as authenticator apps expect.
+pub fn totp_code(secret: &[u8], step: i64) -> String {
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    // Dynamic truncation, RFC 4226 section 5.3
+    let offset = usize::from(digest[digest.len() - 1] & 0x0f);
+    let binary = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+    format!("{:0width$}", binary % 10u32.pow(TOTP_DIGITS), width = TOTP_DIGITS as usize)
+}
+
+/// Step matched by `code` around `unix_time`, refusing steps at or before
+/// `last_used_step` so an observed code cannot be replayed.
+pub fn verify_totp(secret: &[u8], code: &str, unix_time: i64, last_used_step: Option<i64>) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let current = unix_time.div_euclid(TOTP_PERIOD_SECONDS);
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .filter(|step| last_used_step.is_none_or(|used| *step > used))
+        .find(|step| constant_time_eq(totp_code(secret, *step).as_bytes(), code.as_bytes()))
+}
+
+pub fn otpauth_uri(account: &str, secret_base32: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret_base32}&issuer={issuer}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_PERIOD_SECONDS}",
+        issuer = TOTP_ISSUER,
+        account = account
+    )
+}
+
+/// Codes are shown as `XXXXX-XXXXX`; case, spaces and dashes are ignored on entry.
+pub fn normalize_backup_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn generate_backup_codes() -> Vec<String> {
+    (0..BACKUP_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 8];
+            OsRng.fill_bytes(&mut bytes);
+            let code = &BASE32_NOPAD.encode(&bytes)[..BACKUP_CODE_LEN];
+            format!("{}-{}", &code[..BACKUP_CODE_LEN / 2], &code[BACKUP_CODE_LEN / 2..])
+        })
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn secret_context(user_id: Uuid) -> String {
+    format!("user_totp:{}", user_id)
+}
+
+/// Only the account holder may act on their own resources, e.g. their second
+/// factor or plugins. Fails closed when no user is authenticated.
+pub fn require_self(user: Option<Extension<UserContext>>, user_id: Uuid) -> Result<(), StatusCode> {
+    match user {
+        Some(Extension(context)) if context.user_id == user_id => Ok(()),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Instance-wide operations, such as installing plugins or running retention,
+/// are for administrators only.
+pub fn require_admin(user: Option<Extension<UserContext>>) -> Result<UserContext, StatusCode> {
+    match user {
+        Some(Extension(context)) if context.is_admin => Ok(context),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+pub fn elevated_token_from_headers(headers: &HeaderMap) -> Option<&str> {
+    headers.get(ELEVATED_SESSION_HEADER).and_then(|value| value.to_str().ok()).map(str::trim)
+}
+
+/// True when `token` is a live elevated session belonging to `user_id`.
+pub async fn verify_elevated_session(db: &PgPool, user_id: Uuid, token: &str) -> Result<bool, sqlx::Error> {
+    if !token.starts_with(ELEVATED_TOKEN_PREFIX) {
+        return Ok(false);
+    }
+    let session = sqlx::query!(
+        r#"
+        SELECT id FROM elevated_sessions
+        WHERE token_hash = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > NOW()
+        "#,
+        CryptoKeys::global().keyed_hash(token),
+        user_id
+    )
+    .fetch_optional(db)
+    .await?;
+    Ok(session.is_some())
+}
+
+/// True once the user has confirmed a TOTP authenticator.
+pub async fn mfa_enrolled(db: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM user_totp WHERE user_id = $1 AND enabled_at IS NOT NULL) AS "enrolled!""#,
+        user_id
+    )
+    .fetch_one(db)
+    .await
+}
+
+/// Response for endpoints that need a second factor before they proceed.
+pub fn elevation_required_response() -> Response {
+    (
+        StatusCode::PRECONDITION_REQUIRED,
+        Json(serde_json::json!({
+            "error": "Step-up authentication required",
+            "status": StatusCode::PRECONDITION_REQUIRED.as_u16(),
+            "elevation_header": ELEVATED_SESSION_HEADER
+        })),
+    )
+        .into_response()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TotpStage {
+    Pending,
+    Enabled,
+}
+
+/// Checks one factor, counting failures towards a lockout. Runs in the
+/// caller's transaction so the row lock also covers what the caller does next.
+async fn verify_factor(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    stage: TotpStage,
+    totp: Option<&str>,
+    backup_code: Option<&str>,
+) -> Result<FactorMethod, MfaError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT secret_encrypted, enabled_at, last_used_step, locked_until
+        FROM user_totp
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(MfaError::NotEnrolled)?;
+
+    match (stage, row.enabled_at.is_some()) {
+        (TotpStage::Enabled, false) => return Err(MfaError::NotEnrolled),
+        (TotpStage::Pending, true) => return Err(MfaError::AlreadyEnrolled),
+        _ => {}
+    }
+    if let Some(until) = row.locked_until.filter(|until| *until > Utc::now()) {
+        return Err(MfaError::LockedOut { until });
+    }
+
+    let accepted = match (totp, backup_code) {
+        (Some(code), _) => {
+            let secret = CryptoKeys::global()
+                .decrypt_secret(&row.secret_encrypted, &secret_context(user_id))
+                .ok()
+                .and_then(|encoded| BASE32_NOPAD.decode(encoded.as_bytes()).ok())
+                .ok_or(MfaError::Secret)?;
+            match verify_totp(&secret, code, Utc::now().timestamp(), row.last_used_step) {
+                Some(step) => {
+                    sqlx::query!("UPDATE user_totp SET last_used_step = $2 WHERE user_id = $1", user_id, step)
+                        .execute(&mut **tx)
+                        .await?;
+                    Some(FactorMethod::Totp)
+                }
+                None => None,
+            }
+        }
+        (None, Some(code)) if stage == TotpStage::Enabled => {
+            let spent = sqlx::query!(
+                r#"
+                UPDATE user_backup_codes SET used_at = NOW()
+                WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                RETURNING id
+                "#,
+                user_id,
+                CryptoKeys::global().keyed_hash(&normalize_backup_code(code))
+            )
+            .fetch_optional(&mut **tx)
+            .await?;
+            spent.map(|_| FactorMethod::BackupCode)
+        }
+        _ => None,
+    };
+
+    match accepted {
+        Some(method) => {
+            sqlx::query!(
+                "UPDATE user_totp SET failed_attempts = 0, locked_until = NULL, updated_at = NOW() WHERE user_id = $1",
+                user_id
+            )
+            .execute(&mut **tx)
+            .await?;
+            Ok(method)
+        }
+        None => {
+            sqlx::query!(
+                r#"
+                UPDATE user_totp
+                SET failed_attempts = failed_attempts + 1,
+                    locked_until = CASE WHEN failed_attempts + 1 >= $2 THEN NOW() + make_interval(mins => $3) END,
+                    updated_at = NOW()
+                WHERE user_id = $1
+                "#,
+                user_id,
+                MAX_FAILED_ATTEMPTS,
+                LOCKOUT_MINUTES as i32
+            )
+            .execute(&mut **tx)
+            .await?;
+            Err(MfaError::InvalidCode)
+        }
+    }
+}
+
+/// Verifies a factor in its own transaction; failures are committed so they count.
+async fn verify_and_commit(
+    db: &PgPool,
+    user_id: Uuid,
+    totp: Option<&str>,
+    backup_code: Option<&str>,
+) -> Result<(FactorMethod, Transaction<'static, Postgres>), (MfaError, Option<Transaction<'static, Postgres>>)> {
+    let mut tx = db.begin().await.map_err(|e| (MfaError::from(e), None))?;
+    match verify_factor(&mut tx, user_id, TotpStage::Enabled, totp, backup_code).await {
+        Ok(method) => Ok((method, tx)),
+        Err(e) => Err((e, Some(tx))),
+    }
+}
+
+async fn fail(db: &PgPool, user_id: Uuid, error: MfaError, tx: Option<Transaction<'static, Postgres>>, client: &ClientInfo) -> StatusCode {
+    if let Some(tx) = tx {
+        if let Err(e) = tx.commit().await {
+            tracing::error!("Failed to record second-factor failure: {}", e);
+        }
+    }
+    if matches!(error, MfaError::InvalidCode | MfaError::LockedOut { .. }) {
+        audit_mfa_event(db, user_id, "mfa.verification_failed", serde_json::json!({ "reason": error.to_string() }), client).await;
+    }
+    error.into()
+}
+
+async fn store_backup_codes(tx: &mut Transaction<'_, Postgres>, user_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    let codes = generate_backup_codes();
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| CryptoKeys::global().keyed_hash(&normalize_backup_code(code)))
+        .collect();
+    sqlx::query!("DELETE FROM user_backup_codes WHERE user_id = $1", user_id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO user_backup_codes (user_id, code_hash) SELECT $1, UNNEST($2::text[])",
+        user_id,
+        &hashes
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(codes)
+}
+
+pub async fn get_mfa_status(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<MfaStatus>, StatusCode> {
+    require_self(user, user_id)?;
+    let status = sqlx::query_as!(
+        MfaStatus,
+        r#"
+        SELECT t.enabled_at IS NOT NULL AS "totp_enabled!",
+               t.enabled_at,
+               (SELECT COUNT(*) FROM user_backup_codes b WHERE b.user_id = $1 AND b.used_at IS NULL) AS "backup_codes_remaining!",
+               t.locked_until
+        FROM (SELECT $1::uuid AS user_id) u
+        LEFT JOIN user_totp t ON t.user_id = u.user_id
+        "#,
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(db_error)?;
+    Ok(Json(status))
+}
+
+/// Starts enrolment with a fresh secret; nothing is enforced until it is confirmed.
+pub async fn enroll_totp(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Json<TotpEnrollment>, StatusCode> {
+    require_self(user, user_id)?;
+    let mut raw = [0u8; TOTP_SECRET_BYTES];
+    OsRng.fill_bytes(&mut raw);
+    let secret = BASE32_NOPAD.encode(&raw);
+    let encrypted = CryptoKeys::global()
+        .encrypt_secret(&secret, &secret_context(user_id))
+        .map_err(|e| {
+            tracing::error!("Failed to encrypt TOTP secret: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // An enabled factor has to be disabled, with a code, before it can be replaced
+    let stored = sqlx::query!(
+        r#"
+        INSERT INTO user_totp (user_id, secret_encrypted)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret_encrypted = EXCLUDED.secret_encrypted, last_used_step = NULL, updated_at = NOW()
+        WHERE user_totp.enabled_at IS NULL
+        RETURNING user_id
+        "#,
+        user_id,
+        encrypted
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?;
+    if stored.is_none() {
+        return Err(MfaError::AlreadyEnrolled.into());
+    }
+
+    audit_mfa_event(&state.db, user_id, "mfa.enrollment_started", serde_json::json!({}), &client).await;
+    Ok(Json(TotpEnrollment {
+        otpauth_uri: otpauth_uri(&user_id.to_string(), &secret),
+        secret,
+        digits: TOTP_DIGITS,
+        period: TOTP_PERIOD_SECONDS,
+    }))
+}
+
+/// Enables TOTP once the user proves their app produces matching codes.
+pub async fn confirm_totp(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<VerifyCodeRequest>,
+) -> Result<Json<BackupCodes>, StatusCode> {
+    require_self(user, user_id)?;
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    if let Err(e) = verify_factor(&mut tx, user_id, TotpStage::Pending, Some(&request.code), None).await {
+        return Err(fail(&state.db, user_id, e, Some(tx), &client).await);
+    }
+    sqlx::query!("UPDATE user_totp SET enabled_at = NOW(), updated_at = NOW() WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    let codes = store_backup_codes(&mut tx, user_id).await.map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    audit_mfa_event(&state.db, user_id, "mfa.enabled", serde_json::json!({ "backup_codes": codes.len() }), &client).await;
+    Ok(Json(BackupCodes { codes }))
+}
+
+/// Replaces all backup codes; needs a current TOTP code.
+pub async fn regenerate_backup_codes(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<VerifyCodeRequest>,
+) -> Result<Json<BackupCodes>, StatusCode> {
+    require_self(user, user_id)?;
+    let mut tx = match verify_and_commit(&state.db, user_id, Some(&request.code), None).await {
+        Ok((_, tx)) => tx,
+        Err((e, tx)) => return Err(fail(&state.db, user_id, e, tx, &client).await),
+    };
+    let codes = store_backup_codes(&mut tx, user_id).await.map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    audit_mfa_event(&state.db, user_id, "mfa.backup_codes_regenerated", serde_json::json!({ "backup_codes": codes.len() }), &client).await;
+    Ok(Json(BackupCodes { codes }))
+}
+
+/// Turns TOTP off, discarding backup codes and any elevated sessions.
+pub async fn disable_totp(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<StepUpRequest>,
+) -> Result<StatusCode, StatusCode> {
+    require_self(user, user_id)?;
+    let (method, mut tx) = match verify_and_commit(&state.db, user_id, request.code.as_deref(), request.backup_code.as_deref()).await {
+        Ok(verified) => verified,
+        Err((e, tx)) => return Err(fail(&state.db, user_id, e, tx, &client).await),
+    };
+    sqlx::query!("DELETE FROM user_backup_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query!("UPDATE elevated_sessions SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL", user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    audit_mfa_event(&state.db, user_id, "mfa.disabled", serde_json::json!({ "method": method }), &client).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Exchanges a second factor for a short-lived elevated-session token.
+pub async fn step_up(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<StepUpRequest>,
+) -> Result<Json<ElevatedSession>, StatusCode> {
+    require_self(user, user_id)?;
+    let (method, mut tx) = match verify_and_commit(&state.db, user_id, request.code.as_deref(), request.backup_code.as_deref()).await {
+        Ok(verified) => verified,
+        Err((e, tx)) => return Err(fail(&state.db, user_id, e, tx, &client).await),
+    };
+
+    let token = format!("{}{}", ELEVATED_TOKEN_PREFIX, generate_token(CryptoKeys::global().token_bytes));
+    let expires_at = Utc::now() + Duration::seconds(ELEVATED_SESSION_TTL_SECONDS);
+    let session_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO elevated_sessions (user_id, token_hash, method, expires_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+        user_id,
+        CryptoKeys::global().keyed_hash(&token),
+        method.as_str(),
+        expires_at
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    audit_mfa_event(&state.db, user_id, "mfa.step_up", serde_json::json!({
+        "method": method,
+        "elevated_session_id": session_id,
+        "expires_at": expires_at
+    }), &client).await;
+    Ok(Json(ElevatedSession { token, method, expires_at, header: ELEVATED_SESSION_HEADER }))
+}
+
+async fn audit_mfa_event(db: &PgPool, user_id: Uuid, action: &str, details: serde_json::Value, client: &ClientInfo) {
+    record_audit_event(db, Some(user_id), action, MFA_RESOURCE, &user_id.to_string(), details, Some(client)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B, SHA-1 seed, truncated to six digits
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn totp_matches_rfc6238_vectors() {
+        for (time, expected) in [(59, "287082"), (1_111_111_109, "081804"), (1_234_567_890, "005924"), (2_000_000_000, "279037")] {
+            assert_eq!(totp_code(RFC_SECRET, time / TOTP_PERIOD_SECONDS), expected, "t={}", time);
+        }
+    }
+
+    #[test]
+    fn verification_allows_one_step_of_drift() {
+        let now = 1_234_567_890;
+        let previous = totp_code(RFC_SECRET, now / TOTP_PERIOD_SECONDS - 1);
+        let stale = totp_code(RFC_SECRET, now / TOTP_PERIOD_SECONDS - 2);
+        assert_eq!(verify_totp(RFC_SECRET, &previous, now, None), Some(now / TOTP_PERIOD_SECONDS - 1));
+        assert_eq!(verify_totp(RFC_SECRET, &stale, now, None), None);
+        assert_eq!(verify_totp(RFC_SECRET, "12345", now, None), None);
+        assert_eq!(verify_totp(RFC_SECRET, "abcdef", now, None), None);
+    }
+
+    #[test]
+    fn used_codes_cannot_be_replayed() {
+        let now = 1_234_567_890;
+        let step = verify_totp(RFC_SECRET, "005924", now, None).unwrap();
+        assert_eq!(verify_totp(RFC_SECRET, "005924", now, Some(step)), None);
+        // The next step's code is still good
+        let next = totp_code(RFC_SECRET, step + 1);
+        assert_eq!(verify_totp(RFC_SECRET, &next, now + TOTP_PERIOD_SECONDS, Some(step)), Some(step + 1));
+    }
+
+    #[test]
+    fn backup_codes_are_unique_and_normalise() {
+        let codes = generate_backup_codes();
+        assert_eq!(codes.len(), BACKUP_CODE_COUNT);
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), BACKUP_CODE_COUNT);
+        for code in &codes {
+            assert_eq!(code.len(), BACKUP_CODE_LEN + 1);
+            assert_eq!(normalize_backup_code(&code.to_lowercase().replace('-', " ")), normalize_backup_code(code));
+        }
+    }
+
+    #[test]
+    fn otpauth_uri_describes_the_secret() {
+        let uri = otpauth_uri("user-1", "JBSWY3DPEHPK3PXP");
+        assert_eq!(
+            uri,
+            "otpauth://totp/JeanTrail:user-1?secret=JBSWY3DPEHPK3PXP&issuer=JeanTrail&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -0,0 +1,748 @@
+// Security / Privacy / Compliance Layer
+pub mod api_keys;
+pub mod audit_chain;
+pub mod audit_retention;
+pub mod client_ip;
+pub mod consent;
+pub mod crypto;
+pub mod dashboard;
+pub mod dsar;
+pub mod http_layer;
+pub mod mfa;
+pub mod rate_limit;
+pub mod risk;
+pub mod siem;
+
+pub use api_keys::*;
+pub use audit_chain::*;
+pub use audit_retention::*;
+pub use client_ip::*;
+pub use consent::*;
+pub use crypto::*;
+pub use dashboard::*;
+pub use dsar::*;
+pub use http_layer::*;
+pub use mfa::*;
+pub use rate_limit::*;
+pub use risk::*;
+pub use siem::*;
+
+use axum::{Extension, Json, extract::{Path, Query, Request, State}, http::HeaderMap, middleware};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct SecurityState {
+    pub db: PgPool,
+    pub risk: Arc<RiskEngine>,
+    pub consent: ConsentGate,
+    pub alerts: AlertFeed,
+    pub siem: Arc<SiemConfig>,
+}
+
+impl SecurityState {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            risk: Arc::new(RiskEngine::new(RiskConfig::load(), db.clone())),
+            consent: ConsentGate::new(db.clone()),
+            alerts: AlertFeed::new(),
+            siem: Arc::new(SiemConfig::load()),
+            db,
+        }
+    }
+}
+
+// User context for authenticated requests
+#[derive(Debug, Clone)]
+pub struct UserContext {
+    pub user_id: Uuid,
+    pub permissions: Vec<String>,
+    /// Instance administrators manage plugins and security operations.
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub action: String,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    pub details: Value,
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub session_id: Option<String>,
+    pub risk_score: i32,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    // Hash chain position, set once the entry is persisted
+    pub sequence: Option<i64>,
+    pub prev_hash: Option<String>,
+    pub entry_hash: Option<String>,
+    // Set when retention has moved the content to an archive file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ConsentRecord {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub consent_type: String,
+    pub version: String,
+    pub granted: bool,
+    pub granted_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub metadata: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PrivacySetting {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub setting_key: String,
+    pub setting_value: Value,
+    pub category: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The consenting user is always the authenticated caller.
+#[derive(Debug, Deserialize)]
+pub struct CreateConsentRecordRequest {
+    pub consent_type: String,
+    pub version: String,
+    pub granted: bool,
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdatePrivacySettingsRequest {
+    pub settings: HashMap<String, Value>,
+}
+
+// Audit Logging Middleware
+pub async fn audit_logging_middleware(
+    State(state): State<SecurityState>,
+    request: Request,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, axum::http::StatusCode> {
+    let start_time = std::time::Instant::now();
+    let method = request.method().to_string();
+    let uri = request.uri().to_string();
+    let headers = request.headers().clone();
+    let client = ClientInfo::from_request_parts(request.headers(), request.extensions());
+    let user_id = authenticated_user_id(request.extensions());
+
+    let risk_context = RiskContext {
+        user_id,
+        ip_address: client.ip_address,
+        user_agent: client.user_agent.clone(),
+        method: request.method().clone(),
+        path: request.uri().path().to_string(),
+        resource_type: extract_resource_type(&uri),
+        at: Utc::now(),
+    };
+    let mut assessment = state.risk.assess(&risk_context).await;
+    if assessment.action == RiskAction::StepUp {
+        assessment.confirmed = has_elevated_session(&state.db, user_id, &headers).await;
+        // Without a second factor there is no way to elevate, so enrolling one
+        // or revoking a leaked key must not depend on it
+        assessment.step_up_waived = !assessment.confirmed && !has_second_factor(&state.db, user_id).await;
+    }
+    // Elevating is how a caller satisfies step-up, so that request cannot require it
+    let elevating = request.method() == axum::http::Method::POST && risk_context.path.ends_with(STEP_UP_PATH_SUFFIX);
+
+    let response = match assessment.action {
+        RiskAction::Block => {
+            tracing::warn!("Blocked {} {} with risk score {}", method, uri, assessment.score);
+            blocked_response(&assessment)
+        }
+        RiskAction::StepUp if assessment.requires_step_up() && !elevating => step_up_response(&assessment),
+        _ => next.run(request).await,
+    };
+    state.risk.record_response(&risk_context, &response);
+
+    let duration = start_time.elapsed();
+    let status = response.status();
+    let outcome = match assessment.action {
+        RiskAction::Block => "blocked",
+        RiskAction::StepUp if is_gate_response(&response) => "step_up_required",
+        _ if status.is_success() => "success",
+        _ => "failed",
+    };
+
+    // Log the request
+    let audit_log = AuditLog {
+        id: Uuid::new_v4(),
+        user_id,
+        action: format!("{}_{}", method, uri),
+        resource_type: risk_context.resource_type.clone(),
+        resource_id: extract_resource_id(&uri),
+        details: serde_json::json!({
+            "method": method,
+            "uri": uri,
+            "status": status.as_u16(),
+            "duration_ms": duration.as_millis(),
+            "user_agent": risk_context.user_agent,
+            "risk": assessment
+        }),
+        ip_address: risk_context.ip_address,
+        user_agent: risk_context.user_agent.clone(),
+        session_id: client.session_id,
+        risk_score: assessment.score,
+        status: outcome.to_string(),
+        created_at: Utc::now(),
+        sequence: None,
+        prev_hash: None,
+        entry_hash: None,
+        archive_id: None,
+    };
+    
+    if assessment.score >= state.risk.step_up_threshold() || assessment.action != RiskAction::Allow {
+        state.alerts.publish(SecurityAlert::from_audit(&audit_log, &assessment));
+    }
+
+    // Save audit log asynchronously
+    tokio::spawn(async move {
+        if let Err(e) = save_audit_log(&state.db, audit_log).await {
+            tracing::error!("Failed to save audit log: {}", e);
+        }
+    });
+    
+    Ok(response)
+}
+
+const STEP_UP_PATH_SUFFIX: &str = "/mfa/step-up";
+
+/// Risky requests go through once the caller presents a live elevated
+/// session; the step-up proves a second factor rather than echoing a token.
+async fn has_elevated_session(db: &PgPool, user_id: Option<Uuid>, headers: &HeaderMap) -> bool {
+    let (Some(user_id), Some(token)) = (user_id, elevated_token_from_headers(headers)) else {
+        return false;
+    };
+    verify_elevated_session(db, user_id, token).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to verify elevated session for risk step-up: {}", e);
+        false
+    })
+}
+
+/// Fails closed: a lookup error counts as enrolled, so step-up still applies.
+async fn has_second_factor(db: &PgPool, user_id: Option<Uuid>) -> bool {
+    let Some(user_id) = user_id else {
+        return false;
+    };
+    mfa_enrolled(db, user_id).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to check MFA enrolment for risk step-up: {}", e);
+        true
+    })
+}
+
+/// The user an auth layer vouched for. Headers such as `x-user-id` are
+/// client-controlled and never identify anyone.
+pub fn authenticated_user_id(extensions: &axum::http::Extensions) -> Option<Uuid> {
+    extensions.get::<UserContext>().map(|context| context.user_id)
+}
+
+fn extract_resource_type(uri: &str) -> Option<String> {
+    if uri.starts_with("/ai/") || uri.contains("/api/ai/") {
+        Some("ai_request".to_string())
+    } else if uri.contains("/api/proxy/") {
+        Some("proxy_request".to_string())
+    } else if uri.contains("/api/files/") {
+        Some("file_operation".to_string())
+    } else if uri.contains("/api/plugins/") {
+        Some("plugin_operation".to_string())
+    } else {
+        None
+    }
+}
+
+fn extract_resource_id(uri: &str) -> Option<String> {
+    use regex::Regex;
+    let re = Regex::new(r"/([^/]+)/?$").ok()?;
+    let captures = re.captures(uri)?;
+    captures.get(1).map(|m| m.as_str().to_string())
+}
+
+async fn save_audit_log(db: &PgPool, audit_log: AuditLog) -> Result<(), sqlx::Error> {
+    append_audit_log(db, audit_log).await.map(|_| ())
+}
+
+/// Appends an application event, as opposed to a raw request, to the audit chain.
+pub(crate) async fn record_audit_event(
+    db: &PgPool,
+    user_id: Option<Uuid>,
+    action: &str,
+    resource_type: &str,
+    resource_id: &str,
+    details: Value,
+    client: Option<&ClientInfo>,
+) {
+    let entry = AuditLog {
+        id: Uuid::new_v4(),
+        user_id,
+        action: action.to_string(),
+        resource_type: Some(resource_type.to_string()),
+        resource_id: Some(resource_id.to_string()),
+        details,
+        ip_address: client.and_then(|c| c.ip_address),
+        user_agent: client.and_then(|c| c.user_agent.clone()),
+        session_id: client.and_then(|c| c.session_id.clone()),
+        risk_score: 0,
+        status: "success".to_string(),
+        created_at: Utc::now(),
+        sequence: None,
+        prev_hash: None,
+        entry_hash: None,
+        archive_id: None,
+    };
+    if let Err(e) = append_audit_log(db, entry).await {
+        tracing::error!("Failed to audit {} on {} {}: {}", action, resource_type, resource_id, e);
+    }
+}
+
+fn db_error(e: sqlx::Error) -> axum::http::StatusCode {
+    tracing::error!("Database error: {}", e);
+    axum::http::StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Row shape for `audit_logs`; INET comes back as text because sqlx has no
+/// `IpAddr` mapping without the ipnetwork feature.
+struct AuditLogRow {
+    id: Uuid,
+    user_id: Option<Uuid>,
+    action: String,
+    resource_type: Option<String>,
+    resource_id: Option<String>,
+    details: Value,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    session_id: Option<String>,
+    risk_score: i32,
+    status: String,
+    created_at: DateTime<Utc>,
+    sequence: Option<i64>,
+    prev_hash: Option<String>,
+    entry_hash: Option<String>,
+    archive_id: Option<Uuid>,
+}
+
+impl From<AuditLogRow> for AuditLog {
+    fn from(row: AuditLogRow) -> Self {
+        Self {
+            id: row.id,
+            user_id: row.user_id,
+            action: row.action,
+            resource_type: row.resource_type,
+            resource_id: row.resource_id,
+            details: row.details,
+            ip_address: row.ip_address.and_then(|ip| ip.parse().ok()),
+            user_agent: row.user_agent,
+            session_id: row.session_id,
+            risk_score: row.risk_score,
+            status: row.status,
+            created_at: row.created_at,
+            sequence: row.sequence,
+            prev_hash: row.prev_hash,
+            entry_hash: row.entry_hash,
+            archive_id: row.archive_id,
+        }
+    }
+}
+
+/// Chained entries after `after_sequence`, in chain order.
+async fn fetch_chained_logs(db: &PgPool, after_sequence: i64, limit: i64) -> Result<Vec<AuditLog>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        AuditLogRow,
+        r#"
+        SELECT id, user_id, action, resource_type, resource_id,
+               details AS "details!", host(ip_address) AS ip_address, user_agent, session_id,
+               risk_score AS "risk_score!", status AS "status!", created_at AS "created_at!",
+               sequence, prev_hash, entry_hash, archive_id
+        FROM audit_logs
+        WHERE sequence > $1
+        ORDER BY sequence ASC
+        LIMIT $2
+        "#,
+        after_sequence,
+        limit
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().map(AuditLog::from).collect())
+}
+
+async fn query_audit_logs(db: &PgPool, params: &GetAuditLogsQuery) -> Result<Vec<AuditLog>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        AuditLogRow,
+        r#"
+        SELECT id, user_id, action, resource_type, resource_id,
+               details AS "details!", host(ip_address) AS ip_address, user_agent, session_id,
+               risk_score AS "risk_score!", status AS "status!", created_at AS "created_at!",
+               sequence, prev_hash, entry_hash, archive_id
+        FROM audit_logs
+        WHERE archive_id IS NULL
+          AND ($1::uuid IS NULL OR user_id = $1)
+          AND ($2::text IS NULL OR action = $2)
+          AND ($3::text IS NULL OR resource_type = $3)
+          AND ($4::text IS NULL OR resource_id = $4)
+          AND ($5::text IS NULL OR status = $5)
+          AND ($6::int IS NULL OR risk_score >= $6)
+          AND ($7::int IS NULL OR risk_score <= $7)
+          AND ($8::timestamptz IS NULL OR created_at >= $8)
+          AND ($9::timestamptz IS NULL OR created_at <= $9)
+        ORDER BY created_at DESC, sequence DESC NULLS LAST
+        LIMIT $10 OFFSET $11
+        "#,
+        params.user_id,
+        params.action,
+        params.resource_type,
+        params.resource_id,
+        params.status,
+        params.min_risk_score,
+        params.max_risk_score,
+        params.from_date,
+        params.to_date,
+        params.limit.unwrap_or(100).clamp(1, 1000),
+        params.offset.unwrap_or(0).max(0)
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().map(AuditLog::from).collect())
+}
+
+// API Endpoints
+pub async fn get_audit_logs(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+    Query(params): Query<GetAuditLogsQuery>,
+) -> Result<Json<Vec<AuditLog>>, axum::http::StatusCode> {
+    require_admin(user)?;
+    query_audit_logs(&state.db, &params).await.map(Json).map_err(db_error)
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GetAuditLogsQuery {
+    pub user_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    pub status: Option<String>,
+    pub min_risk_score: Option<i32>,
+    pub max_risk_score: Option<i32>,
+    pub from_date: Option<DateTime<Utc>>,
+    pub to_date: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub async fn create_consent_record(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+    Json(request): Json<CreateConsentRecordRequest>,
+) -> Result<Json<ConsentRecord>, axum::http::StatusCode> {
+    let Some(Extension(user)) = user else {
+        return Err(axum::http::StatusCode::UNAUTHORIZED);
+    };
+    if request.consent_type.trim().is_empty() || request.version.trim().is_empty() {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    let granted_at = Utc::now();
+    let consent_record = ConsentRecord {
+        id: Uuid::new_v4(),
+        user_id: user.user_id,
+        expires_at: if request.granted { state.consent.expiry_for(&request.consent_type, granted_at) } else { None },
+        consent_type: request.consent_type,
+        version: request.version,
+        granted: request.granted,
+        granted_at,
+        revoked_at: None,
+        ip_address: client.ip_address,
+        user_agent: client.user_agent.clone(),
+        metadata: request.metadata.unwrap_or_else(|| serde_json::json!({})),
+    };
+
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    // A new decision supersedes any grant still open for the same type
+    sqlx::query!(
+        r#"
+        UPDATE consent_records
+        SET revoked_at = $3
+        WHERE user_id = $1 AND consent_type = $2 AND granted AND revoked_at IS NULL
+        "#,
+        consent_record.user_id,
+        consent_record.consent_type,
+        granted_at
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO consent_records
+            (id, user_id, consent_type, version, granted, granted_at, expires_at, ip_address, user_agent, metadata)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8::text::inet, $9, $10)
+        "#,
+        consent_record.id,
+        consent_record.user_id,
+        consent_record.consent_type,
+        consent_record.version,
+        consent_record.granted,
+        consent_record.granted_at,
+        consent_record.expires_at,
+        consent_record.ip_address.map(|ip| ip.to_string()),
+        consent_record.user_agent,
+        consent_record.metadata
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    let action = if consent_record.granted { "consent.granted" } else { "consent.withdrawn" };
+    record_audit_event(&state.db, Some(consent_record.user_id), action, "consent", &consent_record.consent_type, serde_json::json!({
+        "version": consent_record.version,
+        "expires_at": consent_record.expires_at
+    }), Some(&client)).await;
+
+    if !consent_record.granted {
+        if let Some(purpose) = ConsentPurpose::parse(&consent_record.consent_type) {
+            state.consent.purge_purpose_data(consent_record.user_id, purpose).await.map_err(db_error)?;
+        }
+    }
+
+    Ok(Json(consent_record))
+}
+
+pub async fn get_user_consents(
+    State(state): State<SecurityState>,
+    Path(user_id): Path<Uuid>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<Vec<ConsentRecord>>, axum::http::StatusCode> {
+    require_self(user, user_id)?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, user_id AS "user_id!", consent_type, version, granted, granted_at AS "granted_at!",
+               revoked_at, expires_at, host(ip_address) AS ip_address, user_agent, metadata AS "metadata!"
+        FROM consent_records
+        WHERE user_id = $1
+        ORDER BY granted_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    let consents = rows
+        .into_iter()
+        .map(|row| ConsentRecord {
+            id: row.id,
+            user_id: row.user_id,
+            consent_type: row.consent_type,
+            version: row.version,
+            granted: row.granted,
+            granted_at: row.granted_at,
+            revoked_at: row.revoked_at,
+            expires_at: row.expires_at,
+            ip_address: row.ip_address.and_then(|ip| ip.parse().ok()),
+            user_agent: row.user_agent,
+            metadata: row.metadata,
+        })
+        .collect();
+
+    Ok(Json(consents))
+}
+
+/// Whether the user's consent currently allows processing for a purpose.
+pub async fn get_consent_status(
+    State(state): State<SecurityState>,
+    SafePath((user_id, purpose)): SafePath<(Uuid, String)>,
+    user: Option<Extension<UserContext>>,
+) -> Result<Json<ConsentStatus>, axum::http::StatusCode> {
+    require_self(user, user_id)?;
+    let purpose = ConsentPurpose::parse(&purpose).ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    state
+        .consent
+        .status(Some(user_id), purpose)
+        .await
+        .map(Json)
+        .map_err(db_error)
+}
+
+pub async fn update_privacy_settings(
+    Path(user_id): Path<Uuid>,
+    Json(request): Json<UpdatePrivacySettingsRequest>,
+) -> Result<Json<Vec<PrivacySetting>>, axum::http::StatusCode> {
+    let mut updated_settings = Vec::new();
+    
+    for (key, value) in request.settings {
+        let setting = PrivacySetting {
+            id: Uuid::new_v4(),
+            user_id,
+            setting_key: key.clone(),
+            setting_value: value,
+            category: extract_setting_category(&key),
+            updated_at: Utc::now(),
+        };
+        
+        // Save to database
+        updated_settings.push(setting);
+    }
+
+    Ok(Json(updated_settings))
+}
+
+fn extract_setting_category(key: &str) -> Option<String> {
+    if key.starts_with("data_collection.") {
+        Some("data_collection".to_string())
+    } else if key.starts_with("sharing.") {
+        Some("sharing".to_string())
+    } else if key.starts_with("analytics.") {
+        Some("analytics".to_string())
+    } else if key.starts_with("security.") {
+        Some("security".to_string())
+    } else {
+        None
+    }
+}
+
+// Additional security utilities
+/// Cheap format check before any database lookup; see `authenticate_api_key`.
+pub fn validate_api_key(api_key: &str) -> bool {
+    api_key.strip_prefix(API_KEY_PREFIX).is_some_and(|secret| {
+        secret.len() >= 22 && secret.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+pub fn generate_secure_token() -> String {
+    generate_token(CryptoKeys::global().token_bytes)
+}
+
+/// Keyed, stable digest for values that are looked up but never shown again.
+pub fn hash_sensitive_data(data: &str) -> String {
+    CryptoKeys::global().keyed_hash(data)
+}
+
+/// Re-wraps stored secrets with the active encryption key after a rotation.
+pub async fn rewrap_encrypted_secrets(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+    client: ClientInfo,
+) -> Result<Json<Value>, axum::http::StatusCode> {
+    let admin = require_admin(user)?;
+    let active_key_id = CryptoKeys::global().active_key_id();
+    let ai_models = crate::models::rewrap_model_api_keys(&state.db).await.map_err(db_error)?;
+
+    record_audit_event(&state.db, Some(admin.user_id), "encryption_keys.rewrapped", "encryption_key", active_key_id, serde_json::json!({
+        "ai_models": ai_models
+    }), Some(&client)).await;
+
+    Ok(Json(serde_json::json!({
+        "active_key_id": active_key_id,
+        "ai_models": ai_models
+    })))
+}
+
+pub async fn get_user_privacy_settings(
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<PrivacySetting>>, axum::http::StatusCode> {
+    // Get user's privacy settings
+    let mock_settings = vec![
+        PrivacySetting {
+            id: Uuid::new_v4(),
+            user_id,
+            setting_key: "data_collection.analytics".to_string(),
+            setting_value: serde_json::json!(true),
+            category: Some("data_collection".to_string()),
+            updated_at: Utc::now(),
+        },
+        PrivacySetting {
+            id: Uuid::new_v4(),
+            user_id,
+            setting_key: "sharing.profile_visibility".to_string(),
+            setting_value: serde_json::json!("public"),
+            category: Some("sharing".to_string()),
+            updated_at: Utc::now(),
+        }
+    ];
+
+    Ok(Json(mock_settings))
+}
+
+// Data Retention Policies
+pub async fn cleanup_old_audit_logs(db: &PgPool) -> Result<RetentionReport, AuditArchiveError> {
+    // Expired entries are archived per resource type before they leave the table
+    let report = run_audit_retention(db, &AuditRetentionConfig::load()).await?;
+    tracing::info!(
+        "Archived {} audit logs, removed {}, chain now starts after {}",
+        report.archived,
+        report.deleted,
+        report.pruned_through_sequence
+    );
+    Ok(report)
+}
+
+pub async fn cleanup_expired_consents(db: &PgPool) -> Result<u64, sqlx::Error> {
+    // Expired grants are closed and the data they covered is purged
+    ConsentGate::new(db.clone()).cleanup_expired().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request as HttpRequest;
+
+    #[test]
+    fn forged_user_header_does_not_identify_anyone() {
+        let request = HttpRequest::builder()
+            .header("x-user-id", Uuid::new_v4().to_string())
+            .body(())
+            .unwrap();
+        assert_eq!(authenticated_user_id(request.extensions()), None);
+    }
+
+    #[test]
+    fn user_comes_from_the_authenticated_context() {
+        let user_id = Uuid::new_v4();
+        let mut request = HttpRequest::builder()
+            .header("x-user-id", Uuid::new_v4().to_string())
+            .body(())
+            .unwrap();
+        request.extensions_mut().insert(UserContext { user_id, permissions: Vec::new(), is_admin: false });
+        assert_eq!(authenticated_user_id(request.extensions()), Some(user_id));
+    }
+
+    #[tokio::test]
+    async fn audit_log_queries_are_refused_without_an_admin() {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let state = SecurityState::new(db);
+        let caller = UserContext { user_id: Uuid::new_v4(), permissions: Vec::new(), is_admin: false };
+
+        let anonymous = get_audit_logs(State(state.clone()), None, Query(GetAuditLogsQuery::default())).await;
+        assert_eq!(anonymous.err(), Some(axum::http::StatusCode::UNAUTHORIZED));
+        let non_admin = get_audit_logs(State(state), Some(Extension(caller)), Query(GetAuditLogsQuery::default())).await;
+        assert_eq!(non_admin.err(), Some(axum::http::StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn ai_generation_route_maps_to_the_ai_resource_type() {
+        assert_eq!(extract_resource_type("/ai/generate").as_deref(), Some("ai_request"));
+        assert_eq!(extract_resource_type("/api/plugins/install").as_deref(), Some("plugin_operation"));
+        assert_eq!(extract_resource_type("/api/workspaces/ai/x"), None);
+    }
+}
@@ -0,0 +1,507 @@
+// Per-route-group request rate limiting, shared across instances through Redis
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{Extensions, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use redis::aio::MultiplexedConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+use super::{resolve_client_ip, TrustedProxies, UserContext};
+use crate::error::AppError;
+
+/// Local buckets are swept once the map grows past this many keys.
+const LOCAL_SWEEP_THRESHOLD: usize = 10_000;
+
+// Both scripts read the clock from Redis so every instance agrees on "now"
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local t = redis.call('TIME')
+local now = tonumber(t[1]) * 1000 + math.floor(tonumber(t[2]) / 1000)
+local window = tonumber(ARGV[1])
+local limit = tonumber(ARGV[2])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], 0, now - window)
+local count = redis.call('ZCARD', KEYS[1])
+if count < limit then
+    redis.call('ZADD', KEYS[1], now, now .. '-' .. ARGV[3])
+    redis.call('PEXPIRE', KEYS[1], window)
+    return {1, limit - count - 1, 0}
+end
+local oldest = redis.call('ZRANGE', KEYS[1], 0, 0, 'WITHSCORES')
+return {0, 0, tonumber(oldest[2]) + window - now}
+"#;
+
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local t = redis.call('TIME')
+local now = tonumber(t[1]) * 1000 + math.floor(tonumber(t[2]) / 1000)
+local capacity = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local rate = capacity / window
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(state[1]) or capacity
+local updated = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated) * rate)
+local allowed = 0
+local retry = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+else
+    retry = math.ceil((1 - tokens) / rate)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated', now)
+redis.call('PEXPIRE', KEYS[1], window)
+return {allowed, math.floor(tokens), retry}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteGroup {
+    Ai,
+    Plugins,
+    Proxy,
+}
+
+impl RouteGroup {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteGroup::Ai => "ai",
+            RouteGroup::Plugins => "plugins",
+            RouteGroup::Proxy => "proxy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAlgorithm {
+    /// At most `max_requests` in any trailing window.
+    SlidingWindow,
+    /// Bursts up to `max_requests`, refilled evenly over the window.
+    TokenBucket,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitPolicy {
+    pub algorithm: RateLimitAlgorithm,
+    pub max_requests: u32,
+    pub window_seconds: u64,
+}
+
+impl RateLimitPolicy {
+    fn window_ms(&self) -> i64 {
+        (self.window_seconds.max(1) * 1000) as i64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Shared store for multi-instance deployments; limits are per process without it.
+    pub redis_url: Option<String>,
+    pub key_prefix: String,
+    pub ai: RateLimitPolicy,
+    pub plugins: RateLimitPolicy,
+    pub proxy: RateLimitPolicy,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            redis_url: None,
+            key_prefix: "jeantrail:ratelimit".to_string(),
+            ai: RateLimitPolicy {
+                algorithm: RateLimitAlgorithm::TokenBucket,
+                max_requests: 30,
+                window_seconds: 60,
+            },
+            plugins: RateLimitPolicy {
+                algorithm: RateLimitAlgorithm::SlidingWindow,
+                max_requests: 120,
+                window_seconds: 60,
+            },
+            proxy: RateLimitPolicy {
+                algorithm: RateLimitAlgorithm::SlidingWindow,
+                max_requests: 60,
+                window_seconds: 60,
+            },
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Loads the config from the JSON file named by `RATE_LIMIT_CONFIG`,
+    /// falling back to the defaults. `REDIS_URL` overrides the file's store.
+    pub fn load() -> Self {
+        let mut config = match std::env::var("RATE_LIMIT_CONFIG") {
+            Ok(path) => match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+                Ok(config) => config,
+                Err(e) => {
+                    tracing::warn!("Ignoring rate limit config {}: {}", path, e);
+                    Self::default()
+                }
+            },
+            Err(_) => Self::default(),
+        };
+        if let Ok(url) = std::env::var("REDIS_URL") {
+            config.redis_url = Some(url);
+        }
+        config
+    }
+
+    pub fn policy(&self, group: RouteGroup) -> &RateLimitPolicy {
+        match group {
+            RouteGroup::Ai => &self.ai,
+            RouteGroup::Plugins => &self.plugins,
+            RouteGroup::Proxy => &self.proxy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until the next request would be admitted; zero when allowed.
+    pub retry_after_ms: u64,
+}
+
+impl RateLimitDecision {
+    fn allow_all() -> Self {
+        Self { allowed: true, limit: 0, remaining: 0, retry_after_ms: 0 }
+    }
+
+    pub fn retry_after_secs(&self) -> u64 {
+        self.retry_after_ms.div_ceil(1000).max(1)
+    }
+
+    fn apply_headers(&self, headers: &mut HeaderMap) {
+        if self.limit == 0 {
+            return;
+        }
+        headers.insert("x-ratelimit-limit", HeaderValue::from(self.limit));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(self.remaining));
+        if !self.allowed {
+            headers.insert("x-ratelimit-reset", HeaderValue::from(self.retry_after_secs()));
+        }
+    }
+}
+
+enum LocalBucket {
+    Window(VecDeque<i64>),
+    Tokens { tokens: f64, updated_ms: i64 },
+}
+
+impl LocalBucket {
+    fn new(policy: &RateLimitPolicy) -> Self {
+        match policy.algorithm {
+            RateLimitAlgorithm::SlidingWindow => LocalBucket::Window(VecDeque::new()),
+            RateLimitAlgorithm::TokenBucket => LocalBucket::Tokens {
+                tokens: policy.max_requests as f64,
+                updated_ms: chrono::Utc::now().timestamp_millis(),
+            },
+        }
+    }
+
+    /// Mirrors the Redis scripts so both stores admit the same traffic.
+    fn admit(&mut self, policy: &RateLimitPolicy, now_ms: i64) -> RateLimitDecision {
+        let limit = policy.max_requests;
+        let window = policy.window_ms();
+        match self {
+            LocalBucket::Window(requests) => {
+                while requests.front().is_some_and(|&t| t <= now_ms - window) {
+                    requests.pop_front();
+                }
+                if (requests.len() as u32) < limit {
+                    requests.push_back(now_ms);
+                    RateLimitDecision { allowed: true, limit, remaining: limit - requests.len() as u32, retry_after_ms: 0 }
+                } else {
+                    let oldest = requests.front().copied().unwrap_or(now_ms);
+                    RateLimitDecision { allowed: false, limit, remaining: 0, retry_after_ms: (oldest + window - now_ms).max(0) as u64 }
+                }
+            }
+            LocalBucket::Tokens { tokens, updated_ms } => {
+                let rate = limit as f64 / window as f64;
+                *tokens = (*tokens + (now_ms - *updated_ms).max(0) as f64 * rate).min(limit as f64);
+                *updated_ms = now_ms;
+                if *tokens >= 1.0 {
+                    *tokens -= 1.0;
+                    RateLimitDecision { allowed: true, limit, remaining: *tokens as u32, retry_after_ms: 0 }
+                } else {
+                    RateLimitDecision { allowed: false, limit, remaining: 0, retry_after_ms: ((1.0 - *tokens) / rate).ceil() as u64 }
+                }
+            }
+        }
+    }
+
+    fn is_idle(&self, policy: &RateLimitPolicy, now_ms: i64) -> bool {
+        match self {
+            LocalBucket::Window(requests) => requests.back().is_none_or(|&t| t <= now_ms - policy.window_ms()),
+            LocalBucket::Tokens { updated_ms, .. } => *updated_ms <= now_ms - policy.window_ms(),
+        }
+    }
+}
+
+struct RedisStore {
+    client: redis::Client,
+    connection: OnceCell<MultiplexedConnection>,
+}
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    local: Mutex<HashMap<(RouteGroup, String), LocalBucket>>,
+    redis: Option<RedisStore>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        let redis = config.redis_url.as_deref().and_then(|url| match redis::Client::open(url) {
+            Ok(client) => Some(RedisStore { client, connection: OnceCell::new() }),
+            Err(e) => {
+                tracing::warn!("Invalid REDIS_URL, rate limits will be per instance: {}", e);
+                None
+            }
+        });
+        Self { config, local: Mutex::new(HashMap::new()), redis }
+    }
+
+    /// Counts one request from `subject` against the group's policy.
+    pub async fn check(&self, group: RouteGroup, subject: &str) -> RateLimitDecision {
+        if !self.config.enabled {
+            return RateLimitDecision::allow_all();
+        }
+        let policy = self.config.policy(group);
+        if let Some(store) = &self.redis {
+            match self.check_redis(store, group, policy, subject).await {
+                Ok(decision) => return decision,
+                // Degrade to per-instance limits rather than failing open or closed
+                Err(e) => tracing::warn!("Redis rate limiter unavailable, using local limits: {}", e),
+            }
+        }
+        self.check_local(group, policy, subject)
+    }
+
+    fn check_local(&self, group: RouteGroup, policy: &RateLimitPolicy, subject: &str) -> RateLimitDecision {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let mut buckets = self.local.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if buckets.len() > LOCAL_SWEEP_THRESHOLD {
+            buckets.retain(|(g, _), bucket| !bucket.is_idle(self.config.policy(*g), now_ms));
+        }
+        buckets
+            .entry((group, subject.to_string()))
+            .or_insert_with(|| LocalBucket::new(policy))
+            .admit(policy, now_ms)
+    }
+
+    async fn check_redis(
+        &self,
+        store: &RedisStore,
+        group: RouteGroup,
+        policy: &RateLimitPolicy,
+        subject: &str,
+    ) -> Result<RateLimitDecision, redis::RedisError> {
+        let mut connection = store
+            .connection
+            .get_or_try_init(|| store.client.get_multiplexed_async_connection())
+            .await?
+            .clone();
+        let key = format!("{}:{}:{}", self.config.key_prefix, group.as_str(), subject);
+
+        let (allowed, remaining, retry_after_ms): (i64, i64, i64) = match policy.algorithm {
+            RateLimitAlgorithm::SlidingWindow => {
+                redis::Script::new(SLIDING_WINDOW_SCRIPT)
+                    .key(&key)
+                    .arg(policy.window_ms())
+                    .arg(policy.max_requests)
+                    .arg(uuid::Uuid::new_v4().to_string())
+                    .invoke_async(&mut connection)
+                    .await?
+            }
+            RateLimitAlgorithm::TokenBucket => {
+                redis::Script::new(TOKEN_BUCKET_SCRIPT)
+                    .key(&key)
+                    .arg(policy.max_requests)
+                    .arg(policy.window_ms())
+                    .invoke_async(&mut connection)
+                    .await?
+            }
+        };
+
+        Ok(RateLimitDecision {
+            allowed: allowed == 1,
+            limit: policy.max_requests,
+            remaining: remaining.max(0) as u32,
+            retry_after_ms: retry_after_ms.max(0) as u64,
+        })
+    }
+}
+
+/// Middleware state binding the shared limiter to one route group.
+#[derive(Clone)]
+pub struct RateLimitState {
+    pub limiter: Arc<RateLimiter>,
+    pub group: RouteGroup,
+}
+
+impl RateLimitState {
+    pub fn new(limiter: Arc<RateLimiter>, group: RouteGroup) -> Self {
+        Self { limiter, group }
+    }
+}
+
+/// Picks who a request is counted against: the user the auth layer
+/// authenticated, then the client address. Presented credentials are never
+/// used directly, since made-up keys would each get a fresh bucket.
+pub fn rate_limit_subject(request: &Request) -> String {
+    subject_for(request.headers(), request.extensions(), TrustedProxies::global())
+}
+
+fn subject_for(headers: &HeaderMap, extensions: &Extensions, trusted: &TrustedProxies) -> String {
+    if let Some(user) = extensions.get::<UserContext>() {
+        return format!("user:{}", user.user_id);
+    }
+
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    match resolve_client_ip(headers, peer, trusted) {
+        Some(ip) => format!("ip:{}", ip),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+/// Rejects requests over the group's limit with a 429 and `Retry-After`.
+pub async fn rate_limit_middleware(
+    State(state): State<RateLimitState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let subject = rate_limit_subject(&request);
+    let decision = state.limiter.check(state.group, &subject).await;
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        tracing::warn!("Rate limit exceeded for {} on {} routes", subject, state.group.as_str());
+        AppError::RateLimitExceeded { retry_after: decision.retry_after_secs() }.into_response()
+    };
+    decision.apply_headers(response.headers_mut());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    fn policy(algorithm: RateLimitAlgorithm, max_requests: u32) -> RateLimitPolicy {
+        RateLimitPolicy { algorithm, max_requests, window_seconds: 60 }
+    }
+
+    fn request_from(peer: &str, headers: &[(&'static str, &str)]) -> (HeaderMap, Extensions) {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            header_map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        (header_map, extensions)
+    }
+
+    #[test]
+    fn made_up_api_keys_share_the_client_bucket() {
+        let trusted = TrustedProxies::default();
+        let (first_headers, first_ext) = request_from("203.0.113.7:5000", &[("x-api-key", "random-1")]);
+        let (second_headers, second_ext) = request_from("203.0.113.7:5001", &[("authorization", "Bearer random-2")]);
+
+        let first = subject_for(&first_headers, &first_ext, &trusted);
+        assert_eq!(first, "ip:203.0.113.7");
+        assert_eq!(first, subject_for(&second_headers, &second_ext, &trusted));
+    }
+
+    #[test]
+    fn authenticated_requests_are_counted_against_the_user() {
+        let user_id = Uuid::new_v4();
+        let (headers, mut extensions) = request_from("203.0.113.7:5000", &[("x-api-key", "valid-key")]);
+        extensions.insert(UserContext { user_id, permissions: Vec::new(), is_admin: false });
+
+        assert_eq!(subject_for(&headers, &extensions, &TrustedProxies::default()), format!("user:{}", user_id));
+    }
+
+    #[test]
+    fn forwarded_addresses_from_untrusted_peers_are_ignored() {
+        let (headers, extensions) = request_from("198.51.100.1:5000", &[("x-forwarded-for", "10.9.8.7")]);
+        assert_eq!(subject_for(&headers, &extensions, &TrustedProxies::default()), "ip:198.51.100.1");
+    }
+
+    #[test]
+    fn token_buckets_drain_then_refill_evenly() {
+        let policy = policy(RateLimitAlgorithm::TokenBucket, 3);
+        let mut bucket = LocalBucket::Tokens { tokens: 3.0, updated_ms: 0 };
+
+        let remaining: Vec<u32> = (0..3).map(|_| bucket.admit(&policy, 0).remaining).collect();
+        assert_eq!(remaining, vec![2, 1, 0]);
+
+        // One token comes back every 20 seconds
+        let exhausted = bucket.admit(&policy, 5_000);
+        assert!(!exhausted.allowed);
+        assert_eq!(exhausted.retry_after_ms, 15_000);
+        assert_eq!(exhausted.retry_after_secs(), 15);
+        assert!(bucket.admit(&policy, 20_000).allowed);
+        assert!(!bucket.admit(&policy, 20_000).allowed);
+
+        // Refill is capped at the burst size
+        let refilled = bucket.admit(&policy, 600_000);
+        assert!(refilled.allowed);
+        assert_eq!(refilled.remaining, 2);
+    }
+
+    #[test]
+    fn sliding_windows_admit_again_as_requests_age_out() {
+        let policy = policy(RateLimitAlgorithm::SlidingWindow, 2);
+        let mut bucket = LocalBucket::new(&policy);
+
+        assert_eq!(bucket.admit(&policy, 1_000).remaining, 1);
+        assert_eq!(bucket.admit(&policy, 10_000).remaining, 0);
+        let exhausted = bucket.admit(&policy, 30_000);
+        assert!(!exhausted.allowed);
+        assert_eq!(exhausted.retry_after_ms, 31_000);
+
+        assert!(!bucket.admit(&policy, 60_999).allowed);
+        assert!(bucket.admit(&policy, 61_000).allowed);
+        assert!(!bucket.is_idle(&policy, 61_000));
+        assert!(bucket.is_idle(&policy, 121_000));
+    }
+
+    #[tokio::test]
+    async fn requests_over_the_limit_get_429_with_retry_after() {
+        let config = RateLimitConfig { ai: policy(RateLimitAlgorithm::SlidingWindow, 2), ..RateLimitConfig::default() };
+        let state = RateLimitState::new(Arc::new(RateLimiter::new(config)), RouteGroup::Ai);
+        let app = Router::new()
+            .route("/ai", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(state, rate_limit_middleware));
+        let request = || Request::builder().uri("/ai").body(Body::empty()).unwrap();
+
+        let first = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()["x-ratelimit-limit"], "2");
+        assert_eq!(first.headers()["x-ratelimit-remaining"], "1");
+        assert_eq!(app.clone().oneshot(request()).await.unwrap().status(), StatusCode::OK);
+
+        let limited = app.oneshot(request()).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()["x-ratelimit-remaining"], "0");
+        let retry_after: u64 = limited.headers()[axum::http::header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((59..=60).contains(&retry_after));
+        assert_eq!(limited.headers()["x-ratelimit-reset"], limited.headers()[axum::http::header::RETRY_AFTER]);
+    }
+}
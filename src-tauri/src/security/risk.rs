@@ -0,0 +1,597 @@
+// Rule-based request risk scoring with MFA step-up and blocking
+use axum::{
+    http::{Method, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Timelike, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::ELEVATED_SESSION_HEADER;
+
+pub const MAX_RISK_SCORE: i32 = 100;
+
+/// Activity is kept this long; rules cannot look further back.
+const ACTIVITY_RETENTION_SECONDS: i64 = 3600;
+/// Subjects are swept once the map grows past this many entries.
+const ACTIVITY_SWEEP_THRESHOLD: usize = 50_000;
+/// Known IPs and user agents are seeded from audit history this far back.
+const HISTORY_SEED_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RiskRuleConfig {
+    /// More than `max_requests` from one subject within the window.
+    Velocity { window_seconds: i64, max_requests: usize, points: i32 },
+    /// A user's request comes from an address not seen for them before.
+    NewIp { points: i32 },
+    NewUserAgent { points: i32 },
+    /// Activity outside business hours in the configured zone.
+    OffHours {
+        business_start_hour: u32,
+        business_end_hour: u32,
+        #[serde(default)]
+        utc_offset_minutes: i32,
+        #[serde(default = "default_true")]
+        weekends_off_hours: bool,
+        points: i32,
+    },
+    /// Paths containing any of `paths`, or mapped to one of `resource_types`.
+    SensitiveResource {
+        #[serde(default)]
+        paths: Vec<String>,
+        #[serde(default)]
+        resource_types: Vec<String>,
+        /// Empty matches every method.
+        #[serde(default)]
+        methods: Vec<String>,
+        points: i32,
+    },
+    /// `threshold` or more 401/403 responses for the subject within the window.
+    FailedAuthStreak { threshold: usize, window_seconds: i64, points: i32 },
+    DestructiveMethod { methods: Vec<String>, points: i32 },
+    PathTraversal { points: i32 },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    /// Scores at or above this need an elevated MFA session.
+    pub step_up_threshold: i32,
+    /// Scores at or above this are refused outright.
+    pub block_threshold: i32,
+    pub rules: Vec<RiskRuleConfig>,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            step_up_threshold: 70,
+            block_threshold: 100,
+            rules: vec![
+                RiskRuleConfig::Velocity { window_seconds: 60, max_requests: 120, points: 30 },
+                RiskRuleConfig::NewIp { points: 20 },
+                RiskRuleConfig::NewUserAgent { points: 10 },
+                RiskRuleConfig::OffHours {
+                    business_start_hour: 6,
+                    business_end_hour: 22,
+                    utc_offset_minutes: 0,
+                    weekends_off_hours: false,
+                    points: 10,
+                },
+                RiskRuleConfig::SensitiveResource {
+                    paths: vec!["/admin/".to_string(), "/api/security/".to_string()],
+                    resource_types: Vec::new(),
+                    methods: Vec::new(),
+                    points: 50,
+                },
+                RiskRuleConfig::SensitiveResource {
+                    paths: Vec::new(),
+                    resource_types: vec!["plugin_operation".to_string(), "file_operation".to_string()],
+                    methods: Vec::new(),
+                    points: 25,
+                },
+                RiskRuleConfig::SensitiveResource {
+                    paths: Vec::new(),
+                    resource_types: vec!["ai_request".to_string()],
+                    methods: Vec::new(),
+                    points: 15,
+                },
+                RiskRuleConfig::FailedAuthStreak { threshold: 5, window_seconds: 900, points: 40 },
+                RiskRuleConfig::DestructiveMethod { methods: vec!["DELETE".to_string()], points: 30 },
+                RiskRuleConfig::DestructiveMethod { methods: vec!["PUT".to_string(), "PATCH".to_string()], points: 20 },
+                RiskRuleConfig::PathTraversal { points: 100 },
+            ],
+        }
+    }
+}
+
+impl RiskConfig {
+    /// Loads the config from the JSON file named by `RISK_ENGINE_CONFIG`,
+    /// falling back to the defaults.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("RISK_ENGINE_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring risk engine config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// What the engine knows about the request being scored.
+#[derive(Debug, Clone)]
+pub struct RiskContext {
+    pub user_id: Option<Uuid>,
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub method: Method,
+    pub path: String,
+    pub resource_type: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+impl RiskContext {
+    /// Activity is tracked per user when known, otherwise per address.
+    pub fn subject(&self) -> String {
+        match (self.user_id, self.ip_address) {
+            (Some(user_id), _) => format!("user:{}", user_id),
+            (None, Some(ip)) => format!("ip:{}", ip),
+            (None, None) => "anonymous".to_string(),
+        }
+    }
+}
+
+/// Recent activity for one subject, as seen by this instance.
+#[derive(Debug, Default)]
+pub struct SubjectActivity {
+    requests: VecDeque<DateTime<Utc>>,
+    auth_failures: VecDeque<DateTime<Utc>>,
+    known_ips: HashSet<IpAddr>,
+    known_user_agents: HashSet<String>,
+    seeded: bool,
+}
+
+impl SubjectActivity {
+    pub fn requests_since(&self, since: DateTime<Utc>) -> usize {
+        self.requests.iter().rev().take_while(|at| **at > since).count()
+    }
+
+    pub fn auth_failures_since(&self, since: DateTime<Utc>) -> usize {
+        self.auth_failures.iter().rev().take_while(|at| **at > since).count()
+    }
+
+    /// New-device rules stay quiet until there is something to compare with.
+    pub fn has_history(&self) -> bool {
+        !self.known_ips.is_empty() || !self.known_user_agents.is_empty()
+    }
+
+    pub fn knows_ip(&self, ip: &IpAddr) -> bool {
+        self.known_ips.contains(ip)
+    }
+
+    pub fn knows_user_agent(&self, user_agent: &str) -> bool {
+        self.known_user_agents.contains(user_agent)
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - Duration::seconds(ACTIVITY_RETENTION_SECONDS);
+        while self.requests.front().is_some_and(|at| *at <= cutoff) {
+            self.requests.pop_front();
+        }
+        while self.auth_failures.front().is_some_and(|at| *at <= cutoff) {
+            self.auth_failures.pop_front();
+        }
+    }
+
+    fn last_seen(&self) -> Option<DateTime<Utc>> {
+        self.requests.back().copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FiredRule {
+    pub rule: String,
+    pub points: i32,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskAction {
+    Allow,
+    StepUp,
+    Block,
+}
+
+/// Stored under `details.risk` on the request's audit entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskAssessment {
+    pub score: i32,
+    pub action: RiskAction,
+    pub fired: Vec<FiredRule>,
+    /// Set when a step-up was satisfied by an elevated MFA session.
+    #[serde(default)]
+    pub confirmed: bool,
+    /// Set when step-up was waived because the user has no second factor to step up with.
+    #[serde(default)]
+    pub step_up_waived: bool,
+}
+
+impl RiskAssessment {
+    /// Whether the request must wait for an elevated MFA session.
+    pub fn requires_step_up(&self) -> bool {
+        self.action == RiskAction::StepUp && !self.confirmed && !self.step_up_waived
+    }
+}
+
+/// A scoring rule. Implement this to plug custom signals into the engine.
+pub trait RiskRule: Send + Sync {
+    fn name(&self) -> &str;
+    fn evaluate(&self, context: &RiskContext, activity: &SubjectActivity) -> Option<FiredRule>;
+}
+
+struct ConfiguredRule(RiskRuleConfig);
+
+impl ConfiguredRule {
+    fn fire(&self, points: i32, reason: String) -> Option<FiredRule> {
+        Some(FiredRule { rule: self.name().to_string(), points, reason })
+    }
+}
+
+impl RiskRule for ConfiguredRule {
+    fn name(&self) -> &str {
+        match &self.0 {
+            RiskRuleConfig::Velocity { .. } => "velocity",
+            RiskRuleConfig::NewIp { .. } => "new_ip",
+            RiskRuleConfig::NewUserAgent { .. } => "new_user_agent",
+            RiskRuleConfig::OffHours { .. } => "off_hours",
+            RiskRuleConfig::SensitiveResource { .. } => "sensitive_resource",
+            RiskRuleConfig::FailedAuthStreak { .. } => "failed_auth_streak",
+            RiskRuleConfig::DestructiveMethod { .. } => "destructive_method",
+            RiskRuleConfig::PathTraversal { .. } => "path_traversal",
+        }
+    }
+
+    fn evaluate(&self, context: &RiskContext, activity: &SubjectActivity) -> Option<FiredRule> {
+        match &self.0 {
+            RiskRuleConfig::Velocity { window_seconds, max_requests, points } => {
+                let count = activity.requests_since(context.at - Duration::seconds(*window_seconds));
+                (count > *max_requests).then(|| format!("{} requests in {}s (limit {})", count, window_seconds, max_requests))
+                    .and_then(|reason| self.fire(*points, reason))
+            }
+            RiskRuleConfig::NewIp { points } => {
+                let ip = context.ip_address?;
+                (context.user_id.is_some() && activity.has_history() && !activity.knows_ip(&ip))
+                    .then(|| self.fire(*points, format!("first request from {}", ip)))
+                    .flatten()
+            }
+            RiskRuleConfig::NewUserAgent { points } => {
+                let user_agent = context.user_agent.as_deref()?;
+                (context.user_id.is_some() && activity.has_history() && !activity.knows_user_agent(user_agent))
+                    .then(|| self.fire(*points, "first request from this user agent".to_string()))
+                    .flatten()
+            }
+            RiskRuleConfig::OffHours { business_start_hour, business_end_hour, utc_offset_minutes, weekends_off_hours, points } => {
+                let offset = FixedOffset::east_opt(utc_offset_minutes * 60)?;
+                let local = context.at.with_timezone(&offset);
+                let weekend = matches!(local.weekday(), Weekday::Sat | Weekday::Sun);
+                let hour = local.hour();
+                let in_hours = if business_start_hour <= business_end_hour {
+                    (*business_start_hour..*business_end_hour).contains(&hour)
+                } else {
+                    hour >= *business_start_hour || hour < *business_end_hour
+                };
+                ((*weekends_off_hours && weekend) || !in_hours)
+                    .then(|| self.fire(*points, format!("request at {}", local.format("%a %H:%M %:z"))))
+                    .flatten()
+            }
+            RiskRuleConfig::SensitiveResource { paths, resource_types, methods, points } => {
+                if !methods.is_empty() && !methods.iter().any(|m| m.eq_ignore_ascii_case(context.method.as_str())) {
+                    return None;
+                }
+                if let Some(path) = paths.iter().find(|p| context.path.contains(p.as_str())) {
+                    return self.fire(*points, format!("path contains {}", path));
+                }
+                let resource_type = context.resource_type.as_deref()?;
+                resource_types
+                    .iter()
+                    .any(|t| t == resource_type)
+                    .then(|| self.fire(*points, format!("resource type {}", resource_type)))
+                    .flatten()
+            }
+            RiskRuleConfig::FailedAuthStreak { threshold, window_seconds, points } => {
+                let failures = activity.auth_failures_since(context.at - Duration::seconds(*window_seconds));
+                (failures >= *threshold)
+                    .then(|| self.fire(*points, format!("{} failed authentications in {}s", failures, window_seconds)))
+                    .flatten()
+            }
+            RiskRuleConfig::DestructiveMethod { methods, points } => methods
+                .iter()
+                .any(|m| m.eq_ignore_ascii_case(context.method.as_str()))
+                .then(|| self.fire(*points, format!("{} request", context.method)))
+                .flatten(),
+            RiskRuleConfig::PathTraversal { points } => {
+                let lower = context.path.to_lowercase();
+                (lower.contains("..") || lower.contains("%2e%2e") || lower.contains("%2e."))
+                    .then(|| self.fire(*points, "path traversal sequence in path".to_string()))
+                    .flatten()
+            }
+        }
+    }
+}
+
+pub struct RiskEngine {
+    db: PgPool,
+    step_up_threshold: i32,
+    block_threshold: i32,
+    rules: Vec<Box<dyn RiskRule>>,
+    activity: Mutex<HashMap<String, SubjectActivity>>,
+}
+
+impl RiskEngine {
+    pub fn new(config: RiskConfig, db: PgPool) -> Self {
+        Self {
+            db,
+            step_up_threshold: config.step_up_threshold,
+            block_threshold: config.block_threshold,
+            rules: config.rules.into_iter().map(|rule| Box::new(ConfiguredRule(rule)) as Box<dyn RiskRule>).collect(),
+            activity: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Score at which requests count as high risk.
+    pub fn step_up_threshold(&self) -> i32 {
+        self.step_up_threshold
+    }
+
+    /// Adds a custom rule alongside the configured ones.
+    pub fn register(&mut self, rule: Box<dyn RiskRule>) {
+        self.rules.push(rule);
+    }
+
+    /// Records the request against its subject and scores it.
+    pub async fn assess(&self, context: &RiskContext) -> RiskAssessment {
+        let subject = context.subject();
+        if let Some(user_id) = context.user_id {
+            self.seed_history(&subject, user_id).await;
+        }
+
+        let mut activity = self.activity.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if activity.len() > ACTIVITY_SWEEP_THRESHOLD {
+            let cutoff = context.at - Duration::seconds(ACTIVITY_RETENTION_SECONDS);
+            activity.retain(|_, entry| entry.last_seen().is_some_and(|at| at > cutoff));
+        }
+        let entry = activity.entry(subject).or_default();
+        entry.prune(context.at);
+        entry.requests.push_back(context.at);
+
+        let fired: Vec<FiredRule> = self.rules.iter().filter_map(|rule| rule.evaluate(context, entry)).collect();
+        let score = fired.iter().map(|f| f.points).sum::<i32>().clamp(0, MAX_RISK_SCORE);
+        let action = if score >= self.block_threshold {
+            RiskAction::Block
+        } else if score >= self.step_up_threshold {
+            RiskAction::StepUp
+        } else {
+            RiskAction::Allow
+        };
+
+        RiskAssessment { score, action, fired, confirmed: false, step_up_waived: false }
+    }
+
+    /// Feeds the downstream response back into the subject's history.
+    pub fn record_outcome(&self, context: &RiskContext, status: StatusCode) {
+        let mut activity = self.activity.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = activity.entry(context.subject()).or_default();
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            entry.auth_failures.push_back(context.at);
+        } else if status.is_success() {
+            // Only devices that completed a request become "known"
+            if let Some(ip) = context.ip_address {
+                entry.known_ips.insert(ip);
+            }
+            if let Some(user_agent) = &context.user_agent {
+                entry.known_user_agents.insert(user_agent.clone());
+            }
+        }
+    }
+
+    /// Feeds a response back unless the risk gate produced it; refusing a
+    /// request is not evidence of the caller failing authentication.
+    pub fn record_response(&self, context: &RiskContext, response: &Response) {
+        if !is_gate_response(response) {
+            self.record_outcome(context, response.status());
+        }
+    }
+
+    /// Loads a user's known addresses and agents from audit history once per process.
+    async fn seed_history(&self, subject: &str, user_id: Uuid) {
+        let seeded = self
+            .activity
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(subject)
+            .is_some_and(|entry| entry.seeded);
+        if seeded {
+            return;
+        }
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT host(ip_address) AS ip_address, user_agent
+            FROM audit_logs
+            WHERE user_id = $1 AND status = 'success' AND created_at > NOW() - make_interval(days => $2)
+            LIMIT 500
+            "#,
+            user_id,
+            HISTORY_SEED_DAYS as i32
+        )
+        .fetch_all(&self.db)
+        .await;
+
+        let mut activity = self.activity.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = activity.entry(subject.to_string()).or_default();
+        match rows {
+            Ok(rows) => {
+                for row in rows {
+                    if let Some(ip) = row.ip_address.and_then(|ip| ip.parse().ok()) {
+                        entry.known_ips.insert(ip);
+                    }
+                    if let Some(user_agent) = row.user_agent {
+                        entry.known_user_agents.insert(user_agent);
+                    }
+                }
+                entry.seeded = true;
+            }
+            // Retried on the next request; until then new-device rules only see this process
+            Err(e) => tracing::warn!("Failed to load risk history for user {}: {}", user_id, e),
+        }
+    }
+}
+
+/// Marks responses produced by the risk gate itself.
+#[derive(Debug, Clone, Copy)]
+struct GateResponse;
+
+pub fn is_gate_response(response: &Response) -> bool {
+    response.extensions().get::<GateResponse>().is_some()
+}
+
+/// Response for requests that need an elevated MFA session before they run.
+pub fn step_up_response(assessment: &RiskAssessment) -> Response {
+    let mut response = (
+        StatusCode::PRECONDITION_REQUIRED,
+        Json(serde_json::json!({
+            "error": "Step-up authentication required",
+            "status": StatusCode::PRECONDITION_REQUIRED.as_u16(),
+            "risk_score": assessment.score,
+            "rules": assessment.fired,
+            "elevation_header": ELEVATED_SESSION_HEADER
+        })),
+    )
+        .into_response();
+    response.extensions_mut().insert(GateResponse);
+    response
+}
+
+pub fn blocked_response(assessment: &RiskAssessment) -> Response {
+    let mut response = (
+        StatusCode::FORBIDDEN,
+        Json(serde_json::json!({
+            "error": "Request blocked by risk policy",
+            "status": StatusCode::FORBIDDEN.as_u16(),
+            "risk_score": assessment.score,
+            "rules": assessment.fired
+        })),
+    )
+        .into_response();
+    response.extensions_mut().insert(GateResponse);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn engine(rules: Vec<RiskRuleConfig>) -> RiskEngine {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        RiskEngine::new(RiskConfig { step_up_threshold: 50, block_threshold: 100, rules }, db)
+    }
+
+    fn anonymous_request(ip: &str) -> RiskContext {
+        RiskContext {
+            user_id: None,
+            ip_address: Some(ip.parse().unwrap()),
+            user_agent: Some("test-agent".to_string()),
+            method: Method::GET,
+            path: "/api/ai/chat".to_string(),
+            resource_type: None,
+            at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn gate_responses_do_not_count_as_failed_authentication() {
+        let engine = engine(vec![RiskRuleConfig::FailedAuthStreak { threshold: 1, window_seconds: 60, points: 60 }]);
+        let context = anonymous_request("203.0.113.9");
+
+        let assessment = engine.assess(&context).await;
+        engine.record_response(&context, &blocked_response(&assessment));
+        engine.record_response(&context, &step_up_response(&assessment));
+        assert_eq!(engine.assess(&context).await.action, RiskAction::Allow);
+
+        engine.record_response(&context, &StatusCode::FORBIDDEN.into_response());
+        let assessment = engine.assess(&context).await;
+        assert_eq!(assessment.action, RiskAction::StepUp);
+        assert_eq!(assessment.fired[0].rule, "failed_auth_streak");
+    }
+
+    #[tokio::test]
+    async fn step_up_asks_for_an_elevated_session_without_handing_out_a_bypass() {
+        let engine = engine(vec![RiskRuleConfig::DestructiveMethod { methods: vec!["DELETE".to_string()], points: 60 }]);
+        let context = RiskContext { method: Method::DELETE, ..anonymous_request("203.0.113.9") };
+
+        let assessment = engine.assess(&context).await;
+        assert_eq!(assessment.action, RiskAction::StepUp);
+
+        let response = step_up_response(&assessment);
+        assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+        assert!(is_gate_response(&response));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["elevation_header"], ELEVATED_SESSION_HEADER);
+        assert!(body.as_object().unwrap().keys().all(|key| !key.contains("token")));
+    }
+
+    #[tokio::test]
+    async fn step_up_is_waived_for_users_without_a_second_factor() {
+        let engine = RiskEngine::new(RiskConfig::default(), sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap());
+        let revoke = RiskContext {
+            method: Method::DELETE,
+            path: format!("/api/security/users/{}/api-keys/{}", Uuid::new_v4(), Uuid::new_v4()),
+            resource_type: Some("security".to_string()),
+            at: Utc.with_ymd_and_hms(2026, 3, 4, 12, 0, 0).unwrap(),
+            ..anonymous_request("203.0.113.9")
+        };
+
+        // Revoking a leaked key scores past the default step-up threshold
+        let mut assessment = engine.assess(&revoke).await;
+        assert_eq!(assessment.action, RiskAction::StepUp);
+        assert!(assessment.requires_step_up());
+
+        assessment.step_up_waived = true;
+        assert!(!assessment.requires_step_up());
+        assessment.step_up_waived = false;
+        assessment.confirmed = true;
+        assert!(!assessment.requires_step_up());
+    }
+
+    #[test]
+    fn new_ip_rule_fires_for_known_users_on_unknown_addresses() {
+        let rule = ConfiguredRule(RiskRuleConfig::NewIp { points: 20 });
+        let mut activity = SubjectActivity::default();
+        activity.known_ips.insert("198.51.100.1".parse().unwrap());
+
+        let user = RiskContext { user_id: Some(Uuid::new_v4()), ..anonymous_request("203.0.113.9") };
+        assert_eq!(user.subject(), format!("user:{}", user.user_id.unwrap()));
+        assert!(rule.evaluate(&user, &activity).is_some());
+
+        let known = RiskContext { ip_address: Some("198.51.100.1".parse().unwrap()), ..user.clone() };
+        assert!(rule.evaluate(&known, &activity).is_none());
+        assert!(rule.evaluate(&anonymous_request("203.0.113.9"), &activity).is_none());
+    }
+}
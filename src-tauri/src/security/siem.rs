@@ -0,0 +1,454 @@
+// Audit export in SIEM formats: JSON Lines, ArcSight CEF and RFC 5424 syslog
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::net::UdpSocket;
+use std::path::PathBuf;
+
+use super::{db_error, query_audit_logs, require_admin, AuditLog, GetAuditLogsQuery, SecurityState, UserContext};
+
+/// Upper bound on entries in one export or shipment.
+const EXPORT_MAX_ENTRIES: usize = 100_000;
+const EXPORT_PAGE_SIZE: i64 = 1000;
+/// RFC 5424 facility 13, "log audit".
+const SYSLOG_FACILITY_LOG_AUDIT: u8 = 13;
+/// Private enterprise number reserved for documentation (RFC 5612).
+const SYSLOG_SD_ID: &str = "audit@32473";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SiemFormat {
+    Jsonl,
+    Cef,
+    Syslog,
+}
+
+impl SiemFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SiemFormat::Jsonl => "application/x-ndjson",
+            SiemFormat::Cef | SiemFormat::Syslog => "text/plain; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SiemFormat::Jsonl => "jsonl",
+            SiemFormat::Cef => "cef",
+            SiemFormat::Syslog => "log",
+        }
+    }
+}
+
+/// Where shipped records go. Chosen by configuration only, never by the request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SiemSink {
+    /// Appends one record per line.
+    File { path: PathBuf },
+    /// Sends one record per datagram, as in RFC 5426.
+    Udp { address: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiemConfig {
+    pub format: SiemFormat,
+    pub sink: Option<SiemSink>,
+    /// HOSTNAME field of syslog records; `-` when unknown.
+    pub hostname: String,
+    pub app_name: String,
+}
+
+impl Default for SiemConfig {
+    fn default() -> Self {
+        Self {
+            format: SiemFormat::Syslog,
+            sink: None,
+            hostname: std::env::var("HOSTNAME").unwrap_or_else(|_| "-".to_string()),
+            app_name: "jeantrail".to_string(),
+        }
+    }
+}
+
+impl SiemConfig {
+    /// Loads the config from the JSON file named by `SIEM_EXPORT_CONFIG`,
+    /// falling back to the defaults, which have no sink.
+    pub fn load() -> Self {
+        let Ok(path) = std::env::var("SIEM_EXPORT_CONFIG") else {
+            return Self::default();
+        };
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string())) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("Ignoring SIEM export config {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Renders one entry as a single line, without the trailing newline.
+    pub fn format_entry(&self, format: SiemFormat, log: &AuditLog) -> String {
+        match format {
+            SiemFormat::Jsonl => serde_json::to_string(log).unwrap_or_default(),
+            SiemFormat::Cef => format_cef(log),
+            SiemFormat::Syslog => format_syslog(log, &self.hostname, &self.app_name),
+        }
+    }
+}
+
+/// CEF severity runs 0-10; risk scores run 0-100 and beyond.
+fn cef_severity(log: &AuditLog) -> i32 {
+    (log.risk_score / 10).clamp(0, 10)
+}
+
+fn cef_header_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn cef_extension_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+pub fn format_cef(log: &AuditLog) -> String {
+    let signature = log.resource_type.as_deref().unwrap_or("request");
+    let mut extension = vec![
+        ("rt", log.created_at.timestamp_millis().to_string()),
+        ("externalId", log.id.to_string()),
+        ("act", log.action.clone()),
+        ("outcome", log.status.clone()),
+        ("cn1Label", "riskScore".to_string()),
+        ("cn1", log.risk_score.to_string()),
+    ];
+    if let Some(user_id) = log.user_id {
+        extension.push(("suid", user_id.to_string()));
+    }
+    if let Some(ip) = log.ip_address {
+        extension.push(("src", ip.to_string()));
+    }
+    if let Some(user_agent) = &log.user_agent {
+        extension.push(("requestClientApplication", user_agent.clone()));
+    }
+    if let Some(resource_id) = &log.resource_id {
+        extension.push(("cs1Label", "resourceId".to_string()));
+        extension.push(("cs1", resource_id.clone()));
+    }
+    if let Some(session_id) = &log.session_id {
+        extension.push(("cs2Label", "sessionId".to_string()));
+        extension.push(("cs2", session_id.clone()));
+    }
+    if let (Some(sequence), Some(entry_hash)) = (log.sequence, &log.entry_hash) {
+        extension.push(("cn2Label", "chainSequence".to_string()));
+        extension.push(("cn2", sequence.to_string()));
+        extension.push(("cs3Label", "entryHash".to_string()));
+        extension.push(("cs3", entry_hash.clone()));
+    }
+
+    format!(
+        "CEF:0|JeanTrail|JeanTrail Browser|{}|{}|{}|{}|{}",
+        cef_header_escape(env!("CARGO_PKG_VERSION")),
+        cef_header_escape(signature),
+        cef_header_escape(&log.action),
+        cef_severity(log),
+        extension
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, cef_extension_escape(value)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
+
+fn syslog_severity(log: &AuditLog) -> u8 {
+    match (log.risk_score, log.status.as_str()) {
+        (score, _) if score >= 90 => 3,                  // error
+        (score, _) if score >= 70 => 4,                  // warning
+        (_, "blocked" | "step_up_required") => 4,        // warning
+        (_, "failed") => 5,                              // notice
+        _ => 6,                                          // informational
+    }
+}
+
+/// Header fields are printable US-ASCII without spaces, or `-`.
+fn syslog_header_field(value: &str, max_len: usize) -> String {
+    let cleaned: String = value.chars().filter(|c| c.is_ascii_graphic()).take(max_len).collect();
+    if cleaned.is_empty() {
+        "-".to_string()
+    } else {
+        cleaned
+    }
+}
+
+fn syslog_param_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]")
+}
+
+pub fn format_syslog(log: &AuditLog, hostname: &str, app_name: &str) -> String {
+    let priority = SYSLOG_FACILITY_LOG_AUDIT * 8 + syslog_severity(log);
+    let mut params = vec![
+        ("id", log.id.to_string()),
+        ("status", log.status.clone()),
+        ("risk", log.risk_score.to_string()),
+    ];
+    if let Some(sequence) = log.sequence {
+        params.push(("seq", sequence.to_string()));
+    }
+    if let Some(user_id) = log.user_id {
+        params.push(("user", user_id.to_string()));
+    }
+    if let Some(ip) = log.ip_address {
+        params.push(("ip", ip.to_string()));
+    }
+    if let Some(resource_id) = &log.resource_id {
+        params.push(("resource", resource_id.clone()));
+    }
+    let structured = params
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, syslog_param_escape(value)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(
+        "<{}>1 {} {} {} {} {} [{} {}] {}",
+        priority,
+        log.created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+        syslog_header_field(hostname, 255),
+        syslog_header_field(app_name, 48),
+        std::process::id(),
+        syslog_header_field(log.resource_type.as_deref().unwrap_or("audit"), 32),
+        SYSLOG_SD_ID,
+        structured,
+        log.action.replace(['\r', '\n'], " ")
+    )
+}
+
+/// Delivers formatted records to a sink; returns how many were written.
+pub fn ship_records(sink: &SiemSink, records: &[String]) -> std::io::Result<usize> {
+    match sink {
+        SiemSink::File { path } => {
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+            for record in records {
+                writeln!(file, "{}", record)?;
+            }
+            file.flush()?;
+        }
+        SiemSink::Udp { address } => {
+            let socket = UdpSocket::bind(if address.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })?;
+            socket.connect(address)?;
+            for record in records {
+                socket.send(record.as_bytes())?;
+            }
+        }
+    }
+    Ok(records.len())
+}
+
+/// Read alongside `GetAuditLogsQuery` from the same query string.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditExportQuery {
+    pub format: Option<SiemFormat>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShipmentReport {
+    pub format: SiemFormat,
+    pub shipped: usize,
+    pub truncated: bool,
+}
+
+/// Matching entries, oldest first, capped at `EXPORT_MAX_ENTRIES`.
+async fn collect_for_export(state: &SecurityState, filter: &GetAuditLogsQuery) -> Result<(Vec<AuditLog>, bool), StatusCode> {
+    let mut entries = Vec::new();
+    let mut page = GetAuditLogsQuery {
+        limit: Some(EXPORT_PAGE_SIZE),
+        offset: Some(filter.offset.unwrap_or(0)),
+        ..filter.clone()
+    };
+    let cap = filter.limit.map_or(EXPORT_MAX_ENTRIES, |limit| (limit.max(1) as usize).min(EXPORT_MAX_ENTRIES));
+    loop {
+        let batch = query_audit_logs(&state.db, &page).await.map_err(db_error)?;
+        let exhausted = (batch.len() as i64) < EXPORT_PAGE_SIZE;
+        entries.extend(batch);
+        if exhausted || entries.len() >= cap {
+            break;
+        }
+        page.offset = Some(page.offset.unwrap_or(0) + EXPORT_PAGE_SIZE);
+    }
+    let truncated = entries.len() > cap;
+    entries.truncate(cap);
+    entries.reverse();
+    Ok((entries, truncated))
+}
+
+/// Downloads matching audit entries as JSON Lines, CEF or syslog.
+pub async fn export_audit_logs(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+    Query(params): Query<AuditExportQuery>,
+    Query(filter): Query<GetAuditLogsQuery>,
+) -> Result<Response, StatusCode> {
+    require_admin(user)?;
+    let format = params.format.unwrap_or(SiemFormat::Jsonl);
+    let (entries, _) = collect_for_export(&state, &filter).await?;
+    let mut body = entries
+        .iter()
+        .map(|log| state.siem.format_entry(format, log))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !body.is_empty() {
+        body.push('\n');
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"audit-logs.{}\"", format.extension())),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Sends matching audit entries to the configured SIEM sink.
+pub async fn ship_audit_logs(
+    State(state): State<SecurityState>,
+    user: Option<Extension<UserContext>>,
+    Query(params): Query<AuditExportQuery>,
+    Query(filter): Query<GetAuditLogsQuery>,
+) -> Result<Json<ShipmentReport>, StatusCode> {
+    require_admin(user)?;
+    let sink = state.siem.sink.clone().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let format = params.format.unwrap_or(state.siem.format);
+    let (entries, truncated) = collect_for_export(&state, &filter).await?;
+    let records: Vec<String> = entries.iter().map(|log| state.siem.format_entry(format, log)).collect();
+
+    let shipped = tokio::task::spawn_blocking(move || ship_records(&sink, &records))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            tracing::error!("Failed to ship audit logs: {}", e);
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    Ok(Json(ShipmentReport { format, shipped, truncated }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    fn entry() -> AuditLog {
+        AuditLog {
+            id: Uuid::nil(),
+            user_id: Some(Uuid::nil()),
+            action: "POST_/api/plugins|install=x\nnext".to_string(),
+            resource_type: Some("plugin_operation".to_string()),
+            resource_id: Some("a]b\"c".to_string()),
+            details: serde_json::json!({}),
+            ip_address: Some("203.0.113.5".parse().unwrap()),
+            user_agent: Some("agent=1".to_string()),
+            session_id: None,
+            risk_score: 75,
+            status: "blocked".to_string(),
+            created_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+            sequence: Some(42),
+            prev_hash: None,
+            entry_hash: Some("ab".repeat(32)),
+            archive_id: None,
+        }
+    }
+
+    #[test]
+    fn cef_escapes_header_and_extension_values() {
+        let line = format_cef(&entry());
+        assert!(line.starts_with("CEF:0|JeanTrail|JeanTrail Browser|"));
+        assert!(line.contains("|plugin_operation|POST_/api/plugins\\|install=x next|7|"));
+        assert!(line.contains("act=POST_/api/plugins|install\\=x\\nnext"));
+        assert!(line.contains("requestClientApplication=agent\\=1"));
+        assert!(line.contains("src=203.0.113.5"));
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn syslog_follows_rfc5424_layout() {
+        let line = format_syslog(&entry(), "audit host", "jeantrail");
+        // facility 13 * 8 + warning (4)
+        assert!(line.starts_with("<108>1 2024-05-01T12:00:00.000000Z audithost jeantrail "));
+        assert!(line.contains(" plugin_operation [audit@32473 id=\"00000000-0000-0000-0000-000000000000\""));
+        assert!(line.contains("resource=\"a\\]b\\\"c\""));
+        assert!(line.ends_with("] POST_/api/plugins|install=x next"));
+    }
+
+    #[test]
+    fn udp_sink_delivers_one_datagram_per_record() {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener.set_read_timeout(Some(std::time::Duration::from_secs(2))).unwrap();
+        let sink = SiemSink::Udp { address: listener.local_addr().unwrap().to_string() };
+        let config = SiemConfig::default();
+        let records = vec![config.format_entry(SiemFormat::Syslog, &entry()), config.format_entry(SiemFormat::Cef, &entry())];
+
+        assert_eq!(ship_records(&sink, &records).unwrap(), 2);
+        let mut buffer = [0u8; 4096];
+        for expected in &records {
+            let received = listener.recv(&mut buffer).unwrap();
+            assert_eq!(std::str::from_utf8(&buffer[..received]).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn file_sink_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("jeantrail-siem-{}.jsonl", Uuid::new_v4()));
+        let sink = SiemSink::File { path: path.clone() };
+        let record = SiemConfig::default().format_entry(SiemFormat::Jsonl, &entry());
+        ship_records(&sink, std::slice::from_ref(&record)).unwrap();
+        ship_records(&sink, std::slice::from_ref(&record)).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(parsed["sequence"], 42);
+    }
+
+    fn caller(is_admin: bool) -> Option<Extension<UserContext>> {
+        Some(Extension(UserContext { user_id: Uuid::new_v4(), permissions: Vec::new(), is_admin }))
+    }
+
+    #[tokio::test]
+    async fn exports_and_shipments_are_refused_without_an_admin() {
+        let db = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let state = SecurityState::new(db);
+
+        for (user, expected) in [(None, StatusCode::UNAUTHORIZED), (caller(false), StatusCode::FORBIDDEN)] {
+            let exported = export_audit_logs(
+                State(state.clone()),
+                user.clone(),
+                Query(AuditExportQuery { format: None }),
+                Query(GetAuditLogsQuery::default()),
+            )
+            .await;
+            assert_eq!(exported.err(), Some(expected));
+            let shipped = ship_audit_logs(
+                State(state.clone()),
+                user,
+                Query(AuditExportQuery { format: None }),
+                Query(GetAuditLogsQuery::default()),
+            )
+            .await;
+            assert_eq!(shipped.err(), Some(expected));
+        }
+    }
+}
@@ -5,6 +5,7 @@ use serde_json::Value;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use crate::security::SafePath;
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct VideoProject {
@@ -364,7 +365,7 @@ pub async fn get_video_templates() -> Result<Json<Value>, axum::http::StatusCode
 }
 
 pub async fn create_project_from_template(
-    Path(template_id): Path<String>,
+    SafePath(template_id): SafePath<String>,
     Json(request): Json<Value>,
 ) -> Result<Json<VideoProject>, axum::http::StatusCode> {
     // Get template and create project based on it
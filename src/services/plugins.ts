@@ -324,6 +324,8 @@ export function validatePluginPermission(permission: string): boolean {
     'tabs.write',
     'proxy.read',
     'proxy.write',
+    'memory.read',
+    'orders.read',
     'files.read',
     'files.write',
     'network.request',
@@ -345,6 +347,8 @@ export function getPluginPermissionDescription(permission: string): string {
     'tabs.write': 'Modify browser tabs',
     'proxy.read': 'Read proxy network status',
     'proxy.write': 'Control proxy network settings',
+    'memory.read': 'Be told when Jean saves a memory',
+    'orders.read': 'Be told when you place an order',
     'files.read': 'Read local files',
     'files.write': 'Write local files',
     'network.request': 'Make network requests',